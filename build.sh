@@ -10,3 +10,12 @@ cargo build --package zswap-factory --target wasm32-unknown-unknown --release
 cp target/wasm32-unknown-unknown/release/zswap_pool.wasm res/
 cp target/wasm32-unknown-unknown/release/zswap_manager.wasm res/
 cp target/wasm32-unknown-unknown/release/zswap_factory.wasm res/
+
+echo ">> Generating ABIs..."
+
+# needs cargo-near 0.3: `cargo install cargo-near --version 0.3.1`
+cargo near abi --doc --manifest-path pool/Cargo.toml --out-dir res/abi
+cargo near abi --doc --manifest-path manager/Cargo.toml --out-dir res/abi
+cargo near abi --doc --manifest-path factory/Cargo.toml --out-dir res/abi
+# `ft_on_transfer` message shapes, which the ABI only knows as strings
+UPDATE_SCHEMAS=1 cargo test --package zswap-pool --package zswap-manager token_receiver_message_schema
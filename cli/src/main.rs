@@ -0,0 +1,556 @@
+//! `zswap`, the admin CLI for the zswap contracts.
+//!
+//! Deploys and initializes the factory and the manager, enables fee tiers, creates pools from
+//! a decimal price, uploads new pool code to the factory and inspects pools and positions, on
+//! a local sandbox or on testnet. `--dry-run` prints the near-cli commands instead of sending
+//! them, and `run` executes a runbook, one command per line:
+//!
+//! ```text
+//! $ zswap --network testnet --signer zswap.testnet --dry-run deploy
+//! $ zswap run scripts/sandbox.runbook
+//! ```
+//!
+//! The sandbox is started for the run and stopped after it, so use a runbook to rehearse
+//! several steps against it.
+
+mod session;
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand};
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json::{self, json};
+use near_sdk::{AccountId, Balance};
+use zswap_client::{FactoryClient, ManagerClient, PoolClient, Transport, MAX_GAS};
+use zswap_math_library::json_types::JsonU256;
+use zswap_math_library::price_math;
+
+use session::{read_wasm, Network, Session};
+
+const ONE_NEAR: Balance = 10u128.pow(24);
+// pools paused per call, each pause is a cross-contract call and a callback sharing the call's gas
+const PAUSE_PAGE: u64 = 20;
+
+#[derive(Parser)]
+#[command(
+    name = "zswap",
+    about = "Deploys, configures and inspects the zswap contracts"
+)]
+struct Cli {
+    #[command(flatten)]
+    options: Options,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct Options {
+    #[arg(long, value_enum, default_value_t = Network::Sandbox, global = true)]
+    network: Network,
+    /// Account signing the calls, with its key in `~/.near-credentials/<network>`. Defaults
+    /// to the sandbox's root account
+    #[arg(long, env = "ZSWAP_SIGNER", global = true)]
+    signer: Option<AccountId>,
+    /// Factory contract, if not deployed by this run
+    #[arg(long, env = "ZSWAP_FACTORY", global = true)]
+    factory: Option<AccountId>,
+    /// Manager contract, if not deployed by this run
+    #[arg(long, env = "ZSWAP_MANAGER", global = true)]
+    manager: Option<AccountId>,
+    /// Directory holding the contracts' wasm files
+    #[arg(long, env = "ZSWAP_RES", default_value = "res", global = true)]
+    res: PathBuf,
+    /// Print the actions as near-cli commands instead of sending them. Views are still sent
+    #[arg(long, global = true)]
+    dry_run: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Creates the factory and manager accounts under the signer, deploys and initializes them
+    Deploy {
+        #[arg(long, default_value = "factory")]
+        factory_name: String,
+        #[arg(long, default_value = "manager")]
+        manager_name: String,
+        /// NEAR sent to the factory account, which stores the pool code
+        #[arg(long, value_parser = parse_near, default_value = "50")]
+        factory_balance: Balance,
+        /// NEAR sent to the manager account
+        #[arg(long, value_parser = parse_near, default_value = "20")]
+        manager_balance: Balance,
+    },
+    /// Creates `<name>.<signer>` and deploys the mock fungible token to it, owned by the signer
+    DeployToken {
+        name: String,
+        #[arg(long)]
+        total_supply: u128,
+        #[arg(long, value_parser = parse_near, default_value = "5")]
+        balance: Balance,
+    },
+    /// Enables a fee tier in the factory. Signed by the factory
+    RegisterFee {
+        fee: u32,
+        tick_spacing: u32,
+        /// Makes it a dynamic fee tier, charging from `fee` up to `max_fee` with volatility
+        #[arg(long)]
+        max_fee: Option<u32>,
+    },
+    /// Creates a pool through the manager, at `price` token 1 per token 0
+    CreatePool {
+        /// Sorts before `token_1`, as the pool orders them
+        token_0: AccountId,
+        token_1: AccountId,
+        fee: u32,
+        /// Decimal amount of token 1 paid for one whole token 0, e.g. `2000.5`
+        price: String,
+        #[arg(long, default_value_t = 0)]
+        decimals_0: u8,
+        #[arg(long, default_value_t = 0)]
+        decimals_1: u8,
+        /// Attached NEAR, by default the pool creation and token registration costs
+        #[arg(long, value_parser = parse_near)]
+        deposit: Option<Balance>,
+    },
+    /// Uploads the code the factory deploys to new pools. Signed by the factory
+    UpdatePoolCode {
+        /// Defaults to `zswap_pool.wasm` in `--res`
+        #[arg(long)]
+        wasm: Option<PathBuf>,
+    },
+    /// Sets the account allowed to pause pools, or removes it if none is given. Signed by the
+    /// factory
+    SetGuardian { guardian: Option<AccountId> },
+    /// Pauses the pool's swaps and mints, or every pool's with `--all`. Signed by the guardian
+    Pause {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        all: bool,
+    },
+    /// Unpauses the pool, or every pool with `--all`. Signed by the guardian
+    Unpause {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        all: bool,
+    },
+    /// Prints the pool's price, tick, liquidity and whether it is paused
+    Slot0(PoolArgs),
+    /// Prints the initialized ticks between `--from` and `--to`, both included
+    Ticks {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long, allow_hyphen_values = true)]
+        from: i32,
+        #[arg(long, allow_hyphen_values = true)]
+        to: i32,
+    },
+    /// Prints a position held in the pool. Positions minted through the manager are the
+    /// manager's
+    Position {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        owner: AccountId,
+        #[arg(long, allow_hyphen_values = true)]
+        lower_tick: i32,
+        #[arg(long, allow_hyphen_values = true)]
+        upper_tick: i32,
+    },
+    /// Prints the manager positions, i.e. NFTs, held by `owner`
+    Positions { owner: AccountId },
+    /// Runs the commands in `file`, one per line, in a single session. Empty lines and lines
+    /// starting with `#` are skipped
+    Run { file: PathBuf },
+}
+
+/// The pool, by id or by its tokens and fee
+#[derive(Args)]
+struct PoolArgs {
+    #[arg(long)]
+    pool: Option<AccountId>,
+    #[arg(long, requires_all = ["token_1", "fee"])]
+    token_0: Option<AccountId>,
+    #[arg(long)]
+    token_1: Option<AccountId>,
+    #[arg(long)]
+    fee: Option<u32>,
+}
+
+/// A runbook line
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct Line {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let options = cli.options;
+    // a runbook is checked whole before anything is sent
+    let (commands, source) = match cli.command {
+        Command::Run { file } => {
+            let runbook = std::fs::read_to_string(&file)
+                .with_context(|| format!("can't read {}", file.display()))?;
+            (parse_runbook(&runbook)?, Some(file))
+        }
+        command => (vec![(command, 1)], None),
+    };
+
+    let mut session = Session::connect(options.network, options.signer.as_ref(), options.dry_run)
+        .await
+        .context("can't connect")?;
+    session.factory = options.factory.clone();
+    session.manager = options.manager.clone();
+    for (command, number) in commands {
+        let result = execute(&mut session, &options, command).await;
+        if let Some(file) = &source {
+            result.with_context(|| format!("{}:{} failed", file.display(), number))?;
+        } else {
+            result?;
+        }
+    }
+    Ok(())
+}
+
+/// The runbook's commands with their line numbers
+fn parse_runbook(runbook: &str) -> anyhow::Result<Vec<(Command, usize)>> {
+    let mut commands = Vec::new();
+    for (index, line) in runbook.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let command = Line::try_parse_from(line.split_whitespace())
+            .with_context(|| format!("line {}: {}", index + 1, line))?
+            .command;
+        if let Command::Run { .. } = command {
+            anyhow::bail!("line {}: runbooks can't run other runbooks", index + 1);
+        }
+        commands.push((command, index + 1));
+    }
+    Ok(commands)
+}
+
+async fn execute(session: &mut Session, options: &Options, command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Deploy {
+            factory_name,
+            manager_name,
+            factory_balance,
+            manager_balance,
+        } => {
+            let factory_id = session
+                .create_account(&factory_name, factory_balance)
+                .await?;
+            session
+                .deploy(&factory_id, &options.res.join("zswap_factory.wasm"))
+                .await?;
+            let signer = session.signer_for(&factory_id)?;
+            FactoryClient::new(signer, factory_id.clone())
+                .init()
+                .await?;
+
+            let manager_id = session
+                .create_account(&manager_name, manager_balance)
+                .await?;
+            session
+                .deploy(&manager_id, &options.res.join("zswap_manager.wasm"))
+                .await?;
+            let signer = session.signer_for(&manager_id)?;
+            ManagerClient::new(signer, manager_id.clone())
+                .init(&factory_id)
+                .await?;
+
+            println!("factory: {}", factory_id);
+            println!("manager: {}", manager_id);
+            session.factory = Some(factory_id);
+            session.manager = Some(manager_id);
+        }
+        Command::DeployToken {
+            name,
+            total_supply,
+            balance,
+        } => {
+            let token_id = session.create_account(&name, balance).await?;
+            session
+                .deploy(&token_id, &options.res.join("mock/fungible_token.wasm"))
+                .await?;
+            let args = json!({ "owner_id": session.signer_id(), "total_supply": total_supply.to_string() });
+            session
+                .signer_for(&token_id)?
+                .call(
+                    &token_id,
+                    "new_default_meta",
+                    serde_json::to_vec(&args)?,
+                    0,
+                    MAX_GAS,
+                )
+                .await?;
+            println!("token: {}", token_id);
+        }
+        Command::RegisterFee {
+            fee,
+            tick_spacing,
+            max_fee,
+        } => {
+            let factory_id = factory(session)?;
+            let factory = FactoryClient::new(session.signer_for(&factory_id)?, factory_id);
+            match max_fee {
+                Some(max_fee) => {
+                    factory
+                        .enable_dynamic_fee_amount(fee, tick_spacing, max_fee)
+                        .await?
+                }
+                None => factory.enable_fee_amount(fee, tick_spacing).await?,
+            }
+        }
+        Command::CreatePool {
+            token_0,
+            token_1,
+            fee,
+            price,
+            decimals_0,
+            decimals_1,
+            deposit,
+        } => {
+            if token_0 >= token_1 {
+                anyhow::bail!(
+                    "{} has to sort before {}, the price is of the pool's token 0",
+                    token_0,
+                    token_1
+                );
+            }
+            let sqrt_price_x96 =
+                price_math::price_to_sqrt_price_x96(&price, decimals_0, decimals_1)
+                    .map_err(|err| anyhow::anyhow!("{}: {}", err, price))?;
+            let factory = FactoryClient::new(session.signer(), factory(session)?);
+            let manager = ManagerClient::new(session.signer(), manager(session)?);
+            let deposit = match deposit {
+                Some(deposit) => deposit,
+                None => {
+                    factory.get_pool_creation_cost().await?
+                        + manager
+                            .get_token_registration_cost(&token_0, &token_1)
+                            .await?
+                }
+            };
+            manager
+                .create_pool(&token_0, &token_1, fee, JsonU256(sqrt_price_x96), deposit)
+                .await?;
+            if !options.dry_run {
+                let pool = factory
+                    .get_pool(&token_0, &token_1, fee)
+                    .await?
+                    .context("the pool was not created")?;
+                println!("pool: {}", pool.pool_id);
+            }
+        }
+        Command::UpdatePoolCode { wasm } => {
+            let path = wasm.unwrap_or_else(|| options.res.join("zswap_pool.wasm"));
+            let factory_id = factory(session)?;
+            let factory = FactoryClient::new(session.signer_for(&factory_id)?, factory_id);
+            factory.update_stored_contract(read_wasm(&path)?).await?;
+        }
+        Command::SetGuardian { guardian } => {
+            let factory_id = factory(session)?;
+            let factory = FactoryClient::new(session.signer_for(&factory_id)?, factory_id);
+            factory.set_guardian(guardian.as_ref()).await?;
+        }
+        Command::Pause { pool, all } => set_paused(session, &pool, all, true).await?,
+        Command::Unpause { pool, all } => set_paused(session, &pool, all, false).await?,
+        Command::Slot0(pool) => {
+            let pool = pool_client(session, &pool).await?;
+            print_json(&json!({
+                "pool_id": pool.id(),
+                "slot_0": pool.get_slot_0().await?,
+                "liquidity": pool.get_liquidity().await?.to_string(),
+                "paused": pool.is_paused().await?,
+            }))?;
+        }
+        Command::Ticks { pool, from, to } => {
+            let pool = pool_client(session, &pool).await?;
+            print_json(&pool.get_ticks(from, to).await?)?;
+        }
+        Command::Position {
+            pool,
+            owner,
+            lower_tick,
+            upper_tick,
+        } => {
+            let pool = pool_client(session, &pool).await?;
+            print_json(&pool.get_position(&owner, lower_tick, upper_tick).await?)?;
+        }
+        Command::Positions { owner } => {
+            let manager = ManagerClient::new(session.signer(), manager(session)?);
+            print_json(&manager.positions(&owner).await?)?;
+        }
+        Command::Run { .. } => anyhow::bail!("runbooks can't run other runbooks"),
+    }
+    Ok(())
+}
+
+fn factory(session: &Session) -> anyhow::Result<AccountId> {
+    session
+        .factory
+        .clone()
+        .context("no factory, pass --factory or deploy one first")
+}
+
+fn manager(session: &Session) -> anyhow::Result<AccountId> {
+    session
+        .manager
+        .clone()
+        .context("no manager, pass --manager or deploy one first")
+}
+
+async fn pool_client(
+    session: &Session,
+    args: &PoolArgs,
+) -> anyhow::Result<PoolClient<session::Signer>> {
+    let pool_id = match (&args.pool, &args.token_0, &args.token_1, args.fee) {
+        (Some(pool_id), _, _, _) => pool_id.clone(),
+        (None, Some(token_0), Some(token_1), Some(fee)) => {
+            FactoryClient::new(session.signer(), factory(session)?)
+                .get_pool(token_0, token_1, fee)
+                .await?
+                .with_context(|| format!("no pool for {}/{}/{}", token_0, token_1, fee))?
+                .pool_id
+        }
+        _ => anyhow::bail!("pass --pool, or --token-0, --token-1 and --fee"),
+    };
+    Ok(PoolClient::new(session.signer(), pool_id))
+}
+
+/// Pauses or unpauses the pool, or all of them a page at a time
+async fn set_paused(
+    session: &Session,
+    args: &PoolArgs,
+    all: bool,
+    paused: bool,
+) -> anyhow::Result<()> {
+    let factory = FactoryClient::new(session.signer(), factory(session)?);
+    if all {
+        let count = factory.get_pool_count().await?;
+        for from_index in (0..count).step_by(PAUSE_PAGE as usize) {
+            if paused {
+                factory.pause_all_pools(from_index, PAUSE_PAGE).await?;
+            } else {
+                factory.unpause_all_pools(from_index, PAUSE_PAGE).await?;
+            }
+        }
+        return Ok(());
+    }
+
+    let pool = pool_client(session, args).await?;
+    if paused {
+        factory.pause_pool(pool.id()).await
+    } else {
+        factory.unpause_pool(pool.id()).await
+    }
+}
+
+fn print_json(value: &impl Serialize) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Parses a decimal amount of NEAR, e.g. `2.5`, into yoctoNEAR
+fn parse_near(amount: &str) -> Result<Balance, String> {
+    let invalid = || format!("invalid NEAR amount: {}", amount);
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || fraction.len() > 24
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let whole: Balance = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid())?
+    };
+    let fraction: Balance = format!("{:0<24}", fraction)
+        .parse()
+        .map_err(|_| invalid())?;
+    whole
+        .checked_mul(ONE_NEAR)
+        .and_then(|whole| whole.checked_add(fraction))
+        .ok_or_else(invalid)
+}
+
+/// Prints yoctoNEAR as a decimal amount of NEAR, the way `parse_near` reads it
+fn format_near(amount: Balance) -> String {
+    let fraction = format!("{:024}", amount % ONE_NEAR);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (amount / ONE_NEAR).to_string()
+    } else {
+        format!("{}.{}", amount / ONE_NEAR, fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_near_amounts() {
+        assert_eq!(parse_near("50"), Ok(50 * ONE_NEAR));
+        assert_eq!(parse_near("2.5"), Ok(5 * ONE_NEAR / 2));
+        assert_eq!(parse_near(".000000000000000000000001"), Ok(1));
+        assert!(parse_near("0.0000000000000000000000001").is_err());
+        assert!(parse_near("1 N").is_err());
+        assert!(parse_near("-1").is_err());
+        assert!(parse_near(".").is_err());
+
+        assert_eq!(format_near(50 * ONE_NEAR), "50");
+        assert_eq!(format_near(5 * ONE_NEAR / 2), "2.5");
+        assert_eq!(format_near(1), "0.000000000000000000000001");
+    }
+
+    #[test]
+    fn test_parse_runbook() {
+        let runbook = "\
+            # rehearse a fee tier and a pool\n\
+            deploy --factory-balance 30\n\
+            \n\
+            register-fee 100 1\n\
+            ticks --pool pool.factory.test.near --from -887272 --to 887272\n";
+        let commands = parse_runbook(runbook).unwrap();
+        assert_eq!(
+            commands.iter().map(|(_, line)| *line).collect::<Vec<_>>(),
+            [2, 4, 5]
+        );
+        assert!(matches!(
+            commands[0].0,
+            Command::Deploy { factory_balance, .. } if factory_balance == 30 * ONE_NEAR
+        ));
+        assert!(matches!(
+            commands[1].0,
+            Command::RegisterFee {
+                fee: 100,
+                tick_spacing: 1,
+                max_fee: None,
+            }
+        ));
+        assert!(matches!(
+            commands[2].0,
+            Command::Ticks {
+                from: -887272,
+                to: 887272,
+                ..
+            }
+        ));
+
+        assert!(parse_runbook("run other.txt").is_err());
+        assert!(parse_runbook("register-fee 100").is_err());
+    }
+}
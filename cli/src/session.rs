@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use clap::ValueEnum;
+use near_sdk::{AccountId, Balance, Gas};
+use workspaces::network::{Sandbox, Testnet};
+use workspaces::{Account, Worker};
+use zswap_client::Transport;
+
+use crate::format_near;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Network {
+    /// A fresh local sandbox, started for this run and gone after it
+    Sandbox,
+    Testnet,
+}
+
+impl Network {
+    fn name(&self) -> &'static str {
+        match self {
+            Network::Sandbox => "sandbox",
+            Network::Testnet => "testnet",
+        }
+    }
+}
+
+enum Connection {
+    Sandbox(Worker<Sandbox>),
+    Testnet(Worker<Testnet>),
+}
+
+/// The network connection and the accounts the CLI can sign with.
+///
+/// In a dry run, accounts are neither created nor deployed to and calls are only printed, as
+/// the near-cli commands doing the same. Views are still sent to the network.
+pub struct Session {
+    network: Network,
+    connection: Connection,
+    dry_run: bool,
+    signer: Account,
+    /// Accounts created during this run, so that later commands can sign with them
+    accounts: HashMap<AccountId, Account>,
+    pub factory: Option<AccountId>,
+    pub manager: Option<AccountId>,
+}
+
+impl Session {
+    /// Connects to `network` and loads `signer`'s key, which defaults to the sandbox's root
+    /// account
+    pub async fn connect(
+        network: Network,
+        signer: Option<&AccountId>,
+        dry_run: bool,
+    ) -> anyhow::Result<Self> {
+        let connection = match network {
+            Network::Sandbox => Connection::Sandbox(workspaces::sandbox().await?),
+            Network::Testnet => Connection::Testnet(workspaces::testnet().await?),
+        };
+        let signer = match (signer, &connection) {
+            (Some(signer), _) => load_account(network, &connection, signer)?,
+            (None, Connection::Sandbox(worker)) => worker.root_account()?,
+            (None, Connection::Testnet(_)) => anyhow::bail!("--signer is required on testnet"),
+        };
+        Ok(Self {
+            network,
+            connection,
+            dry_run,
+            signer,
+            accounts: HashMap::new(),
+            factory: None,
+            manager: None,
+        })
+    }
+
+    pub fn signer_id(&self) -> AccountId {
+        account_id(&self.signer)
+    }
+
+    /// Signs as the `--signer` account
+    pub fn signer(&self) -> Signer {
+        Signer {
+            id: self.signer_id(),
+            account: self.signer.clone(),
+            dry_run: self.dry_run,
+        }
+    }
+
+    /// Signs as `id`, created during this run or with its key in the credentials directory
+    pub fn signer_for(&mut self, id: &AccountId) -> anyhow::Result<Signer> {
+        let account = match self.account(id) {
+            Ok(account) => account,
+            // a dry run never creates the account, so there is no key to load
+            Err(_) if self.dry_run => self.signer.clone(),
+            Err(err) => return Err(err),
+        };
+        Ok(Signer {
+            id: id.clone(),
+            account,
+            dry_run: self.dry_run,
+        })
+    }
+
+    /// Creates `<name>.<signer>` funded with `balance`. Its key is stored in the credentials
+    /// directory on testnet
+    pub async fn create_account(
+        &mut self,
+        name: &str,
+        balance: Balance,
+    ) -> anyhow::Result<AccountId> {
+        let id: AccountId = format!("{}.{}", name, self.signer.id()).parse()?;
+        if self.dry_run {
+            println!(
+                "near create-account {} --masterAccount {} --initialBalance {}",
+                id,
+                self.signer.id(),
+                format_near(balance)
+            );
+            return Ok(id);
+        }
+
+        let account = self
+            .signer
+            .create_subaccount(name)
+            .initial_balance(balance)
+            .transact()
+            .await?
+            .into_result()?;
+        if self.network == Network::Testnet {
+            account
+                .store_credentials(credentials_dir(self.network)?)
+                .await?;
+        }
+        self.accounts.insert(id.clone(), account);
+        Ok(id)
+    }
+
+    /// Deploys the wasm file at `path` to `id`
+    pub async fn deploy(&mut self, id: &AccountId, path: &Path) -> anyhow::Result<()> {
+        let code = read_wasm(path)?;
+        if self.dry_run {
+            println!(
+                "near deploy --accountId {} --wasmFile {}",
+                id,
+                path.display()
+            );
+            return Ok(());
+        }
+        self.account(id)?.deploy(&code).await?.into_result()?;
+        Ok(())
+    }
+
+    fn account(&mut self, id: &AccountId) -> anyhow::Result<Account> {
+        if self.signer.id().as_str() == id.as_str() {
+            return Ok(self.signer.clone());
+        }
+        if let Some(account) = self.accounts.get(id) {
+            return Ok(account.clone());
+        }
+        let account = load_account(self.network, &self.connection, id)?;
+        self.accounts.insert(id.clone(), account.clone());
+        Ok(account)
+    }
+}
+
+/// Sends the calls signed by `id`, or only prints them in a dry run
+#[derive(Clone)]
+pub struct Signer {
+    id: AccountId,
+    account: Account,
+    dry_run: bool,
+}
+
+#[async_trait]
+impl Transport for Signer {
+    async fn call(
+        &self,
+        contract_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+        deposit: Balance,
+        gas: Gas,
+    ) -> anyhow::Result<Vec<u8>> {
+        if self.dry_run {
+            println!(
+                "{}",
+                describe_call(&self.id, contract_id, method, &args, deposit, gas)
+            );
+            // read as `null`, the calls' results are not needed to print the next ones
+            return Ok(Vec::new());
+        }
+        Transport::call(&self.account, contract_id, method, args, deposit, gas).await
+    }
+
+    async fn view(
+        &self,
+        contract_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+    ) -> anyhow::Result<Vec<u8>> {
+        Transport::view(&self.account, contract_id, method, args).await
+    }
+}
+
+/// The near-cli command making the call
+fn describe_call(
+    signer_id: &AccountId,
+    contract_id: &AccountId,
+    method: &str,
+    args: &[u8],
+    deposit: Balance,
+    gas: Gas,
+) -> String {
+    let args = match serde_json::from_slice::<serde_json::Value>(args) {
+        Ok(args) => args.to_string(),
+        Err(_) => format!("<{} bytes of raw input>", args.len()),
+    };
+    format!(
+        "near call {} {} '{}' --accountId {} --depositYocto {} --gas {}",
+        contract_id, method, args, signer_id, deposit, gas.0
+    )
+}
+
+pub fn read_wasm(path: &Path) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("can't read {}", path.display()))
+}
+
+/// `~/.near-credentials/<network>`, where near-cli keeps its keys
+fn credentials_dir(network: Network) -> anyhow::Result<PathBuf> {
+    let home = std::env::var("HOME").context("HOME is not set")?;
+    Ok(PathBuf::from(home)
+        .join(".near-credentials")
+        .join(network.name()))
+}
+
+fn load_account(
+    network: Network,
+    connection: &Connection,
+    id: &AccountId,
+) -> anyhow::Result<Account> {
+    let path = credentials_dir(network)?.join(format!("{}.json", id));
+    let account = match connection {
+        Connection::Sandbox(worker) => Account::from_file(&path, worker),
+        Connection::Testnet(worker) => Account::from_file(&path, worker),
+    };
+    account.with_context(|| format!("no key for {} in {}", id, path.display()))
+}
+
+fn account_id(account: &Account) -> AccountId {
+    account.id().as_str().parse().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::serde_json::json;
+    use near_sdk::{AccountId, Gas};
+
+    use super::describe_call;
+
+    #[test]
+    fn test_describe_call() {
+        let signer: AccountId = "factory.test.near".parse().unwrap();
+        let args = serde_json::to_vec(&json!({ "fee": 100, "tick_spacing": 1 })).unwrap();
+        assert_eq!(
+            describe_call(
+                &signer,
+                &signer,
+                "enable_fee_amount",
+                &args,
+                0,
+                Gas(300_000_000_000_000)
+            ),
+            "near call factory.test.near enable_fee_amount '{\"fee\":100,\"tick_spacing\":1}' \
+             --accountId factory.test.near --depositYocto 0 --gas 300000000000000"
+        );
+
+        let code = b"\0asm\x01\0\0\0".to_vec();
+        assert_eq!(
+            describe_call(
+                &signer,
+                &signer,
+                "update_stored_contract",
+                &code,
+                0,
+                Gas(300_000_000_000_000)
+            ),
+            "near call factory.test.near update_stored_contract '<8 bytes of raw input>' \
+             --accountId factory.test.near --depositYocto 0 --gas 300000000000000"
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{AccountId, Balance};
+use zswap_factory::pool::PoolView;
+use zswap_math_library::json_types::JsonU256;
+
+use crate::transport::{self, Transport, MAX_GAS};
+
+#[derive(Clone, Debug)]
+pub struct FactoryClient<T> {
+    transport: T,
+    id: AccountId,
+}
+
+impl<T: Transport> FactoryClient<T> {
+    pub fn new(transport: T, id: AccountId) -> Self {
+        Self { transport, id }
+    }
+
+    pub fn id(&self) -> &AccountId {
+        &self.id
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Calls the `new` initializer
+    pub async fn init(&self) -> anyhow::Result<()> {
+        transport::call(&self.transport, &self.id, "new", json!({}), 0).await
+    }
+
+    /// Creates and initializes the pool. `deposit` has to cover `get_pool_creation_cost`
+    pub async fn create_pool(
+        &self,
+        token_0: &AccountId,
+        token_1: &AccountId,
+        fee: u32,
+        sqrt_price_x96: JsonU256,
+        deposit: Balance,
+    ) -> anyhow::Result<Option<AccountId>> {
+        let args = json!({
+            "token_0": token_0,
+            "token_1": token_1,
+            "fee": fee,
+            "sqrt_price_x96": sqrt_price_x96,
+        });
+        transport::call(&self.transport, &self.id, "create_pool", args, deposit).await
+    }
+
+    /// Creates and initializes a pool calling `hook` around its swaps and liquidity changes
+    pub async fn create_pool_with_hook(
+        &self,
+        token_0: &AccountId,
+        token_1: &AccountId,
+        fee: u32,
+        sqrt_price_x96: JsonU256,
+        hook: &AccountId,
+        deposit: Balance,
+    ) -> anyhow::Result<Option<AccountId>> {
+        let args = json!({
+            "token_0": token_0,
+            "token_1": token_1,
+            "fee": fee,
+            "sqrt_price_x96": sqrt_price_x96,
+            "hook": hook,
+        });
+        transport::call(&self.transport, &self.id, "create_pool", args, deposit).await
+    }
+
+    pub async fn get_pool(
+        &self,
+        token_0: &AccountId,
+        token_1: &AccountId,
+        fee: u32,
+    ) -> anyhow::Result<Option<PoolView>> {
+        let args = json!({ "token_0": token_0, "token_1": token_1, "fee": fee });
+        transport::view(&self.transport, &self.id, "get_pool", args).await
+    }
+
+    pub async fn get_pool_with_hook(
+        &self,
+        token_0: &AccountId,
+        token_1: &AccountId,
+        fee: u32,
+        hook: &AccountId,
+    ) -> anyhow::Result<Option<PoolView>> {
+        let args = json!({ "token_0": token_0, "token_1": token_1, "fee": fee, "hook": hook });
+        transport::view(&self.transport, &self.id, "get_pool", args).await
+    }
+
+    pub async fn get_tokens(&self) -> anyhow::Result<Vec<AccountId>> {
+        transport::view(&self.transport, &self.id, "get_tokens", json!({})).await
+    }
+
+    /// Deposit the factory needs to create a pool, the same for any tokens
+    pub async fn get_pool_creation_cost(&self) -> anyhow::Result<Balance> {
+        let cost: U128 = transport::view(
+            &self.transport,
+            &self.id,
+            "get_pool_creation_cost",
+            json!({}),
+        )
+        .await?;
+        Ok(cost.0)
+    }
+
+    /// Enables a fee tier. The factory has to sign
+    pub async fn enable_fee_amount(&self, fee: u32, tick_spacing: u32) -> anyhow::Result<()> {
+        let args = json!({ "fee": fee, "tick_spacing": tick_spacing });
+        transport::call(&self.transport, &self.id, "enable_fee_amount", args, 0).await
+    }
+
+    /// Enables a dynamic fee tier, charging from `fee` up to `max_fee`. The factory has to sign
+    pub async fn enable_dynamic_fee_amount(
+        &self,
+        fee: u32,
+        tick_spacing: u32,
+        max_fee: u32,
+    ) -> anyhow::Result<()> {
+        let args = json!({ "fee": fee, "tick_spacing": tick_spacing, "max_fee": max_fee });
+        transport::call(
+            &self.transport,
+            &self.id,
+            "enable_dynamic_fee_amount",
+            args,
+            0,
+        )
+        .await
+    }
+
+    pub async fn get_fee_amount_tick_spacing(&self, fee: u32) -> anyhow::Result<Option<u32>> {
+        let args = json!({ "fee": fee });
+        let method = "get_fee_amount_tick_spacing";
+        transport::view(&self.transport, &self.id, method, args).await
+    }
+
+    pub async fn get_fee_amount_max_fee(&self, fee: u32) -> anyhow::Result<Option<u32>> {
+        let args = json!({ "fee": fee });
+        transport::view(&self.transport, &self.id, "get_fee_amount_max_fee", args).await
+    }
+
+    /// Sets the account allowed to pause pools, or removes it. The factory has to sign
+    pub async fn set_guardian(&self, guardian: Option<&AccountId>) -> anyhow::Result<()> {
+        let args = json!({ "guardian": guardian });
+        transport::call(&self.transport, &self.id, "set_guardian", args, 0).await
+    }
+
+    pub async fn get_guardian(&self) -> anyhow::Result<Option<AccountId>> {
+        transport::view(&self.transport, &self.id, "get_guardian", json!({})).await
+    }
+
+    /// Pauses the pool's swaps and mints. The guardian or the factory has to sign
+    pub async fn pause_pool(&self, pool_id: &AccountId) -> anyhow::Result<()> {
+        let args = json!({ "pool_id": pool_id });
+        transport::call(&self.transport, &self.id, "pause_pool", args, 0).await
+    }
+
+    pub async fn unpause_pool(&self, pool_id: &AccountId) -> anyhow::Result<()> {
+        let args = json!({ "pool_id": pool_id });
+        transport::call(&self.transport, &self.id, "unpause_pool", args, 0).await
+    }
+
+    /// Pauses `limit` pools from `from_index`, out of `get_pool_count`
+    pub async fn pause_all_pools(&self, from_index: u64, limit: u64) -> anyhow::Result<()> {
+        let args = json!({ "from_index": from_index, "limit": limit });
+        transport::call(&self.transport, &self.id, "pause_all_pools", args, 0).await
+    }
+
+    pub async fn unpause_all_pools(&self, from_index: u64, limit: u64) -> anyhow::Result<()> {
+        let args = json!({ "from_index": from_index, "limit": limit });
+        transport::call(&self.transport, &self.id, "unpause_all_pools", args, 0).await
+    }
+
+    pub async fn get_pool_count(&self) -> anyhow::Result<u64> {
+        transport::view(&self.transport, &self.id, "get_pool_count", json!({})).await
+    }
+
+    /// Replaces the code deployed to new pools. The factory has to sign
+    pub async fn update_stored_contract(&self, code: Vec<u8>) -> anyhow::Result<()> {
+        // the method reads the raw input instead of JSON arguments
+        self.transport
+            .call(&self.id, "update_stored_contract", code, 0, MAX_GAS)
+            .await?;
+        Ok(())
+    }
+}
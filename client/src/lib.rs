@@ -0,0 +1,145 @@
+//! Typed client for the zswap contracts.
+//!
+//! `FactoryClient`, `ManagerClient` and `PoolClient` wrap every public method of the three
+//! contracts, and `TokenClient` the fungible token methods needed to fund them, including the
+//! `ft_transfer_call` messages the manager and the pools accept. Arguments and results use the
+//! contracts' own types (`MintParams`, `PoolView`, `Slot0`, the `TokenReceiverMessage` enums).
+//!
+//! Every client sends its calls through a `Transport`, which signs and submits them. Enable the
+//! `workspaces` feature to use a workspaces `Account` or `Contract` as the transport, in the
+//! sandbox or on testnet:
+//!
+//! ```ignore
+//! let manager = ManagerClient::new(account, manager_id);
+//! manager.deposit(&token_0, U128(1_000)).await?;
+//! let amounts = manager.mint(&params).await?;
+//! ```
+
+pub mod factory;
+pub mod manager;
+pub mod pool;
+#[cfg(feature = "workspaces")]
+mod sandbox;
+pub mod token;
+mod transport;
+
+pub use factory::FactoryClient;
+pub use manager::ManagerClient;
+pub use pool::PoolClient;
+pub use token::TokenClient;
+pub use transport::{Transport, MAX_GAS};
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use near_sdk::serde_json::{self, json, Value};
+    use near_sdk::{AccountId, Balance, Gas, ONE_YOCTO};
+
+    use crate::{ManagerClient, PoolClient, Transport};
+
+    /// Records the calls and answers each with the same result
+    struct MockTransport {
+        result: Vec<u8>,
+        calls: Mutex<Vec<(AccountId, String, Value, Balance)>>,
+    }
+
+    impl MockTransport {
+        fn new(result: &str) -> Self {
+            Self {
+                result: result.as_bytes().to_vec(),
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn last_call(&self) -> (AccountId, String, Value, Balance) {
+            self.calls.lock().unwrap().last().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl Transport for MockTransport {
+        async fn call(
+            &self,
+            contract_id: &AccountId,
+            method: &str,
+            args: Vec<u8>,
+            deposit: Balance,
+            _gas: Gas,
+        ) -> anyhow::Result<Vec<u8>> {
+            let args = serde_json::from_slice(&args)?;
+            let call = (contract_id.clone(), method.to_string(), args, deposit);
+            self.calls.lock().unwrap().push(call);
+            Ok(self.result.clone())
+        }
+
+        async fn view(
+            &self,
+            contract_id: &AccountId,
+            method: &str,
+            args: Vec<u8>,
+        ) -> anyhow::Result<Vec<u8>> {
+            self.call(contract_id, method, args, 0, Gas(0)).await
+        }
+    }
+
+    fn account(id: &str) -> AccountId {
+        id.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_swap_single_message() {
+        let manager = ManagerClient::new(MockTransport::new(r#""1000""#), account("manager"));
+        let used_amount = manager
+            .swap_single(
+                &account("token_a"),
+                1000,
+                &account("token_b"),
+                3000,
+                990,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(used_amount, 1000);
+
+        let (contract_id, method, args, deposit) = manager.transport().last_call();
+        assert_eq!(contract_id, account("token_a"));
+        assert_eq!(method, "ft_transfer_call");
+        assert_eq!(deposit, ONE_YOCTO);
+        assert_eq!(args["receiver_id"], "manager");
+        assert_eq!(args["amount"], "1000");
+        let msg: Value = serde_json::from_str(args["msg"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            msg,
+            json!({
+                "swap_single": {
+                    "token_out": "token_b",
+                    "fee": 3000,
+                    "amount_out_min": "990",
+                    "sqrt_price_limit_x96": null,
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signed_amounts_and_empty_results() {
+        let pool = PoolClient::new(MockTransport::new(r#"["1000","-99"]"#), account("pool"));
+        let amounts = pool
+            .swap(&account("alice"), true, 1000, None, 90)
+            .await
+            .unwrap();
+        assert_eq!(amounts, [1000, -99]);
+        let (_, method, args, _) = pool.transport().last_call();
+        assert_eq!(method, "swap");
+        assert_eq!(args["amount_specified"], "1000");
+        assert_eq!(args["amount_out_min"], "90");
+
+        // methods returning nothing
+        let pool = PoolClient::new(MockTransport::new(""), account("pool"));
+        pool.withdraw(&account("token_a"), 5).await.unwrap();
+        assert_eq!(pool.transport().last_call().3, ONE_YOCTO);
+    }
+}
@@ -0,0 +1,565 @@
+use std::collections::HashMap;
+
+use near_contract_standards::non_fungible_token::metadata::NFTContractMetadata;
+use near_contract_standards::non_fungible_token::{Token, TokenId};
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::{self, json};
+use near_sdk::{AccountId, Balance, ONE_YOCTO};
+use zswap_manager::ft_receiver::TokenReceiverMessage;
+use zswap_manager::utils::{
+    LimitOrderParams, LimitOrderView, MintParams, NftLiquidityInfo, SwapSingleParams,
+};
+use zswap_math_library::json_types::JsonU256;
+use zswap_pool::utils::Slot0;
+
+use crate::token::TokenClient;
+use crate::transport::{self, Transport};
+
+#[derive(Clone, Debug)]
+pub struct ManagerClient<T> {
+    transport: T,
+    id: AccountId,
+}
+
+impl<T: Transport> ManagerClient<T> {
+    pub fn new(transport: T, id: AccountId) -> Self {
+        Self { transport, id }
+    }
+
+    pub fn id(&self) -> &AccountId {
+        &self.id
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Calls the `new` initializer
+    pub async fn init(&self, factory: &AccountId) -> anyhow::Result<()> {
+        let args = json!({ "factory": factory });
+        transport::call(&self.transport, &self.id, "new", args, 0).await
+    }
+
+    /// Creates the pool through the factory and returns its id. `deposit` has to cover the
+    /// factory's `get_pool_creation_cost` plus `get_token_registration_cost`
+    pub async fn create_pool(
+        &self,
+        token_0: &AccountId,
+        token_1: &AccountId,
+        fee: u32,
+        sqrt_price_x96: JsonU256,
+        deposit: Balance,
+    ) -> anyhow::Result<Option<AccountId>> {
+        let args = json!({
+            "token_0": token_0,
+            "token_1": token_1,
+            "fee": fee,
+            "sqrt_price_x96": sqrt_price_x96,
+        });
+        transport::call(&self.transport, &self.id, "create_pool", args, deposit).await
+    }
+
+    /// Like `create_pool`, but also works for pools that already exist
+    pub async fn create_and_initialize_pool_if_necessary(
+        &self,
+        token_0: &AccountId,
+        token_1: &AccountId,
+        fee: u32,
+        sqrt_price_x96: JsonU256,
+        deposit: Balance,
+    ) -> anyhow::Result<Option<AccountId>> {
+        let args = json!({
+            "token_0": token_0,
+            "token_1": token_1,
+            "fee": fee,
+            "sqrt_price_x96": sqrt_price_x96,
+        });
+        let method = "create_and_initialize_pool_if_necessary";
+        transport::call(&self.transport, &self.id, method, args, deposit).await
+    }
+
+    /// Deposits `amount` of the token into the manager with an empty `ft_transfer_call` message
+    pub async fn deposit(&self, token_id: &AccountId, amount: Balance) -> anyhow::Result<Balance> {
+        self.token(token_id)
+            .ft_transfer_call(&self.id, amount, String::new())
+            .await
+    }
+
+    /// Swaps `amount_in` straight from the wallet with a `SwapSingle` message, the output is
+    /// credited to the caller's deposit. Returns the input amount the manager kept
+    pub async fn swap_single(
+        &self,
+        token_in: &AccountId,
+        amount_in: Balance,
+        token_out: &AccountId,
+        fee: u32,
+        amount_out_min: Balance,
+        sqrt_price_limit_x96: Option<JsonU256>,
+    ) -> anyhow::Result<Balance> {
+        let msg = TokenReceiverMessage::SwapSingle {
+            token_out: token_out.clone(),
+            fee,
+            amount_out_min: U128(amount_out_min),
+            sqrt_price_limit_x96,
+        };
+        self.token(token_in)
+            .ft_transfer_call(&self.id, amount_in, serde_json::to_string(&msg)?)
+            .await
+    }
+
+    /// Mints from the caller's deposits and returns the amounts the pool took. The caller's
+    /// storage balance pays for the position
+    pub async fn mint(&self, params: &MintParams) -> anyhow::Result<[Balance; 2]> {
+        let args = json!({ "params": params });
+        let amounts: [U128; 2] =
+            transport::call(&self.transport, &self.id, "mint", args, 0).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    /// Swaps from the caller's deposits and returns the amount credited back to them
+    pub async fn swap(&self, params: &SwapSingleParams) -> anyhow::Result<Balance> {
+        let args = json!({ "params": params });
+        let amount_out: U128 = transport::call(&self.transport, &self.id, "swap", args, 0).await?;
+        Ok(amount_out.0)
+    }
+
+    /// Withdraws deposited tokens to the caller's wallet
+    pub async fn withdraw(&self, token_id: &AccountId, amount: Balance) -> anyhow::Result<()> {
+        let args = json!({ "token_id": token_id, "amount": U128(amount) });
+        transport::call(&self.transport, &self.id, "withdraw", args, ONE_YOCTO).await
+    }
+
+    /// Burns the position NFT and returns the amounts collected to the caller's wallet
+    pub async fn burn(&self, nft_id: u128) -> anyhow::Result<[Balance; 2]> {
+        let args = json!({ "nft_id": U128(nft_id) });
+        let amounts: [U128; 2] =
+            transport::call(&self.transport, &self.id, "burn", args, 0).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    /// Places a limit order from the caller's deposit and returns its id, or `None` if the pool
+    /// rejected it. The caller's storage balance pays for the order
+    pub async fn place_limit_order(
+        &self,
+        params: &LimitOrderParams,
+    ) -> anyhow::Result<Option<u128>> {
+        let args = json!({ "params": params });
+        let order_id: Option<U128> =
+            transport::call(&self.transport, &self.id, "place_limit_order", args, 0).await?;
+        Ok(order_id.map(|order_id| order_id.0))
+    }
+
+    /// Closes a filled limit order, anyone's, crediting its output to the owner's deposit
+    pub async fn claim_limit_order(&self, order_id: u128) -> anyhow::Result<[Balance; 2]> {
+        let args = json!({ "order_id": U128(order_id) });
+        let amounts: [U128; 2] =
+            transport::call(&self.transport, &self.id, "claim_limit_order", args, 0).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    /// Cancels the caller's unfilled limit order, crediting what it holds to their deposit
+    pub async fn cancel_limit_order(&self, order_id: u128) -> anyhow::Result<[Balance; 2]> {
+        let args = json!({ "order_id": U128(order_id) });
+        let amounts: [U128; 2] =
+            transport::call(&self.transport, &self.id, "cancel_limit_order", args, 0).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    pub async fn get_limit_order(&self, order_id: u128) -> anyhow::Result<Option<LimitOrderView>> {
+        let args = json!({ "order_id": U128(order_id) });
+        transport::view(&self.transport, &self.id, "get_limit_order", args).await
+    }
+
+    pub async fn get_limit_orders(
+        &self,
+        from_index: Option<u128>,
+        limit: Option<u64>,
+    ) -> anyhow::Result<Vec<LimitOrderView>> {
+        let args = json!({ "from_index": from_index.map(U128), "limit": limit });
+        transport::view(&self.transport, &self.id, "get_limit_orders", args).await
+    }
+
+    pub async fn get_liquidity_for_amounts(
+        &self,
+        slot_0: &Slot0,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount_0_desired: Balance,
+        amount_1_desired: Balance,
+    ) -> anyhow::Result<u128> {
+        let args = json!({
+            "slot_0": slot_0,
+            "lower_tick": lower_tick,
+            "upper_tick": upper_tick,
+            "amount_0_desired": U128(amount_0_desired),
+            "amount_1_desired": U128(amount_1_desired),
+        });
+        let method = "get_liquidity_for_amounts";
+        let liquidity: U128 = transport::view(&self.transport, &self.id, method, args).await?;
+        Ok(liquidity.0)
+    }
+
+    pub async fn calculate_amount_1_with_amount_0(
+        &self,
+        amount_0: Balance,
+        sqrt_price_x96: JsonU256,
+        lower_tick: i32,
+        upper_tick: i32,
+    ) -> anyhow::Result<Balance> {
+        let args = json!({
+            "amount_0": U128(amount_0),
+            "sqrt_price_x96": sqrt_price_x96,
+            "lower_tick": lower_tick,
+            "upper_tick": upper_tick,
+        });
+        let method = "calculate_amount_1_with_amount_0";
+        let amount_1: U128 = transport::view(&self.transport, &self.id, method, args).await?;
+        Ok(amount_1.0)
+    }
+
+    pub async fn calculate_amount_0_with_amount_1(
+        &self,
+        amount_1: Balance,
+        sqrt_price_x96: JsonU256,
+        lower_tick: i32,
+        upper_tick: i32,
+    ) -> anyhow::Result<Balance> {
+        let args = json!({
+            "amount_1": U128(amount_1),
+            "sqrt_price_x96": sqrt_price_x96,
+            "lower_tick": lower_tick,
+            "upper_tick": upper_tick,
+        });
+        let method = "calculate_amount_0_with_amount_1";
+        let amount_0: U128 = transport::view(&self.transport, &self.id, method, args).await?;
+        Ok(amount_0.0)
+    }
+
+    pub async fn get_amounts_for_liquidity(
+        &self,
+        sqrt_price_x96: JsonU256,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity: u128,
+        round_up: bool,
+    ) -> anyhow::Result<[Balance; 2]> {
+        let args = json!({
+            "sqrt_price_x96": sqrt_price_x96,
+            "lower_tick": lower_tick,
+            "upper_tick": upper_tick,
+            "liquidity": U128(liquidity),
+            "round_up": round_up,
+        });
+        let method = "get_amounts_for_liquidity";
+        let amounts: [U128; 2] = transport::view(&self.transport, &self.id, method, args).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    pub async fn price_to_sqrt_price_x96(
+        &self,
+        price: &str,
+        decimals_0: u8,
+        decimals_1: u8,
+    ) -> anyhow::Result<JsonU256> {
+        let args = json!({ "price": price, "decimals_0": decimals_0, "decimals_1": decimals_1 });
+        transport::view(&self.transport, &self.id, "price_to_sqrt_price_x96", args).await
+    }
+
+    pub async fn sqrt_price_x96_to_price(
+        &self,
+        sqrt_price_x96: JsonU256,
+        decimals_0: u8,
+        decimals_1: u8,
+        precision: u8,
+    ) -> anyhow::Result<String> {
+        let args = json!({
+            "sqrt_price_x96": sqrt_price_x96,
+            "decimals_0": decimals_0,
+            "decimals_1": decimals_1,
+            "precision": precision,
+        });
+        transport::view(&self.transport, &self.id, "sqrt_price_x96_to_price", args).await
+    }
+
+    pub async fn price_to_nearest_usable_tick(
+        &self,
+        price: &str,
+        decimals_0: u8,
+        decimals_1: u8,
+        tick_spacing: u32,
+    ) -> anyhow::Result<i32> {
+        let args = json!({
+            "price": price,
+            "decimals_0": decimals_0,
+            "decimals_1": decimals_1,
+            "tick_spacing": tick_spacing,
+        });
+        let method = "price_to_nearest_usable_tick";
+        transport::view(&self.transport, &self.id, method, args).await
+    }
+
+    pub async fn tick_to_price(
+        &self,
+        tick: i32,
+        decimals_0: u8,
+        decimals_1: u8,
+        precision: u8,
+    ) -> anyhow::Result<String> {
+        let args = json!({
+            "tick": tick,
+            "decimals_0": decimals_0,
+            "decimals_1": decimals_1,
+            "precision": precision,
+        });
+        transport::view(&self.transport, &self.id, "tick_to_price", args).await
+    }
+
+    pub async fn get_fungible_tokens(&self) -> anyhow::Result<Vec<AccountId>> {
+        transport::view(&self.transport, &self.id, "get_fungible_tokens", json!({})).await
+    }
+
+    pub async fn get_deposit(
+        &self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+    ) -> anyhow::Result<Balance> {
+        let args = json!({ "account_id": account_id, "token_id": token_id });
+        let deposit: U128 = transport::view(&self.transport, &self.id, "get_deposit", args).await?;
+        Ok(deposit.0)
+    }
+
+    pub async fn get_deposits(
+        &self,
+        account_id: &AccountId,
+    ) -> anyhow::Result<HashMap<AccountId, Balance>> {
+        let args = json!({ "account_id": account_id });
+        let deposits: HashMap<AccountId, U128> =
+            transport::view(&self.transport, &self.id, "get_deposits", args).await?;
+        Ok(deposits
+            .into_iter()
+            .map(|(token_id, deposit)| (token_id, deposit.0))
+            .collect())
+    }
+
+    pub async fn get_token_registration_cost(
+        &self,
+        token_0: &AccountId,
+        token_1: &AccountId,
+    ) -> anyhow::Result<Balance> {
+        let args = json!({ "token_0": token_0, "token_1": token_1 });
+        let method = "get_token_registration_cost";
+        let cost: U128 = transport::view(&self.transport, &self.id, method, args).await?;
+        Ok(cost.0)
+    }
+
+    pub async fn nft_transfer(
+        &self,
+        receiver_id: &AccountId,
+        token_id: &TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) -> anyhow::Result<()> {
+        let args = json!({
+            "receiver_id": receiver_id,
+            "token_id": token_id,
+            "approval_id": approval_id,
+            "memo": memo,
+        });
+        transport::call(&self.transport, &self.id, "nft_transfer", args, ONE_YOCTO).await
+    }
+
+    /// Returns whether the receiver kept the NFT
+    pub async fn nft_transfer_call(
+        &self,
+        receiver_id: &AccountId,
+        token_id: &TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> anyhow::Result<bool> {
+        let args = json!({
+            "receiver_id": receiver_id,
+            "token_id": token_id,
+            "approval_id": approval_id,
+            "memo": memo,
+            "msg": msg,
+        });
+        transport::call(
+            &self.transport,
+            &self.id,
+            "nft_transfer_call",
+            args,
+            ONE_YOCTO,
+        )
+        .await
+    }
+
+    /// Approves `account_id` to transfer the NFT. `deposit` pays for storing the approval
+    pub async fn nft_approve(
+        &self,
+        token_id: &TokenId,
+        account_id: &AccountId,
+        msg: Option<String>,
+        deposit: Balance,
+    ) -> anyhow::Result<()> {
+        let args = json!({ "token_id": token_id, "account_id": account_id, "msg": msg });
+        let _: Option<serde_json::Value> =
+            transport::call(&self.transport, &self.id, "nft_approve", args, deposit).await?;
+        Ok(())
+    }
+
+    pub async fn nft_revoke(
+        &self,
+        token_id: &TokenId,
+        account_id: &AccountId,
+    ) -> anyhow::Result<()> {
+        let args = json!({ "token_id": token_id, "account_id": account_id });
+        transport::call(&self.transport, &self.id, "nft_revoke", args, ONE_YOCTO).await
+    }
+
+    pub async fn nft_revoke_all(&self, token_id: &TokenId) -> anyhow::Result<()> {
+        let args = json!({ "token_id": token_id });
+        transport::call(&self.transport, &self.id, "nft_revoke_all", args, ONE_YOCTO).await
+    }
+
+    pub async fn nft_is_approved(
+        &self,
+        token_id: &TokenId,
+        approved_account_id: &AccountId,
+        approval_id: Option<u64>,
+    ) -> anyhow::Result<bool> {
+        let args = json!({
+            "token_id": token_id,
+            "approved_account_id": approved_account_id,
+            "approval_id": approval_id,
+        });
+        transport::view(&self.transport, &self.id, "nft_is_approved", args).await
+    }
+
+    pub async fn nft_token(&self, token_id: &TokenId) -> anyhow::Result<Option<Token>> {
+        let args = json!({ "token_id": token_id });
+        transport::view(&self.transport, &self.id, "nft_token", args).await
+    }
+
+    pub async fn nft_total_supply(&self) -> anyhow::Result<u128> {
+        let supply: U128 =
+            transport::view(&self.transport, &self.id, "nft_total_supply", json!({})).await?;
+        Ok(supply.0)
+    }
+
+    pub async fn nft_tokens(
+        &self,
+        from_index: Option<u128>,
+        limit: Option<u64>,
+    ) -> anyhow::Result<Vec<Token>> {
+        let args = json!({ "from_index": from_index.map(U128), "limit": limit });
+        transport::view(&self.transport, &self.id, "nft_tokens", args).await
+    }
+
+    pub async fn nft_supply_for_owner(&self, account_id: &AccountId) -> anyhow::Result<u128> {
+        let args = json!({ "account_id": account_id });
+        let supply: U128 =
+            transport::view(&self.transport, &self.id, "nft_supply_for_owner", args).await?;
+        Ok(supply.0)
+    }
+
+    pub async fn nft_tokens_for_owner(
+        &self,
+        account_id: &AccountId,
+        from_index: Option<u128>,
+        limit: Option<u64>,
+    ) -> anyhow::Result<Vec<Token>> {
+        let args = json!({
+            "account_id": account_id,
+            "from_index": from_index.map(U128),
+            "limit": limit,
+        });
+        transport::view(&self.transport, &self.id, "nft_tokens_for_owner", args).await
+    }
+
+    pub async fn nft_metadata(&self) -> anyhow::Result<NFTContractMetadata> {
+        transport::view(&self.transport, &self.id, "nft_metadata", json!({})).await
+    }
+
+    /// The positions held by `account_id`, as `(nft_id, info)` read from the NFT metadata
+    pub async fn positions(
+        &self,
+        account_id: &AccountId,
+    ) -> anyhow::Result<Vec<(TokenId, NftLiquidityInfo)>> {
+        self.nft_tokens_for_owner(account_id, None, None)
+            .await?
+            .into_iter()
+            .map(|token| {
+                let extra = token
+                    .metadata
+                    .and_then(|metadata| metadata.extra)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("NFT {} has no liquidity info", token.token_id)
+                    })?;
+                Ok((token.token_id, serde_json::from_str(&extra)?))
+            })
+            .collect()
+    }
+
+    /// Registers `account_id`, or the caller, in the manager or adds `deposit` to their storage balance
+    pub async fn storage_deposit(
+        &self,
+        account_id: Option<&AccountId>,
+        deposit: Balance,
+    ) -> anyhow::Result<StorageBalance> {
+        let args = json!({ "account_id": account_id });
+        transport::call(&self.transport, &self.id, "storage_deposit", args, deposit).await
+    }
+
+    /// Withdraws `amount` of the caller's available storage balance, or all of it
+    pub async fn storage_withdraw(
+        &self,
+        amount: Option<Balance>,
+    ) -> anyhow::Result<StorageBalance> {
+        let args = json!({ "amount": amount.map(U128) });
+        transport::call(
+            &self.transport,
+            &self.id,
+            "storage_withdraw",
+            args,
+            ONE_YOCTO,
+        )
+        .await
+    }
+
+    /// Unregisters the caller, who must not hold anything in the manager anymore
+    pub async fn storage_unregister(&self) -> anyhow::Result<bool> {
+        let args = json!({});
+        transport::call(
+            &self.transport,
+            &self.id,
+            "storage_unregister",
+            args,
+            ONE_YOCTO,
+        )
+        .await
+    }
+
+    pub async fn storage_balance_of(
+        &self,
+        account_id: &AccountId,
+    ) -> anyhow::Result<Option<StorageBalance>> {
+        let args = json!({ "account_id": account_id });
+        transport::view(&self.transport, &self.id, "storage_balance_of", args).await
+    }
+
+    pub async fn storage_balance_bounds(&self) -> anyhow::Result<StorageBalanceBounds> {
+        transport::view(
+            &self.transport,
+            &self.id,
+            "storage_balance_bounds",
+            json!({}),
+        )
+        .await
+    }
+
+    fn token(&self, token_id: &AccountId) -> TokenClient<&T> {
+        TokenClient::new(&self.transport, token_id.clone())
+    }
+}
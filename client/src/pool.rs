@@ -0,0 +1,388 @@
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
+use near_sdk::json_types::{I128, U128, U64};
+use near_sdk::serde_json::{self, json};
+use near_sdk::{AccountId, Balance, ONE_YOCTO};
+use zswap_math_library::json_types::JsonU256;
+use zswap_pool::ft_receiver::TokenReceiverMessage;
+use zswap_pool::utils::{LimitOrderEpochView, PlacedLimitOrder, PositionView, Slot0, TickView};
+
+use crate::token::TokenClient;
+use crate::transport::{self, Transport};
+
+/// Pools are deployed by the factory, look their id up with `FactoryClient::get_pool`
+#[derive(Clone, Debug)]
+pub struct PoolClient<T> {
+    transport: T,
+    id: AccountId,
+}
+
+impl<T: Transport> PoolClient<T> {
+    pub fn new(transport: T, id: AccountId) -> Self {
+        Self { transport, id }
+    }
+
+    pub fn id(&self) -> &AccountId {
+        &self.id
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Sets the price of a pool created without one
+    pub async fn initialize(&self, sqrt_price_x96: JsonU256) -> anyhow::Result<()> {
+        let args = json!({ "sqrt_price_x96": sqrt_price_x96 });
+        transport::call(&self.transport, &self.id, "initialize", args, 0).await
+    }
+
+    /// Adds `amount` of liquidity for `recipient`, paid from `payer`'s deposit. Callers other
+    /// than the payer need an allowance. `deposit` is added to the recipient's storage balance.
+    /// Returns the amounts of token 0 and token 1 taken
+    pub async fn mint(
+        &self,
+        payer: &AccountId,
+        recipient: &AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount: u128,
+        deposit: Balance,
+    ) -> anyhow::Result<[Balance; 2]> {
+        let args = json!({
+            "payer": payer,
+            "recipient": recipient,
+            "lower_tick": lower_tick,
+            "upper_tick": upper_tick,
+            "amount": U128(amount),
+        });
+        let amounts: [U128; 2] =
+            transport::call(&self.transport, &self.id, "mint", args, deposit).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    /// Swaps from the caller's deposit and returns the pool's token 0 and token 1 deltas, the
+    /// negative one being what `recipient` received. Fails below `amount_out_min`
+    pub async fn swap(
+        &self,
+        recipient: &AccountId,
+        zero_for_one: bool,
+        amount_specified: Balance,
+        sqrt_price_limit_x96: Option<JsonU256>,
+        amount_out_min: Balance,
+    ) -> anyhow::Result<[i128; 2]> {
+        let args = json!({
+            "recipient": recipient,
+            "zero_for_one": zero_for_one,
+            "amount_specified": U128(amount_specified),
+            "sqrt_price_limit_x96": sqrt_price_limit_x96,
+            "amount_out_min": U128(amount_out_min),
+        });
+        let amounts: [I128; 2] =
+            transport::call(&self.transport, &self.id, "swap", args, 0).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    /// Removes `amount` of the caller's liquidity and returns the amounts of token 0 and token 1
+    /// it owes them, to `collect` afterwards
+    pub async fn burn(
+        &self,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount: u128,
+    ) -> anyhow::Result<[Balance; 2]> {
+        let args = json!({
+            "lower_tick": lower_tick,
+            "upper_tick": upper_tick,
+            "amount": U128(amount),
+        });
+        let amounts: [U128; 2] =
+            transport::call(&self.transport, &self.id, "burn", args, 0).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    /// Sends up to the requested amounts owed to the caller's position to `recipient`
+    pub async fn collect(
+        &self,
+        recipient: &AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount_0_requested: Balance,
+        amount_1_requested: Balance,
+    ) -> anyhow::Result<[Balance; 2]> {
+        let args = json!({
+            "recipient": recipient,
+            "lower_tick": lower_tick,
+            "upper_tick": upper_tick,
+            "amount_0_requested": U128(amount_0_requested),
+            "amount_1_requested": U128(amount_1_requested),
+        });
+        let amounts: [U128; 2] =
+            transport::call(&self.transport, &self.id, "collect", args, 0).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    /// Places a limit order selling `amount_in` of token 0 (`zero_for_one`) or token 1 from
+    /// `payer`'s deposit on `[lower_tick, lower_tick + tick_spacing]`. `deposit` is added to the
+    /// recipient's storage balance
+    pub async fn mint_limit_order(
+        &self,
+        payer: &AccountId,
+        recipient: &AccountId,
+        lower_tick: i32,
+        zero_for_one: bool,
+        amount_in: Balance,
+        deposit: Balance,
+    ) -> anyhow::Result<PlacedLimitOrder> {
+        let args = json!({
+            "payer": payer,
+            "recipient": recipient,
+            "lower_tick": lower_tick,
+            "zero_for_one": zero_for_one,
+            "amount_in": U128(amount_in),
+        });
+        transport::call(&self.transport, &self.id, "mint_limit_order", args, deposit).await
+    }
+
+    /// Cancels `liquidity` of the caller's order in an unfilled epoch, sending what it holds to
+    /// `recipient`
+    pub async fn burn_limit_order(
+        &self,
+        recipient: &AccountId,
+        epoch: u64,
+        liquidity: u128,
+    ) -> anyhow::Result<[Balance; 2]> {
+        let args = json!({
+            "recipient": recipient,
+            "epoch": U64(epoch),
+            "liquidity": U128(liquidity),
+        });
+        let amounts: [U128; 2] =
+            transport::call(&self.transport, &self.id, "burn_limit_order", args, 0).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    /// Sends the caller's share of a filled epoch, for `liquidity` of its orders, to `recipient`
+    pub async fn collect_limit_order(
+        &self,
+        recipient: &AccountId,
+        epoch: u64,
+        liquidity: u128,
+    ) -> anyhow::Result<[Balance; 2]> {
+        let args = json!({
+            "recipient": recipient,
+            "epoch": U64(epoch),
+            "liquidity": U128(liquidity),
+        });
+        let amounts: [U128; 2] =
+            transport::call(&self.transport, &self.id, "collect_limit_order", args, 0).await?;
+        Ok(amounts.map(|amount| amount.0))
+    }
+
+    pub async fn get_limit_order_epoch(
+        &self,
+        epoch: u64,
+    ) -> anyhow::Result<Option<LimitOrderEpochView>> {
+        let args = json!({ "epoch": U64(epoch) });
+        transport::view(&self.transport, &self.id, "get_limit_order_epoch", args).await
+    }
+
+    pub async fn get_limit_order_liquidity(
+        &self,
+        owner: &AccountId,
+        epoch: u64,
+    ) -> anyhow::Result<u128> {
+        let args = json!({ "owner": owner, "epoch": U64(epoch) });
+        let liquidity: U128 =
+            transport::view(&self.transport, &self.id, "get_limit_order_liquidity", args).await?;
+        Ok(liquidity.0)
+    }
+
+    pub async fn get_slot_0(&self) -> anyhow::Result<Slot0> {
+        transport::view(&self.transport, &self.id, "get_slot_0", json!({})).await
+    }
+
+    pub async fn get_hook(&self) -> anyhow::Result<Option<AccountId>> {
+        transport::view(&self.transport, &self.id, "get_hook", json!({})).await
+    }
+
+    pub async fn is_paused(&self) -> anyhow::Result<bool> {
+        transport::view(&self.transport, &self.id, "is_paused", json!({})).await
+    }
+
+    pub async fn get_liquidity(&self) -> anyhow::Result<u128> {
+        let liquidity: U128 =
+            transport::view(&self.transport, &self.id, "get_liquidity", json!({})).await?;
+        Ok(liquidity.0)
+    }
+
+    pub async fn get_tick(&self, tick: i32) -> anyhow::Result<Option<TickView>> {
+        let args = json!({ "tick": tick });
+        transport::view(&self.transport, &self.id, "get_tick", args).await
+    }
+
+    /// The initialized ticks between `lower_tick` and `upper_tick`, both included
+    pub async fn get_ticks(
+        &self,
+        lower_tick: i32,
+        upper_tick: i32,
+    ) -> anyhow::Result<Vec<TickView>> {
+        let args = json!({ "lower_tick": lower_tick, "upper_tick": upper_tick });
+        transport::view(&self.transport, &self.id, "get_ticks", args).await
+    }
+
+    pub async fn get_position(
+        &self,
+        owner: &AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+    ) -> anyhow::Result<Option<PositionView>> {
+        let args = json!({ "owner": owner, "lower_tick": lower_tick, "upper_tick": upper_tick });
+        transport::view(&self.transport, &self.id, "get_position", args).await
+    }
+
+    /// Deposits `amount` of the token into the pool with an empty `ft_transfer_call` message
+    pub async fn deposit(&self, token_id: &AccountId, amount: Balance) -> anyhow::Result<Balance> {
+        self.token(token_id)
+            .ft_transfer_call(&self.id, amount, String::new())
+            .await
+    }
+
+    /// Deposits `amount` of the token and adds it to `account_id`'s allowance with an
+    /// `Approve` message
+    pub async fn deposit_and_approve(
+        &self,
+        token_id: &AccountId,
+        amount: Balance,
+        account_id: &AccountId,
+    ) -> anyhow::Result<Balance> {
+        let msg = TokenReceiverMessage::Approve {
+            account_id: account_id.clone(),
+        };
+        self.token(token_id)
+            .ft_transfer_call(&self.id, amount, serde_json::to_string(&msg)?)
+            .await
+    }
+
+    /// Swaps `amount_in` straight from the wallet with a `Swap` message, the output is sent to
+    /// `recipient`. Returns the input amount the pool kept
+    pub async fn swap_from_wallet(
+        &self,
+        token_in: &AccountId,
+        amount_in: Balance,
+        zero_for_one: bool,
+        sqrt_price_limit_x96: Option<JsonU256>,
+        amount_out_min: Balance,
+        recipient: &AccountId,
+    ) -> anyhow::Result<Balance> {
+        let msg = TokenReceiverMessage::Swap {
+            zero_for_one,
+            sqrt_price_limit_x96,
+            amount_out_min: U128(amount_out_min),
+            recipient: recipient.clone(),
+        };
+        self.token(token_in)
+            .ft_transfer_call(&self.id, amount_in, serde_json::to_string(&msg)?)
+            .await
+    }
+
+    /// Withdraws deposited tokens to the caller's wallet
+    pub async fn withdraw(&self, token_id: &AccountId, amount: Balance) -> anyhow::Result<()> {
+        let args = json!({ "token_id": token_id, "amount": U128(amount) });
+        transport::call(&self.transport, &self.id, "withdraw", args, ONE_YOCTO).await
+    }
+
+    /// Sets the amount of the caller's deposit `account_id` can spend
+    pub async fn approve(
+        &self,
+        token_id: &AccountId,
+        account_id: &AccountId,
+        amount: Balance,
+    ) -> anyhow::Result<()> {
+        let args = json!({
+            "token_id": token_id,
+            "account_id": account_id,
+            "amount": U128(amount),
+        });
+        transport::call(&self.transport, &self.id, "approve", args, ONE_YOCTO).await
+    }
+
+    pub async fn revoke(&self, token_id: &AccountId, account_id: &AccountId) -> anyhow::Result<()> {
+        let args = json!({ "token_id": token_id, "account_id": account_id });
+        transport::call(&self.transport, &self.id, "revoke", args, ONE_YOCTO).await
+    }
+
+    pub async fn allowance(
+        &self,
+        token_id: &AccountId,
+        owner_id: &AccountId,
+        account_id: &AccountId,
+    ) -> anyhow::Result<Balance> {
+        let args = json!({
+            "token_id": token_id,
+            "owner_id": owner_id,
+            "account_id": account_id,
+        });
+        let allowance: U128 = transport::view(&self.transport, &self.id, "allowance", args).await?;
+        Ok(allowance.0)
+    }
+
+    /// Registers `account_id`, or the caller, in the pool or adds `deposit` to their storage balance
+    pub async fn storage_deposit(
+        &self,
+        account_id: Option<&AccountId>,
+        deposit: Balance,
+    ) -> anyhow::Result<StorageBalance> {
+        let args = json!({ "account_id": account_id });
+        transport::call(&self.transport, &self.id, "storage_deposit", args, deposit).await
+    }
+
+    /// Withdraws `amount` of the caller's available storage balance, or all of it
+    pub async fn storage_withdraw(
+        &self,
+        amount: Option<Balance>,
+    ) -> anyhow::Result<StorageBalance> {
+        let args = json!({ "amount": amount.map(U128) });
+        transport::call(
+            &self.transport,
+            &self.id,
+            "storage_withdraw",
+            args,
+            ONE_YOCTO,
+        )
+        .await
+    }
+
+    /// Unregisters the caller, who must not hold anything in the pool anymore
+    pub async fn storage_unregister(&self) -> anyhow::Result<bool> {
+        let args = json!({});
+        transport::call(
+            &self.transport,
+            &self.id,
+            "storage_unregister",
+            args,
+            ONE_YOCTO,
+        )
+        .await
+    }
+
+    pub async fn storage_balance_of(
+        &self,
+        account_id: &AccountId,
+    ) -> anyhow::Result<Option<StorageBalance>> {
+        let args = json!({ "account_id": account_id });
+        transport::view(&self.transport, &self.id, "storage_balance_of", args).await
+    }
+
+    pub async fn storage_balance_bounds(&self) -> anyhow::Result<StorageBalanceBounds> {
+        transport::view(
+            &self.transport,
+            &self.id,
+            "storage_balance_bounds",
+            json!({}),
+        )
+        .await
+    }
+
+    fn token(&self, token_id: &AccountId) -> TokenClient<&T> {
+        TokenClient::new(&self.transport, token_id.clone())
+    }
+}
@@ -0,0 +1,66 @@
+use async_trait::async_trait;
+use near_sdk::{AccountId, Balance, Gas};
+use workspaces::{Account, Contract};
+
+use crate::transport::Transport;
+
+/// Signs as the account, through the worker it was created by
+#[async_trait]
+impl Transport for Account {
+    async fn call(
+        &self,
+        contract_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+        deposit: Balance,
+        gas: Gas,
+    ) -> anyhow::Result<Vec<u8>> {
+        let contract_id: workspaces::AccountId = contract_id.as_str().parse()?;
+        let result = Account::call(self, &contract_id, method)
+            .args(args)
+            .deposit(deposit)
+            .gas(gas.0)
+            .transact()
+            .await?
+            .into_result()?;
+        Ok(result.raw_bytes()?)
+    }
+
+    async fn view(
+        &self,
+        contract_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let contract_id: workspaces::AccountId = contract_id.as_str().parse()?;
+        let result = Account::call(self, &contract_id, method)
+            .args(args)
+            .view()
+            .await?;
+        Ok(result.result)
+    }
+}
+
+/// Signs as the contract's own account, e.g. to call its initializer
+#[async_trait]
+impl Transport for Contract {
+    async fn call(
+        &self,
+        contract_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+        deposit: Balance,
+        gas: Gas,
+    ) -> anyhow::Result<Vec<u8>> {
+        Transport::call(self.as_account(), contract_id, method, args, deposit, gas).await
+    }
+
+    async fn view(
+        &self,
+        contract_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+    ) -> anyhow::Result<Vec<u8>> {
+        Transport::view(self.as_account(), contract_id, method, args).await
+    }
+}
@@ -0,0 +1,72 @@
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{AccountId, Balance, ONE_YOCTO};
+
+use crate::transport::{self, Transport};
+
+/// The NEP-141 and NEP-145 methods needed to fund the contracts
+#[derive(Clone, Debug)]
+pub struct TokenClient<T> {
+    transport: T,
+    id: AccountId,
+}
+
+impl<T: Transport> TokenClient<T> {
+    pub fn new(transport: T, id: AccountId) -> Self {
+        Self { transport, id }
+    }
+
+    pub fn id(&self) -> &AccountId {
+        &self.id
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Registers `account_id`, or the caller, in the token
+    pub async fn storage_deposit(
+        &self,
+        account_id: Option<&AccountId>,
+        deposit: Balance,
+    ) -> anyhow::Result<StorageBalance> {
+        let args = json!({ "account_id": account_id });
+        transport::call(&self.transport, &self.id, "storage_deposit", args, deposit).await
+    }
+
+    pub async fn ft_transfer(
+        &self,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) -> anyhow::Result<()> {
+        let args = json!({ "receiver_id": receiver_id, "amount": U128(amount) });
+        transport::call(&self.transport, &self.id, "ft_transfer", args, ONE_YOCTO).await
+    }
+
+    /// Returns the amount the receiver kept
+    pub async fn ft_transfer_call(
+        &self,
+        receiver_id: &AccountId,
+        amount: Balance,
+        msg: String,
+    ) -> anyhow::Result<Balance> {
+        let args = json!({ "receiver_id": receiver_id, "amount": U128(amount), "msg": msg });
+        let used_amount: U128 = transport::call(
+            &self.transport,
+            &self.id,
+            "ft_transfer_call",
+            args,
+            ONE_YOCTO,
+        )
+        .await?;
+        Ok(used_amount.0)
+    }
+
+    pub async fn ft_balance_of(&self, account_id: &AccountId) -> anyhow::Result<Balance> {
+        let args = json!({ "account_id": account_id });
+        let balance: U128 =
+            transport::view(&self.transport, &self.id, "ft_balance_of", args).await?;
+        Ok(balance.0)
+    }
+}
@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use near_sdk::serde::de::DeserializeOwned;
+use near_sdk::serde_json::{self, Value};
+use near_sdk::{AccountId, Balance, Gas};
+
+/// Gas attached to every change call, the most a transaction can use
+pub const MAX_GAS: Gas = Gas(300_000_000_000_000);
+
+/// Sends the clients' calls to the chain.
+///
+/// A transport signs as one account. `call` has to wait for every receipt the call spawns and
+/// fail if any of them failed.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Submits a function call and returns the value it finally resolved to
+    async fn call(
+        &self,
+        contract_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+        deposit: Balance,
+        gas: Gas,
+    ) -> anyhow::Result<Vec<u8>>;
+
+    async fn view(
+        &self,
+        contract_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+    ) -> anyhow::Result<Vec<u8>>;
+}
+
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for &T {
+    async fn call(
+        &self,
+        contract_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+        deposit: Balance,
+        gas: Gas,
+    ) -> anyhow::Result<Vec<u8>> {
+        (**self).call(contract_id, method, args, deposit, gas).await
+    }
+
+    async fn view(
+        &self,
+        contract_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+    ) -> anyhow::Result<Vec<u8>> {
+        (**self).view(contract_id, method, args).await
+    }
+}
+
+pub(crate) async fn call<T: Transport, R: DeserializeOwned>(
+    transport: &T,
+    contract_id: &AccountId,
+    method: &str,
+    args: Value,
+    deposit: Balance,
+) -> anyhow::Result<R> {
+    let result = transport
+        .call(
+            contract_id,
+            method,
+            serde_json::to_vec(&args)?,
+            deposit,
+            MAX_GAS,
+        )
+        .await?;
+    decode(result)
+}
+
+pub(crate) async fn view<T: Transport, R: DeserializeOwned>(
+    transport: &T,
+    contract_id: &AccountId,
+    method: &str,
+    args: Value,
+) -> anyhow::Result<R> {
+    let result = transport
+        .view(contract_id, method, serde_json::to_vec(&args)?)
+        .await?;
+    decode(result)
+}
+
+/// Methods returning nothing resolve to an empty value, decoded as `null`
+fn decode<R: DeserializeOwned>(result: Vec<u8>) -> anyhow::Result<R> {
+    let result = if result.is_empty() {
+        b"null".to_vec()
+    } else {
+        result
+    };
+    Ok(serde_json::from_slice(&result)?)
+}
@@ -22,7 +22,8 @@ impl Contract {
         token_0: AccountId,
         token_1: AccountId,
         fee: u32,
-        sqrt_price_x96: U128,
+        sqrt_price_x96: JsonU256,
+        hook: Option<AccountId>,
     ) -> Promise {
         let tick_spacing_opt = self.fees.get(&fee);
         if tick_spacing_opt.is_none() {
@@ -43,23 +44,22 @@ impl Contract {
             Ordering::Equal => env::panic_str(TOKENS_MUST_BE_DIFFERENT),
         }
 
-        let subaccount = pool_account::compute_account(
+        let subaccount = pool_account::compute_hooked_account(
             &env::current_account_id(),
             &ordered_token_0,
             &ordered_token_1,
             fee,
+            hook.as_ref(),
         );
 
         if !env::is_valid_account_id(subaccount.as_bytes()) {
             env::panic_str(INVALID_SUBACCOUNT);
         }
 
-        if !self.pools.get(&subaccount).unwrap_or_default() {
+        if !self.pools.contains(&subaccount) {
             env::panic_str("Pool does not exist");
         }
 
-        self.pools.insert(&subaccount, &true);
-
         // Assert enough money is attached to create the account and deploy the contract
         let attached = env::attached_deposit();
 
@@ -73,28 +73,20 @@ impl Contract {
 
         log!("Signer Public Key {:?}", env::signer_account_pk());
 
-        let promise = Promise::new(subaccount.clone())
+        // private, so the deposit of a failed redeploy comes back to the factory itself
+        Promise::new(subaccount.clone())
             .create_account()
             .add_full_access_key(env::signer_account_pk())
             .transfer(attached)
             .deploy_contract(code)
-            .and(ext_zswap_pool::ext(subaccount.clone()).new(
-                ordered_token_0.clone(),
-                ordered_token_1.clone(),
+            .and(ext_zswap_pool::ext(subaccount).new(
+                ordered_token_0,
+                ordered_token_1,
                 tick_spacing_opt.unwrap(),
                 fee,
                 sqrt_price_x96,
-            ));
-
-        // Add callback
-        promise.then(
-            Self::ext(env::current_account_id()).create_factory_subaccount_and_deploy_callback(
-                subaccount,
-                env::predecessor_account_id(),
-                ordered_token_0,
-                ordered_token_1,
-                attached,
-            ),
-        )
+                self.dynamic_fees.get(&fee),
+                hook,
+            ))
     }
 }
@@ -2,3 +2,14 @@ pub const POOL_ALREADY_EXISTS: &str = "Pool already exists";
 pub const TOKENS_MUST_BE_DIFFERENT: &str = "Tokens must be different";
 pub const UNSUPPORTED_FEE: &str = "Unsupported fee";
 pub const INVALID_SUBACCOUNT: &str = "Invalid subaccount";
+pub const NOT_ENOUGH_DEPOSIT: &str = "Not enough deposit";
+pub const FT_STORAGE_BOUNDS_FAILED: &str = "Failed to read the tokens' storage balance bounds";
+pub const FEE_ALREADY_ENABLED: &str = "Fee already enabled";
+pub const INVALID_FEE: &str = "Fee must be below 1000000";
+pub const INVALID_TICK_SPACING: &str = "Tick spacing must be between 1 and 16383";
+pub const INVALID_MAX_FEE: &str = "Max fee must be between the fee and 1000000";
+pub const POOL_NOT_FOUND: &str = "Pool not found";
+pub const NOT_GUARDIAN: &str = "Only the guardian can pause and unpause pools";
+pub const PAUSE_FAILED: &str = "Failed to pause pool";
+pub const UNPAUSE_FAILED: &str = "Failed to unpause pool";
+pub const NOTHING_TO_MIGRATE: &str = "No state to migrate";
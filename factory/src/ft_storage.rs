@@ -1,4 +1,4 @@
-use near_contract_standards::storage_management::StorageBalance;
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
 use near_sdk::{ext_contract, AccountId};
 
 #[ext_contract(ext_ft_storage)]
@@ -8,4 +8,6 @@ pub trait StorageManagement {
         account_id: Option<AccountId>,
         registration_only: Option<bool>,
     ) -> StorageBalance;
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds;
 }
@@ -0,0 +1,138 @@
+use near_sdk::{env, log, near_bindgen, AccountId, Promise, PromiseError};
+
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the account allowed to pause and unpause pools, or removes it with `None`
+    #[private]
+    pub fn set_guardian(&mut self, guardian: Option<AccountId>) {
+        match &guardian {
+            Some(guardian) => log!("Guardian set to {}", guardian),
+            None => log!("Guardian removed"),
+        }
+        self.guardian = guardian;
+    }
+
+    pub fn get_guardian(&self) -> Option<AccountId> {
+        self.guardian.clone()
+    }
+
+    /// Stops the pool's swaps and mints. Burns, collects and withdrawals keep working
+    pub fn pause_pool(&mut self, pool_id: AccountId) -> Promise {
+        self.assert_guardian();
+        if !self.pools.contains(&pool_id) {
+            env::panic_str(POOL_NOT_FOUND);
+        }
+        Self::internal_set_paused(pool_id, true)
+    }
+
+    pub fn unpause_pool(&mut self, pool_id: AccountId) -> Promise {
+        self.assert_guardian();
+        if !self.pools.contains(&pool_id) {
+            env::panic_str(POOL_NOT_FOUND);
+        }
+        Self::internal_set_paused(pool_id, false)
+    }
+
+    /// Pauses up to `limit` pools from `from_index`, in creation order. With many pools, pausing
+    /// them all takes one call per page of `get_pool_count`
+    pub fn pause_all_pools(&mut self, from_index: Option<u64>, limit: Option<u64>) {
+        self.assert_guardian();
+        self.internal_set_all_paused(from_index, limit, true)
+    }
+
+    pub fn unpause_all_pools(&mut self, from_index: Option<u64>, limit: Option<u64>) {
+        self.assert_guardian();
+        self.internal_set_all_paused(from_index, limit, false)
+    }
+
+    pub fn get_pool_count(&self) -> u64 {
+        self.pools.len()
+    }
+
+    /// Logs the pools that couldn't be paused or unpaused, as `pause_all_pools` doesn't wait
+    /// for them. Returns whether the pool was
+    #[private]
+    pub fn set_paused_callback(
+        &self,
+        #[callback_result] result: Result<(), PromiseError>,
+        pool_id: AccountId,
+        paused: bool,
+    ) -> bool {
+        if result.is_err() {
+            let error = if paused { PAUSE_FAILED } else { UNPAUSE_FAILED };
+            log!("{} {}", error, pool_id);
+            return false;
+        }
+        true
+    }
+}
+
+impl Contract {
+    fn assert_guardian(&self) {
+        let caller = env::predecessor_account_id();
+        if caller != env::current_account_id() && Some(caller) != self.guardian {
+            env::panic_str(NOT_GUARDIAN);
+        }
+    }
+
+    fn internal_set_all_paused(&self, from_index: Option<u64>, limit: Option<u64>, paused: bool) {
+        let pool_ids = self
+            .pools
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.map_or(usize::MAX, |limit| limit as usize));
+        for pool_id in pool_ids {
+            Self::internal_set_paused(pool_id, paused);
+        }
+    }
+
+    fn internal_set_paused(pool_id: AccountId, paused: bool) -> Promise {
+        let action = if paused { "Pausing" } else { "Unpausing" };
+        log!(
+            "{} pool {} by {}",
+            action,
+            pool_id,
+            env::predecessor_account_id()
+        );
+        ext_zswap_pool::ext(pool_id.clone())
+            .set_paused(paused)
+            .then(Self::ext(env::current_account_id()).set_paused_callback(pool_id, paused))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::{get_logs, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    #[test]
+    fn test_set_paused_callback_logs_failed_pools() {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account("factory.testnet"))
+            .predecessor_account_id(account("factory.testnet"))
+            .build());
+        let contract = Contract::new();
+        let pool_id = account("2e4e39194a383739.factory.testnet");
+
+        assert!(contract.set_paused_callback(Ok(()), pool_id.clone(), true));
+        assert!(get_logs().is_empty());
+
+        assert!(!contract.set_paused_callback(Err(PromiseError::Failed), pool_id.clone(), true));
+        assert!(!contract.set_paused_callback(Err(PromiseError::Failed), pool_id, false));
+        assert_eq!(
+            get_logs(),
+            vec![
+                "Failed to pause pool 2e4e39194a383739.factory.testnet",
+                "Failed to unpause pool 2e4e39194a383739.factory.testnet",
+            ]
+        );
+    }
+}
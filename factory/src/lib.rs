@@ -1,11 +1,15 @@
+use near_contract_standards::storage_management::StorageBalanceBounds;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::{LazyOption, LookupMap, UnorderedSet};
 use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
     env, log, near_bindgen, AccountId, Balance, BorshStorageKey, PanicOnDefault, Promise,
-    PromiseResult,
+    PromiseError, PromiseOrValue, PromiseResult, StorageUsage,
 };
+use schemars::JsonSchema;
 use std::cmp::Ordering;
+use zswap_math_library::json_types::JsonU256;
 use zswap_math_library::pool_account;
 
 use crate::ft_storage::ext_ft_storage;
@@ -14,14 +18,35 @@ use pool::*;
 
 mod error;
 mod ft_storage;
+mod guardian;
+mod migrate;
 pub mod pool;
 
 mod dev; // Disable this line on production
 
 const NEAR_PER_STORAGE: Balance = 10_000_000_000_000_000_000; // 10e18yⓃ
+/// Quoted by `get_pool_creation_cost` for each token's registration, the pool pays the token's
+/// actual `storage_balance_bounds` minimum and the rest is refunded
 const FT_STORAGE_DEPOSIT: Balance = 1500 * NEAR_PER_STORAGE;
+// Account record and a full access key with the longest (secp256k1) public key, as counted by
+// the runtime's storage usage config: 100 bytes per account, 40 per record
+const POOL_ACCOUNT_STORAGE: StorageUsage = 100 + 40 + 65 + 9;
+// The factory's own records of a pool with a 64-character id and of its two tokens
+const FACTORY_POOL_STORAGE: StorageUsage = 708;
 const ZSWAP_POOL_CONTRACT: &[u8] = include_bytes!("../../res/zswap_pool.wasm");
 
+/// A pool being created, passed along the creation callbacks
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolCreation {
+    pub pool_id: AccountId,
+    pub token_0: AccountId,
+    pub token_1: AccountId,
+    pub deployer: AccountId,
+    /// Deposit attached to `create_pool`
+    pub attached: U128,
+}
+
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Pool {
     pub token_0: AccountId,
@@ -34,19 +59,25 @@ pub struct Pool {
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
     fees: LookupMap<u32, u32>,
-    pools: LookupMap<AccountId, bool>,
+    // max fee of the dynamic fee tiers, keyed by their fee at rest
+    dynamic_fees: LookupMap<u32, u32>,
+    pools: UnorderedSet<AccountId>,
     tokens: UnorderedSet<AccountId>,
     // Since a contract is something big to store, we use LazyOptions
     // this way it is not deserialized on each method call
     code: LazyOption<Vec<u8>>,
+    // can pause and unpause pools, besides the factory itself
+    guardian: Option<AccountId>,
 }
 
+// new keys go last, deployed factories keep their collections under the earlier ones
 #[derive(BorshStorageKey, BorshSerialize)]
 pub(crate) enum StorageKey {
     Fees,
     Pools,
     Tokens,
     Code,
+    DynamicFees,
 }
 
 #[near_bindgen]
@@ -59,19 +90,24 @@ impl Contract {
 
         Self {
             fees,
-            pools: LookupMap::new(StorageKey::Pools),
+            dynamic_fees: LookupMap::new(StorageKey::DynamicFees),
+            pools: UnorderedSet::new(StorageKey::Pools),
             tokens: UnorderedSet::new(StorageKey::Tokens),
             code: LazyOption::new(StorageKey::Code, Some(&ZSWAP_POOL_CONTRACT.to_vec())),
+            guardian: None,
         }
     }
 
+    /// Creates the pool of `token_0` and `token_1` with `fee`, calling `hook` around its swaps and
+    /// liquidity changes if set. A pool with a hook is a different pool than the one without
     #[payable]
     pub fn create_pool(
         &mut self,
         token_0: AccountId,
         token_1: AccountId,
         fee: u32,
-        sqrt_price_x96: U128,
+        sqrt_price_x96: JsonU256,
+        hook: Option<AccountId>,
     ) -> Promise {
         let tick_spacing_opt = self.fees.get(&fee);
         if tick_spacing_opt.is_none() {
@@ -92,122 +128,219 @@ impl Contract {
             Ordering::Equal => env::panic_str(TOKENS_MUST_BE_DIFFERENT),
         }
 
-        let subaccount = pool_account::compute_account(
+        let subaccount = pool_account::compute_hooked_account(
             &env::current_account_id(),
-            &ordered_token_0.clone(),
-            &ordered_token_1.clone(),
+            &ordered_token_0,
+            &ordered_token_1,
             fee,
+            hook.as_ref(),
         );
 
         if !env::is_valid_account_id(subaccount.as_bytes()) {
             env::panic_str(INVALID_SUBACCOUNT);
         }
 
-        if self.pools.get(&subaccount).unwrap_or_default() {
+        if self.pools.contains(&subaccount) {
             env::panic_str(POOL_ALREADY_EXISTS);
         }
 
-        self.pools.insert(&subaccount, &true);
-        self.tokens.insert(&ordered_token_0);
-        self.tokens.insert(&ordered_token_1);
-
-        // Assert enough money is attached to create the account and deploy the contract
+        // Assert enough money is attached to create the account, deploy the contract and record
+        // the pool. The tokens' registration is checked once their storage bounds are known
         let attached = env::attached_deposit();
-
-        let code = self.code.get().unwrap();
-        let contract_bytes = code.len() as u128;
-        let minimum_needed = NEAR_PER_STORAGE * contract_bytes;
-
+        let minimum_needed = self.internal_pool_creation_cost();
         if attached < minimum_needed {
-            env::panic_str(&format!("Attach at least {} yⓃ", minimum_needed));
+            env::panic_str(&format!(
+                "{}: attach at least {} yⓃ",
+                NOT_ENOUGH_DEPOSIT,
+                minimum_needed + FT_STORAGE_DEPOSIT * 2
+            ));
         }
 
-        let pool_storage_staking = attached - FT_STORAGE_DEPOSIT * 2;
+        // reserved now so that the pool can't be created twice, released if creation fails
+        self.pools.insert(&subaccount);
+
+        let creation = PoolCreation {
+            pool_id: subaccount,
+            token_0: ordered_token_0.clone(),
+            token_1: ordered_token_1.clone(),
+            deployer: env::predecessor_account_id(),
+            attached: U128(attached),
+        };
+        ext_ft_storage::ext(ordered_token_0)
+            .storage_balance_bounds()
+            .and(ext_ft_storage::ext(ordered_token_1).storage_balance_bounds())
+            .then(
+                Self::ext(env::current_account_id()).create_pool_with_storage_bounds(
+                    creation,
+                    fee,
+                    sqrt_price_x96,
+                    hook,
+                ),
+            )
+    }
+
+    #[private]
+    pub fn create_pool_with_storage_bounds(
+        &mut self,
+        #[callback_result] bounds_0_res: Result<StorageBalanceBounds, PromiseError>,
+        #[callback_result] bounds_1_res: Result<StorageBalanceBounds, PromiseError>,
+        creation: PoolCreation,
+        fee: u32,
+        sqrt_price_x96: JsonU256,
+        hook: Option<AccountId>,
+    ) -> PromiseOrValue<Option<AccountId>> {
+        let (bounds_0, bounds_1) = match (bounds_0_res, bounds_1_res) {
+            (Ok(bounds_0), Ok(bounds_1)) => (bounds_0, bounds_1),
+            _ => {
+                log!("{}", FT_STORAGE_BOUNDS_FAILED);
+                self.internal_cancel_pool_creation(creation, 0);
+                return PromiseOrValue::Value(None);
+            }
+        };
+
+        let storage_deposits = [bounds_0.min, bounds_1.min];
+        let minimum_needed =
+            self.internal_pool_creation_cost() + storage_deposits[0].0 + storage_deposits[1].0;
+        if creation.attached.0 < minimum_needed {
+            log!(
+                "{}: attach at least {} yⓃ",
+                NOT_ENOUGH_DEPOSIT,
+                minimum_needed
+            );
+            self.internal_cancel_pool_creation(creation, 0);
+            return PromiseOrValue::Value(None);
+        }
 
-        let create_pool_promise = Promise::new(subaccount.clone())
+        let code = self.code.get().unwrap();
+        let pool_storage_cost = Self::internal_pool_storage_cost(code.len());
+        let create_pool_promise = Promise::new(creation.pool_id.clone())
             .create_account()
             .add_full_access_key(env::signer_account_pk())
-            .transfer(pool_storage_staking)
+            .transfer(pool_storage_cost)
             .deploy_contract(code);
 
-        let init_pool_promise = ext_zswap_pool::ext(subaccount.clone()).new(
-            ordered_token_0.clone(),
-            ordered_token_1.clone(),
-            tick_spacing_opt.unwrap(),
+        let init_pool_promise = ext_zswap_pool::ext(creation.pool_id.clone()).new(
+            creation.token_0.clone(),
+            creation.token_1.clone(),
+            self.fees.get(&fee).unwrap(),
             fee,
             sqrt_price_x96,
+            self.dynamic_fees.get(&fee),
+            hook,
         );
 
-        let token_0_storage_deposit_promise = ext_ft_storage::ext(ordered_token_0.clone())
-            .with_attached_deposit(FT_STORAGE_DEPOSIT)
-            .storage_deposit(Some(subaccount.clone()), None);
-        let token_1_storage_deposit_promise = ext_ft_storage::ext(ordered_token_1.clone())
-            .with_attached_deposit(FT_STORAGE_DEPOSIT)
-            .storage_deposit(Some(subaccount.clone()), None);
-
-        // Add callback
+        let refund = U128(creation.attached.0 - minimum_needed);
         create_pool_promise
             .and(init_pool_promise)
-            .and(token_0_storage_deposit_promise)
-            .and(token_1_storage_deposit_promise)
             .then(
                 Self::ext(env::current_account_id()).create_factory_subaccount_and_deploy_callback(
-                    subaccount,
-                    env::predecessor_account_id(),
-                    ordered_token_0,
-                    ordered_token_1,
-                    attached,
+                    creation,
+                    storage_deposits,
+                    refund,
+                    U128(pool_storage_cost),
                 ),
             )
+            .into()
     }
 
+    /// Registers the created pool in both tokens, or releases it if the account couldn't be
+    /// created or initialized. Nothing is spent until the account exists, so a failed creation
+    /// returns the whole deposit. A failed `new` leaves the account with its storage, `pool_storage`,
+    /// and returns the rest
     #[private]
     pub fn create_factory_subaccount_and_deploy_callback(
         &mut self,
-        account: AccountId,
-        deployer: AccountId,
-        token_0: AccountId,
-        token_1: AccountId,
-        attached: Balance,
-    ) -> Option<AccountId> {
-        let create_deploy_promise_idx = 0;
-        let token_0_storage_deposit_promise_idx = 2;
-        let token_1_storage_deposit_promise_idx = 3;
+        creation: PoolCreation,
+        storage_deposits: [U128; 2],
+        refund: U128,
+        pool_storage: U128,
+    ) -> PromiseOrValue<Option<AccountId>> {
+        let (create_deploy_promise_idx, init_promise_idx) = (0, 1);
+        if !matches!(
+            env::promise_result(create_deploy_promise_idx),
+            PromiseResult::Successful(_)
+        ) {
+            log!("Error creating {}", creation.pool_id);
+            self.internal_cancel_pool_creation(creation, 0);
+            return PromiseOrValue::Value(None);
+        }
+        if !matches!(
+            env::promise_result(init_promise_idx),
+            PromiseResult::Successful(_)
+        ) {
+            log!("Error initializing {}", creation.pool_id);
+            self.internal_cancel_pool_creation(creation, pool_storage.0);
+            return PromiseOrValue::Value(None);
+        }
 
-        match env::promise_result(create_deploy_promise_idx) {
-            PromiseResult::Successful(_) => {
-                log!("Correctly created and deployed to {}", account);
+        log!("Correctly created and deployed to {}", creation.pool_id);
+        self.tokens.insert(&creation.token_0);
+        self.tokens.insert(&creation.token_1);
+
+        if refund.0 > 0 {
+            log!(
+                "Returning {}yⓃ of excess deposit to {}",
+                refund.0,
+                creation.deployer
+            );
+            Promise::new(creation.deployer.clone()).transfer(refund.0);
+        }
 
-                match env::promise_result(token_0_storage_deposit_promise_idx) {
-                    PromiseResult::Successful(_) => {
-                        log!("Correctly deposited storage into token 0 {}", token_0);
-                    }
-                    _ => {
-                        log!("Failed to deposit storage {}", token_0);
-                    }
-                }
+        ext_ft_storage::ext(creation.token_0.clone())
+            .with_attached_deposit(storage_deposits[0].0)
+            .storage_deposit(Some(creation.pool_id.clone()), Some(true))
+            .and(
+                ext_ft_storage::ext(creation.token_1.clone())
+                    .with_attached_deposit(storage_deposits[1].0)
+                    .storage_deposit(Some(creation.pool_id.clone()), Some(true)),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .register_pool_callback(creation, storage_deposits),
+            )
+            .into()
+    }
 
-                match env::promise_result(token_1_storage_deposit_promise_idx) {
-                    PromiseResult::Successful(_) => {
-                        log!("Correctly deposited storage into token 1 {}", token_1);
-                    }
-                    _ => {
-                        log!("Failed to deposit storage {}", token_1);
+    /// Logs the pool's registration in its tokens, returning the deposit of a failed one
+    #[private]
+    pub fn register_pool_callback(
+        &mut self,
+        creation: PoolCreation,
+        storage_deposits: [U128; 2],
+    ) -> Option<AccountId> {
+        for (promise_idx, token_id) in [&creation.token_0, &creation.token_1]
+            .into_iter()
+            .enumerate()
+        {
+            match env::promise_result(promise_idx as u64) {
+                PromiseResult::Successful(_) => {
+                    log!("Correctly deposited storage into {}", token_id);
+                }
+                _ => {
+                    let deposit = storage_deposits[promise_idx].0;
+                    log!(
+                        "Failed to deposit storage into {}, returning {}yⓃ to {}",
+                        token_id,
+                        deposit,
+                        creation.deployer
+                    );
+                    if deposit > 0 {
+                        Promise::new(creation.deployer.clone()).transfer(deposit);
                     }
                 }
-
-                Some(account)
-            }
-            _ => {
-                log!("Error creating {account}, returning {attached}yⓃ to {deployer}");
-
-                Promise::new(deployer).transfer(attached);
-                None
             }
         }
+
+        Some(creation.pool_id)
     }
 
-    pub fn get_pool(&self, token_0: AccountId, token_1: AccountId, fee: u32) -> Option<PoolView> {
+    pub fn get_pool(
+        &self,
+        token_0: AccountId,
+        token_1: AccountId,
+        fee: u32,
+        hook: Option<AccountId>,
+    ) -> Option<PoolView> {
         let ordered_token_0;
         let ordered_token_1;
         match token_0.cmp(&token_1) {
@@ -222,14 +355,15 @@ impl Contract {
             Ordering::Equal => return None,
         }
 
-        let pool_id = pool_account::compute_account(
+        let pool_id = pool_account::compute_hooked_account(
             &env::current_account_id(),
             &ordered_token_0,
             &ordered_token_1,
             fee,
+            hook.as_ref(),
         );
 
-        if !self.pools.get(&pool_id).unwrap_or_default() {
+        if !self.pools.contains(&pool_id) {
             return None;
         }
 
@@ -239,6 +373,8 @@ impl Contract {
             token_1: ordered_token_1,
             fee,
             tick_spacing: self.fees.get(&fee).unwrap(),
+            max_fee: self.dynamic_fees.get(&fee),
+            hook,
         };
 
         Some(pool_view)
@@ -247,4 +383,218 @@ impl Contract {
     pub fn get_tokens(&self) -> Vec<AccountId> {
         self.tokens.to_vec()
     }
+
+    /// Returns the deposit `create_pool` needs: storage for the pool code and state, plus a
+    /// quote for the pool's storage registration in both tokens. The same for any tokens, what
+    /// their registration doesn't take is refunded
+    pub fn get_pool_creation_cost(&self) -> U128 {
+        U128::from(self.internal_pool_creation_cost() + FT_STORAGE_DEPOSIT * 2)
+    }
+
+    /// Enables pools with `fee`, in hundredths of a bip, spaced by `tick_spacing`.
+    /// A fee tier cannot be changed or removed once enabled
+    #[private]
+    pub fn enable_fee_amount(&mut self, fee: u32, tick_spacing: u32) {
+        self.internal_enable_fee_amount(fee, tick_spacing);
+        log!(
+            "Fee amount {} enabled with tick spacing {}",
+            fee,
+            tick_spacing
+        );
+    }
+
+    /// Enables dynamic-fee pools charging `fee` at rest and up to `max_fee` as volatility
+    /// rises, spaced by `tick_spacing`. Like any fee tier, it cannot be changed once enabled
+    #[private]
+    pub fn enable_dynamic_fee_amount(&mut self, fee: u32, tick_spacing: u32, max_fee: u32) {
+        if max_fee < fee || max_fee >= 1_000_000 {
+            env::panic_str(INVALID_MAX_FEE);
+        }
+        self.internal_enable_fee_amount(fee, tick_spacing);
+        self.dynamic_fees.insert(&fee, &max_fee);
+        log!(
+            "Dynamic fee amount {} enabled with tick spacing {}, up to {}",
+            fee,
+            tick_spacing,
+            max_fee
+        );
+    }
+
+    pub fn get_fee_amount_tick_spacing(&self, fee: u32) -> Option<u32> {
+        self.fees.get(&fee)
+    }
+
+    /// Returns the max fee of a dynamic fee tier, or `None` for a fixed one
+    pub fn get_fee_amount_max_fee(&self, fee: u32) -> Option<u32> {
+        self.dynamic_fees.get(&fee)
+    }
+}
+
+impl Contract {
+    fn internal_enable_fee_amount(&mut self, fee: u32, tick_spacing: u32) {
+        if fee >= 1_000_000 {
+            env::panic_str(INVALID_FEE);
+        }
+        // capped so that a tick bitmap word never spans more ticks than exist
+        if tick_spacing == 0 || tick_spacing >= 16384 {
+            env::panic_str(INVALID_TICK_SPACING);
+        }
+        if self.fees.get(&fee).is_some() {
+            env::panic_str(FEE_ALREADY_ENABLED);
+        }
+
+        self.fees.insert(&fee, &tick_spacing);
+    }
+
+    fn internal_pool_storage_cost(code_len: usize) -> Balance {
+        let bytes =
+            code_len as StorageUsage + POOL_ACCOUNT_STORAGE + pool_account::POOL_STATE_STORAGE;
+        NEAR_PER_STORAGE * bytes as Balance
+    }
+
+    /// Storage of the pool account and of the factory's records of it, without the tokens'
+    /// registration
+    fn internal_pool_creation_cost(&self) -> Balance {
+        let code = self.code.get().unwrap();
+        Self::internal_pool_storage_cost(code.len())
+            + NEAR_PER_STORAGE * FACTORY_POOL_STORAGE as Balance
+    }
+
+    /// Releases a pool that won't be created and returns the deposit, but what was `spent`
+    fn internal_cancel_pool_creation(&mut self, creation: PoolCreation, spent: Balance) {
+        self.pools.remove(&creation.pool_id);
+        let refund = creation.attached.0 - spent;
+        log!("Returning {}yⓃ to {}", refund, creation.deployer);
+        Promise::new(creation.deployer).transfer(refund);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    fn long_account(c: char) -> AccountId {
+        account(&c.to_string().repeat(64))
+    }
+
+    // leaves room for the 16-character prefix of pool accounts
+    fn factory() -> AccountId {
+        account(&"f".repeat(47))
+    }
+
+    fn called_by(predecessor: &AccountId, attached_deposit: Balance) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(factory())
+            .predecessor_account_id(predecessor.clone())
+            .attached_deposit(attached_deposit)
+            .build());
+    }
+
+    #[test]
+    fn test_factory_pool_storage() {
+        called_by(&factory(), 0);
+        let mut contract = Contract::new();
+        let pool_id = pool_account::compute_hooked_account(
+            &factory(),
+            &long_account('a'),
+            &long_account('b'),
+            3000,
+            Some(&long_account('h')),
+        );
+        assert_eq!(pool_id.as_str().len(), 64);
+
+        let initial_storage = env::storage_usage();
+        contract.pools.insert(&pool_id);
+        contract.tokens.insert(&long_account('a'));
+        contract.tokens.insert(&long_account('b'));
+        assert_eq!(env::storage_usage() - initial_storage, FACTORY_POOL_STORAGE);
+    }
+
+    #[test]
+    fn test_failed_creation_releases_the_pool() {
+        let deployer = account("alice.testnet");
+        let token_0 = account("token0.testnet");
+        let token_1 = account("token1.testnet");
+        called_by(&factory(), 0);
+        let mut contract = Contract::new();
+        let cost = contract.get_pool_creation_cost();
+
+        let create = |contract: &mut Contract| {
+            called_by(&deployer, cost.0);
+            contract.create_pool(
+                token_0.clone(),
+                token_1.clone(),
+                3000,
+                JsonU256::from(2_u128.pow(96)),
+                None,
+            );
+            let pool_id = pool_account::compute_account(&factory(), &token_0, &token_1, 3000);
+            assert!(contract.pools.contains(&pool_id));
+            PoolCreation {
+                pool_id,
+                token_0: token_0.clone(),
+                token_1: token_1.clone(),
+                deployer: deployer.clone(),
+                attached: cost,
+            }
+        };
+        let bounds = |min: Balance| StorageBalanceBounds {
+            min: U128(min),
+            max: None,
+        };
+
+        // a token asking more than the quote
+        let creation = create(&mut contract);
+        let pool_id = creation.pool_id.clone();
+        called_by(&factory(), 0);
+        let created = contract.create_pool_with_storage_bounds(
+            Ok(bounds(FT_STORAGE_DEPOSIT)),
+            Ok(bounds(FT_STORAGE_DEPOSIT + 1)),
+            creation,
+            3000,
+            JsonU256::from(2_u128.pow(96)),
+            None,
+        );
+        assert!(matches!(created, PromiseOrValue::Value(None)));
+        assert!(!contract.pools.contains(&pool_id));
+
+        // the account couldn't be created, or `new` failed on it
+        for results in [
+            vec![PromiseResult::Failed, PromiseResult::Failed],
+            vec![PromiseResult::Successful(vec![]), PromiseResult::Failed],
+        ] {
+            let creation = create(&mut contract);
+            testing_env!(
+                VMContextBuilder::new()
+                    .current_account_id(factory())
+                    .predecessor_account_id(factory())
+                    .build(),
+                near_sdk::VMConfig::test(),
+                near_sdk::RuntimeFeesConfig::test(),
+                Default::default(),
+                results,
+            );
+            let created = contract.create_factory_subaccount_and_deploy_callback(
+                creation,
+                [U128(FT_STORAGE_DEPOSIT), U128(FT_STORAGE_DEPOSIT)],
+                U128(0),
+                U128(Contract::internal_pool_storage_cost(
+                    ZSWAP_POOL_CONTRACT.len(),
+                )),
+            );
+            assert!(matches!(created, PromiseOrValue::Value(None)));
+            assert!(!contract.pools.contains(&pool_id));
+            assert!(contract.get_tokens().is_empty());
+        }
+
+        // so it can be created again
+        create(&mut contract);
+    }
 }
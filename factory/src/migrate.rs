@@ -0,0 +1,119 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedSet};
+use near_sdk::{env, log, near_bindgen, AccountId};
+
+use crate::*;
+
+/// State of a factory deployed before dynamic fees and pausing
+#[derive(BorshDeserialize, BorshSerialize)]
+struct ContractV1 {
+    fees: LookupMap<u32, u32>,
+    pools: LookupMap<AccountId, bool>,
+    tokens: UnorderedSet<AccountId>,
+    code: LazyOption<Vec<u8>>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Moves a factory deployed before dynamic fees and pausing to the current state. Its pools
+    /// weren't enumerable, so `pools` lists every pool it created, each checked against its
+    /// records. It starts with no dynamic fee tiers and no guardian
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate(pools: Vec<AccountId>) -> Self {
+        let mut old: ContractV1 =
+            env::state_read().unwrap_or_else(|| env::panic_str(NOTHING_TO_MIGRATE));
+
+        // under the same prefix, the set's keys go on with `e` or `i` and the map's with an
+        // account id's length, at most 64, so they don't overlap
+        let mut migrated = UnorderedSet::new(StorageKey::Pools);
+        for pool_id in pools {
+            if old.pools.remove(&pool_id).is_none() {
+                env::panic_str(POOL_NOT_FOUND);
+            }
+            migrated.insert(&pool_id);
+        }
+        log!("Migrated {} pools", migrated.len());
+
+        Self {
+            fees: old.fees,
+            dynamic_fees: LookupMap::new(StorageKey::DynamicFees),
+            pools: migrated,
+            tokens: old.tokens,
+            code: old.code,
+            guardian: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    /// Prefixes of the collections of a factory deployed before the migration, by their
+    /// position in its `StorageKey`
+    const FEES: &[u8] = &[0];
+    const POOLS: &[u8] = &[1];
+    const TOKENS: &[u8] = &[2];
+    const CODE: &[u8] = &[3];
+
+    #[test]
+    fn test_migrate_lists_the_old_pools() {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account("factory.testnet"))
+            .predecessor_account_id(account("factory.testnet"))
+            .build());
+        let pool_ids = [
+            account("2e4e39194a383739.factory.testnet"),
+            account("1bd3f8a1c2e4d5f6.factory.testnet"),
+        ];
+        let mut fees = LookupMap::new(FEES);
+        fees.insert(&3000, &60);
+        let mut pools = LookupMap::new(POOLS);
+        let mut tokens = UnorderedSet::new(TOKENS);
+        for pool_id in &pool_ids {
+            pools.insert(pool_id, &true);
+        }
+        tokens.insert(&account("token0.testnet"));
+        tokens.insert(&account("token1.testnet"));
+        env::state_write(&ContractV1 {
+            fees,
+            pools,
+            tokens,
+            code: LazyOption::new(CODE, Some(&vec![0, 1, 2])),
+        });
+
+        let contract = Contract::migrate(pool_ids.to_vec());
+        assert_eq!(contract.get_pool_count(), 2);
+        assert!(pool_ids
+            .iter()
+            .all(|pool_id| contract.pools.contains(pool_id)));
+        assert_eq!(contract.get_tokens().len(), 2);
+        assert_eq!(contract.fees.get(&3000), Some(60));
+        assert_eq!(contract.code.get(), Some(vec![0, 1, 2]));
+        assert_eq!(contract.get_guardian(), None);
+        assert_eq!(contract.get_fee_amount_max_fee(3000), None);
+
+        // the old records are gone
+        let old_pools = LookupMap::<AccountId, bool>::new(POOLS);
+        assert!(pool_ids
+            .iter()
+            .all(|pool_id| !old_pools.contains_key(pool_id)));
+
+        // and the migrated factory reads and writes its state like a new one
+        env::state_write(&contract);
+        let mut contract: Contract = env::state_read().unwrap();
+        contract.enable_dynamic_fee_amount(100, 1, 1_000);
+        assert_eq!(contract.get_fee_amount_max_fee(100), Some(1_000));
+        assert_eq!(contract.get_fee_amount_tick_spacing(3000), Some(60));
+        assert_eq!(contract.get_pool_count(), 2);
+        assert_eq!(contract.get_tokens().len(), 2);
+    }
+}
@@ -1,9 +1,12 @@
+#![allow(clippy::too_many_arguments)]
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{ext_contract, AccountId};
+use schemars::JsonSchema;
+use zswap_math_library::json_types::JsonU256;
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct PoolView {
     pub pool_id: AccountId,
@@ -11,6 +14,10 @@ pub struct PoolView {
     pub token_1: AccountId,
     pub fee: u32,
     pub tick_spacing: u32,
+    /// Set for dynamic-fee pools, which charge between `fee` and `max_fee`
+    pub max_fee: Option<u32>,
+    /// Called by the pool around its swaps and liquidity changes
+    pub hook: Option<AccountId>,
 }
 
 #[derive(Serialize)]
@@ -29,6 +36,10 @@ pub trait FtZswapPool {
         token_1: AccountId,
         tick_spacing: u32,
         fee: u32,
-        sqrt_price_x96: U128,
+        sqrt_price_x96: JsonU256,
+        max_fee: Option<u32>,
+        hook: Option<AccountId>,
     ) -> Self;
+
+    fn set_paused(&mut self, paused: bool);
 }
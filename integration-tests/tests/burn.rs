@@ -1,11 +1,9 @@
 use near_sdk::{json_types::U128, ONE_YOCTO};
-use near_units::parse_near;
 use serde_json::json;
 
 use zswap_manager::utils::MintParams;
 
 use helper::*;
-use zswap_pool::ft_receiver::TokenReceiverMessage as PoolTokenReceiverMessage;
 use zswap_pool::utils::Slot0;
 
 mod helper;
@@ -18,21 +16,17 @@ async fn test_burn_properly() -> anyhow::Result<()> {
     let liquidity_provider = context.deployer;
     println!("✅ Setup done");
 
-    // deposit token 0 & 1 into deployer
+    // deposit token 0 & 1 into manager
     let token_0_amount = U128::from(10_000_000);
     let token_1_amount = U128::from(100_000_000);
 
-    let approve_msg = PoolTokenReceiverMessage::Approve {
-        account_id: context.manager_contract.id().to_string().parse().unwrap(),
-    };
-
     liquidity_provider
         .call(context.token_0_contract.id(), "ft_transfer_call")
         .args_json((
-            context.pool_id.clone(),
+            context.manager_contract.id(),
             token_0_amount,
             None::<String>,
-            near_sdk::serde_json::to_string(&approve_msg).unwrap(),
+            String::new(),
         ))
         .deposit(ONE_YOCTO)
         .max_gas()
@@ -42,17 +36,17 @@ async fn test_burn_properly() -> anyhow::Result<()> {
     liquidity_provider
         .call(context.token_1_contract.id(), "ft_transfer_call")
         .args_json((
-            context.pool_id.clone(),
+            context.manager_contract.id(),
             token_1_amount,
             None::<String>,
-            near_sdk::serde_json::to_string(&approve_msg).unwrap(),
+            String::new(),
         ))
         .deposit(ONE_YOCTO)
         .max_gas()
         .transact()
         .await?
         .into_result()?;
-    println!("✅ Deposited token 0 & 1 into `ZswapPool`");
+    println!("✅ Deposited token 0 & 1 into `ZswapManager`");
 
     let before_slot_0 = liquidity_provider
         .call(&context.pool_id, "get_slot_0")
@@ -75,7 +69,6 @@ async fn test_burn_properly() -> anyhow::Result<()> {
     let added_amounts = liquidity_provider
         .call(context.manager_contract.id(), "mint")
         .args_json(json!({ "params": mint_params }))
-        .deposit(parse_near!("0.1 N"))
         .max_gas()
         .transact()
         .await?
@@ -0,0 +1,74 @@
+use near_sdk::json_types::U128;
+use near_units::parse_near;
+use serde_json::json;
+use zswap_factory::pool::PoolView;
+
+use helper::*;
+
+mod helper;
+
+#[tokio::test]
+async fn test_create_and_initialize_pool_if_necessary() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    println!("\nContracts setup...");
+    let context = init(&worker).await?;
+    let deployer = context.deployer;
+    println!("✅ Setup done");
+
+    let sqrt_price_x96 = U128::from(10 * (2_u128).pow(96));
+
+    // both tokens are known by the manager, but this fee tier has no pool yet
+    let new_pool_fee = 500;
+    let created_pool_id = deployer
+        .call(
+            context.manager_contract.id(),
+            "create_and_initialize_pool_if_necessary",
+        )
+        .args_json(json!({
+            "token_0": context.token_0_contract.id(),
+            "token_1": context.token_1_contract.id(),
+            "fee": new_pool_fee,
+            "sqrt_price_x96": sqrt_price_x96,
+        }))
+        .deposit(parse_near!("30 N"))
+        .max_gas()
+        .transact()
+        .await?
+        .json::<Option<String>>()?;
+
+    let pool = deployer
+        .call(context.factory_contract.id(), "get_pool")
+        .args_json(json!({
+            "token_0": context.token_0_contract.id(),
+            "token_1": context.token_1_contract.id(),
+            "fee": new_pool_fee,
+        }))
+        .view()
+        .await?
+        .json::<Option<PoolView>>()?;
+    assert!(pool.is_some());
+    assert_eq!(created_pool_id, Some(pool.unwrap().pool_id.to_string()));
+    println!("✅ Created missing pool");
+
+    // the pool already exists and is initialized, so only its id is returned
+    let existing_pool_id = deployer
+        .call(
+            context.manager_contract.id(),
+            "create_and_initialize_pool_if_necessary",
+        )
+        .args_json(json!({
+            "token_0": context.token_0_contract.id(),
+            "token_1": context.token_1_contract.id(),
+            "fee": POOL_FEE,
+            "sqrt_price_x96": sqrt_price_x96,
+        }))
+        .deposit(parse_near!("30 N"))
+        .max_gas()
+        .transact()
+        .await?
+        .json::<Option<String>>()?;
+    assert_eq!(existing_pool_id, Some(context.pool_id.to_string()));
+    println!("✅ Returned existing pool");
+
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! Gas benchmark for the pool operations.
+//!
+//! Measures mint, burn, collect and swaps crossing 0, 1, 10 and 50 initialized ticks in the
+//! sandbox and writes the gas burnt by each to a JSON report, `target/gas_report.json` unless
+//! `GAS_REPORT` points elsewhere. Any operation burning more than `GAS_REGRESSION_THRESHOLD`
+//! percent (10 by default) above its baseline in `gas_baseline.json`, next to this crate's
+//! manifest, fails the run, and so does a missing baseline or operation. Run with
+//! `GAS_BASELINE_UPDATE=1` to write the report over the baseline instead.
+
+use std::collections::BTreeMap;
+
+use near_sdk::{json_types::U128, ONE_YOCTO};
+use serde_json::{json, Value};
+use workspaces::result::ExecutionFinalResult;
+use workspaces::Account;
+
+use zswap_manager::utils::{MintParams, SwapSingleParams};
+use zswap_math_library::json_types::JsonU256;
+use zswap_math_library::tick_math;
+use zswap_pool::utils::Slot0;
+
+use helper::*;
+
+mod helper;
+
+/// Tick spacing of the `POOL_FEE` tier
+const TICK_SPACING: i32 = 60;
+/// Highest usable tick below the initial tick (~46054)
+const FIRST_LADDER_TICK: i32 = 46020;
+/// Upper tick shared by the ladder positions
+const LADDER_UPPER_TICK: i32 = 48000;
+/// Initialized ticks crossed by each benchmarked swap, in order
+const CROSSINGS: [usize; 4] = [0, 1, 10, 50];
+
+const MAX_GAS: u64 = 300 * 10_u64.pow(12);
+/// Operations must leave this much of the prepaid gas unused
+const MIN_HEADROOM_PERCENT: u64 = 20;
+const DEFAULT_THRESHOLD_PERCENT: u64 = 10;
+const BASELINE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/gas_baseline.json");
+
+async fn deposit(
+    context: &TestContext,
+    account: &Account,
+    amounts: [u128; 2],
+) -> anyhow::Result<()> {
+    for (token, amount) in [
+        (&context.token_0_contract, amounts[0]),
+        (&context.token_1_contract, amounts[1]),
+    ] {
+        account
+            .call(token.id(), "ft_transfer_call")
+            .args_json((
+                context.manager_contract.id(),
+                U128::from(amount),
+                None::<String>,
+                String::new(),
+            ))
+            .deposit(ONE_YOCTO)
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+    }
+    Ok(())
+}
+
+async fn mint(
+    context: &TestContext,
+    account: &Account,
+    lower_tick: i32,
+    upper_tick: i32,
+    amounts: [u128; 2],
+) -> anyhow::Result<ExecutionFinalResult> {
+    let mint_params = MintParams {
+        token_0: context.token_0_contract.id().parse().unwrap(),
+        token_1: context.token_1_contract.id().parse().unwrap(),
+        fee: POOL_FEE,
+        lower_tick,
+        upper_tick,
+        amount_0_desired: U128::from(amounts[0]),
+        amount_1_desired: U128::from(amounts[1]),
+        amount_0_min: U128::from(0),
+        amount_1_min: U128::from(0),
+    };
+    let outcome = account
+        .call(context.manager_contract.id(), "mint")
+        .args_json(json!({ "params": mint_params }))
+        .max_gas()
+        .transact()
+        .await?;
+    Ok(outcome)
+}
+
+async fn get_slot_0(context: &TestContext) -> anyhow::Result<Slot0> {
+    Ok(context
+        .deployer
+        .call(&context.pool_id, "get_slot_0")
+        .view()
+        .await?
+        .json::<Slot0>()?)
+}
+
+/// The `i`th initialized tick below the initial tick
+fn ladder_tick(i: usize) -> i32 {
+    FIRST_LADDER_TICK - TICK_SPACING * i as i32
+}
+
+#[tokio::test]
+async fn test_gas_benchmark() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    println!("\nContracts setup...");
+    let context = init(&worker).await?;
+    let account = context.deployer.clone();
+    println!("✅ Setup done");
+
+    let mut gas_burnt = BTreeMap::new();
+    deposit(&context, &account, [10_000_000_000, 200_000_000_000]).await?;
+
+    // full range liquidity, so the swaps below never run out of it
+    let outcome = mint(
+        &context,
+        &account,
+        -887220,
+        887220,
+        [1_000_000_000, 100_000_000_000],
+    )
+    .await?;
+    gas_burnt.insert("mint".to_string(), outcome.total_gas_burnt);
+    outcome.into_result()?;
+
+    // one initialized tick every spacing below the current tick, as many as the swaps cross
+    let ladder_len: usize = CROSSINGS.iter().sum();
+    for i in 0..ladder_len {
+        mint(
+            &context,
+            &account,
+            ladder_tick(i),
+            LADDER_UPPER_TICK,
+            [1_000, 100_000],
+        )
+        .await?
+        .into_result()?;
+    }
+    println!("✅ Minted {} ladder positions", ladder_len);
+
+    // each swap stops between two ladder ticks, right past the last one it has to cross
+    let mut crossed = 0;
+    for crossings in CROSSINGS {
+        let limit_tick = if crossings == 0 {
+            ladder_tick(crossed) + TICK_SPACING / 3
+        } else {
+            ladder_tick(crossed + crossings - 1) - TICK_SPACING / 2
+        };
+        let swap_params = SwapSingleParams {
+            token_in: context.token_0_contract.id().parse().unwrap(),
+            token_out: context.token_1_contract.id().parse().unwrap(),
+            fee: POOL_FEE,
+            amount_in: U128::from(1_000_000_000),
+            amount_out_min: U128::from(0),
+            sqrt_price_limit_x96: Some(JsonU256(tick_math::get_sqrt_ratio_at_tick(limit_tick))),
+        };
+        let outcome = account
+            .call(context.manager_contract.id(), "swap")
+            .args_json(json!({ "params": swap_params }))
+            .max_gas()
+            .transact()
+            .await?;
+        gas_burnt.insert(
+            format!("swap_crossing_{}_ticks", crossings),
+            outcome.total_gas_burnt,
+        );
+        outcome.into_result()?;
+
+        assert_eq!(get_slot_0(&context).await?.tick, limit_tick);
+        crossed += crossings;
+    }
+    println!("✅ Swapped across {} initialized ticks", crossed);
+
+    // the manager burns and collects in one go, the pool's collect receipt is the one logging it
+    let outcome = account
+        .call(context.manager_contract.id(), "burn")
+        .args_json(json!({"nft_id": "0"}))
+        .max_gas()
+        .transact()
+        .await?;
+    gas_burnt.insert("burn".to_string(), outcome.total_gas_burnt);
+    let collect_gas_burnt = outcome
+        .receipt_outcomes()
+        .iter()
+        .find(|receipt| {
+            receipt
+                .logs
+                .iter()
+                .any(|log| log.starts_with("Collected amount"))
+        })
+        .map(|receipt| receipt.gas_burnt)
+        .expect("no collect receipt");
+    gas_burnt.insert("collect".to_string(), collect_gas_burnt);
+    outcome.into_result()?;
+    println!("✅ Burned and collected");
+
+    let threshold_percent = std::env::var("GAS_REGRESSION_THRESHOLD")
+        .map(|threshold| threshold.parse().expect("invalid GAS_REGRESSION_THRESHOLD"))
+        .unwrap_or(DEFAULT_THRESHOLD_PERCENT);
+    let report = json!({
+        "threshold_percent": threshold_percent,
+        "gas_burnt": gas_burnt,
+    });
+    let report_path = std::env::var("GAS_REPORT").unwrap_or_else(|_| {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../target/gas_report.json").into()
+    });
+    std::fs::write(&report_path, serde_json::to_string_pretty(&report)?)?;
+    println!("\tReport written to {}", report_path);
+
+    let update_baseline = std::env::var("GAS_BASELINE_UPDATE").is_ok();
+    if update_baseline {
+        std::fs::write(BASELINE_PATH, serde_json::to_string_pretty(&report)? + "\n")?;
+        println!("\tBaseline written to {}", BASELINE_PATH);
+    }
+    let baseline: Value = match std::fs::read_to_string(BASELINE_PATH) {
+        Ok(baseline) => serde_json::from_str(&baseline)?,
+        Err(_) => panic!(
+            "no baseline at {}, record one with GAS_BASELINE_UPDATE=1",
+            BASELINE_PATH
+        ),
+    };
+
+    let mut failures = Vec::new();
+    for (operation, gas) in gas_burnt.iter() {
+        let baseline_gas = baseline["gas_burnt"][operation].as_u64();
+        println!(
+            "\t{:<24} {:>4} TGas{}",
+            operation,
+            gas / 10_u64.pow(12),
+            baseline_gas.map_or(String::new(), |baseline_gas| format!(
+                " (baseline {} TGas)",
+                baseline_gas / 10_u64.pow(12)
+            ))
+        );
+
+        if gas * 100 > MAX_GAS * (100 - MIN_HEADROOM_PERCENT) {
+            failures.push(format!(
+                "{} leaves less than {}% of max gas",
+                operation, MIN_HEADROOM_PERCENT
+            ));
+        }
+        match baseline_gas {
+            Some(baseline_gas) if gas * 100 > baseline_gas * (100 + threshold_percent) => failures
+                .push(format!(
+                    "{} regressed by more than {}%: {} > {}",
+                    operation, threshold_percent, gas, baseline_gas
+                )),
+            Some(_) => {}
+            None => failures.push(format!("{} has no baseline", operation)),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+    println!("✅ Gas within budget");
+
+    Ok(())
+}
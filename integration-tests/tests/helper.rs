@@ -69,6 +69,16 @@ pub async fn init(worker: &Worker<impl DevNetwork>) -> anyhow::Result<TestContex
         .into_result()?;
     println!("\tManager contract: {}", manager_contract.id());
 
+    // the deployer's storage balance pays for its deposits, positions and orders in the manager
+    deployer
+        .call(manager_contract.id(), "storage_deposit")
+        .args_json(json!({}))
+        .deposit(parse_near!("1 N"))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
     let initial_sqrt_price_x96 = U128::from(10 * (2_u128).pow(96));
     deployer
         .call(manager_contract.id(), "create_pool")
@@ -85,7 +95,11 @@ pub async fn init(worker: &Worker<impl DevNetwork>) -> anyhow::Result<TestContex
         .into_result()?;
     let pool = deployer
         .call(factory_contract.id(), "get_pool")
-        .args_json((token_0_contract.id(), token_1_contract.id(), POOL_FEE))
+        .args_json(json!({
+            "token_0": token_0_contract.id(),
+            "token_1": token_1_contract.id(),
+            "fee": POOL_FEE,
+        }))
         .view()
         .await?
         .json::<PoolView>()?;
@@ -1,13 +1,11 @@
 use near_contract_standards::non_fungible_token::Token as NFT;
 use near_sdk::json_types::U128;
 use near_sdk::ONE_YOCTO;
-use near_units::parse_near;
 use serde_json::json;
 
 use zswap_manager::utils::MintParams;
 
 use helper::*;
-use zswap_pool::ft_receiver::TokenReceiverMessage as PoolTokenReceiverMessage;
 use zswap_pool::utils::Slot0;
 
 mod helper;
@@ -20,21 +18,17 @@ async fn test_mint_properly() -> anyhow::Result<()> {
     let liquidity_provider = context.deployer;
     println!("✅ Setup done");
 
-    // deposit token 0 & 1 into deployer
+    // deposit token 0 & 1 into manager
     let token_0_amount = U128::from(10_000_000);
     let token_1_amount = U128::from(100_000_000);
 
-    let approve_msg = PoolTokenReceiverMessage::Approve {
-        account_id: context.manager_contract.id().to_string().parse().unwrap(),
-    };
-
     liquidity_provider
         .call(context.token_0_contract.id(), "ft_transfer_call")
         .args_json((
-            context.pool_id.clone(),
+            context.manager_contract.id(),
             token_0_amount,
             None::<String>,
-            near_sdk::serde_json::to_string(&approve_msg).unwrap(),
+            String::new(),
         ))
         .deposit(ONE_YOCTO)
         .max_gas()
@@ -44,17 +38,17 @@ async fn test_mint_properly() -> anyhow::Result<()> {
     liquidity_provider
         .call(context.token_1_contract.id(), "ft_transfer_call")
         .args_json((
-            context.pool_id.clone(),
+            context.manager_contract.id(),
             token_1_amount,
             None::<String>,
-            near_sdk::serde_json::to_string(&approve_msg).unwrap(),
+            String::new(),
         ))
         .deposit(ONE_YOCTO)
         .max_gas()
         .transact()
         .await?
         .into_result()?;
-    println!("✅ Deposited token 0 & 1 into `ZswapPool`");
+    println!("✅ Deposited token 0 & 1 into `ZswapManager`");
 
     let before_slot_0 = liquidity_provider
         .call(&context.pool_id, "get_slot_0")
@@ -77,7 +71,6 @@ async fn test_mint_properly() -> anyhow::Result<()> {
     let added_amounts = liquidity_provider
         .call(context.manager_contract.id(), "mint")
         .args_json(json!({ "params": mint_params }))
-        .deposit(parse_near!("0.1 N"))
         .max_gas()
         .transact()
         .await?
@@ -85,6 +78,21 @@ async fn test_mint_properly() -> anyhow::Result<()> {
     println!("\tMinted amount 0: {}", added_amounts[0].0);
     println!("\tMinted amount 1: {}", added_amounts[1].0);
 
+    let unused_amount_0 = liquidity_provider
+        .call(context.manager_contract.id(), "get_deposit")
+        .args_json((liquidity_provider.id(), context.token_0_contract.id()))
+        .view()
+        .await?
+        .json::<U128>()?;
+    let unused_amount_1 = liquidity_provider
+        .call(context.manager_contract.id(), "get_deposit")
+        .args_json((liquidity_provider.id(), context.token_1_contract.id()))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(unused_amount_0.0, token_0_amount.0 - added_amounts[0].0);
+    assert_eq!(unused_amount_1.0, token_1_amount.0 - added_amounts[1].0);
+
     let nfts = liquidity_provider
         .call(context.manager_contract.id(), "nft_tokens_for_owner")
         .args_json(json!({"account_id":liquidity_provider.id()}))
@@ -114,7 +122,7 @@ async fn test_mint_failed() -> anyhow::Result<()> {
     let liquidity_provider = context.deployer;
     println!("✅ Setup done");
 
-    // deposit token 0 & 1 into deployer
+    // deposit token 0 & 1 into manager
     let token_0_amount = U128::from(10_000_000);
     let token_1_amount = U128::from(100_000_000);
 
@@ -139,7 +147,6 @@ async fn test_mint_failed() -> anyhow::Result<()> {
     let mint_res = liquidity_provider
         .call(context.manager_contract.id(), "mint")
         .args_json(json!({ "params": mint_params }))
-        .deposit(parse_near!("0.1 N"))
         .max_gas()
         .transact()
         .await?
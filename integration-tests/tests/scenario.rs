@@ -0,0 +1,49 @@
+use zswap_test_support::scenario::{Scenario, ACCOUNT_BALANCE};
+
+#[tokio::test]
+async fn test_swaps_across_fee_tiers() -> anyhow::Result<()> {
+    let env = Scenario::new()
+        .pool(3000, "100")
+        .pool(500, "100")
+        .mint("alice", 0, 42000, 48000, [10_000_000, 100_000_000])
+        .mint("bob", 1, 45000, 47000, [10_000_000, 100_000_000])
+        .swap("carol", 0, true, 10_000)
+        .swap("carol", 1, true, 10_000)
+        .swap("carol", 1, false, 2_000_000)
+        .burn("alice", 0)
+        .run()
+        .await?;
+    println!("✅ Scenario done");
+
+    // the narrower range of the cheaper pool gives a better price
+    assert_eq!(env.swaps[0].amount_in, 10_000);
+    assert_eq!(env.swaps[1].amount_in, 10_000);
+    assert!(env.swaps[1].amount_out > env.swaps[0].amount_out);
+    assert!(env.slot_0(1).await?.tick > env.slot_0(0).await?.tick);
+
+    let token_0 = env.pools[0].tokens[0];
+    let carol_token_0 = ACCOUNT_BALANCE - 20_000 + env.swaps[2].amount_out;
+    env.assert_balance("carol", token_0, carol_token_0).await?;
+
+    // alice's position took in the first swap's token 0 and paid out its token 1
+    let minted = env.positions_minted[0].amounts;
+    let burned = env.burns[0];
+    assert!(burned[0] + 1 >= minted[0] && burned[0] <= minted[0] + 10_000);
+    assert!(burned[1] < minted[1]);
+    assert!(env.positions("alice").await?.is_empty());
+    // what the mint did not use stays deposited in the manager
+    env.assert_balance("alice", token_0, ACCOUNT_BALANCE - 10_000_000 + burned[0])
+        .await?;
+    assert_eq!(env.deposit("alice", token_0).await?, 10_000_000 - minted[0]);
+
+    let bob_positions = env.positions("bob").await?;
+    assert_eq!(bob_positions.len(), 1);
+    assert_eq!(
+        (bob_positions[0].lower_tick, bob_positions[0].upper_tick),
+        (45000, 47000)
+    );
+    assert!(env.positions_minted[1].amounts[1] <= 100_000_000);
+    println!("✅ Balances and positions checked");
+
+    Ok(())
+}
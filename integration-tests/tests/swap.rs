@@ -1,9 +1,8 @@
 use near_sdk::{json_types::U128, ONE_YOCTO};
-use near_units::parse_near;
 use serde_json::json;
 
 use zswap_manager::ft_receiver::TokenReceiverMessage as ManagerTokenReceiverMessage;
-use zswap_manager::utils::MintParams;
+use zswap_manager::utils::{MintParams, SwapSingleParams};
 use zswap_pool::ft_receiver::TokenReceiverMessage as PoolTokenReceiverMessage;
 
 use helper::*;
@@ -18,21 +17,17 @@ async fn test_mint_properly() -> anyhow::Result<()> {
     let liquidity_provider = context.deployer;
     println!("✅ Setup done");
 
-    // deposit token 0 & 1 into deployer
+    // deposit token 0 & 1 into manager
     let token_0_amount = U128::from(100_000);
     let token_1_amount = U128::from(500_000);
 
-    let approve_msg = PoolTokenReceiverMessage::Approve {
-        account_id: context.manager_contract.id().to_string().parse().unwrap(),
-    };
-
     liquidity_provider
         .call(context.token_0_contract.id(), "ft_transfer_call")
         .args_json((
-            context.pool_id.clone(),
+            context.manager_contract.id(),
             token_0_amount,
             None::<String>,
-            near_sdk::serde_json::to_string(&approve_msg).unwrap(),
+            String::new(),
         ))
         .deposit(ONE_YOCTO)
         .max_gas()
@@ -42,17 +37,17 @@ async fn test_mint_properly() -> anyhow::Result<()> {
     liquidity_provider
         .call(context.token_1_contract.id(), "ft_transfer_call")
         .args_json((
-            context.pool_id.clone(),
+            context.manager_contract.id(),
             token_1_amount,
             None::<String>,
-            near_sdk::serde_json::to_string(&approve_msg).unwrap(),
+            String::new(),
         ))
         .deposit(ONE_YOCTO)
         .max_gas()
         .transact()
         .await?
         .into_result()?;
-    println!("✅ Deposited token 0 & 1 into `ZswapPool`");
+    println!("✅ Deposited token 0 & 1 into `ZswapManager`");
 
     let mint_params = MintParams {
         token_0: context.token_0_contract.id().parse().unwrap(),
@@ -68,7 +63,6 @@ async fn test_mint_properly() -> anyhow::Result<()> {
     liquidity_provider
         .call(context.manager_contract.id(), "mint")
         .args_json(json!({ "params": mint_params }))
-        .deposit(parse_near!("0.1 N"))
         .max_gas()
         .transact()
         .await?
@@ -83,9 +77,9 @@ async fn test_mint_properly() -> anyhow::Result<()> {
         .await?
         .json::<U128>()?;
 
-    let token_1_balance_before_swap = liquidity_provider
-        .call(context.token_1_contract.id(), "ft_balance_of")
-        .args_json(json!({"account_id": liquidity_provider.id()}))
+    let token_1_deposit_before_swap = liquidity_provider
+        .call(context.manager_contract.id(), "get_deposit")
+        .args_json((liquidity_provider.id(), context.token_1_contract.id()))
         .view()
         .await?
         .json::<U128>()?;
@@ -93,6 +87,7 @@ async fn test_mint_properly() -> anyhow::Result<()> {
     let msg = ManagerTokenReceiverMessage::SwapSingle {
         token_out: context.token_1_contract.id().parse().unwrap(),
         fee: POOL_FEE,
+        amount_out_min: U128::from(1),
         sqrt_price_limit_x96: None,
     };
     liquidity_provider
@@ -115,17 +110,259 @@ async fn test_mint_properly() -> anyhow::Result<()> {
         .view()
         .await?
         .json::<U128>()?;
-    let token_1_balance_after_swap = liquidity_provider
+    let token_1_deposit_after_swap = liquidity_provider
+        .call(context.manager_contract.id(), "get_deposit")
+        .args_json((liquidity_provider.id(), context.token_1_contract.id()))
+        .view()
+        .await?
+        .json::<U128>()?;
+
+    assert!(token_0_balance_before_swap.0 > token_0_balance_after_swap.0);
+    assert!(token_1_deposit_before_swap.0 < token_1_deposit_after_swap.0);
+
+    println!("✅ Swapped token 0 to token 1");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_swap_from_deposit() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    println!("\nContracts setup...");
+    let context = init(&worker).await?;
+    let liquidity_provider = context.deployer;
+    println!("✅ Setup done");
+
+    // deposit token 0 & 1 into manager, with some token 0 left to swap
+    let token_0_amount = U128::from(100_000);
+    let token_1_amount = U128::from(500_000);
+    let swap_amount = U128::from(777);
+
+    liquidity_provider
+        .call(context.token_0_contract.id(), "ft_transfer_call")
+        .args_json((
+            context.manager_contract.id(),
+            U128::from(token_0_amount.0 + swap_amount.0),
+            None::<String>,
+            String::new(),
+        ))
+        .deposit(ONE_YOCTO)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    liquidity_provider
+        .call(context.token_1_contract.id(), "ft_transfer_call")
+        .args_json((
+            context.manager_contract.id(),
+            token_1_amount,
+            None::<String>,
+            String::new(),
+        ))
+        .deposit(ONE_YOCTO)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    println!("✅ Deposited token 0 & 1 into `ZswapManager`");
+
+    let mint_params = MintParams {
+        token_0: context.token_0_contract.id().parse().unwrap(),
+        token_1: context.token_1_contract.id().parse().unwrap(),
+        fee: POOL_FEE,
+        lower_tick: 42000,
+        upper_tick: 48000,
+        amount_0_desired: token_0_amount,
+        amount_1_desired: token_1_amount,
+        amount_0_min: U128::from(0),
+        amount_1_min: U128::from(0),
+    };
+    liquidity_provider
+        .call(context.manager_contract.id(), "mint")
+        .args_json(json!({ "params": mint_params }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    println!("✅ Minted liquidity tokens");
+
+    let token_1_deposit_before_swap = liquidity_provider
+        .call(context.manager_contract.id(), "get_deposit")
+        .args_json((liquidity_provider.id(), context.token_1_contract.id()))
+        .view()
+        .await?
+        .json::<U128>()?;
+
+    let swap_params = SwapSingleParams {
+        token_in: context.token_0_contract.id().parse().unwrap(),
+        token_out: context.token_1_contract.id().parse().unwrap(),
+        fee: POOL_FEE,
+        amount_in: swap_amount,
+        amount_out_min: U128::from(1),
+        sqrt_price_limit_x96: None,
+    };
+    let amount_out = liquidity_provider
+        .call(context.manager_contract.id(), "swap")
+        .args_json(json!({ "params": swap_params }))
+        .max_gas()
+        .transact()
+        .await?
+        .json::<U128>()?;
+    assert!(amount_out.0 > 0);
+
+    let token_1_deposit_after_swap = liquidity_provider
+        .call(context.manager_contract.id(), "get_deposit")
+        .args_json((liquidity_provider.id(), context.token_1_contract.id()))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(
+        token_1_deposit_after_swap.0,
+        token_1_deposit_before_swap.0 + amount_out.0
+    );
+    println!("✅ Swapped deposited token 0 to token 1");
+
+    let token_1_balance_before_withdraw = liquidity_provider
+        .call(context.token_1_contract.id(), "ft_balance_of")
+        .args_json(json!({"account_id": liquidity_provider.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    liquidity_provider
+        .call(context.manager_contract.id(), "withdraw")
+        .args_json((context.token_1_contract.id(), token_1_deposit_after_swap))
+        .deposit(ONE_YOCTO)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let token_1_balance_after_withdraw = liquidity_provider
         .call(context.token_1_contract.id(), "ft_balance_of")
         .args_json(json!({"account_id": liquidity_provider.id()}))
         .view()
         .await?
         .json::<U128>()?;
+    assert_eq!(
+        token_1_balance_after_withdraw.0,
+        token_1_balance_before_withdraw.0 + token_1_deposit_after_swap.0
+    );
+    println!("✅ Withdrew token 1 from `ZswapManager`");
 
-    assert!(token_0_balance_before_swap.0 > token_0_balance_after_swap.0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_swap_through_pool() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    println!("\nContracts setup...");
+    let context = init(&worker).await?;
+    let liquidity_provider = context.deployer;
+    println!("✅ Setup done");
+
+    let token_0_amount = U128::from(100_000);
+    let token_1_amount = U128::from(500_000);
+
+    liquidity_provider
+        .call(context.token_0_contract.id(), "ft_transfer_call")
+        .args_json((
+            context.manager_contract.id(),
+            token_0_amount,
+            None::<String>,
+            String::new(),
+        ))
+        .deposit(ONE_YOCTO)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    liquidity_provider
+        .call(context.token_1_contract.id(), "ft_transfer_call")
+        .args_json((
+            context.manager_contract.id(),
+            token_1_amount,
+            None::<String>,
+            String::new(),
+        ))
+        .deposit(ONE_YOCTO)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let mint_params = MintParams {
+        token_0: context.token_0_contract.id().parse().unwrap(),
+        token_1: context.token_1_contract.id().parse().unwrap(),
+        fee: POOL_FEE,
+        lower_tick: 42000,
+        upper_tick: 48000,
+        amount_0_desired: token_0_amount,
+        amount_1_desired: token_1_amount,
+        amount_0_min: U128::from(0),
+        amount_1_min: U128::from(0),
+    };
+    liquidity_provider
+        .call(context.manager_contract.id(), "mint")
+        .args_json(json!({ "params": mint_params }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    println!("✅ Minted liquidity tokens");
+
+    let token_0_balance_before_swap = liquidity_provider
+        .call(context.token_0_contract.id(), "ft_balance_of")
+        .args_json(json!({"account_id": liquidity_provider.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    let token_1_balance_before_swap = liquidity_provider
+        .call(context.token_1_contract.id(), "ft_balance_of")
+        .args_json(json!({"account_id": liquidity_provider.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+
+    let swap_amount = U128::from(777);
+    let msg = PoolTokenReceiverMessage::Swap {
+        zero_for_one: true,
+        sqrt_price_limit_x96: None,
+        amount_out_min: U128::from(1),
+        recipient: liquidity_provider.id().to_string().parse().unwrap(),
+    };
+    let used_amount = liquidity_provider
+        .call(context.token_0_contract.id(), "ft_transfer_call")
+        .args_json((
+            context.pool_id.clone(),
+            swap_amount,
+            None::<String>,
+            near_sdk::serde_json::to_string(&msg).unwrap(),
+        ))
+        .deposit(ONE_YOCTO)
+        .max_gas()
+        .transact()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(used_amount, swap_amount);
+
+    let token_0_balance_after_swap = liquidity_provider
+        .call(context.token_0_contract.id(), "ft_balance_of")
+        .args_json(json!({"account_id": liquidity_provider.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    let token_1_balance_after_swap = liquidity_provider
+        .call(context.token_1_contract.id(), "ft_balance_of")
+        .args_json(json!({"account_id": liquidity_provider.id()}))
+        .view()
+        .await?
+        .json::<U128>()?;
+    assert_eq!(
+        token_0_balance_before_swap.0 - token_0_balance_after_swap.0,
+        swap_amount.0
+    );
     assert!(token_1_balance_before_swap.0 < token_1_balance_after_swap.0);
 
-    println!("✅ Swapped token 0 to token 1");
+    println!("✅ Swapped token 0 to token 1 through `ZswapPool`");
 
     Ok(())
 }
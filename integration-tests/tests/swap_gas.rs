@@ -0,0 +1,234 @@
+use near_sdk::{json_types::U128, ONE_YOCTO};
+use serde_json::json;
+use workspaces::Account;
+
+use zswap_manager::utils::{MintParams, SwapSingleParams};
+use zswap_pool::utils::Slot0;
+
+use helper::*;
+
+mod helper;
+
+/// Tick spacing of the `POOL_FEE` tier
+const TICK_SPACING: i32 = 60;
+
+async fn deposit_and_mint(
+    context: &TestContext,
+    liquidity_provider: &Account,
+    lower_tick: i32,
+    upper_tick: i32,
+    amount_0: U128,
+    amount_1: U128,
+) -> anyhow::Result<()> {
+    for (token, amount) in [
+        (&context.token_0_contract, amount_0),
+        (&context.token_1_contract, amount_1),
+    ] {
+        liquidity_provider
+            .call(token.id(), "ft_transfer_call")
+            .args_json((
+                context.manager_contract.id(),
+                amount,
+                None::<String>,
+                String::new(),
+            ))
+            .deposit(ONE_YOCTO)
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+    }
+
+    let mint_params = MintParams {
+        token_0: context.token_0_contract.id().parse().unwrap(),
+        token_1: context.token_1_contract.id().parse().unwrap(),
+        fee: POOL_FEE,
+        lower_tick,
+        upper_tick,
+        amount_0_desired: amount_0,
+        amount_1_desired: amount_1,
+        amount_0_min: U128::from(0),
+        amount_1_min: U128::from(0),
+    };
+    liquidity_provider
+        .call(context.manager_contract.id(), "mint")
+        .args_json(json!({ "params": mint_params }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_swap_gas() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    println!("\nContracts setup...");
+    let context = init(&worker).await?;
+    let liquidity_provider = context.deployer.clone();
+    println!("✅ Setup done");
+
+    // nested positions around the current tick (~46054) so a large swap crosses several ticks
+    let positions = [(45000, 47040), (45960, 46140), (45540, 46560)];
+    for (lower_tick, upper_tick) in positions {
+        deposit_and_mint(
+            &context,
+            &liquidity_provider,
+            lower_tick,
+            upper_tick,
+            U128::from(100_000),
+            U128::from(10_000_000),
+        )
+        .await?;
+    }
+    println!("✅ Minted {} positions", positions.len());
+
+    let swap_amount = U128::from(200_000);
+    liquidity_provider
+        .call(context.token_0_contract.id(), "ft_transfer_call")
+        .args_json((
+            context.manager_contract.id(),
+            swap_amount,
+            None::<String>,
+            String::new(),
+        ))
+        .deposit(ONE_YOCTO)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let before_slot_0 = liquidity_provider
+        .call(&context.pool_id, "get_slot_0")
+        .view()
+        .await?
+        .json::<Slot0>()?;
+
+    let swap_params = SwapSingleParams {
+        token_in: context.token_0_contract.id().parse().unwrap(),
+        token_out: context.token_1_contract.id().parse().unwrap(),
+        fee: POOL_FEE,
+        amount_in: swap_amount,
+        amount_out_min: U128::from(0),
+        sqrt_price_limit_x96: None,
+    };
+    let outcome = liquidity_provider
+        .call(context.manager_contract.id(), "swap")
+        .args_json(json!({ "params": swap_params }))
+        .max_gas()
+        .transact()
+        .await?;
+    let total_gas_burnt = outcome.total_gas_burnt;
+    outcome.into_result()?;
+
+    let after_slot_0 = liquidity_provider
+        .call(&context.pool_id, "get_slot_0")
+        .view()
+        .await?
+        .json::<Slot0>()?;
+    assert!(after_slot_0.tick < before_slot_0.tick);
+
+    let crossed_ticks = positions
+        .iter()
+        .flat_map(|(lower_tick, upper_tick)| [*lower_tick, *upper_tick])
+        .filter(|tick| (after_slot_0.tick..before_slot_0.tick).contains(tick))
+        .count();
+    println!(
+        "\tSwap moved tick {} -> {}, crossing {} initialized ticks",
+        before_slot_0.tick, after_slot_0.tick, crossed_ticks
+    );
+    println!(
+        "\tTotal gas burnt: {} TGas",
+        total_gas_burnt / 10_u64.pow(12)
+    );
+    println!(
+        "\tGas per crossed tick: {} TGas",
+        total_gas_burnt / 10_u64.pow(12) / (crossed_ticks as u64 + 1)
+    );
+    println!("✅ Measured swap gas");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sparse_swap_gas() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    println!("\nContracts setup...");
+    let context = init(&worker).await?;
+    let liquidity_provider = context.deployer.clone();
+    println!("✅ Setup done");
+
+    // a single full range position, so the bitmap is empty between the current tick and the edges
+    deposit_and_mint(
+        &context,
+        &liquidity_provider,
+        -887220,
+        887220,
+        U128::from(100_000),
+        U128::from(10_000_000),
+    )
+    .await?;
+    println!("✅ Minted a full range position");
+
+    // moves the price from ~100 to ~0.00003, around 150000 ticks down
+    let swap_amount = U128::from(200_000_000);
+    liquidity_provider
+        .call(context.token_0_contract.id(), "ft_transfer_call")
+        .args_json((
+            context.manager_contract.id(),
+            swap_amount,
+            None::<String>,
+            String::new(),
+        ))
+        .deposit(ONE_YOCTO)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let before_slot_0 = liquidity_provider
+        .call(&context.pool_id, "get_slot_0")
+        .view()
+        .await?
+        .json::<Slot0>()?;
+
+    let swap_params = SwapSingleParams {
+        token_in: context.token_0_contract.id().parse().unwrap(),
+        token_out: context.token_1_contract.id().parse().unwrap(),
+        fee: POOL_FEE,
+        amount_in: swap_amount,
+        amount_out_min: U128::from(0),
+        sqrt_price_limit_x96: None,
+    };
+    let outcome = liquidity_provider
+        .call(context.manager_contract.id(), "swap")
+        .args_json(json!({ "params": swap_params }))
+        .max_gas()
+        .transact()
+        .await?;
+    let total_gas_burnt = outcome.total_gas_burnt;
+    outcome.into_result()?;
+
+    let after_slot_0 = liquidity_provider
+        .call(&context.pool_id, "get_slot_0")
+        .view()
+        .await?
+        .json::<Slot0>()?;
+
+    // each bitmap word covers 256 spaced ticks
+    let word = |tick: i32| (tick / TICK_SPACING).div_euclid(256);
+    let words = word(before_slot_0.tick) - word(after_slot_0.tick);
+    assert!(words > 1);
+    println!(
+        "\tSwap moved tick {} -> {} through {} empty bitmap words",
+        before_slot_0.tick, after_slot_0.tick, words
+    );
+    println!(
+        "\tTotal gas burnt: {} TGas",
+        total_gas_burnt / 10_u64.pow(12)
+    );
+    println!("✅ Measured sparse swap gas");
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+//! Compares the limb-level big integer conversions with the decimal string round trips they replaced.
+//!
+//! Run with `cargo bench --package zswap-math-library --bench conversions`.
+//! Native timings track the number of wasm instructions closely, so the ratio between
+//! the two columns is a good proxy for the gas saved on chain. Absolute gas per swap, and per
+//! crossed tick, is reported by the `gas_benchmark` and `swap_gas` integration tests.
+
+use std::hint::black_box;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use zswap_math_library::bit_math::BitMathTrait;
+use zswap_math_library::fixed_point_96;
+use zswap_math_library::num256::{I256, U256};
+use zswap_math_library::num512::U512;
+use zswap_math_library::{swap_math, tick_math};
+
+const ITERATIONS: u32 = 100_000;
+
+fn measure<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn report(name: &str, legacy: Duration, current: Duration) {
+    println!(
+        "{:<28} {:>10?} {:>10?} {:>8.1}x",
+        name,
+        legacy,
+        current,
+        legacy.as_nanos() as f64 / current.as_nanos().max(1) as f64
+    );
+}
+
+fn legacy_as_i256(x: U256) -> I256 {
+    I256::from_str(&x.to_string()).unwrap()
+}
+
+fn legacy_to_u256(x: I256) -> U256 {
+    U256::from_str_radix(&x.abs().to_string(), 10).unwrap()
+}
+
+fn legacy_to_u512(x: U256) -> U512 {
+    U512::from_dec_str(&x.to_string()).unwrap()
+}
+
+fn legacy_most_significant_bit(mut x: U256) -> u8 {
+    let mut r: u8 = 0;
+    for (threshold, shift) in [
+        ("0x100000000000000000000000000000000", 128),
+        ("0x10000000000000000", 64),
+        ("0x100000000", 32),
+        ("0x10000", 16),
+        ("0x100", 8),
+        ("0x10", 4),
+        ("0x4", 2),
+        ("0x2", 1),
+    ] {
+        if x >= U256::from_str(threshold).unwrap() {
+            x >>= shift;
+            r += shift;
+        }
+    }
+    r
+}
+
+fn main() {
+    let sqrt_price_x96 = U256::from_dec_str("792281450588003167884250659085").unwrap();
+    let signed = I256::from_str("-291339464771989622907027621153398088495").unwrap();
+
+    println!(
+        "{:<28} {:>10} {:>10} {:>9}",
+        "per call", "legacy", "current", "speedup"
+    );
+    report(
+        "U256::to_i256",
+        measure(|| legacy_as_i256(black_box(sqrt_price_x96))),
+        measure(|| black_box(sqrt_price_x96).to_i256()),
+    );
+    report(
+        "I256::unsigned_abs",
+        measure(|| legacy_to_u256(black_box(signed))),
+        measure(|| black_box(signed).unsigned_abs()),
+    );
+    report(
+        "U512::from(U256)",
+        measure(|| legacy_to_u512(black_box(sqrt_price_x96))),
+        measure(|| U512::from(black_box(sqrt_price_x96))),
+    );
+    report(
+        "most_significant_bit",
+        measure(|| legacy_most_significant_bit(black_box(sqrt_price_x96))),
+        measure(|| black_box(sqrt_price_x96).most_significant_bit()),
+    );
+    report(
+        "get_q96",
+        measure(|| U256::from(2).pow(U256::from(96))),
+        measure(fixed_point_96::get_q96),
+    );
+
+    // a whole swap step: price to tick conversion plus the step computation itself
+    let sqrt_price_target_x96 = tick_math::get_sqrt_ratio_at_tick(45000);
+    let step = measure(|| {
+        let (sqrt_price_next_x96, _, _, _) = swap_math::compute_swap_step(
+            black_box(sqrt_price_x96),
+            sqrt_price_target_x96,
+            1_517_882_343_751_509_868_544,
+            I256::from(1_000_000_000_000_000_000_u128),
+            3000,
+        );
+        tick_math::get_tick_at_sqrt_ratio(sqrt_price_next_x96)
+    });
+    println!("{:<28} {:>21?}", "swap step", step);
+}
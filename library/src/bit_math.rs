@@ -1,7 +1,14 @@
-use std::str::FromStr;
-
 use crate::num256::U256;
 
+const TWO_POW_128: U256 = U256([0, 0, 1, 0]);
+const TWO_POW_64: U256 = U256([0, 1, 0, 0]);
+const TWO_POW_32: U256 = U256([1 << 32, 0, 0, 0]);
+const TWO_POW_16: U256 = U256([1 << 16, 0, 0, 0]);
+const TWO_POW_8: U256 = U256([1 << 8, 0, 0, 0]);
+const TWO_POW_4: U256 = U256([1 << 4, 0, 0, 0]);
+const TWO_POW_2: U256 = U256([1 << 2, 0, 0, 0]);
+const TWO_POW_1: U256 = U256([1 << 1, 0, 0, 0]);
+
 pub trait BitMathTrait {
     fn most_significant_bit(&self) -> u8;
     fn least_significant_bit(&self) -> u8;
@@ -19,35 +26,35 @@ impl BitMathTrait for U256 {
         assert!(x > U256::zero(), "Value must be greater than 0");
 
         let mut r: u8 = 0;
-        if x >= U256::from_str("0x100000000000000000000000000000000").unwrap() {
+        if x >= TWO_POW_128 {
             x >>= 128;
             r += 128;
         }
-        if x >= U256::from_str("0x10000000000000000").unwrap() {
+        if x >= TWO_POW_64 {
             x >>= 64;
             r += 64;
         }
-        if x >= U256::from_str("0x100000000").unwrap() {
+        if x >= TWO_POW_32 {
             x >>= 32;
             r += 32;
         }
-        if x >= U256::from_str("0x10000").unwrap() {
+        if x >= TWO_POW_16 {
             x >>= 16;
             r += 16;
         }
-        if x >= U256::from_str("0x100").unwrap() {
+        if x >= TWO_POW_8 {
             x >>= 8;
             r += 8;
         }
-        if x >= U256::from_str("0x10").unwrap() {
+        if x >= TWO_POW_4 {
             x >>= 4;
             r += 4;
         }
-        if x >= U256::from_str("0x4").unwrap() {
+        if x >= TWO_POW_2 {
             x >>= 2;
             r += 2;
         }
-        if x >= U256::from_str("0x2").unwrap() {
+        if x >= TWO_POW_1 {
             r += 1;
         }
         r
@@ -69,22 +76,22 @@ impl BitMathTrait for U256 {
         } else {
             x >>= 128;
         }
-        if x & U256::MAX >> 64 > U256::zero() {
+        if x & U256::from(u64::MAX) > U256::zero() {
             r -= 64;
         } else {
             x >>= 64;
         }
-        if x & U256::MAX >> 32 > U256::zero() {
+        if x & U256::from(u32::MAX) > U256::zero() {
             r -= 32;
         } else {
             x >>= 32;
         }
-        if x & U256::MAX >> 16 > U256::zero() {
+        if x & U256::from(u16::MAX) > U256::zero() {
             r -= 16;
         } else {
             x >>= 16;
         }
-        if x & U256::MAX >> 8 > U256::zero() {
+        if x & U256::from(u8::MAX) > U256::zero() {
             r -= 8;
         } else {
             x >>= 8;
@@ -149,4 +156,16 @@ mod tests {
             assert_eq!(msb, x.most_significant_bit());
         }
     }
+
+    #[test]
+    fn test_least_significant_bit_above_the_mask() {
+        // each step masks the low half of what is left, not `U256::MAX >> n`: a bit just above
+        // the half used to be taken as set inside it
+        for i in 0..=255u8 {
+            let x = U256::one() << i;
+            assert_eq!(x.least_significant_bit(), i);
+            assert_eq!((x | (U256::one() << 255)).least_significant_bit(), i);
+        }
+        assert_eq!((U256::one() << 70).least_significant_bit(), 70);
+    }
 }
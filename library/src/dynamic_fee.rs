@@ -0,0 +1,67 @@
+//! !ADDED
+//! Fee of dynamic-fee pools, shared by the pool contract and the simulator so that both charge
+//! the same fee for the same moves. The caller passes the block height in.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Fee added for each tick of price movement per block, in hundredths of a bip, so that the fee
+/// keeps up with the move an arbitrageur can take from one block to the next
+pub const FEE_PER_TICK_OF_VOLATILITY: u32 = 100;
+
+/// State of a dynamic-fee pool, whose fee rises from the pool's `fee` at rest up to `max_fee`
+/// with volatility.
+///
+/// Volatility is the price movement per block, in ticks, measured between the first swaps of
+/// two blocks. It follows a move at once and halves for every block after it.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DynamicFee {
+    pub max_fee: u32,
+    pub volatility: u32,
+    /// Tick before the first swap of `last_block`
+    pub reference_tick: i32,
+    pub last_block: u64,
+}
+
+impl DynamicFee {
+    pub fn new(max_fee: u32, tick: i32, block: u64) -> Self {
+        Self {
+            max_fee,
+            volatility: 0,
+            reference_tick: tick,
+            last_block: block,
+        }
+    }
+
+    /// Volatility the first swap of `block` sees, with the pool at `tick`
+    pub fn volatility_at(&self, tick: i32, block: u64) -> u32 {
+        if block <= self.last_block {
+            return self.volatility;
+        }
+
+        let elapsed = block - self.last_block;
+        let moved = (tick.abs_diff(self.reference_tick) as u64 / elapsed) as u32;
+        let decayed = if elapsed >= 32 {
+            0
+        } else {
+            self.volatility >> elapsed
+        };
+        moved.max(decayed)
+    }
+
+    /// Measures the volatility on the first swap of `block`, with the pool at `tick`. Later swaps
+    /// of the same block keep that measure
+    pub fn update(&mut self, tick: i32, block: u64) {
+        if block > self.last_block {
+            self.volatility = self.volatility_at(tick, block);
+            self.reference_tick = tick;
+            self.last_block = block;
+        }
+    }
+
+    /// Fee for `volatility`, from `base_fee` up to `max_fee`
+    pub fn fee(&self, base_fee: u32, volatility: u32) -> u32 {
+        base_fee
+            .saturating_add(volatility.saturating_mul(FEE_PER_TICK_OF_VOLATILITY))
+            .min(self.max_fee)
+    }
+}
@@ -0,0 +1,8 @@
+pub const INVALID_PRICE: &str = "Invalid price";
+pub const PRICE_OUT_OF_RANGE: &str = "Price out of range";
+pub const INVALID_DECIMALS: &str = "Invalid decimals";
+pub const INVALID_PRECISION: &str = "Invalid precision";
+pub const INVALID_TICK: &str = "Invalid tick";
+pub const INVALID_TICK_SPACING: &str = "Invalid tick spacing";
+pub const INVALID_PRICE_LIMIT: &str = "Invalid price limit";
+pub const NOT_ENOUGH_LIQUIDITY: &str = "Not enough liquidity";
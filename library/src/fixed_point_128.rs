@@ -1,8 +1,10 @@
 use crate::num256::U256;
 
 pub const RESOLUTION: u8 = 128;
+pub const Q128: U256 = U256([0, 0, 1, 0]);
+
 pub fn get_q128() -> U256 {
-    U256::from(2).pow(U256::from(128))
+    Q128
 }
 
 //
@@ -1,6 +1,8 @@
 use crate::num256::U256;
 
 pub const RESOLUTION: u8 = 96;
+pub const Q96: U256 = U256([0, 1 << 32, 0, 0]);
+
 pub fn get_q96() -> U256 {
-    U256::from(2).pow(U256::from(96))
+    Q96
 }
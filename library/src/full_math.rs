@@ -31,10 +31,10 @@ impl MathOps for U256 {
         }
     }
     fn sub(self, other: Self) -> Self {
-        self.overflowing_sub(other).0
+        self.wrapping_sub(other)
     }
     fn add(self, other: Self) -> Self {
-        self.overflowing_add(other).0
+        self.wrapping_add(other)
     }
     fn div(self, other: Self) -> Self {
         self / other
@@ -43,7 +43,7 @@ impl MathOps for U256 {
         self % other
     }
     fn mul(self, other: Self) -> Self {
-        self.overflowing_mul(other).0
+        self.wrapping_mul(other)
     }
     // https://locklessinc.com/articles/256bit_arithmetic/
 
@@ -299,6 +299,9 @@ mod tests {
     use super::*;
 
     use crate::full_math::MathOps;
+    use crate::num512::U512;
+    use crate::strategies;
+    use proptest::prelude::*;
 
     #[test]
     fn test_mulmod() {
@@ -587,4 +590,25 @@ mod tests {
             }
         }
     }
+
+    proptest! {
+        // U512 holds any product of two U256, so plain division is the oracle
+        #[test]
+        fn prop_mul_div_matches_u512(
+            a in strategies::u256(),
+            b in strategies::u256(),
+            denominator in strategies::u256(),
+        ) {
+            prop_assume!(!denominator.is_zero());
+            let (quotient, remainder) = (U512::from(a) * U512::from(b)).div_mod(U512::from(denominator));
+
+            if let Ok(expected) = U256::try_from(quotient) {
+                prop_assert_eq!(FullMath::mul_div(a, b, denominator), expected);
+            }
+            let quotient_up = if remainder.is_zero() { quotient } else { quotient + 1 };
+            if let Ok(expected) = U256::try_from(quotient_up) {
+                prop_assert_eq!(FullMath::mul_div_rounding_up(a, b, denominator), expected);
+            }
+        }
+    }
 }
@@ -0,0 +1,109 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::num256::U256;
+
+/// 256-bit unsigned integer carried as a decimal string in JSON, like `near_sdk::json_types::U128`.
+///
+/// Q64.96 sqrt prices go up to `TickConstants::max_sqrt_ratio()`, about 2^160, so they do not fit in `U128`.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    BorshDeserialize,
+    BorshSerialize,
+)]
+pub struct JsonU256(pub U256);
+
+impl From<U256> for JsonU256 {
+    fn from(v: U256) -> Self {
+        Self(v)
+    }
+}
+
+impl From<u128> for JsonU256 {
+    fn from(v: u128) -> Self {
+        Self(U256::from(v))
+    }
+}
+
+impl From<JsonU256> for U256 {
+    fn from(v: JsonU256) -> U256 {
+        v.0
+    }
+}
+
+impl Serialize for JsonU256 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonU256 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        U256::from_dec_str(&s)
+            .map(Self)
+            .map_err(|err| de::Error::custom(format!("invalid 256-bit integer {}: {:?}", s, err)))
+    }
+}
+
+#[cfg(feature = "abi")]
+impl schemars::JsonSchema for JsonU256 {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        String::schema_name()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tick_math::TickConstants;
+
+    #[test]
+    fn test_json_u256_round_trip() {
+        let max_sqrt_ratio = JsonU256(TickConstants::max_sqrt_ratio());
+        let json = serde_json::to_string(&max_sqrt_ratio).unwrap();
+        assert_eq!(
+            json,
+            "\"1461446703485210103287273052203988822378723970342\""
+        );
+        assert_eq!(
+            serde_json::from_str::<JsonU256>(&json).unwrap(),
+            max_sqrt_ratio
+        );
+
+        let sqrt_price_x96 = JsonU256::from(10 * 2_u128.pow(96));
+        assert_eq!(
+            serde_json::to_string(&sqrt_price_x96).unwrap(),
+            "\"792281625142643375935439503360\""
+        );
+    }
+
+    #[test]
+    fn test_json_u256_rejects_invalid_input() {
+        assert!(serde_json::from_str::<JsonU256>("\"-1\"").is_err());
+        assert!(serde_json::from_str::<JsonU256>("\"0x10\"").is_err());
+        assert!(serde_json::from_str::<JsonU256>("42").is_err());
+    }
+}
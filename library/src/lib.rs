@@ -1,7 +1,10 @@
 pub mod bit_math;
+pub mod dynamic_fee;
+pub mod error;
 pub mod fixed_point_128;
 pub mod fixed_point_96;
 pub mod full_math;
+pub mod json_types;
 pub mod liquidity_math;
 pub mod num160;
 pub mod num24;
@@ -10,7 +13,11 @@ pub mod num512;
 pub mod num56;
 pub mod pool_account;
 pub mod position;
+pub mod price_math;
 pub mod sqrt_price_math;
+#[cfg(test)]
+mod strategies;
+pub mod swap;
 pub mod swap_math;
 pub mod tick;
 pub mod tick_bitmap;
@@ -19,52 +26,51 @@ pub mod utils;
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use crate::num256::I256;
+    use crate::sqrt_price_math::{get_amount_0_delta_signed, get_amount_1_delta_signed};
+    use crate::tick_math::get_sqrt_ratio_at_tick;
+
+    #[test]
+    fn test_calc_amount_0_delta() {
+        let amount_0 = get_amount_0_delta_signed(
+            get_sqrt_ratio_at_tick(85176),
+            get_sqrt_ratio_at_tick(86129),
+            1517882343751509868544,
+        );
+
+        assert_eq!(amount_0, I256::new(998833192822975409)); // 0.998833192822975409
+    }
+
+    #[test]
+    fn test_calc_amount_1_delta() {
+        let amount_1 = get_amount_1_delta_signed(
+            get_sqrt_ratio_at_tick(84222),
+            get_sqrt_ratio_at_tick(85176),
+            1517882343751509868544,
+        );
+
+        assert_eq!(amount_1, I256::new(4999187247111820044641)); // 4999.187247111820044641
+    }
+
+    #[test]
+    fn test_calc_amount_0_delta_negative() {
+        let amount_0 = get_amount_0_delta_signed(
+            get_sqrt_ratio_at_tick(85176),
+            get_sqrt_ratio_at_tick(86129),
+            -1517882343751509868544,
+        );
+
+        assert_eq!(amount_0, I256::new(-998833192822975408)); // -0.998833192822975408
+    }
+
+    #[test]
+    fn test_calc_amount_1_delta_negative() {
+        let amount_1 = get_amount_1_delta_signed(
+            get_sqrt_ratio_at_tick(84222),
+            get_sqrt_ratio_at_tick(85176),
+            -1517882343751509868544,
+        );
 
-    // use crate::lib::Math;
-    // use crate::lib::TickMath;
-    //
-    // #[test]
-    // fn test_calc_amount_0_delta() {
-    //   let amount0 = Math::calc_amount_0_delta(
-    //     TickMath::get_sqrt_ratio_at_tick(85176),
-    //     TickMath::get_sqrt_ratio_at_tick(86129),
-    //     1517882343751509868544,
-    //   );
-    //
-    //   assert_eq!(amount0, 0.998833192822975409);
-    // }
-    //
-    // #[test]
-    // fn test_calc_amount_1_delta() {
-    //   let amount1 = Math::calc_amount_1_delta(
-    //     TickMath::get_sqrt_ratio_at_tick(84222),
-    //     TickMath::get_sqrt_ratio_at_tick(85176),
-    //     1517882343751509868544,
-    //   );
-    //
-    //   assert_eq!(amount1, 4999.187247111820044641);
-    // }
-    //
-    // #[test]
-    // fn test_calc_amount_0_delta_negative() {
-    //   let amount0 = Math::calc_amount_0_delta(
-    //     TickMath::get_sqrt_ratio_at_tick(85176),
-    //     TickMath::get_sqrt_ratio_at_tick(86129),
-    //     -1517882343751509868544,
-    //   );
-    //
-    //   assert_eq!(amount0, -0.998833192822975408);
-    // }
-    //
-    // #[test]
-    // fn test_calc_amount_1_delta_negative() {
-    //   let amount1 = Math::calc_amount_1_delta(
-    //     TickMath::get_sqrt_ratio_at_tick(84222),
-    //     TickMath::get_sqrt_ratio_at_tick(85176),
-    //     -1517882343751509868544,
-    //   );
-    //
-    //   assert_eq!(amount1, -4999.187247111820044640);
-    // }
+        assert_eq!(amount_1, I256::new(-4999187247111820044640)); // -4999.187247111820044640
+    }
 }
@@ -1,6 +1,7 @@
 use crate::fixed_point_96;
 use crate::num256::U256;
 use crate::num512::U512;
+use crate::sqrt_price_math;
 
 /// @notice Add a signed liquidity delta to liquidity and revert if it overflows or underflows
 /// @param x The liquidity before change
@@ -30,10 +31,10 @@ pub fn get_liquidity_for_amount_0(
         (_sqrt_price_a_x96, _sqrt_price_b_x96)
     };
 
-    let amount_0 = U512::from_dec_str(&amount_0.to_string()).unwrap();
-    let sqrt_price_a_x96 = U512::from_dec_str(&sqrt_price_a_x96.to_string()).unwrap();
-    let sqrt_price_b_x96 = U512::from_dec_str(&sqrt_price_b_x96.to_string()).unwrap();
-    let fixed_point_96 = U512::from_dec_str(&fixed_point_96::get_q96().to_string()).unwrap();
+    let amount_0 = U512::from(amount_0);
+    let sqrt_price_a_x96 = U512::from(sqrt_price_a_x96);
+    let sqrt_price_b_x96 = U512::from(sqrt_price_b_x96);
+    let fixed_point_96 = U512::from(fixed_point_96::Q96);
 
     let intermediate = (sqrt_price_a_x96 * sqrt_price_b_x96) / fixed_point_96;
     let liquidity = (amount_0 * intermediate) / (sqrt_price_b_x96 - sqrt_price_a_x96);
@@ -63,10 +64,10 @@ pub fn get_liquidity_for_amount_1(
         (_sqrt_price_a_x96, _sqrt_price_b_x96)
     };
 
-    let amount_1 = U512::from_dec_str(&amount_1.to_string()).unwrap();
-    let sqrt_price_a_x96 = U512::from_dec_str(&sqrt_price_a_x96.to_string()).unwrap();
-    let sqrt_price_b_x96 = U512::from_dec_str(&sqrt_price_b_x96.to_string()).unwrap();
-    let fixed_point_96 = U512::from_dec_str(&fixed_point_96::get_q96().to_string()).unwrap();
+    let amount_1 = U512::from(amount_1);
+    let sqrt_price_a_x96 = U512::from(sqrt_price_a_x96);
+    let sqrt_price_b_x96 = U512::from(sqrt_price_b_x96);
+    let fixed_point_96 = U512::from(fixed_point_96::Q96);
     let liquidity = (amount_1 * fixed_point_96) / (sqrt_price_b_x96 - sqrt_price_a_x96);
 
     // let liquidity = FullMath::mul_div(
@@ -108,6 +109,61 @@ pub fn get_liquidity_for_amounts(
     liquidity
 }
 
+/// $\Delta x = L \frac{\sqrt{P_u} - \sqrt{P_l}}{\sqrt{P_u} \sqrt{P_l}}$
+pub fn get_amount_0_for_liquidity(
+    sqrt_price_a_x96: U256,
+    sqrt_price_b_x96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> u128 {
+    sqrt_price_math::get_amount_0_delta(sqrt_price_a_x96, sqrt_price_b_x96, liquidity, round_up)
+        .as_u128()
+}
+
+/// $\Delta y = L (\sqrt{P_u} - \sqrt{P_l})$
+pub fn get_amount_1_for_liquidity(
+    sqrt_price_a_x96: U256,
+    sqrt_price_b_x96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> u128 {
+    sqrt_price_math::get_amount_1_delta(sqrt_price_a_x96, sqrt_price_b_x96, liquidity, round_up)
+        .as_u128()
+}
+
+/// Returns the token amounts held by `liquidity` between the two prices at the current price.
+/// Round up for what a mint has to pay and down for what a burn releases.
+pub fn get_amounts_for_liquidity(
+    sqrt_price_x96: U256,
+    _sqrt_price_a_x96: U256,
+    _sqrt_price_b_x96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> [u128; 2] {
+    let (sqrt_price_a_x96, sqrt_price_b_x96) = if _sqrt_price_a_x96 > _sqrt_price_b_x96 {
+        (_sqrt_price_b_x96, _sqrt_price_a_x96)
+    } else {
+        (_sqrt_price_a_x96, _sqrt_price_b_x96)
+    };
+
+    if sqrt_price_x96 <= sqrt_price_a_x96 {
+        [
+            get_amount_0_for_liquidity(sqrt_price_a_x96, sqrt_price_b_x96, liquidity, round_up),
+            0,
+        ]
+    } else if sqrt_price_x96 < sqrt_price_b_x96 {
+        [
+            get_amount_0_for_liquidity(sqrt_price_x96, sqrt_price_b_x96, liquidity, round_up),
+            get_amount_1_for_liquidity(sqrt_price_a_x96, sqrt_price_x96, liquidity, round_up),
+        ]
+    } else {
+        [
+            0,
+            get_amount_1_for_liquidity(sqrt_price_a_x96, sqrt_price_b_x96, liquidity, round_up),
+        ]
+    }
+}
+
 pub fn add_liquidity(x: u128, y: i128) -> u128 {
     let z: u128 = if y < 0 {
         x - y.unsigned_abs()
@@ -174,6 +230,68 @@ mod tests {
         assert_eq!(liquidity, 54505);
     }
 
+    #[test]
+    fn test_get_amounts_for_liquidity() {
+        let sqrt_price_x96 = U256::from_dec_str("792281450588003167884250659085").unwrap(); // tick = 46056
+        let sqrt_price_a_x96 = U256::from_dec_str("646922711029656030980122427077").unwrap(); // tick = 42000
+        let sqrt_price_b_x96 = U256::from_dec_str("873241221460953509178849710283").unwrap(); // tick = 48000
+
+        // token 1 bounds the liquidity bought with 1_000 / 100_000, and rounding down never gives back more
+        let amounts_down = get_amounts_for_liquidity(
+            sqrt_price_x96,
+            sqrt_price_a_x96,
+            sqrt_price_b_x96,
+            54505,
+            false,
+        );
+        assert!(amounts_down[0] <= 1_000 && amounts_down[1] <= 100_000);
+        assert_eq!(amounts_down, [505, 99_999]);
+
+        let amounts_up = get_amounts_for_liquidity(
+            sqrt_price_x96,
+            sqrt_price_b_x96,
+            sqrt_price_a_x96,
+            54505,
+            true,
+        );
+        assert_eq!(amounts_up[0], amounts_down[0] + 1);
+        assert_eq!(amounts_up[1], amounts_down[1] + 1);
+
+        // out of range positions hold a single token
+        let below = get_amounts_for_liquidity(
+            sqrt_price_a_x96,
+            sqrt_price_a_x96,
+            sqrt_price_b_x96,
+            54505,
+            false,
+        );
+        assert_eq!(
+            below,
+            [
+                get_amount_0_for_liquidity(sqrt_price_a_x96, sqrt_price_b_x96, 54505, false),
+                0
+            ]
+        );
+        let above = get_amounts_for_liquidity(
+            sqrt_price_b_x96,
+            sqrt_price_a_x96,
+            sqrt_price_b_x96,
+            54505,
+            false,
+        );
+        assert_eq!(
+            above,
+            [
+                0,
+                get_amount_1_for_liquidity(sqrt_price_a_x96, sqrt_price_b_x96, 54505, false)
+            ]
+        );
+        assert_eq!(
+            get_amounts_for_liquidity(sqrt_price_x96, sqrt_price_a_x96, sqrt_price_b_x96, 0, true),
+            [0, 0]
+        );
+    }
+
     #[test]
     fn test_add_liquidity() {
         // TODO: @galin-chung-nguyen
@@ -1,7 +1,4 @@
-use ethnum::I256;
-use std::str::FromStr;
-
-use crate::num256::U256;
+use crate::num256::{I256, U256};
 
 pub type U160 = U256;
 pub type I160 = I256;
@@ -9,12 +6,11 @@ pub type I160 = I256;
 pub struct Constants;
 
 impl Constants {
-    fn get_u160_max() -> U160 {
-        (U160::one() << 160) - U160::one()
-    }
+    /// 2^160 - 1
+    pub const U160_MAX: U160 = U256([u64::MAX, u64::MAX, u32::MAX as u64, 0]);
 
-    fn get_i160_max() -> I160 {
-        (I160::ONE << I160::new(59)) - I160::new(2)
+    fn get_u160_max() -> U160 {
+        Self::U160_MAX
     }
 }
 
@@ -36,14 +32,9 @@ pub trait AsI160 {
 }
 
 impl AsI160 for I256 {
+    /// Keeps the lowest 160 bits and sign extends bit 159, like Solidity `int160(x)`
     fn as_i160(&self) -> I160 {
-        let u160_max = I256::from_str(&Constants::get_u160_max().to_string()).unwrap();
-        let x = self & u160_max;
-        if (x & (I256::ONE << 159)) == I256::ZERO {
-            x as I160
-        } else {
-            (x | (!u160_max)) as I160
-        }
+        (*self << 96) >> 96
     }
 }
 
@@ -62,40 +53,6 @@ impl Num160Trait for U160 {
     }
 }
 
-impl Num160Trait for I160 {
-    fn add160(&self, other: Self) -> Self {
-        let sum = self.as_i160() + other.as_i160();
-        assert!(
-            sum <= Constants::get_i160_max(),
-            "U160 overflow when adding"
-        );
-        sum.as_i160()
-    }
-}
-
-// fn printBits2160(x: I2160) {
-//   print!("{} => ", x);
-//   for i in 0..=255 {
-//     print!("{}", (x >> (255 - i)) & I2160::ONE);
-//   }
-//   println!();
-// }
-//
-// fn printBits128(x: i128) {
-//   print!("{} => ", x);
-//   for i in 0..=127 {
-//     print!("{}", (x >> (127 - i)) & 1);
-//   }
-//   println!();
-// }
-// fn printBits32(x: i32) {
-//   print!("{} => ", x);
-//   for i in 0..=31 {
-//     print!("{}", (x >> (31 - i)) & 1);
-//   }
-//   println!();
-// }
-
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod tests {
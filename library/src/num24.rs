@@ -1,6 +1,4 @@
-use ethnum::{AsI256, I256};
-
-use crate::num256::U256;
+use crate::num256::{I256, U256};
 
 pub type U24 = u32;
 pub type I24 = i32;
@@ -11,11 +9,6 @@ impl Constants {
     fn get_u24_max() -> U24 {
         (1 << 24) - 1
     }
-
-    fn get_i24_max() -> I24 {
-        let tmp: u32 = 1 << 23;
-        (tmp as I24) - 2
-    }
 }
 
 ////////////////////////////////////////////
@@ -70,51 +63,11 @@ impl AsI24 for i128 {
 }
 
 impl AsI24 for I256 {
+    /// Keeps the lowest 24 bits and sign extends bit 23, like Solidity `int24(x)`
     fn as_i24(&self) -> I24 {
-        let x = self & Constants::get_u24_max().as_i256();
-        if (x & (1 << 23)) == 0 {
-            x.as_i32() as I24
-        } else {
-            (x | (!Constants::get_u24_max().as_i256())).as_i32() as I24
-        }
-    }
-}
-
-pub trait Num24Trait {
-    fn add24(self, other: Self) -> Self;
-}
-
-impl Num24Trait for U24 {
-    fn add24(self, other: Self) -> Self {
-        let sum: u32 = self.as_u24() + other.as_u24();
-        assert!(sum <= Constants::get_u24_max(), "U24 overflow when adding");
-        sum.as_u24()
-    }
-}
-
-impl Num24Trait for I24 {
-    fn add24(self, other: Self) -> Self {
-        let sum: i32 = self.as_i24() + other.as_i24();
-        assert!(sum <= Constants::get_i24_max(), "U24 overflow when adding");
-        sum.as_i24()
+        self.as_i32().as_i24()
     }
 }
-//
-// fn printBits256(x: I256) {
-//   print!("{} => ", x);
-//   for i in 0..=255 {
-//     print!("{}", (x >> (255 - i)) & I256::ONE);
-//   }
-//   println!();
-// }
-//
-// fn printBits32(x: i32) {
-//   print!("{} => ", x);
-//   for i in 0..=31 {
-//     print!("{}", (x >> (31 - i)) & 1);
-//   }
-//   println!();
-// }
 
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
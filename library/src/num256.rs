@@ -1,8 +1,15 @@
 #![allow(clippy::assign_op_pattern)]
 
-use ethnum::I256;
-use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use uint::construct_uint;
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::{
+    Add, AddAssign, BitAnd, BitOr, BitOrAssign, BitXor, Div, Mul, MulAssign, Neg, Not, Rem, Shl,
+    Shr, Sub, SubAssign,
+};
+use core::str::FromStr;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use uint::{construct_uint, FromDecStrErr};
 
 construct_uint! {
     /// 256-bit unsigned integer.
@@ -11,22 +18,539 @@ construct_uint! {
     pub struct U256(4);
 }
 
-pub trait AsI256 {
-    fn as_i256(&self) -> I256;
+impl U256 {
+    /// Addition modulo 2^256, like Solidity `unchecked { a + b }`
+    pub fn wrapping_add(self, other: Self) -> Self {
+        self.overflowing_add(other).0
+    }
+
+    /// Subtraction modulo 2^256, like Solidity `unchecked { a - b }`
+    pub fn wrapping_sub(self, other: Self) -> Self {
+        self.overflowing_sub(other).0
+    }
+
+    /// Multiplication modulo 2^256, like Solidity `unchecked { a * b }`
+    pub fn wrapping_mul(self, other: Self) -> Self {
+        self.overflowing_mul(other).0
+    }
+
+    /// Converts to `I256`, panicking if the value doesn't fit, like Solidity `SafeCast.toInt256`
+    pub fn to_i256(self) -> I256 {
+        let value = I256(self);
+        assert!(!value.is_negative(), "U256 overflows I256");
+        value
+    }
+}
+
+/// 256-bit signed integer stored as the two's complement of a `U256`.
+///
+/// Operators follow Solidity 0.8 `int256`: `+`, `-`, `*`, `/` and unary `-` panic on overflow,
+/// division truncates toward zero, `%` never overflows (`MIN % -1` is 0), `<<` drops the bits shifted out and `>>` is arithmetic.
+/// The `wrapping_*`, `checked_*` and `saturating_*` methods cover the other behaviours.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, BorshDeserialize, BorshSerialize)]
+pub struct I256(U256);
+
+impl I256 {
+    pub const ZERO: Self = Self(U256([0, 0, 0, 0]));
+    pub const ONE: Self = Self(U256([1, 0, 0, 0]));
+    pub const MINUS_ONE: Self = Self(U256([u64::MAX, u64::MAX, u64::MAX, u64::MAX]));
+    pub const MIN: Self = Self(U256([0, 0, 0, 1 << 63]));
+    pub const MAX: Self = Self(U256([u64::MAX, u64::MAX, u64::MAX, u64::MAX >> 1]));
+
+    pub const fn new(value: i128) -> Self {
+        let sign = if value < 0 { u64::MAX } else { 0 };
+        Self(U256([value as u64, (value >> 64) as u64, sign, sign]))
+    }
+
+    /// Builds a value from its high and low 128-bit halves
+    pub const fn from_words(hi: i128, lo: i128) -> Self {
+        Self(U256([
+            lo as u64,
+            (lo >> 64) as u64,
+            hi as u64,
+            (hi >> 64) as u64,
+        ]))
+    }
+
+    /// Splits the value into its high and low 128-bit halves
+    pub const fn into_words(self) -> (i128, i128) {
+        let U256(words) = self.0;
+        (
+            ((words[3] as u128) << 64 | words[2] as u128) as i128,
+            ((words[1] as u128) << 64 | words[0] as u128) as i128,
+        )
+    }
+
+    /// Reinterprets the two's complement bits, like Solidity `int256(x)` on a `uint256`
+    pub const fn from_bits(bits: U256) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the two's complement bits, like Solidity `uint256(x)` on an `int256`
+    pub const fn to_bits(self) -> U256 {
+        self.0
+    }
+
+    pub const fn is_negative(self) -> bool {
+        let U256(words) = self.0;
+        words[3] >> 63 == 1
+    }
+
+    pub fn is_positive(self) -> bool {
+        !self.is_negative() && !self.0.is_zero()
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// Returns the absolute value, panicking on `I256::MIN`
+    pub fn abs(self) -> Self {
+        if self.is_negative() {
+            -self
+        } else {
+            self
+        }
+    }
+
+    pub fn unsigned_abs(self) -> U256 {
+        if self.is_negative() {
+            (!self.0).wrapping_add(U256::one())
+        } else {
+            self.0
+        }
+    }
+
+    /// Truncates to the lowest 128 bits
+    pub fn as_i128(self) -> i128 {
+        self.0.low_u128() as i128
+    }
+
+    /// Truncates to the lowest 128 bits, reinterpreted as unsigned
+    pub fn as_u128(self) -> u128 {
+        self.0.low_u128()
+    }
+
+    /// Truncates to the lowest 32 bits
+    pub fn as_i32(self) -> i32 {
+        self.0.low_u64() as i32
+    }
+
+    pub fn overflowing_add(self, other: Self) -> (Self, bool) {
+        let sum = Self(self.0.wrapping_add(other.0));
+        let overflow =
+            self.is_negative() == other.is_negative() && sum.is_negative() != self.is_negative();
+        (sum, overflow)
+    }
+
+    pub fn overflowing_sub(self, other: Self) -> (Self, bool) {
+        let difference = Self(self.0.wrapping_sub(other.0));
+        let overflow = self.is_negative() != other.is_negative()
+            && difference.is_negative() != self.is_negative();
+        (difference, overflow)
+    }
+
+    pub fn overflowing_mul(self, other: Self) -> (Self, bool) {
+        let product = Self(self.0.wrapping_mul(other.0));
+        let (magnitude, magnitude_overflow) =
+            self.unsigned_abs().overflowing_mul(other.unsigned_abs());
+        let negative = self.is_negative() != other.is_negative();
+        let overflow =
+            magnitude_overflow || (!magnitude.is_zero() && product.is_negative() != negative);
+        (product, overflow)
+    }
+
+    pub fn overflowing_neg(self) -> (Self, bool) {
+        Self::ZERO.overflowing_sub(self)
+    }
+
+    /// Division truncating toward zero; `MIN / -1` overflows to `MIN`
+    pub fn overflowing_div(self, other: Self) -> (Self, bool) {
+        assert!(!other.is_zero(), "I256 division by zero");
+        if self == Self::MIN && other == Self::MINUS_ONE {
+            return (Self::MIN, true);
+        }
+        let quotient = Self(self.unsigned_abs() / other.unsigned_abs());
+        if self.is_negative() != other.is_negative() {
+            (quotient.wrapping_neg(), false)
+        } else {
+            (quotient, false)
+        }
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        checked(self.overflowing_add(other))
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        checked(self.overflowing_sub(other))
+    }
+
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        checked(self.overflowing_mul(other))
+    }
+
+    pub fn checked_neg(self) -> Option<Self> {
+        checked(self.overflowing_neg())
+    }
+
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        if other.is_zero() {
+            None
+        } else {
+            checked(self.overflowing_div(other))
+        }
+    }
+
+    pub fn wrapping_add(self, other: Self) -> Self {
+        self.overflowing_add(other).0
+    }
+
+    pub fn wrapping_sub(self, other: Self) -> Self {
+        self.overflowing_sub(other).0
+    }
+
+    pub fn wrapping_mul(self, other: Self) -> Self {
+        self.overflowing_mul(other).0
+    }
+
+    pub fn wrapping_neg(self) -> Self {
+        self.overflowing_neg().0
+    }
+
+    pub fn wrapping_div(self, other: Self) -> Self {
+        self.overflowing_div(other).0
+    }
+
+    pub fn saturating_add(self, other: Self) -> Self {
+        self.checked_add(other)
+            .unwrap_or_else(|| saturated(other.is_negative()))
+    }
+
+    pub fn saturating_sub(self, other: Self) -> Self {
+        self.checked_sub(other)
+            .unwrap_or_else(|| saturated(!other.is_negative()))
+    }
+
+    pub fn saturating_mul(self, other: Self) -> Self {
+        self.checked_mul(other)
+            .unwrap_or_else(|| saturated(self.is_negative() != other.is_negative()))
+    }
+
+    pub fn saturating_neg(self) -> Self {
+        self.checked_neg().unwrap_or(Self::MAX)
+    }
+}
+
+fn checked((value, overflow): (I256, bool)) -> Option<I256> {
+    if overflow {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+fn saturated(negative: bool) -> I256 {
+    if negative {
+        I256::MIN
+    } else {
+        I256::MAX
+    }
+}
+
+macro_rules! impl_i256_from {
+    ($($t:ty),*) => {$(
+        impl From<$t> for I256 {
+            fn from(value: $t) -> Self {
+                Self::new(value as i128)
+            }
+        }
+    )*};
+}
+
+impl_i256_from!(i8, i16, i32, i64, i128, u8, u16, u32, u64);
+
+impl From<u128> for I256 {
+    fn from(value: u128) -> Self {
+        Self(U256::from(value))
+    }
+}
+
+impl Ord for I256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.is_negative(), other.is_negative()) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            // two's complement keeps the unsigned order within one sign
+            _ => self.0.cmp(&other.0),
+        }
+    }
+}
+
+impl PartialOrd for I256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq<i128> for I256 {
+    fn eq(&self, other: &i128) -> bool {
+        *self == Self::new(*other)
+    }
+}
+
+impl PartialOrd<i128> for I256 {
+    fn partial_cmp(&self, other: &i128) -> Option<Ordering> {
+        Some(self.cmp(&Self::new(*other)))
+    }
+}
+
+macro_rules! impl_i256_checked_op {
+    ($op:ident, $method:ident, $assign_op:ident, $assign_method:ident, $checked:ident) => {
+        impl $op for I256 {
+            type Output = Self;
+
+            fn $method(self, other: Self) -> Self {
+                self.$checked(other)
+                    .unwrap_or_else(|| panic!("I256 overflow in {}", stringify!($method)))
+            }
+        }
+
+        impl $op<i128> for I256 {
+            type Output = Self;
+
+            fn $method(self, other: i128) -> Self {
+                self.$method(Self::new(other))
+            }
+        }
+
+        impl $assign_op for I256 {
+            fn $assign_method(&mut self, other: Self) {
+                *self = self.$method(other);
+            }
+        }
+    };
+}
+
+impl_i256_checked_op!(Add, add, AddAssign, add_assign, checked_add);
+impl_i256_checked_op!(Sub, sub, SubAssign, sub_assign, checked_sub);
+impl_i256_checked_op!(Mul, mul, MulAssign, mul_assign, checked_mul);
+
+impl Div for I256 {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        self.checked_div(other)
+            .unwrap_or_else(|| panic!("I256 overflow in div"))
+    }
+}
+
+impl Rem for I256 {
+    type Output = Self;
+
+    /// Remainder with the sign of the dividend, like Solidity `%`. `MIN % -1` is 0 as with EVM
+    /// `smod`, even though `MIN / -1` overflows
+    fn rem(self, other: Self) -> Self {
+        self.wrapping_sub(self.wrapping_div(other).wrapping_mul(other))
+    }
+}
+
+impl Neg for I256 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.checked_neg()
+            .unwrap_or_else(|| panic!("I256 overflow in neg"))
+    }
+}
+
+impl Not for I256 {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl BitAnd for I256 {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+impl BitOr for I256 {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for I256 {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 = self.0 | other.0;
+    }
+}
+
+impl BitXor for I256 {
+    type Output = Self;
+
+    fn bitxor(self, other: Self) -> Self {
+        Self(self.0 ^ other.0)
+    }
+}
+
+impl Shl<u32> for I256 {
+    type Output = Self;
+
+    fn shl(self, shift: u32) -> Self {
+        if shift >= 256 {
+            Self::ZERO
+        } else {
+            Self(self.0 << shift)
+        }
+    }
+}
+
+impl Shr<u32> for I256 {
+    type Output = Self;
+
+    fn shr(self, shift: u32) -> Self {
+        match (shift >= 256, self.is_negative()) {
+            (true, true) => Self::MINUS_ONE,
+            (true, false) => Self::ZERO,
+            (false, true) => Self(!(!self.0 >> shift)),
+            (false, false) => Self(self.0 >> shift),
+        }
+    }
 }
 
-impl AsI256 for U256 {
-    fn as_i256(&self) -> I256 {
-        I256::from_str_radix(&self.to_string(), 10).unwrap()
+impl fmt::Display for I256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_negative() {
+            write!(f, "-{}", self.unsigned_abs())
+        } else {
+            write!(f, "{}", self.0)
+        }
     }
 }
 
-pub trait ToU256 {
-    fn to_u256(&self) -> U256;
+impl fmt::Debug for I256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl FromStr for I256 {
+    type Err = FromDecStrErr;
+
+    /// Parses a decimal string with an optional leading `-`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value),
+        };
+        let magnitude = U256::from_dec_str(digits)?;
+        let min_magnitude = I256::MIN.0;
+        if negative && magnitude <= min_magnitude {
+            Ok(Self(magnitude).wrapping_neg())
+        } else if !negative && magnitude < min_magnitude {
+            Ok(Self(magnitude))
+        } else {
+            Err(FromDecStrErr::InvalidLength)
+        }
+    }
 }
 
-impl ToU256 for I256 {
-    fn to_u256(&self) -> U256 {
-        U256::from_str_radix(&self.abs().to_string(), 10).unwrap()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_to_i256() {
+        let values = [
+            "0",
+            "1",
+            "18446744073709551616",
+            "340282366920938463463374607431768211456",
+            "1461446703485210103287273052203988822378723970342",
+            "57896044618658097711785492504343953926634992332820282019728792003956564819967",
+        ];
+        for value in values {
+            assert_eq!(
+                U256::from_dec_str(value).unwrap().to_i256(),
+                I256::from_str(value).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_unsigned_abs() {
+        let values = [
+            "0",
+            "-1",
+            "18446744073709551616",
+            "-340282366920938463463374607431768211456",
+            "1461446703485210103287273052203988822378723970342",
+            "-57896044618658097711785492504343953926634992332820282019728792003956564819968",
+        ];
+        for value in values {
+            let expected = value.trim_start_matches('-');
+            assert_eq!(
+                I256::from_str(value).unwrap().unsigned_abs(),
+                U256::from_dec_str(expected).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_i256_solidity_semantics() {
+        let seven = I256::from(7);
+        let minus_seven = I256::from(-7);
+        assert_eq!(minus_seven / I256::from(2), I256::from(-3));
+        assert_eq!(minus_seven % I256::from(2), I256::from(-1));
+        assert_eq!(seven % I256::from(-2), I256::ONE);
+        assert_eq!(minus_seven >> 1, I256::from(-4));
+        assert_eq!(I256::MINUS_ONE >> 300, I256::MINUS_ONE);
+        assert_eq!(I256::MAX << 1, I256::from(-2));
+        assert_eq!(I256::from(-3) * I256::from(5), I256::from(-15));
+        assert!(I256::MIN < minus_seven && minus_seven < I256::ZERO && seven < I256::MAX);
+
+        assert_eq!(I256::MAX.checked_add(I256::ONE), None);
+        assert_eq!(I256::MIN.checked_sub(I256::ONE), None);
+        assert_eq!(I256::MIN.checked_neg(), None);
+        assert_eq!(I256::MIN.checked_div(I256::MINUS_ONE), None);
+        assert_eq!(I256::MIN % I256::MINUS_ONE, I256::ZERO);
+        assert_eq!(I256::MIN % I256::from(2), I256::ZERO);
+        assert_eq!((I256::MIN + I256::ONE) % I256::MIN, I256::MIN + I256::ONE);
+        assert_eq!(I256::MIN.checked_mul(I256::MINUS_ONE), None);
+        assert_eq!(I256::MAX.checked_mul(I256::from(2)), None);
+        assert_eq!((I256::MIN >> 1).checked_mul(I256::from(2)), Some(I256::MIN));
+
+        assert_eq!(I256::MAX.wrapping_add(I256::ONE), I256::MIN);
+        assert_eq!(I256::MIN.wrapping_neg(), I256::MIN);
+        assert_eq!(I256::MAX.wrapping_mul(I256::from(2)), I256::from(-2));
+        assert_eq!(I256::MAX.saturating_add(I256::ONE), I256::MAX);
+        assert_eq!(I256::MIN.saturating_sub(I256::ONE), I256::MIN);
+        assert_eq!(I256::MAX.saturating_mul(I256::from(-2)), I256::MIN);
+
+        assert_eq!(U256::MAX.wrapping_add(U256::one()), U256::zero());
+        assert_eq!(U256::zero().wrapping_sub(U256::one()), U256::MAX);
+    }
+
+    #[test]
+    fn test_i256_parse_and_display() {
+        let min = "-57896044618658097711785492504343953926634992332820282019728792003956564819968";
+        let max = "57896044618658097711785492504343953926634992332820282019728792003956564819967";
+        assert_eq!(I256::from_str(min).unwrap(), I256::MIN);
+        assert_eq!(I256::from_str(max).unwrap(), I256::MAX);
+        assert_eq!(I256::MIN.to_string(), min);
+        assert_eq!(I256::from(-42).to_string(), "-42");
+        assert!(I256::from_str(&format!("{}8", &max[..max.len() - 1])).is_err());
+        assert_eq!(I256::from_words(-1, -5).into_words(), (-1, -5));
+        assert_eq!(I256::from_words(-1, -5), I256::from(-5));
     }
 }
@@ -2,7 +2,55 @@
 
 use uint::construct_uint;
 
+use crate::num256::U256;
+
 construct_uint! {
     /// 512-bit unsigned integer.
     pub struct U512(8);
 }
+
+impl From<U256> for U512 {
+    fn from(value: U256) -> Self {
+        let U256(ref words) = value;
+        let mut ret = [0u64; 8];
+        ret[..4].copy_from_slice(words);
+        U512(ret)
+    }
+}
+
+impl TryFrom<U512> for U256 {
+    type Error = &'static str;
+
+    /// Keeps the lower limbs, failing if any of the upper ones is set
+    fn try_from(value: U512) -> Result<Self, Self::Error> {
+        let U512(ref words) = value;
+        if words[4..].iter().any(|word| *word != 0) {
+            return Err("U512 overflows U256");
+        }
+        let mut ret = [0u64; 4];
+        ret.copy_from_slice(&words[..4]);
+        Ok(U256(ret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_u256() {
+        let value = U256::MAX - U256::from(12345);
+        assert_eq!(
+            U512::from(value),
+            U512::from_dec_str(&value.to_string()).unwrap()
+        );
+        assert_eq!(U512::from(U256::zero()), U512::zero());
+    }
+
+    #[test]
+    fn test_try_into_u256() {
+        let value = U256::MAX - U256::from(12345);
+        assert_eq!(U256::try_from(U512::from(value)), Ok(value));
+        assert!(U256::try_from(U512::from(U256::MAX) + 1).is_err());
+    }
+}
@@ -1,6 +1,4 @@
-use ethnum::{AsI256, I256};
-
-use crate::num256::U256;
+use crate::num256::{I256, U256};
 
 pub type U56 = u128;
 pub type I56 = i128;
@@ -11,11 +9,6 @@ impl Constants {
     fn get_u56_max() -> U56 {
         (1 << 56) - 1
     }
-
-    fn get_i56_max() -> I56 {
-        let tmp: u128 = 1 << 55;
-        (tmp as I56) - 2
-    }
 }
 
 ////////////////////////////////////////////
@@ -53,59 +46,12 @@ impl AsI56 for i128 {
 }
 
 impl AsI56 for I256 {
+    /// Keeps the lowest 56 bits and sign extends bit 55, like Solidity `int56(x)`
     fn as_i56(&self) -> I56 {
-        let x = self & Constants::get_u56_max().as_i256();
-        if (x & (1 << 55)) == 0 {
-            x.as_i128() as I56
-        } else {
-            (x | (!Constants::get_u56_max().as_i256())).as_i128() as I56
-        }
+        self.as_i128().as_i56()
     }
 }
 
-pub trait Num56Trait {
-    fn add56(self, other: Self) -> Self;
-}
-
-impl Num56Trait for U56 {
-    fn add56(self, other: Self) -> Self {
-        let sum: u128 = self.as_u56() + other.as_u56();
-        assert!(sum <= Constants::get_u56_max(), "U56 overflow when adding");
-        sum.as_u56()
-    }
-}
-
-impl Num56Trait for I56 {
-    fn add56(self, other: Self) -> Self {
-        let sum: i128 = self.as_i56() + other.as_i56();
-        assert!(sum <= Constants::get_i56_max(), "U56 overflow when adding");
-        sum.as_i56()
-    }
-}
-
-// fn printBits256(x: I256) {
-//   print!("{} => ", x);
-//   for i in 0..=255 {
-//     print!("{}", (x >> (255 - i)) & I256::ONE);
-//   }
-//   println!();
-// }
-//
-// fn printBits128(x: i128) {
-//   print!("{} => ", x);
-//   for i in 0..=127 {
-//     print!("{}", (x >> (127 - i)) & 1);
-//   }
-//   println!();
-// }
-// fn printBits32(x: i32) {
-//   print!("{} => ", x);
-//   for i in 0..=31 {
-//     print!("{}", (x >> (31 - i)) & 1);
-//   }
-//   println!();
-// }
-
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod tests {
@@ -1,4 +1,33 @@
-use near_sdk::{env, AccountId};
+#[cfg(feature = "near")]
+pub use near_sdk::AccountId;
+
+/// Plain account id used when the library is built without the `near` feature
+#[cfg(not(feature = "near"))]
+pub type AccountId = String;
+
+#[cfg(feature = "near")]
+fn keccak256(data: &[u8]) -> Vec<u8> {
+    near_sdk::env::keccak256(data)
+}
+
+#[cfg(not(feature = "near"))]
+fn keccak256(data: &[u8]) -> Vec<u8> {
+    use sha3::{Digest, Keccak256};
+
+    Keccak256::digest(data).to_vec()
+}
+
+/// Bytes of a pool's state right after `new`, with 64-character token and hook ids and a
+/// dynamic fee. Measured by the pool's tests, the factory charges it on creation
+pub const POOL_STATE_STORAGE: u64 = 824;
+
+/// Most bytes a pool's `mint` adds for a 64-character owner: the position, both its ticks and
+/// their bitmap words, each with the record of who paid for it. Measured by the pool's tests,
+/// the manager pays it to the pool
+pub const POOL_POSITION_STORAGE: u64 = 1394;
+
+/// Most bytes a pool's `mint_limit_order` adds for a 64-character owner, measured the same way
+pub const POOL_LIMIT_ORDER_STORAGE: u64 = 1588;
 
 pub fn compute_account(
     factory: &AccountId,
@@ -6,7 +35,7 @@ pub fn compute_account(
     token_1: &AccountId,
     fee: u32,
 ) -> AccountId {
-    let hash_data = env::keccak256(
+    let hash_data = keccak256(
         [token_0.as_bytes(), token_1.as_bytes(), &fee.to_le_bytes()]
             .concat()
             .as_slice(),
@@ -18,3 +47,62 @@ pub fn compute_account(
 
     subaccount
 }
+
+/// Account of a pool created with `hook`, apart from the pool of the same tokens and fee
+/// without one
+pub fn compute_hooked_account(
+    factory: &AccountId,
+    token_0: &AccountId,
+    token_1: &AccountId,
+    fee: u32,
+    hook: Option<&AccountId>,
+) -> AccountId {
+    let hook = match hook {
+        Some(hook) => hook,
+        None => return compute_account(factory, token_0, token_1, fee),
+    };
+    let hash_data = keccak256(
+        [
+            token_0.as_bytes(),
+            token_1.as_bytes(),
+            &fee.to_le_bytes(),
+            hook.as_bytes(),
+        ]
+        .concat()
+        .as_slice(),
+    );
+
+    format!("{}.{}", hex::encode(&hash_data[0..8]), factory)
+        .parse()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_account() {
+        let account = |id: &str| -> AccountId { id.parse().unwrap() };
+        let pool = compute_account(
+            &account("factory.testnet"),
+            &account("token0.testnet"),
+            &account("token1.testnet"),
+            3000,
+        );
+        assert_eq!(pool, account("40c2d345427b8c7d.factory.testnet"));
+
+        let hook = account("hook.testnet");
+        let compute = |hook| {
+            compute_hooked_account(
+                &account("factory.testnet"),
+                &account("token0.testnet"),
+                &account("token1.testnet"),
+                3000,
+                hook,
+            )
+        };
+        assert_eq!(compute(None), pool);
+        assert_ne!(compute(Some(&hook)), pool);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::num256::U256;
 use crate::{fixed_point_128, liquidity_math};
 
-use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use borsh::{BorshDeserialize, BorshSerialize};
 
 use super::full_math::FullMath;
 use crate::full_math::FullMathTrait;
@@ -35,14 +35,14 @@ impl PositionInfo {
 
         // calculate accumulated fees
         let tokens_owed_0 = FullMath::mul_div(
-            U256::from(fee_growth_inside_0_x128 - self.fee_growth_inside_0_last_x128),
+            U256::from(fee_growth_inside_0_x128.wrapping_sub(self.fee_growth_inside_0_last_x128)),
             U256::from(self.liquidity),
             fixed_point_128::get_q128(),
         )
         .as_u128();
 
         let tokens_owed_1 = FullMath::mul_div(
-            U256::from(fee_growth_inside_1_x128 - self.fee_growth_inside_1_last_x128),
+            U256::from(fee_growth_inside_1_x128.wrapping_sub(self.fee_growth_inside_1_last_x128)),
             U256::from(self.liquidity),
             fixed_point_128::get_q128(),
         )
@@ -57,19 +57,42 @@ impl PositionInfo {
 
         if tokens_owed_0 > 0 || tokens_owed_1 > 0 {
             // overflow is acceptable, have to withdraw before you hit type(uint128).max fees
-            self.tokens_owed_0 = self.tokens_owed_0.overflowing_add(tokens_owed_0).0;
-            self.tokens_owed_1 = self.tokens_owed_1.overflowing_add(tokens_owed_1).0;
+            self.tokens_owed_0 = self.tokens_owed_0.wrapping_add(tokens_owed_0);
+            self.tokens_owed_1 = self.tokens_owed_1.wrapping_add(tokens_owed_1);
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // use ethnum::U256;
-    // use std::panic;
+    use super::*;
+
+    const HALF_X128: u128 = 1 << 127;
 
     #[test]
     fn test_update() {
-        // TODO: @galin-chung-nguyen
+        let mut position = PositionInfo::default();
+        position.update(100, 0, 0);
+        assert_eq!(position.liquidity, 100);
+        assert_eq!((position.tokens_owed_0, position.tokens_owed_1), (0, 0));
+
+        // half a token of fees per unit of liquidity in token 0, a quarter in token 1
+        position.update(0, HALF_X128, HALF_X128 / 2);
+        assert_eq!(position.liquidity, 100);
+        assert_eq!((position.tokens_owed_0, position.tokens_owed_1), (50, 25));
+        assert_eq!(position.fee_growth_inside_0_last_x128, HALF_X128);
+
+        // fees are only credited once
+        position.update(0, HALF_X128, HALF_X128 / 2);
+        assert_eq!((position.tokens_owed_0, position.tokens_owed_1), (50, 25));
+
+        // fees accrued before a burn are credited on the old liquidity
+        position.update(-60, HALF_X128 + HALF_X128 / 2, HALF_X128 / 2);
+        assert_eq!(position.liquidity, 40);
+        assert_eq!((position.tokens_owed_0, position.tokens_owed_1), (75, 25));
+
+        position.update(-40, HALF_X128 + HALF_X128 / 2, HALF_X128 / 2);
+        assert_eq!(position.liquidity, 0);
+        assert_eq!((position.tokens_owed_0, position.tokens_owed_1), (75, 25));
     }
 }
@@ -0,0 +1,327 @@
+//! Exact conversions between human-readable prices and the pool's `sqrt_price_x96` and ticks.
+//!
+//! A price is the decimal amount of token 1 paid for one whole token 0, e.g. `"2000.5"`.
+//! Raw pool prices are in the tokens' smallest units, so `decimals_0` and `decimals_1`
+//! rescale between the two: `raw = price * 10^decimals_1 / 10^decimals_0`.
+//! Everything is computed on integers, with no floats involved.
+
+use crate::error::*;
+use crate::num24::I24;
+use crate::num256::U256;
+use crate::num512::U512;
+use crate::tick_math::{self, TickConstants};
+
+/// Token decimals above this are rejected; NEP-141 tokens use at most 24
+pub const MAX_DECIMALS: u8 = 24;
+/// Significant digits accepted in a price string
+pub const MAX_PRICE_DIGITS: usize = 60;
+/// Fractional digits a price can be displayed with
+pub const MAX_PRECISION: u8 = 32;
+
+/// Returns the `sqrt_price_x96` of `price`, rounded down
+pub fn price_to_sqrt_price_x96(
+    price: &str,
+    decimals_0: u8,
+    decimals_1: u8,
+) -> Result<U256, &'static str> {
+    check_decimals(decimals_0, decimals_1)?;
+    let (digits, scale) = parse_price(price)?;
+
+    // sqrt_price_x96 = sqrt(digits * 10^decimals_1 * 2^192 / (10^scale * 10^decimals_0))
+    let numerator = (digits * pow_10(decimals_1 as u32)) << 192;
+    let denominator = pow_10(scale) * pow_10(decimals_0 as u32);
+    let sqrt_price_x96 = (numerator / denominator).integer_sqrt();
+
+    if sqrt_price_x96 < U512::from(TickConstants::MIN_SQRT_RATIO)
+        || sqrt_price_x96 >= U512::from(TickConstants::MAX_SQRT_RATIO)
+    {
+        return Err(PRICE_OUT_OF_RANGE);
+    }
+    U256::try_from(sqrt_price_x96)
+}
+
+/// Returns the price of `sqrt_price_x96` with `precision` fractional digits, rounded half up
+pub fn sqrt_price_x96_to_price(
+    sqrt_price_x96: U256,
+    decimals_0: u8,
+    decimals_1: u8,
+    precision: u8,
+) -> Result<String, &'static str> {
+    check_decimals(decimals_0, decimals_1)?;
+    if precision > MAX_PRECISION {
+        return Err(INVALID_PRECISION);
+    }
+    if sqrt_price_x96 < TickConstants::MIN_SQRT_RATIO
+        || sqrt_price_x96 >= TickConstants::MAX_SQRT_RATIO
+    {
+        return Err(PRICE_OUT_OF_RANGE);
+    }
+
+    // price * 10^precision = sqrt_price_x96^2 * 10^(decimals_0 + precision) / (2^192 * 10^decimals_1)
+    let sqrt_price_x96 = U512::from(sqrt_price_x96);
+    let mut numerator = sqrt_price_x96 * sqrt_price_x96;
+    let mut denominator = U512::one() << 192;
+    let exponent = decimals_0 as i32 + precision as i32 - decimals_1 as i32;
+    if exponent >= 0 {
+        numerator *= pow_10(exponent as u32);
+    } else {
+        denominator *= pow_10(exponent.unsigned_abs());
+    }
+
+    let mut scaled = numerator / denominator;
+    if (numerator % denominator) * 2 >= denominator {
+        scaled += U512::one();
+    }
+
+    Ok(format_fixed(scaled, precision))
+}
+
+/// Returns the usable tick, i.e. multiple of `tick_spacing`, whose price is closest to `price`
+pub fn price_to_nearest_usable_tick(
+    price: &str,
+    decimals_0: u8,
+    decimals_1: u8,
+    tick_spacing: u32,
+) -> Result<I24, &'static str> {
+    if tick_spacing == 0 || tick_spacing > TickConstants::MAX_TICK as u32 {
+        return Err(INVALID_TICK_SPACING);
+    }
+    let sqrt_price_x96 = price_to_sqrt_price_x96(price, decimals_0, decimals_1)?;
+    let tick_spacing = tick_spacing as I24;
+
+    let tick = tick_math::get_tick_at_sqrt_ratio(sqrt_price_x96);
+    let lower_tick = tick.div_euclid(tick_spacing) * tick_spacing;
+    let upper_tick = lower_tick + tick_spacing;
+
+    if lower_tick < TickConstants::MIN_TICK {
+        return Ok(upper_tick);
+    }
+    if upper_tick > TickConstants::MAX_TICK {
+        return Ok(lower_tick);
+    }
+
+    // ticks are linear in log(price), so the midpoint between them is the geometric mean of the prices:
+    // compare sqrt_price^2 with sqrt_price_lower * sqrt_price_upper
+    let sqrt_price_x96 = U512::from(sqrt_price_x96);
+    let midpoint = U512::from(tick_math::get_sqrt_ratio_at_tick(lower_tick))
+        * U512::from(tick_math::get_sqrt_ratio_at_tick(upper_tick));
+    if sqrt_price_x96 * sqrt_price_x96 >= midpoint {
+        Ok(upper_tick)
+    } else {
+        Ok(lower_tick)
+    }
+}
+
+/// Rounds `tick` to the closest multiple of `tick_spacing` that stays within the tick range.
+/// Ties round up.
+pub fn nearest_usable_tick(tick: I24, tick_spacing: u32) -> I24 {
+    assert!(tick_spacing > 0, "Tick spacing must be greater than 0");
+    assert!(
+        (TickConstants::MIN_TICK..=TickConstants::MAX_TICK).contains(&tick),
+        "Tick out of range"
+    );
+    let tick_spacing = tick_spacing as I24;
+
+    let rounded = (2 * tick + tick_spacing).div_euclid(2 * tick_spacing) * tick_spacing;
+    if rounded < TickConstants::MIN_TICK {
+        rounded + tick_spacing
+    } else if rounded > TickConstants::MAX_TICK {
+        rounded - tick_spacing
+    } else {
+        rounded
+    }
+}
+
+/// Returns the price at `tick` with `precision` fractional digits, rounded half up
+pub fn tick_to_price(
+    tick: I24,
+    decimals_0: u8,
+    decimals_1: u8,
+    precision: u8,
+) -> Result<String, &'static str> {
+    if !(TickConstants::MIN_TICK..=TickConstants::MAX_TICK).contains(&tick) {
+        return Err(INVALID_TICK);
+    }
+    // MAX_TICK maps onto MAX_SQRT_RATIO itself, which no pool price can reach
+    let sqrt_price_x96 =
+        tick_math::get_sqrt_ratio_at_tick(tick).min(TickConstants::MAX_SQRT_RATIO - U256::one());
+    sqrt_price_x96_to_price(sqrt_price_x96, decimals_0, decimals_1, precision)
+}
+
+fn check_decimals(decimals_0: u8, decimals_1: u8) -> Result<(), &'static str> {
+    if decimals_0 > MAX_DECIMALS || decimals_1 > MAX_DECIMALS {
+        Err(INVALID_DECIMALS)
+    } else {
+        Ok(())
+    }
+}
+
+/// Splits a plain decimal like `"12.345"` into its digits and the number of fractional ones,
+/// `(12345, 3)`. Signs, exponents and separators are rejected.
+fn parse_price(price: &str) -> Result<(U512, u32), &'static str> {
+    let (integer, fraction) = price.split_once('.').unwrap_or((price, ""));
+    let digit_count = integer.len() + fraction.len();
+    if digit_count == 0 || digit_count > MAX_PRICE_DIGITS {
+        return Err(INVALID_PRICE);
+    }
+
+    let mut digits = U512::zero();
+    for c in integer.chars().chain(fraction.chars()) {
+        let digit = c.to_digit(10).ok_or(INVALID_PRICE)?;
+        digits = digits * 10 + digit;
+    }
+    if digits.is_zero() {
+        return Err(INVALID_PRICE);
+    }
+
+    Ok((digits, fraction.len() as u32))
+}
+
+fn pow_10(exponent: u32) -> U512 {
+    U512::from(10).pow(U512::from(exponent))
+}
+
+/// Prints `value / 10^precision` with exactly `precision` fractional digits
+fn format_fixed(value: U512, precision: u8) -> String {
+    let precision = precision as usize;
+    let digits = format!("{:0>width$}", value.to_string(), width = precision + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - precision);
+    if fraction.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_to_sqrt_price_x96() {
+        assert_eq!(price_to_sqrt_price_x96("1", 18, 18), Ok(U256::one() << 96));
+        // README pool: 1 ZNEAR = 100 ZUSD
+        assert_eq!(
+            price_to_sqrt_price_x96("100", 18, 18),
+            Ok(U256::from_dec_str("792281625142643375935439503360").unwrap())
+        );
+        // decimals only shift the raw price: 1 token 0 (6 decimals) = 100 token 1 (8 decimals)
+        assert_eq!(
+            price_to_sqrt_price_x96("100", 6, 8),
+            Ok(U256::from(100) * (U256::one() << 96))
+        );
+        assert_eq!(
+            price_to_sqrt_price_x96("0.25", 18, 18),
+            Ok(U256::one() << 95)
+        );
+
+        for invalid in ["", ".", "1.2.3", "-1", "+1", "1e5", " 1", "0", "0.000"] {
+            assert_eq!(
+                price_to_sqrt_price_x96(invalid, 18, 18),
+                Err(INVALID_PRICE),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(
+            price_to_sqrt_price_x96(&format!("1{}", "0".repeat(40)), 18, 18),
+            Err(PRICE_OUT_OF_RANGE)
+        );
+        assert_eq!(
+            price_to_sqrt_price_x96(&format!("0.{}1", "0".repeat(40)), 18, 18),
+            Err(PRICE_OUT_OF_RANGE)
+        );
+        assert_eq!(price_to_sqrt_price_x96("1", 25, 18), Err(INVALID_DECIMALS));
+    }
+
+    #[test]
+    fn test_sqrt_price_x96_to_price() {
+        assert_eq!(
+            sqrt_price_x96_to_price(U256::one() << 96, 18, 18, 2),
+            Ok("1.00".to_string())
+        );
+        assert_eq!(
+            sqrt_price_x96_to_price(U256::one() << 95, 18, 18, 0),
+            Ok("0".to_string())
+        );
+        assert_eq!(
+            sqrt_price_x96_to_price(U256::one() << 95, 18, 18, 1),
+            Ok("0.3".to_string())
+        );
+
+        // ETH (18 decimals) / USDC (6 decimals) at 2000 survives the round trip
+        let sqrt_price_x96 = price_to_sqrt_price_x96("2000", 18, 6).unwrap();
+        assert_eq!(
+            sqrt_price_x96_to_price(sqrt_price_x96, 18, 6, 6),
+            Ok("2000.000000".to_string())
+        );
+        let sqrt_price_x96 = price_to_sqrt_price_x96("0.000123456789", 6, 24).unwrap();
+        assert_eq!(
+            sqrt_price_x96_to_price(sqrt_price_x96, 6, 24, 12),
+            Ok("0.000123456789".to_string())
+        );
+
+        assert_eq!(
+            sqrt_price_x96_to_price(U256::one() << 96, 18, 18, 33),
+            Err(INVALID_PRECISION)
+        );
+        assert_eq!(
+            sqrt_price_x96_to_price(U256::zero(), 18, 18, 2),
+            Err(PRICE_OUT_OF_RANGE)
+        );
+    }
+
+    #[test]
+    fn test_nearest_usable_tick() {
+        assert_eq!(nearest_usable_tick(5, 10), 10);
+        assert_eq!(nearest_usable_tick(4, 10), 0);
+        assert_eq!(nearest_usable_tick(-5, 10), 0);
+        assert_eq!(nearest_usable_tick(-6, 10), -10);
+        assert_eq!(nearest_usable_tick(46054, 60), 46080);
+        assert_eq!(nearest_usable_tick(TickConstants::MAX_TICK, 60), 887220);
+        assert_eq!(nearest_usable_tick(TickConstants::MIN_TICK, 60), -887220);
+        assert_eq!(
+            nearest_usable_tick(TickConstants::MIN_TICK, 1),
+            TickConstants::MIN_TICK
+        );
+    }
+
+    #[test]
+    fn test_price_to_nearest_usable_tick() {
+        assert_eq!(price_to_nearest_usable_tick("1", 18, 18, 60), Ok(0));
+        assert_eq!(price_to_nearest_usable_tick("100", 18, 18, 60), Ok(46080));
+        assert_eq!(price_to_nearest_usable_tick("100", 18, 18, 1), Ok(46054));
+        assert_eq!(price_to_nearest_usable_tick("0.01", 18, 18, 1), Ok(-46054));
+        assert_eq!(
+            price_to_nearest_usable_tick("0.01", 18, 18, 200),
+            Ok(-46000)
+        );
+        assert_eq!(
+            price_to_nearest_usable_tick("100", 18, 18, 0),
+            Err(INVALID_TICK_SPACING)
+        );
+
+        // the midpoint between two usable ticks is the geometric mean of their prices
+        let below = tick_to_price(46049, 18, 18, 18).unwrap();
+        let above = tick_to_price(46051, 18, 18, 18).unwrap();
+        assert_eq!(price_to_nearest_usable_tick(&below, 18, 18, 60), Ok(46020));
+        assert_eq!(price_to_nearest_usable_tick(&above, 18, 18, 60), Ok(46080));
+    }
+
+    #[test]
+    fn test_tick_to_price() {
+        assert_eq!(tick_to_price(0, 18, 18, 4), Ok("1.0000".to_string()));
+        assert_eq!(tick_to_price(0, 18, 6, 0), Ok("1000000000000".to_string()));
+        assert_eq!(tick_to_price(46080, 18, 18, 4), Ok("100.2603".to_string()));
+        assert_eq!(
+            tick_to_price(-46080, 18, 18, 8),
+            Ok("0.00997404".to_string())
+        );
+        assert!(tick_to_price(TickConstants::MAX_TICK, 18, 18, 0).is_ok());
+        assert!(tick_to_price(TickConstants::MIN_TICK, 18, 18, 0).is_ok());
+        assert_eq!(
+            tick_to_price(TickConstants::MAX_TICK + 1, 18, 18, 0),
+            Err(INVALID_TICK)
+        );
+    }
+}
@@ -1,9 +1,7 @@
-use ethnum::I256;
-
 use crate::fixed_point_96;
 use crate::full_math::{FullMath, FullMathTrait, MathOps};
 use crate::num160::{AsU160, Num160Trait, U160};
-use crate::num256::{AsI256, U256};
+use crate::num256::{I256, U256};
 
 /// Gets the next sqrt price given a delta of token0
 /// Always rounds up, because in the exact output case (increasing price) we need to move the price at least
@@ -208,9 +206,9 @@ pub fn get_amount_0_delta_signed(
             liquidity.unsigned_abs(),
             false,
         )
-        .as_i256()
+        .to_i256()
     } else {
-        get_amount_0_delta(sqrt_ratio_a_x96, sqrt_ratio_b_x96, liquidity as u128, true).as_i256()
+        get_amount_0_delta(sqrt_ratio_a_x96, sqrt_ratio_b_x96, liquidity as u128, true).to_i256()
     }
 }
 
@@ -228,9 +226,9 @@ pub fn get_amount_1_delta_signed(
             -liquidity as u128,
             false,
         )
-        .as_i256()
+        .to_i256()
     } else {
-        get_amount_1_delta(sqrt_ratio_a_x96, sqrt_ratio_b_x96, liquidity as u128, true).as_i256()
+        get_amount_1_delta(sqrt_ratio_a_x96, sqrt_ratio_b_x96, liquidity as u128, true).to_i256()
     }
 }
 
@@ -0,0 +1,20 @@
+//! `proptest` strategies shared by the unit tests
+
+use proptest::prelude::*;
+
+use crate::num256::U256;
+use crate::tick_math::TickConstants;
+
+/// Any `U256`, shifted by a random amount so that small and large values are equally likely
+pub fn u256() -> impl Strategy<Value = U256> {
+    (any::<[u64; 4]>(), 0..256_u32).prop_map(|(words, shift)| U256(words) >> shift)
+}
+
+/// A sqrt price in `[MIN_SQRT_RATIO, MAX_SQRT_RATIO)`, spread over the whole tick range
+pub fn sqrt_price_x96() -> impl Strategy<Value = U256> {
+    (any::<[u64; 3]>(), 0..128_u32).prop_map(|([lo, mid, hi], shift)| {
+        (U256([lo, mid, hi & u32::MAX as u64, 0]) >> shift)
+            .max(TickConstants::MIN_SQRT_RATIO)
+            .min(TickConstants::MAX_SQRT_RATIO - 1)
+    })
+}
@@ -0,0 +1,215 @@
+//! !ADDED
+//! The swap loop, shared by the pool contract and the simulator so that both move the price,
+//! cross ticks and accrue fees the same way. Each keeps its own state behind `SwapPool`.
+
+use crate::error::{INVALID_PRICE_LIMIT, NOT_ENOUGH_LIQUIDITY};
+use crate::full_math::{FullMath, FullMathTrait};
+use crate::num160::AsU160;
+use crate::num256::{I256, U256};
+use crate::tick_bitmap::{self, TickBitmap};
+use crate::tick_math::{self, TickConstants};
+use crate::{fixed_point_128, liquidity_math, swap_math};
+
+pub struct SwapState {
+    pub amount_specified_remaining: u128,
+    pub amount_calculated: u128,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub fee_growth_global_x128: u128,
+    pub liquidity: u128,
+}
+
+#[derive(Default)]
+pub struct StepState {
+    pub sqrt_price_start_x96: U256,
+    pub next_tick: i32,
+    pub initialized: bool,
+    pub sqrt_price_next_x96: U256,
+    pub amount_in: u128,
+    pub amount_out: u128,
+    pub fee_amount: u128,
+}
+
+/// The pool state the swap loop reads and writes
+pub trait SwapPool {
+    type TickBitmap: TickBitmap + ?Sized;
+
+    fn tick_spacing(&self) -> u32;
+    fn tick_bitmap(&self) -> &Self::TickBitmap;
+    fn sqrt_price_x96(&self) -> U256;
+    fn tick(&self) -> i32;
+    fn liquidity(&self) -> u128;
+    fn fee_growth_global_x128(&self) -> [u128; 2];
+
+    /// Crosses the initialized `tick` with `TickInfo::cross`, returning its net liquidity
+    fn cross_tick(
+        &mut self,
+        tick: i32,
+        fee_growth_global_0_x128: u128,
+        fee_growth_global_1_x128: u128,
+    ) -> i128;
+
+    /// Writes the price, tick, active liquidity and the input token's fee growth back
+    fn write_swap_state(&mut self, zero_for_one: bool, state: &SwapState);
+
+    /// Fills the limit orders the swap just crossed out of, right after the pool state was
+    /// written back at `crossed_tick`
+    fn fill_limit_orders(&mut self, crossed_tick: i32, zero_for_one: bool);
+}
+
+/// Swaps `amount_specified` of the input token at `fee`, down to `sqrt_price_limit_x96` if
+/// given, and returns `[amount_in, amount_out]`.
+///
+/// Crossed ticks and filled limit orders are written as the loop goes, so a pool must drop
+/// its state when this fails, like a reverted contract call does
+pub fn swap<P: SwapPool + ?Sized>(
+    pool: &mut P,
+    zero_for_one: bool,
+    amount_specified: u128,
+    sqrt_price_limit_x96: Option<U256>,
+    fee: u32,
+) -> Result<[u128; 2], &'static str> {
+    let sqrt_price_limit_x96 = match sqrt_price_limit_x96 {
+        Some(sqrt_price_limit) => {
+            if zero_for_one && (sqrt_price_limit > pool.sqrt_price_x96()) {
+                return Err(INVALID_PRICE_LIMIT);
+            }
+
+            if !zero_for_one && (sqrt_price_limit < pool.sqrt_price_x96()) {
+                return Err(INVALID_PRICE_LIMIT);
+            }
+
+            sqrt_price_limit.as_u160()
+        }
+        None => {
+            if zero_for_one {
+                TickConstants::min_sqrt_ratio()
+            } else {
+                TickConstants::max_sqrt_ratio()
+            }
+        }
+    };
+
+    let [fee_growth_global_0_x128, fee_growth_global_1_x128] = pool.fee_growth_global_x128();
+    let mut state = SwapState {
+        amount_specified_remaining: amount_specified,
+        amount_calculated: 0,
+        sqrt_price_x96: pool.sqrt_price_x96(),
+        tick: pool.tick(),
+        fee_growth_global_x128: if zero_for_one {
+            fee_growth_global_0_x128
+        } else {
+            fee_growth_global_1_x128
+        },
+        liquidity: pool.liquidity(),
+    };
+
+    while state.amount_specified_remaining > 0 && state.sqrt_price_x96 != sqrt_price_limit_x96 {
+        let mut step = StepState::default();
+
+        (step.next_tick, step.initialized) = tick_bitmap::next_initialized_tick_within_words(
+            pool.tick_bitmap(),
+            state.tick,
+            pool.tick_spacing() as i32,
+            zero_for_one,
+            tick_bitmap::MAX_SEARCH_WORDS,
+        );
+
+        step.sqrt_price_start_x96 = state.sqrt_price_x96;
+        step.sqrt_price_next_x96 = tick_math::get_sqrt_ratio_at_tick(step.next_tick);
+
+        let sqrt_target_price_x96 = if (zero_for_one
+            && step.sqrt_price_next_x96 < sqrt_price_limit_x96)
+            || (!zero_for_one && step.sqrt_price_next_x96 > sqrt_price_limit_x96)
+        {
+            sqrt_price_limit_x96
+        } else {
+            step.sqrt_price_next_x96
+        };
+
+        let (sqrt_price_x96, amount_in, amount_out, fee_amount) = swap_math::compute_swap_step(
+            state.sqrt_price_x96,
+            sqrt_target_price_x96,
+            state.liquidity,
+            I256::from(state.amount_specified_remaining),
+            fee,
+        );
+
+        (
+            state.sqrt_price_x96,
+            step.amount_in,
+            step.amount_out,
+            step.fee_amount,
+        ) = (
+            sqrt_price_x96,
+            amount_in.as_u128(),
+            amount_out.as_u128(),
+            fee_amount.as_u128(),
+        );
+
+        state.amount_specified_remaining -= step.amount_in + step.fee_amount;
+        state.amount_calculated += step.amount_out;
+
+        if state.liquidity > 0 {
+            state.fee_growth_global_x128 += FullMath::mul_div(
+                U256::from(step.fee_amount),
+                fixed_point_128::get_q128(),
+                U256::from(state.liquidity),
+            )
+            .as_u128();
+        }
+
+        if state.sqrt_price_x96 == step.sqrt_price_next_x96 {
+            // only initialized ticks carry liquidity and fee growth to flip
+            if step.initialized {
+                let (fee_growth_global_0_x128, fee_growth_global_1_x128) = if zero_for_one {
+                    (state.fee_growth_global_x128, fee_growth_global_1_x128)
+                } else {
+                    (fee_growth_global_0_x128, state.fee_growth_global_x128)
+                };
+
+                let mut liquidity_delta = pool.cross_tick(
+                    step.next_tick,
+                    fee_growth_global_0_x128,
+                    fee_growth_global_1_x128,
+                );
+
+                if zero_for_one {
+                    liquidity_delta = -liquidity_delta;
+                }
+
+                state.liquidity = liquidity_math::add_delta(state.liquidity, liquidity_delta);
+                if state.liquidity == 0 {
+                    return Err(NOT_ENOUGH_LIQUIDITY);
+                }
+            } else if state.liquidity == 0
+                && (step.next_tick == TickConstants::MIN_TICK
+                    || step.next_tick == TickConstants::MAX_TICK)
+            {
+                // the end of an empty search window is not a cross, the swap walks on at zero
+                // liquidity unless there is nothing left to walk to
+                return Err(NOT_ENOUGH_LIQUIDITY);
+            }
+
+            state.tick = if zero_for_one {
+                step.next_tick - 1
+            } else {
+                step.next_tick
+            };
+
+            if step.initialized {
+                pool.write_swap_state(zero_for_one, &state);
+                pool.fill_limit_orders(step.next_tick, zero_for_one);
+            }
+        } else {
+            state.tick = tick_math::get_tick_at_sqrt_ratio(state.sqrt_price_x96);
+        }
+    }
+
+    pool.write_swap_state(zero_for_one, &state);
+
+    let amount_in = amount_specified - state.amount_specified_remaining;
+    let amount_out = state.amount_calculated;
+
+    Ok([amount_in, amount_out])
+}
@@ -1,9 +1,7 @@
-use ethnum::I256;
-
 use crate::full_math::{FullMath, FullMathTrait};
 use crate::num160::U160;
 use crate::num24::U24;
-use crate::num256::{ToU256, U256};
+use crate::num256::{I256, U256};
 use crate::sqrt_price_math;
 
 /// Computes the result of swapping some amount in, or amount out, given the parameters of the swap
@@ -25,14 +23,14 @@ pub fn compute_swap_step(
     fee_pips: U24,
 ) -> (U160, U256, U256, U256) {
     let zero_for_one = sqrt_ratio_current_x96 >= sqrt_ratio_target_x96;
-    let exact_in = amount_remaining >= 0;
+    let exact_in = !amount_remaining.is_negative();
 
     let sqrt_ratio_next_x96;
     let mut amount_in = U256::zero();
     let mut amount_out = U256::zero();
     if exact_in {
         let amount_remaining_less_fee = FullMath::mul_div(
-            amount_remaining.to_u256(),
+            amount_remaining.unsigned_abs(),
             U256::from((1_000_000 - fee_pips) as u128),
             U256::from(1_000_000),
         );
@@ -79,13 +77,13 @@ pub fn compute_swap_step(
             )
         };
 
-        if (I256::ZERO - amount_remaining).to_u256() >= amount_out {
+        if (I256::ZERO - amount_remaining).unsigned_abs() >= amount_out {
             sqrt_ratio_next_x96 = sqrt_ratio_target_x96;
         } else {
             sqrt_ratio_next_x96 = sqrt_price_math::get_next_sqrt_price_from_output(
                 sqrt_ratio_current_x96,
                 liquidity,
-                (I256::ZERO - amount_remaining).to_u256(),
+                (I256::ZERO - amount_remaining).unsigned_abs(),
                 zero_for_one,
             );
         }
@@ -140,12 +138,12 @@ pub fn compute_swap_step(
     }
 
     // cap the output amount to not exceed the remaining output amount
-    if !exact_in && amount_out > (I256::ZERO - amount_remaining).to_u256() {
-        amount_out = (I256::ZERO - amount_remaining).to_u256();
+    if !exact_in && amount_out > (I256::ZERO - amount_remaining).unsigned_abs() {
+        amount_out = (I256::ZERO - amount_remaining).unsigned_abs();
     }
 
     let fee_amount: U256 = if exact_in && sqrt_ratio_next_x96 != sqrt_ratio_target_x96 {
-        (I256::ZERO - amount_remaining).to_u256() - amount_in
+        (I256::ZERO - amount_remaining).unsigned_abs() - amount_in
     } else {
         FullMath::mul_div_rounding_up(
             amount_in,
@@ -161,12 +159,13 @@ mod tests {
     use super::*;
     use crate::full_math::{FullMath, FullMathTrait, MathOps};
     use crate::num24::AsU24;
-    use crate::num256::AsI256;
+    use crate::num256::I256;
     use crate::sqrt_price_math::{get_next_sqrt_price_from_input, get_next_sqrt_price_from_output};
+    use crate::strategies;
     use crate::swap_math::compute_swap_step;
     use crate::utils::{encode_price_sqrt_u128, expand_to_18_decimals};
+    use proptest::prelude::*;
 
-    use ethnum::I256;
     use std::ops::Sub;
     use std::str::FromStr;
 
@@ -177,7 +176,7 @@ mod tests {
             let price = encode_price_sqrt_u128(1, 1);
             let price_target = encode_price_sqrt_u128(101, 100);
             let liquidity = expand_to_18_decimals(2);
-            let amount = expand_to_18_decimals(1).as_i256();
+            let amount = expand_to_18_decimals(1).to_i256();
             let fee = 600_u128.as_u24();
             let zero_for_one = false;
 
@@ -187,12 +186,12 @@ mod tests {
             assert_eq!(amount_in, U256::from(9975124224178055 as u128));
             assert_eq!(fee_amount, U256::from(5988667735148 as u128));
             assert_eq!(amount_out, U256::from(9925619580021728 as u128));
-            assert!(amount_in.add(fee_amount).as_i256() < amount);
+            assert!(amount_in.add(fee_amount).to_i256() < amount);
 
             let price_after_whole_input_amount = get_next_sqrt_price_from_input(
                 price,
                 liquidity.as_u128(),
-                amount.to_u256(),
+                amount.unsigned_abs(),
                 zero_for_one,
             );
 
@@ -207,7 +206,7 @@ mod tests {
             let price = encode_price_sqrt_u128(1, 1);
             let price_target = encode_price_sqrt_u128(101, 100);
             let liquidity = expand_to_18_decimals(2);
-            let amount = expand_to_18_decimals(1).as_i256() * I256::from(-1);
+            let amount = expand_to_18_decimals(1).to_i256() * I256::from(-1);
             let fee = 600_u128.as_u24();
             let zero_for_one = false;
 
@@ -217,12 +216,12 @@ mod tests {
             assert_eq!(amount_in, U256::from(9975124224178055 as u128));
             assert_eq!(fee_amount, U256::from(5988667735148 as u128));
             assert_eq!(amount_out, U256::from(9925619580021728 as u128));
-            assert!(amount_out < (amount * I256::from(-1)).to_u256());
+            assert!(amount_out < (amount * I256::from(-1)).unsigned_abs());
 
             let price_after_whole_output_amount = get_next_sqrt_price_from_output(
                 price,
                 liquidity.as_u128(),
-                (amount * I256::from(-1)).to_u256(),
+                (amount * I256::from(-1)).unsigned_abs(),
                 zero_for_one,
             );
 
@@ -234,7 +233,7 @@ mod tests {
             let price = encode_price_sqrt_u128(1, 1);
             let price_target = encode_price_sqrt_u128(1000, 100);
             let liquidity = expand_to_18_decimals(2);
-            let amount = expand_to_18_decimals(1).as_i256();
+            let amount = expand_to_18_decimals(1).to_i256();
             let fee = 600_u128.as_u24();
             let zero_for_one = false;
 
@@ -244,12 +243,12 @@ mod tests {
             assert_eq!(amount_in, U256::from(999400000000000000 as u128));
             assert_eq!(fee_amount, U256::from(600000000000000 as u128));
             assert_eq!(amount_out, U256::from(666399946655997866 as u128));
-            assert_eq!(amount_in.add(fee_amount), amount.to_u256());
+            assert_eq!(amount_in.add(fee_amount), amount.unsigned_abs());
 
             let price_after_whole_input_amount_less_fee = get_next_sqrt_price_from_input(
                 price,
                 liquidity.as_u128(),
-                amount.sub(fee_amount.as_i256()).to_u256(),
+                amount.sub(fee_amount.to_i256()).unsigned_abs(),
                 zero_for_one,
             );
 
@@ -261,7 +260,7 @@ mod tests {
             let price = encode_price_sqrt_u128(1, 1);
             let price_target = encode_price_sqrt_u128(10000, 100);
             let liquidity = expand_to_18_decimals(2);
-            let amount = expand_to_18_decimals(1).as_i256() * I256::new(-1);
+            let amount = expand_to_18_decimals(1).to_i256() * I256::new(-1);
             let fee = 600_u128.as_u24();
             let zero_for_one = false;
 
@@ -270,12 +269,12 @@ mod tests {
 
             assert_eq!(amount_in, U256::from(2000000000000000000 as u128));
             assert_eq!(fee_amount, U256::from(1200720432259356 as u128));
-            assert_eq!(amount_out, (amount * I256::from(-1)).to_u256());
+            assert_eq!(amount_out, (amount * I256::from(-1)).unsigned_abs());
 
             let price_after_whole_output_amount = get_next_sqrt_price_from_output(
                 price,
                 liquidity.as_u128(),
-                (amount * I256::from(-1)).to_u256(),
+                (amount * I256::from(-1)).unsigned_abs(),
                 zero_for_one,
             );
 
@@ -393,4 +392,53 @@ mod tests {
             assert_eq!(fee_amount, U256::from_dec_str("1").unwrap());
         }
     }
+
+    proptest! {
+        // the invariants of the Uniswap v3 SwapMath echidna test
+        #[test]
+        fn prop_compute_swap_step_invariants(
+            sqrt_price_x96 in strategies::sqrt_price_x96(),
+            sqrt_price_target_x96 in strategies::sqrt_price_x96(),
+            liquidity in 1..=u128::MAX,
+            amount_remaining in any::<i128>(),
+            fee_pips in 1..1_000_000_u32,
+        ) {
+            let amount_remaining = I256::from(amount_remaining);
+            let (sqrt_q, amount_in, amount_out, fee_amount) = compute_swap_step(
+                sqrt_price_x96,
+                sqrt_price_target_x96,
+                liquidity,
+                amount_remaining,
+                fee_pips,
+            );
+
+            prop_assert!(amount_in <= U256::MAX - fee_amount);
+            if amount_remaining.is_negative() {
+                prop_assert!(amount_out <= amount_remaining.unsigned_abs());
+            } else {
+                prop_assert!(amount_in + fee_amount <= amount_remaining.unsigned_abs());
+            }
+
+            if sqrt_price_x96 == sqrt_price_target_x96 {
+                prop_assert!(amount_in.is_zero() && amount_out.is_zero() && fee_amount.is_zero());
+                prop_assert_eq!(sqrt_q, sqrt_price_target_x96);
+            }
+
+            // didn't reach the price target, so the entire amount must be consumed
+            if sqrt_q != sqrt_price_target_x96 {
+                if amount_remaining.is_negative() {
+                    prop_assert_eq!(amount_out, amount_remaining.unsigned_abs());
+                } else {
+                    prop_assert_eq!(amount_in + fee_amount, amount_remaining.unsigned_abs());
+                }
+            }
+
+            // the price moves towards the target without passing it
+            if sqrt_price_target_x96 <= sqrt_price_x96 {
+                prop_assert!(sqrt_price_target_x96 <= sqrt_q && sqrt_q <= sqrt_price_x96);
+            } else {
+                prop_assert!(sqrt_price_x96 <= sqrt_q && sqrt_q <= sqrt_price_target_x96);
+            }
+        }
+    }
 }
@@ -1,9 +1,8 @@
-use ethnum::AsI256;
-use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use borsh::{BorshDeserialize, BorshSerialize};
 
 use crate::liquidity_math;
 use crate::num160::U160;
-use crate::num24::{AsI24, AsU24, I24};
+use crate::num24::{AsU24, I24};
 use crate::num256::U256;
 use crate::num56::I56;
 use crate::tick_math::TickConstants;
@@ -69,18 +68,11 @@ impl TickInfo {
 
         // when the lower (upper) tick is crossed left to right (right to left), liquidity must be added (removed)
         self.liquidity_net = if upper {
-            self.liquidity_net
-                .as_i256()
-                .checked_sub(liquidity_delta.as_i256())
-                .unwrap()
-                .as_i128()
+            self.liquidity_net.checked_sub(liquidity_delta)
         } else {
-            self.liquidity_net
-                .as_i256()
-                .checked_add(liquidity_delta.as_i256())
-                .unwrap()
-                .as_i128()
-        };
+            self.liquidity_net.checked_add(liquidity_delta)
+        }
+        .expect("liquidity_net overflow");
 
         flipped
     }
@@ -121,12 +113,8 @@ impl TickInfo {
 ///     e.g., a tickSpacing of 3 requires ticks to be initialized every 3rd tick i.e., ..., -6, -3, 0, 3, 6, ...
 /// @return The max liquidity per tick
 pub fn tick_spacing_to_max_liquidity_per_tick(tick_spacing: I24) -> u128 {
-    let min_tick = ((TickConstants::MIN_TICK.as_i256() / tick_spacing.as_i256())
-        * tick_spacing.as_i256())
-    .as_i24();
-    let max_tick = ((TickConstants::MAX_TICK.as_i256() / tick_spacing.as_i256())
-        * tick_spacing.as_i256())
-    .as_i24();
+    let min_tick = (TickConstants::MIN_TICK / tick_spacing) * tick_spacing;
+    let max_tick = (TickConstants::MAX_TICK / tick_spacing) * tick_spacing;
     let num_ticks = U256::from((max_tick - min_tick) / tick_spacing + 1).as_u24();
     u128::MAX / (num_ticks as u128)
 }
@@ -167,10 +155,14 @@ pub fn get_fee_growth_inside(
         fee_growth_above1_x128 = fee_growth_global_1_x128 - upper.fee_growth_outside_1_x128;
     }
 
-    let fee_growth_inside_0_x128 =
-        (fee_growth_global_0_x128 - fee_growth_below0_x128) - fee_growth_above0_x128;
-    let fee_growth_inside_1_x128 =
-        (fee_growth_global_1_x128 - fee_growth_below1_x128) - fee_growth_above1_x128;
+    // the growth inside can underflow, e.g. for a range above the price that was never crossed,
+    // and wraps like in Solidity; only differences between two readings are meaningful
+    let fee_growth_inside_0_x128 = fee_growth_global_0_x128
+        .wrapping_sub(fee_growth_below0_x128)
+        .wrapping_sub(fee_growth_above0_x128);
+    let fee_growth_inside_1_x128 = fee_growth_global_1_x128
+        .wrapping_sub(fee_growth_below1_x128)
+        .wrapping_sub(fee_growth_above1_x128);
 
     [fee_growth_inside_0_x128, fee_growth_inside_1_x128]
 }
@@ -178,7 +170,6 @@ pub fn get_fee_growth_inside(
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod tests {
-    use ethnum::AsI256;
     use std::collections::HashMap;
 
     use crate::num160::U160;
@@ -220,7 +211,7 @@ mod tests {
 
         for (tick_spacing, expected) in expected_values.iter() {
             let max_liquidity_per_tick =
-                tick::tick_spacing_to_max_liquidity_per_tick(tick_spacing.as_i256().as_i24());
+                tick::tick_spacing_to_max_liquidity_per_tick((*tick_spacing as i128).as_i24());
             let expected_liquidity: u128 = (*expected).into();
             assert_eq!(max_liquidity_per_tick, expected_liquidity);
         }
@@ -322,35 +313,34 @@ mod tests {
             assert_eq!(fee_growth_inside_0_x128, 9);
             assert_eq!(fee_growth_inside_1_x128, 11);
         }
-        // UPDATED: Does not work with overflow on inside tick
-        // {
-        //     // works correctly with overflow on inside tick
-        //     let lower = TickInfo {
-        //         fee_growth_outside_0_x128: u128::MAX - 3,
-        //         fee_growth_outside_1_x128: u128::MAX - 2,
-        //         liquidity_gross: 0,
-        //         liquidity_net: 0,
-        //         seconds_per_liquidity_outside_x128: U160::zero(),
-        //         tick_cumulative_outside: 0,
-        //         seconds_outside: 0,
-        //         initialized: true,
-        //     };
-        //     let upper = TickInfo {
-        //         fee_growth_outside_0_x128: 3,
-        //         fee_growth_outside_1_x128: 5,
-        //         liquidity_gross: 0,
-        //         liquidity_net: 0,
-        //         seconds_per_liquidity_outside_x128: U160::zero(),
-        //         tick_cumulative_outside: 0,
-        //         seconds_outside: 0,
-        //         initialized: true,
-        //     };
-
-        //     let [fee_growth_inside_0_x128, fee_growth_inside_1_x128] =
-        //         get_fee_growth_inside(-2, 2, &lower, &upper, 0, 15, 15);
-        //     assert_eq!(fee_growth_inside_0_x128, 16);
-        //     assert_eq!(fee_growth_inside_1_x128, 13);
-        // }
+        {
+            // works correctly with overflow on inside tick
+            let lower = TickInfo {
+                fee_growth_outside_0_x128: u128::MAX - 3,
+                fee_growth_outside_1_x128: u128::MAX - 2,
+                liquidity_gross: 0,
+                liquidity_net: 0,
+                seconds_per_liquidity_outside_x128: U160::zero(),
+                tick_cumulative_outside: 0,
+                seconds_outside: 0,
+                initialized: true,
+            };
+            let upper = TickInfo {
+                fee_growth_outside_0_x128: 3,
+                fee_growth_outside_1_x128: 5,
+                liquidity_gross: 0,
+                liquidity_net: 0,
+                seconds_per_liquidity_outside_x128: U160::zero(),
+                tick_cumulative_outside: 0,
+                seconds_outside: 0,
+                initialized: true,
+            };
+
+            let [fee_growth_inside_0_x128, fee_growth_inside_1_x128] =
+                get_fee_growth_inside(-2, 2, &lower, &upper, 0, 15, 15);
+            assert_eq!(fee_growth_inside_0_x128, 16);
+            assert_eq!(fee_growth_inside_1_x128, 13);
+        }
     }
 
     #[test]
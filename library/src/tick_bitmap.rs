@@ -1,3 +1,6 @@
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "near")]
 use near_sdk::collections::LookupMap;
 
 use crate::bit_math::BitMathTrait;
@@ -9,6 +12,49 @@ use crate::tick_math::TickConstants;
 /// @notice Stores a packed mapping of tick index to its initialized state
 /// @dev The mapping uses int16 for keys since ticks are represented as int24 and there are 256 (2^8) values per word.
 
+/// Storage of the packed bitmap words, keyed by word position.
+/// Missing words read as zero.
+pub trait TickBitmap {
+    fn get_word(&self, word_pos: i16) -> U256;
+    fn set_word(&mut self, word_pos: i16, word: U256);
+}
+
+#[cfg(feature = "near")]
+impl TickBitmap for LookupMap<i16, U256> {
+    fn get_word(&self, word_pos: i16) -> U256 {
+        self.get(&word_pos).unwrap_or_default()
+    }
+
+    /// Empty words are removed, freeing their storage
+    fn set_word(&mut self, word_pos: i16, word: U256) {
+        if word.is_zero() {
+            self.remove(&word_pos);
+        } else {
+            self.insert(&word_pos, &word);
+        }
+    }
+}
+
+impl TickBitmap for HashMap<i16, U256> {
+    fn get_word(&self, word_pos: i16) -> U256 {
+        self.get(&word_pos).copied().unwrap_or_default()
+    }
+
+    fn set_word(&mut self, word_pos: i16, word: U256) {
+        self.insert(word_pos, word);
+    }
+}
+
+impl TickBitmap for BTreeMap<i16, U256> {
+    fn get_word(&self, word_pos: i16) -> U256 {
+        self.get(&word_pos).copied().unwrap_or_default()
+    }
+
+    fn set_word(&mut self, word_pos: i16, word: U256) {
+        self.insert(word_pos, word);
+    }
+}
+
 /// @notice Computes the position in the mapping where the initialized bit for a tick lives
 /// @param tick The tick for which to compute the position
 /// @return wordPos The key in the mapping containing the word in which the bit is stored
@@ -29,7 +75,7 @@ pub fn position(tick: i32) -> (i16, u8) {
 /// @param tick The tick to flip
 /// @param
 /// @param tickSpacing The spacing between usable ticks
-pub fn flip_tick(tick_bitmap: &mut LookupMap<i16, U256>, tick: i32, tick_spacing: i32) {
+pub fn flip_tick<T: TickBitmap + ?Sized>(tick_bitmap: &mut T, tick: i32, tick_spacing: i32) {
     assert!(
         (TickConstants::MIN_TICK..=TickConstants::MAX_TICK).contains(&tick),
         "INVALID TICK RANGE",
@@ -37,9 +83,8 @@ pub fn flip_tick(tick_bitmap: &mut LookupMap<i16, U256>, tick: i32, tick_spacing
     assert_eq!(tick % tick_spacing, 0); // ensure that the tick is spaced
     let (word_pos, bit_pos) = position(tick / tick_spacing);
     let mask = U256::one() << bit_pos;
-    let mut current = tick_bitmap.get(&word_pos).unwrap_or_default();
-    current ^= mask;
-    tick_bitmap.insert(&word_pos, &current);
+    let current = tick_bitmap.get_word(word_pos) ^ mask;
+    tick_bitmap.set_word(word_pos, current);
 }
 
 /// !MODIFIED
@@ -52,8 +97,8 @@ pub fn flip_tick(tick_bitmap: &mut LookupMap<i16, U256>, tick: i32, tick_spacing
 /// !@param get_word A function to get word by word position from a tick bitmap
 /// @return next The next initialized or uninitialized tick up to 256 ticks away from the current tick
 /// @return initialized Whether the next tick is initialized, as the function only searches within up to 256 ticks
-pub fn next_initialized_tick_within_one_word(
-    tick_bitmap: &LookupMap<i16, U256>,
+pub fn next_initialized_tick_within_one_word<T: TickBitmap + ?Sized>(
+    tick_bitmap: &T,
     tick: i32,
     tick_spacing: i32,
     lte: bool,
@@ -75,7 +120,7 @@ pub fn next_initialized_tick_within_one_word(
         let (word_pos, bit_pos) = position(compressed);
         // all the 1s at or to the right of the current bitPos
         let mask = (U256::one() << bit_pos) - U256::one() + (U256::one() << bit_pos);
-        let masked = tick_bitmap.get(&word_pos).unwrap_or_default() & mask;
+        let masked = tick_bitmap.get_word(word_pos) & mask;
 
         // if there are no initialized ticks to the right of or at the current tick, return rightmost in the word
         initialized = masked != U256::zero();
@@ -91,7 +136,7 @@ pub fn next_initialized_tick_within_one_word(
         let (word_pos, bit_pos) = position(compressed + 1);
         // all the 1s at or to the left of the bitPos
         let mask = !((U256::one() << bit_pos) - U256::one());
-        let masked = tick_bitmap.get(&word_pos).unwrap_or_default() & mask;
+        let masked = tick_bitmap.get_word(word_pos) & mask;
 
         // if there are no initialized ticks to the left of the current tick, return leftmost in the word
         initialized = masked != U256::zero();
@@ -106,20 +151,219 @@ pub fn next_initialized_tick_within_one_word(
     (next, initialized)
 }
 
+/// How many bitmap words a swap step searches before it settles for an uninitialized tick
+pub const MAX_SEARCH_WORDS: u32 = 16;
+
+/// !ADDED
+/// Like `next_initialized_tick_within_one_word`, but moves on to the neighbouring word while the
+/// current one is empty, reading at most `max_words` words. An empty word costs a storage read
+/// here instead of a whole swap step, which keeps wide swaps through sparse pools within gas.
+/// The returned tick is clamped to `[MIN_TICK, MAX_TICK]`
+pub fn next_initialized_tick_within_words<T: TickBitmap + ?Sized>(
+    tick_bitmap: &T,
+    tick: i32,
+    tick_spacing: i32,
+    lte: bool,
+    max_words: u32,
+) -> (i32, bool) {
+    assert_ne!(max_words, 0);
+    let mut tick = tick;
+    let mut words = 1;
+    loop {
+        let (next, initialized) =
+            next_initialized_tick_within_one_word(tick_bitmap, tick, tick_spacing, lte);
+        let next = next.clamp(TickConstants::MIN_TICK, TickConstants::MAX_TICK);
+
+        // `next` is the outermost tick of an empty word, the search goes on from the tick past it
+        let following = if lte { next - 1 } else { next };
+        if initialized
+            || words == max_words
+            || !(TickConstants::MIN_TICK..TickConstants::MAX_TICK).contains(&following)
+        {
+            return (next, initialized);
+        }
+        tick = following;
+        words += 1;
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use proptest::collection::btree_set;
+    use proptest::prelude::*;
+
+    fn bitmap_with(ticks: &[i32]) -> HashMap<i16, U256> {
+        let mut tick_bitmap = HashMap::new();
+        for tick in ticks {
+            flip_tick(&mut tick_bitmap, *tick, 1);
+        }
+        tick_bitmap
+    }
+
     #[test]
     fn test_position() {
-        // TODO: @galin-chung-nguyen
+        assert_eq!(position(0), (0, 0));
+        assert_eq!(position(255), (0, 255));
+        assert_eq!(position(256), (1, 0));
+        assert_eq!(position(-1), (-1, 255));
+        assert_eq!(position(-230), (-1, 26));
     }
 
     #[test]
     fn test_flip_tick() {
-        // TODO: @galin-chung-nguyen
+        let mut tick_bitmap = bitmap_with(&[-230, -259]);
+        assert_eq!(tick_bitmap.get_word(-1), U256::one() << 26);
+        assert_eq!(tick_bitmap.get_word(-2), U256::one() << 253);
+
+        flip_tick(&mut tick_bitmap, -230, 1);
+        assert_eq!(tick_bitmap.get_word(-1), U256::zero());
+        assert_eq!(tick_bitmap.get_word(-2), U256::one() << 253);
     }
 
     #[test]
     fn test_next_initialized_tick_within_one_word() {
-        // TODO: @galin-chung-nguyen
+        let ticks = [-200, -55, -4, 70, 78, 84, 139, 240, 535];
+        let tick_bitmap = bitmap_with(&ticks);
+        let next = |tick, lte| next_initialized_tick_within_one_word(&tick_bitmap, tick, 1, lte);
+
+        // to the right
+        assert_eq!(next(78, false), (84, true));
+        assert_eq!(next(77, false), (78, true));
+        assert_eq!(next(-56, false), (-55, true));
+        assert_eq!(next(255, false), (511, false));
+        assert_eq!(next(-257, false), (-200, true));
+        assert_eq!(next(508, false), (511, false));
+        assert_eq!(next(383, false), (511, false));
+
+        // at or to the left
+        assert_eq!(next(78, true), (78, true));
+        assert_eq!(next(79, true), (78, true));
+        assert_eq!(next(258, true), (256, false));
+        assert_eq!(next(256, true), (256, false));
+        assert_eq!(next(72, true), (70, true));
+        assert_eq!(next(-257, true), (-512, false));
+        assert_eq!(next(1023, true), (768, false));
+        assert_eq!(next(900, true), (768, false));
+
+        let mut tick_bitmap: BTreeMap<i16, U256> = BTreeMap::new();
+        for tick in ticks.iter().chain([340, 329].iter()) {
+            flip_tick(&mut tick_bitmap, *tick, 1);
+        }
+        assert_eq!(
+            next_initialized_tick_within_one_word(&tick_bitmap, 328, 1, false),
+            (329, true)
+        );
+        assert_eq!(
+            next_initialized_tick_within_one_word(&tick_bitmap, 456, 1, true),
+            (340, true)
+        );
+    }
+
+    #[test]
+    fn test_next_initialized_tick_within_words() {
+        let tick_bitmap = bitmap_with(&[-200, 70, 2000]);
+        let next = |tick, lte, max_words| {
+            next_initialized_tick_within_words(&tick_bitmap, tick, 1, lte, max_words)
+        };
+
+        assert_eq!(next(71, false, MAX_SEARCH_WORDS), (2000, true));
+        assert_eq!(next(71, false, 2), (511, false));
+        assert_eq!(
+            next(71, false, 1),
+            next_initialized_tick_within_one_word(&tick_bitmap, 71, 1, false)
+        );
+        assert_eq!(next(1000, true, MAX_SEARCH_WORDS), (70, true));
+        assert_eq!(next(-201, true, MAX_SEARCH_WORDS), (-4096, false));
+
+        // the search stops at the tick range boundaries
+        let max_tick = TickConstants::MAX_TICK;
+        let min_tick = TickConstants::MIN_TICK;
+        assert_eq!(
+            next(max_tick - 1000, false, MAX_SEARCH_WORDS),
+            (max_tick, false)
+        );
+        assert_eq!(
+            next(min_tick + 1000, true, MAX_SEARCH_WORDS),
+            (min_tick, false)
+        );
+        assert_eq!(next(min_tick, true, 1), (min_tick, false));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_next_initialized_tick_matches_scan(
+            compressed_ticks in btree_set(-1024..1024_i32, 0..64),
+            compressed in -1024..1024_i32,
+            offset in 0..200_i32,
+            tick_spacing in 1..200_i32,
+            lte in any::<bool>(),
+        ) {
+            let mut tick_bitmap: BTreeMap<i16, U256> = BTreeMap::new();
+            for tick in compressed_ticks.iter() {
+                flip_tick(&mut tick_bitmap, tick * tick_spacing, tick_spacing);
+            }
+            let tick = compressed * tick_spacing + offset % tick_spacing;
+
+            // walk the word bit by bit instead of masking it
+            let expected = if lte {
+                let word_start = (compressed >> 8) << 8;
+                (word_start..=compressed)
+                    .rev()
+                    .find(|tick| compressed_ticks.contains(tick))
+                    .map_or((word_start * tick_spacing, false), |tick| (tick * tick_spacing, true))
+            } else {
+                let word_end = (((compressed + 1) >> 8) << 8) + 255;
+                (compressed + 1..=word_end)
+                    .find(|tick| compressed_ticks.contains(tick))
+                    .map_or((word_end * tick_spacing, false), |tick| (tick * tick_spacing, true))
+            };
+
+            prop_assert_eq!(
+                next_initialized_tick_within_one_word(&tick_bitmap, tick, tick_spacing, lte),
+                expected
+            );
+        }
+
+        #[test]
+        fn prop_next_initialized_tick_within_words_matches_scan(
+            compressed_ticks in btree_set(-1024..1024_i32, 0..16),
+            compressed in -1024..1024_i32,
+            offset in 0..200_i32,
+            tick_spacing in 1..200_i32,
+            lte in any::<bool>(),
+            max_words in 1..8_i32,
+        ) {
+            let mut tick_bitmap: BTreeMap<i16, U256> = BTreeMap::new();
+            for tick in compressed_ticks.iter() {
+                flip_tick(&mut tick_bitmap, tick * tick_spacing, tick_spacing);
+            }
+            let tick = compressed * tick_spacing + offset % tick_spacing;
+            let clamp = |tick: i32| tick.clamp(TickConstants::MIN_TICK, TickConstants::MAX_TICK);
+
+            let expected = if lte {
+                let first = ((compressed >> 8) - (max_words - 1)) << 8;
+                (first..=compressed)
+                    .rev()
+                    .find(|tick| compressed_ticks.contains(tick))
+                    .map_or((clamp(first * tick_spacing), false), |tick| (tick * tick_spacing, true))
+            } else {
+                let last = ((((compressed + 1) >> 8) + max_words - 1) << 8) + 255;
+                (compressed + 1..=last)
+                    .find(|tick| compressed_ticks.contains(tick))
+                    .map_or((clamp(last * tick_spacing), false), |tick| (tick * tick_spacing, true))
+            };
+
+            prop_assert_eq!(
+                next_initialized_tick_within_words(
+                    &tick_bitmap,
+                    tick,
+                    tick_spacing,
+                    lte,
+                    max_words as u32
+                ),
+                expected
+            );
+        }
     }
 }
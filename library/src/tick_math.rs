@@ -1,12 +1,10 @@
-use ethnum::I256;
-use std::str::FromStr;
 // use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 // use near_sdk::{env, log};
 // use near_sdk::ext_contract;
 
 use crate::num160::{AsU160, U160};
 use crate::num24::{AsI24, I24};
-use crate::num256::{AsI256, ToU256, U256};
+use crate::num256::{I256, U256};
 
 pub struct TickConstants;
 
@@ -14,15 +12,27 @@ impl TickConstants {
     pub const MIN_TICK: I24 = -887272;
     pub const MAX_TICK: I24 = -TickConstants::MIN_TICK;
 
+    /// 4295128739, the value of `get_sqrt_ratio_at_tick(MIN_TICK)`
+    pub const MIN_SQRT_RATIO: U160 = U256([4295128739, 0, 0, 0]);
+    /// 1461446703485210103287273052203988822378723970342, the value of `get_sqrt_ratio_at_tick(MAX_TICK)`
+    pub const MAX_SQRT_RATIO: U160 = U256([0x5d951d5263988d26, 0xefd1fc6a50648849, 0xfffd8963, 0]);
+
     pub fn min_sqrt_ratio() -> U160 {
-        U160::from_dec_str("4295128739").unwrap_or(U160::zero())
+        Self::MIN_SQRT_RATIO
     }
 
     pub fn max_sqrt_ratio() -> U160 {
-        U160::from_dec_str("1461446703485210103287273052203988822378723970342")
-            .unwrap_or(U160::zero())
+        Self::MAX_SQRT_RATIO
     }
 }
+
+const TWO_POW_128: U256 = U256([0, 0, 1, 0]);
+const U128_MAX: U256 = U256([u64::MAX, u64::MAX, 0, 0]);
+const U64_MAX: U256 = U256([u64::MAX, 0, 0, 0]);
+const U32_MAX: U256 = U256([u32::MAX as u64, 0, 0, 0]);
+/// 291339464771989622907027621153398088495, the upper error bound of `log_sqrt10001` as a 128.128 number
+const TICK_HI_ERROR: I256 =
+    I256::from_words(0, 291339464771989622907027621153398088495_u128 as i128);
 //
 // fn printBits256(x: I256) {
 //   print!("{} => ", x);
@@ -60,7 +70,7 @@ pub fn get_sqrt_ratio_at_tick(tick: I24) -> U160 {
     let mut ratio = if (abs_tick & U256::one()) != U256::zero() {
         U256::from(0xfffcb933bd6fad37aa2d162d1a594001_u128)
     } else {
-        U256::from_str_radix("0x100000000000000000000000000000000", 16).unwrap()
+        TWO_POW_128
     };
 
     if (abs_tick & U256::from(0x2)) != U256::zero() {
@@ -154,21 +164,15 @@ pub fn get_tick_at_sqrt_ratio(sqrt_price_x96: U160) -> I24 {
     let mut r = ratio;
     let mut msb: U256 = U256::from(0);
 
-    let f = U256::from(u128::from(
-        r > U256::from_str_radix("0xffffffffffffffffffffffffffffffff", 16).unwrap(),
-    )) << 7;
+    let f = U256::from(u128::from(r > U128_MAX)) << 7;
     msb |= f;
     r >>= f;
 
-    let f = U256::from(u128::from(
-        r > U256::from_str_radix("0xffffffffffffffff", 16).unwrap(),
-    )) << 6;
+    let f = U256::from(u128::from(r > U64_MAX)) << 6;
     msb |= f;
     r >>= f;
 
-    let f = U256::from(u128::from(
-        r > U256::from_str_radix("0xffffffff", 16).unwrap(),
-    )) << 5;
+    let f = U256::from(u128::from(r > U32_MAX)) << 5;
     msb |= f;
     r >>= f;
 
@@ -197,24 +201,20 @@ pub fn get_tick_at_sqrt_ratio(sqrt_price_x96: U160) -> I24 {
         r = ratio << (U256::from(127) - msb);
     }
 
-    let mut log_2: I256 = (msb.as_i256() - 128) << 64;
+    let mut log_2: I256 = (msb.to_i256() - 128) << 64;
 
     for i in 0..14 {
         r = (r * r) >> 127;
         let f = I256::from(((r >> 128) as U256).as_u128());
         log_2 |= f << (63 - i);
         if i < 13 {
-            r >>= f.to_u256();
+            r >>= f.unsigned_abs();
         }
     }
 
     let log_sqrt10001: I256 = log_2 * 255738958999603826347141; // 128.128 number
-    let tick_low =
-        (((log_sqrt10001 - 3402992956809132418596140100660247210i128) >> 128) as I256).as_i24();
-    let tick_hi = (((log_sqrt10001
-        + I256::from_str("291339464771989622907027621153398088495").unwrap())
-        >> 128) as I256)
-        .as_i24();
+    let tick_low = ((log_sqrt10001 - 3402992956809132418596140100660247210i128) >> 128).as_i24();
+    let tick_hi = ((log_sqrt10001 + TICK_HI_ERROR) >> 128).as_i24();
 
     // let mut l = -887272;
     // let mut r = 887272;
@@ -242,8 +242,30 @@ pub fn get_tick_at_sqrt_ratio(sqrt_price_x96: U160) -> I24 {
 mod tests {
     use super::*;
     use crate::full_math::MathOps;
+    use crate::strategies;
+    use proptest::prelude::*;
     use std::panic;
 
+    #[test]
+    fn test_sqrt_ratio_constants() {
+        assert_eq!(
+            TickConstants::MIN_SQRT_RATIO,
+            U160::from_dec_str("4295128739").unwrap()
+        );
+        assert_eq!(
+            TickConstants::MAX_SQRT_RATIO,
+            U160::from_dec_str("1461446703485210103287273052203988822378723970342").unwrap()
+        );
+        assert_eq!(
+            get_sqrt_ratio_at_tick(TickConstants::MIN_TICK),
+            TickConstants::MIN_SQRT_RATIO
+        );
+        assert_eq!(
+            get_sqrt_ratio_at_tick(TickConstants::MAX_TICK),
+            TickConstants::MAX_SQRT_RATIO
+        );
+    }
+
     #[test]
     fn test_get_sqrt_ratio_at_tick() {
         assert!(panic::catch_unwind(|| {
@@ -316,4 +338,20 @@ mod tests {
             TickConstants::MAX_TICK - 1
         );
     }
+
+    proptest! {
+        #[test]
+        fn prop_tick_round_trip(tick in TickConstants::MIN_TICK..TickConstants::MAX_TICK) {
+            let sqrt_price_x96 = get_sqrt_ratio_at_tick(tick);
+            prop_assert_eq!(get_tick_at_sqrt_ratio(sqrt_price_x96), tick);
+            prop_assert!(sqrt_price_x96 < get_sqrt_ratio_at_tick(tick + 1));
+        }
+
+        #[test]
+        fn prop_tick_at_sqrt_ratio_is_floor(sqrt_price_x96 in strategies::sqrt_price_x96()) {
+            let tick = get_tick_at_sqrt_ratio(sqrt_price_x96);
+            prop_assert!(get_sqrt_ratio_at_tick(tick) <= sqrt_price_x96);
+            prop_assert!(sqrt_price_x96 < get_sqrt_ratio_at_tick(tick + 1));
+        }
+    }
 }
@@ -0,0 +1,164 @@
+//! Differential tests against the reference vectors of the Uniswap v3-core test suite,
+//! checked in under `tests/fixtures`.
+
+use serde_json::Value;
+use zswap_math_library::full_math::{FullMath, FullMathTrait};
+use zswap_math_library::num256::{I256, U256};
+use zswap_math_library::{sqrt_price_math, swap_math, tick_math};
+
+const VECTORS: &str = include_str!("fixtures/uniswap_v3_vectors.json");
+
+fn vectors(name: &str) -> Vec<Value> {
+    let vectors: Value = serde_json::from_str(VECTORS).unwrap();
+    vectors[name].as_array().unwrap().clone()
+}
+
+fn u256(value: &Value) -> U256 {
+    U256::from_dec_str(value.as_str().unwrap()).unwrap()
+}
+
+fn u128(value: &Value) -> u128 {
+    value.as_str().unwrap().parse().unwrap()
+}
+
+#[test]
+fn test_get_sqrt_ratio_at_tick() {
+    for vector in vectors("get_sqrt_ratio_at_tick") {
+        let tick = vector["tick"].as_i64().unwrap() as i32;
+        assert_eq!(
+            tick_math::get_sqrt_ratio_at_tick(tick),
+            u256(&vector["sqrt_price_x96"]),
+            "tick {}",
+            tick
+        );
+    }
+}
+
+#[test]
+fn test_get_tick_at_sqrt_ratio() {
+    for vector in vectors("get_tick_at_sqrt_ratio") {
+        assert_eq!(
+            tick_math::get_tick_at_sqrt_ratio(u256(&vector["sqrt_price_x96"])),
+            vector["tick"].as_i64().unwrap() as i32,
+            "{}",
+            vector
+        );
+    }
+}
+
+#[test]
+fn test_mul_div() {
+    for vector in vectors("mul_div") {
+        let (a, b, denominator) = (
+            u256(&vector["a"]),
+            u256(&vector["b"]),
+            u256(&vector["denominator"]),
+        );
+        assert_eq!(
+            FullMath::mul_div(a, b, denominator),
+            u256(&vector["result"]),
+            "{}",
+            vector
+        );
+        assert_eq!(
+            FullMath::mul_div_rounding_up(a, b, denominator),
+            u256(&vector["result_rounding_up"]),
+            "{}",
+            vector
+        );
+    }
+}
+
+#[test]
+fn test_get_next_sqrt_price() {
+    for vector in vectors("get_next_sqrt_price_from_input") {
+        assert_eq!(
+            sqrt_price_math::get_next_sqrt_price_from_input(
+                u256(&vector["sqrt_price_x96"]),
+                u128(&vector["liquidity"]),
+                u256(&vector["amount"]),
+                vector["zero_for_one"].as_bool().unwrap(),
+            ),
+            u256(&vector["sqrt_price_next_x96"]),
+            "{}",
+            vector
+        );
+    }
+    for vector in vectors("get_next_sqrt_price_from_output") {
+        assert_eq!(
+            sqrt_price_math::get_next_sqrt_price_from_output(
+                u256(&vector["sqrt_price_x96"]),
+                u128(&vector["liquidity"]),
+                u256(&vector["amount"]),
+                vector["zero_for_one"].as_bool().unwrap(),
+            ),
+            u256(&vector["sqrt_price_next_x96"]),
+            "{}",
+            vector
+        );
+    }
+}
+
+#[test]
+fn test_get_amount_delta() {
+    for vector in vectors("get_amount_delta") {
+        let (sqrt_price_a_x96, sqrt_price_b_x96, liquidity) = (
+            u256(&vector["sqrt_price_a_x96"]),
+            u256(&vector["sqrt_price_b_x96"]),
+            u128(&vector["liquidity"]),
+        );
+        for (round_up, amount_0, amount_1) in [
+            (true, "amount_0_rounding_up", "amount_1_rounding_up"),
+            (false, "amount_0", "amount_1"),
+        ] {
+            assert_eq!(
+                sqrt_price_math::get_amount_0_delta(
+                    sqrt_price_a_x96,
+                    sqrt_price_b_x96,
+                    liquidity,
+                    round_up
+                ),
+                u256(&vector[amount_0])
+            );
+            assert_eq!(
+                sqrt_price_math::get_amount_1_delta(
+                    sqrt_price_a_x96,
+                    sqrt_price_b_x96,
+                    liquidity,
+                    round_up
+                ),
+                u256(&vector[amount_1])
+            );
+        }
+    }
+}
+
+#[test]
+fn test_compute_swap_step() {
+    for vector in vectors("compute_swap_step") {
+        let name = vector["name"].as_str().unwrap();
+        let (sqrt_price_next_x96, amount_in, amount_out, fee_amount) = swap_math::compute_swap_step(
+            u256(&vector["sqrt_price_x96"]),
+            u256(&vector["sqrt_price_target_x96"]),
+            u128(&vector["liquidity"]),
+            vector["amount_remaining"]
+                .as_str()
+                .unwrap()
+                .parse::<I256>()
+                .unwrap(),
+            vector["fee_pips"].as_u64().unwrap() as u32,
+        );
+
+        if !vector["sqrt_price_next_x96"].is_null() {
+            assert_eq!(
+                sqrt_price_next_x96,
+                u256(&vector["sqrt_price_next_x96"]),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(amount_in, u256(&vector["amount_in"]), "{}", name);
+        assert_eq!(amount_out, u256(&vector["amount_out"]), "{}", name);
+        assert_eq!(fee_amount, u256(&vector["fee_amount"]), "{}", name);
+    }
+}
@@ -1,61 +1,157 @@
+use near_contract_standards::non_fungible_token::events::NftMint;
 use near_contract_standards::non_fungible_token::metadata::TokenMetadata as NftMetadata;
-use near_sdk::json_types::{Base64VecU8, U128};
-use near_sdk::{env, near_bindgen, serde_json, Promise, PromiseError};
-use zswap_math_library::pool_account;
+use near_sdk::json_types::{Base64VecU8, I128, U128};
+use near_sdk::{
+    env, is_promise_success, log, near_bindgen, serde_json, AccountId, Promise, PromiseError,
+};
+use zswap_math_library::pool_account::{self, POOL_POSITION_STORAGE};
 
-use crate::error::{MINT_CALLBACK_ERROR, SLIPPAGE_CHECK_FAILED};
+use crate::error::{
+    BURN_FAILED, COLLECT_FAILED, MINT_CALLBACK_ERROR, POOL_CREATION_FAILED, SLIPPAGE_CHECK_FAILED,
+    SWAP_FAILED,
+};
+use crate::ft_storage::ext_ft_storage;
 use crate::nft::NftPosition;
+use crate::pool::ext_zswap_pool;
 use crate::utils::{generate_nft_media, MintCallbackParams, NftLiquidityInfo};
-use crate::Contract;
 use crate::ContractExt;
+use crate::{Contract, POOL_STORAGE_DEPOSIT};
 
 pub trait ManagerCallback {
+    fn create_pool_callback(
+        &mut self,
+        pool_res: Result<Option<AccountId>, PromiseError>,
+        payer: AccountId,
+        amount: U128,
+    ) -> Option<AccountId>;
+
     fn mint_callback(
         &mut self,
         used_amounts_res: Result<[U128; 2], PromiseError>,
         params: MintCallbackParams,
     ) -> [U128; 2];
 
-    fn manager_swap_callback(
+    fn unwind_mint_callback(
         &mut self,
-        amount_out_min: U128,
-        amount_received: Result<U128, PromiseError>,
+        burned_res: Result<[U128; 2], PromiseError>,
+        pool: AccountId,
+        params: MintCallbackParams,
     );
+
+    fn collect_to_deposit_callback(
+        &mut self,
+        collected_res: Result<[U128; 2], PromiseError>,
+        pool: AccountId,
+        recipient: AccountId,
+        token_0: AccountId,
+        token_1: AccountId,
+    ) -> [U128; 2];
+
+    fn collect_callback(
+        &mut self,
+        collected_res: Result<[U128; 2], PromiseError>,
+        pool: AccountId,
+    ) -> [U128; 2];
+
+    fn swap_callback(
+        &mut self,
+        amounts_res: Result<[I128; 2], PromiseError>,
+        payer: AccountId,
+        pool: AccountId,
+        token_in: AccountId,
+        token_out: AccountId,
+        amount_in: U128,
+    ) -> U128;
+
+    fn withdraw_callback(&mut self, account_id: AccountId, token_id: AccountId, amount: U128);
 }
 
 #[near_bindgen]
 impl ManagerCallback for Contract {
     #[private]
-    #[payable]
+    fn create_pool_callback(
+        &mut self,
+        #[callback_result] pool_res: Result<Option<AccountId>, PromiseError>,
+        payer: AccountId,
+        amount: U128,
+    ) -> Option<AccountId> {
+        match pool_res {
+            Ok(Some(pool)) => {
+                // the manager deposits in the pool when it mints or swaps through it
+                ext_ft_storage::ext(pool.clone())
+                    .with_attached_deposit(POOL_STORAGE_DEPOSIT)
+                    .storage_deposit(None, None);
+                Some(pool)
+            }
+            _ => {
+                // The factory returns the whole deposit to the manager when creation fails
+                log!(
+                    "{}, returning {}yⓃ to {}",
+                    POOL_CREATION_FAILED,
+                    amount.0,
+                    payer
+                );
+                Promise::new(payer).transfer(amount.0);
+                None
+            }
+        }
+    }
+
+    #[private]
     fn mint_callback(
         &mut self,
         #[callback_result] used_amounts_res: Result<[U128; 2], PromiseError>,
         params: MintCallbackParams,
     ) -> [U128; 2] {
+        assert!(params.token_0 < params.token_1);
+        let pool = pool_account::compute_account(
+            &self.factory,
+            &params.token_0,
+            &params.token_1,
+            params.fee,
+        );
+
         if used_amounts_res.is_err() {
-            Promise::new(params.recipient).transfer(env::attached_deposit());
-            env::panic_str(MINT_CALLBACK_ERROR)
+            log!("{}, refunding {}", MINT_CALLBACK_ERROR, params.recipient);
+            self.internal_deposit(
+                &params.recipient,
+                &params.token_0,
+                params.amount_0_desired.0,
+            );
+            self.internal_deposit(
+                &params.recipient,
+                &params.token_1,
+                params.amount_1_desired.0,
+            );
+            self.internal_withdraw_from_pool(&pool, params.token_0, params.amount_0_desired.0);
+            self.internal_withdraw_from_pool(&pool, params.token_1, params.amount_1_desired.0);
+            return [U128(0), U128(0)];
         }
 
         let received_amounts = used_amounts_res.unwrap();
         let amount_0 = received_amounts[0];
         let amount_1 = received_amounts[1];
 
+        // the pool keeps only what the liquidity needs, the rest goes back to the recipient
+        let unused_amount_0 = params.amount_0_desired.0 - amount_0.0;
+        let unused_amount_1 = params.amount_1_desired.0 - amount_1.0;
+        self.internal_deposit(&params.recipient, &params.token_0, unused_amount_0);
+        self.internal_deposit(&params.recipient, &params.token_1, unused_amount_1);
+        self.internal_withdraw_from_pool(&pool, params.token_0.clone(), unused_amount_0);
+        self.internal_withdraw_from_pool(&pool, params.token_1.clone(), unused_amount_1);
+
+        // the price moved since `mint_calculate_liquidity` checked it, take the liquidity back
         if amount_0 < params.amount_0_min || amount_1 < params.amount_1_min {
-            Promise::new(params.recipient).transfer(env::attached_deposit());
-            env::panic_str(SLIPPAGE_CHECK_FAILED)
+            log!("{}, refunding {}", SLIPPAGE_CHECK_FAILED, params.recipient);
+            ext_zswap_pool::ext(pool.clone())
+                .burn(params.lower_tick, params.upper_tick, U128(params.liquidity))
+                .then(Self::ext(env::current_account_id()).unwind_mint_callback(pool, params));
+            return [U128(0), U128(0)];
         }
 
         // mint nft
         let symbol_0 = &params.symbol_0;
         let symbol_1 = &params.symbol_1;
-        assert!(params.token_0 < params.token_1);
-        let pool = pool_account::compute_account(
-            &self.factory,
-            &params.token_0,
-            &params.token_1,
-            params.fee,
-        );
 
         let nft_title = format!("{}/{}", symbol_0, symbol_1);
         let nft_description = format!("ZSwap Liquidity NFT for {}", &pool);
@@ -92,11 +188,20 @@ impl ManagerCallback for Contract {
             reference_hash: None,
         };
 
-        self.nft.internal_mint(
+        // the recipient's storage balance pays for the NFT, checked by `mint`
+        let initial_storage = env::storage_usage();
+        let token = self.nft.internal_mint_with_refund(
             self.nft_id.to_string(),
-            params.recipient,
+            params.recipient.clone(),
             Some(liquidity_nft_metadata),
+            None,
         );
+        NftMint {
+            owner_id: &token.owner_id,
+            token_ids: &[&token.token_id],
+            memo: None,
+        }
+        .emit();
         self.nft_positions.insert(
             &self.nft_id,
             &NftPosition {
@@ -107,17 +212,122 @@ impl ManagerCallback for Contract {
             },
         );
         self.nft_id += 1;
+        self.internal_record_storage(&params.recipient, initial_storage);
+        self.internal_use_storage(&params.recipient, POOL_POSITION_STORAGE);
 
         [amount_0, amount_1]
     }
 
-    #[allow(unused)]
+    /// Collects the burned liquidity of a mint that failed its slippage check into the manager
     #[private]
-    fn manager_swap_callback(
+    fn unwind_mint_callback(
         &mut self,
-        amount_out_min: U128,
-        #[callback_result] amount_received: Result<U128, PromiseError>,
+        #[callback_result] burned_res: Result<[U128; 2], PromiseError>,
+        pool: AccountId,
+        params: MintCallbackParams,
     ) {
-        todo!("check output amount is enough or not")
+        let burned = match burned_res {
+            Ok(burned) => burned,
+            Err(_) => {
+                log!(
+                    "{}, {} liquidity of {} is left in {}",
+                    BURN_FAILED,
+                    params.liquidity,
+                    params.recipient,
+                    pool
+                );
+                return;
+            }
+        };
+
+        ext_zswap_pool::ext(pool.clone())
+            .collect(
+                env::current_account_id(),
+                params.lower_tick,
+                params.upper_tick,
+                burned[0],
+                burned[1],
+            )
+            .then(
+                Self::ext(env::current_account_id()).collect_to_deposit_callback(
+                    pool,
+                    params.recipient,
+                    params.token_0,
+                    params.token_1,
+                ),
+            );
+    }
+
+    /// Credits what the pool sent the manager on `collect` to the recipient's deposit, and
+    /// takes back the storage the unwound position took in the pool
+    #[private]
+    fn collect_to_deposit_callback(
+        &mut self,
+        #[callback_result] collected_res: Result<[U128; 2], PromiseError>,
+        pool: AccountId,
+        recipient: AccountId,
+        token_0: AccountId,
+        token_1: AccountId,
+    ) -> [U128; 2] {
+        let collected = collected_res.unwrap_or([U128(0), U128(0)]);
+        self.internal_deposit(&recipient, &token_0, collected[0].0);
+        self.internal_deposit(&recipient, &token_1, collected[1].0);
+        self.internal_reclaim_pool_storage(&pool, POOL_POSITION_STORAGE);
+        collected
+    }
+
+    /// Returns what `burn` collected, once the pool removed the position, and takes back the
+    /// storage it took there
+    #[private]
+    fn collect_callback(
+        &mut self,
+        #[callback_result] collected_res: Result<[U128; 2], PromiseError>,
+        pool: AccountId,
+    ) -> [U128; 2] {
+        let collected = collected_res.unwrap_or_else(|_| env::panic_str(COLLECT_FAILED));
+        self.internal_reclaim_pool_storage(&pool, POOL_POSITION_STORAGE);
+        collected
+    }
+
+    #[private]
+    fn swap_callback(
+        &mut self,
+        #[callback_result] amounts_res: Result<[I128; 2], PromiseError>,
+        payer: AccountId,
+        pool: AccountId,
+        token_in: AccountId,
+        token_out: AccountId,
+        amount_in: U128,
+    ) -> U128 {
+        let (amount_in_used, amount_out) = match amounts_res {
+            Ok(amounts) if token_in < token_out => (amounts[0].0, -amounts[1].0),
+            Ok(amounts) => (amounts[1].0, -amounts[0].0),
+            Err(_) => {
+                log!("{}, refunding {}", SWAP_FAILED, payer);
+                (0, 0)
+            }
+        };
+
+        let unused_amount_in = amount_in.0 - amount_in_used as u128;
+        self.internal_deposit(&payer, &token_in, unused_amount_in);
+        self.internal_deposit(&payer, &token_out, amount_out as u128);
+        self.internal_withdraw_from_pool(&pool, token_in, unused_amount_in);
+
+        U128::from(amount_out as u128)
+    }
+
+    #[private]
+    fn withdraw_callback(&mut self, account_id: AccountId, token_id: AccountId, amount: U128) {
+        if is_promise_success() {
+            return;
+        }
+
+        log!(
+            "Failed to withdraw {} {}, restoring deposit of {}",
+            amount.0,
+            token_id,
+            account_id
+        );
+        self.internal_deposit(&account_id, &token_id, amount.0);
     }
 }
@@ -1,11 +1,34 @@
 pub const SLIPPAGE_CHECK_FAILED: &str = "Slippage check failed";
 // pub const TOO_LITTLE_RECEIVED: &str = "Too little received";
 pub const TOKENS_MUST_BE_DIFFERENT: &str = "Tokens must be different";
+pub const SWAP_FAILED: &str = "Swap failed";
 pub const MINT_CALLBACK_ERROR: &str = "Mint callback error";
+pub const BURN_FAILED: &str = "Burn failed";
+pub const COLLECT_FAILED: &str = "Collect failed";
 pub const WRONG_MSG_FORMAT: &str = "Wrong msg format";
-pub const POOL_ALREADY_EXISTS: &str = "Pool already exists";
+pub const POOL_CREATION_FAILED: &str = "Pool creation failed";
+pub const NOT_ENOUGH_DEPOSIT: &str = "Not enough deposit";
 
 pub const NFT_NOT_OWNED_BY_CALLER: &str = "NFT not owned by caller";
 pub const NFT_NOT_FOUND: &str = "NFT not found";
 
 pub const INVALID_FT_METADATA: &str = "Invalid FT metadata";
+pub const ZERO_LIQUIDITY: &str = "Zero liquidity";
+pub const ZERO_AMOUNT: &str = "Zero amount";
+pub const TRANSFER_TO_POOL_FAILED: &str = "Transfer to pool failed";
+
+pub const NOT_ENOUGH_BALANCE: &str = "Not enough balance";
+pub const UNSUPPORTED_TOKEN: &str = "Unsupported token";
+
+pub const STORAGE_NOT_REGISTERED: &str = "Account is not registered, call storage_deposit first";
+pub const NOT_ENOUGH_STORAGE_DEPOSIT: &str =
+    "Attached deposit is less than the minimum storage balance";
+pub const NOT_ENOUGH_STORAGE_BALANCE: &str = "Not enough storage balance";
+pub const STORAGE_STILL_USED: &str =
+    "Account still holds state in the manager, withdraw, burn and close it first";
+pub const FORCE_UNREGISTER_NOT_SUPPORTED: &str = "Force unregister is not supported";
+
+pub const LIMIT_ORDER_NOT_FOUND: &str = "Limit order not found";
+pub const LIMIT_ORDER_NOT_OWNED_BY_CALLER: &str = "Limit order not owned by caller";
+pub const PLACE_LIMIT_ORDER_FAILED: &str = "Place limit order failed";
+pub const CLOSE_LIMIT_ORDER_FAILED: &str = "Close limit order failed";
@@ -1,4 +1,17 @@
+use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{ext_contract, json_types::U128, AccountId, Promise};
+use schemars::JsonSchema;
+use zswap_math_library::json_types::JsonU256;
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolView {
+    pub pool_id: AccountId,
+    pub token_0: AccountId,
+    pub token_1: AccountId,
+    pub fee: u32,
+    pub tick_spacing: u32,
+}
 
 #[ext_contract(ext_zswap_factory)]
 pub trait ZswapFactory {
@@ -7,6 +20,11 @@ pub trait ZswapFactory {
         token_0: AccountId,
         token_1: AccountId,
         fee: u32,
-        sqrt_price_x96: U128,
+        sqrt_price_x96: JsonU256,
     ) -> Promise;
+
+    /// Leaves `hook` out, so only the unhooked pool is looked up
+    fn get_pool(&self, token_0: AccountId, token_1: AccountId, fee: u32) -> Option<PoolView>;
+
+    fn get_pool_creation_cost(&self) -> U128;
 }
@@ -1,24 +1,27 @@
-use near_contract_standards::fungible_token::core::ext_ft_core;
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::ONE_YOCTO;
 use near_sdk::{env, json_types::U128, near_bindgen, serde_json, AccountId, PromiseOrValue};
-use zswap_math_library::pool_account;
+use schemars::JsonSchema;
+use zswap_math_library::json_types::JsonU256;
 
 use crate::error::*;
-use crate::pool::ext_zswap_pool;
+use crate::utils::SwapSingleParams;
 use crate::{Contract, ContractExt};
 
 /// Message parameters to receive via token function call.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 #[serde(rename_all = "snake_case")]
 pub enum TokenReceiverMessage {
-    /// Alternative to deposit + execute actions call.
+    /// Alternative to deposit + execute actions call. Deposits the transferred tokens and swaps
+    /// them like `swap`, crediting the output to the sender's deposit
     SwapSingle {
         token_out: AccountId,
         fee: u32,
-        sqrt_price_limit_x96: Option<U128>,
+        /// The swap fails, refunding the input to the sender's deposit, if it would credit less
+        /// than this
+        amount_out_min: U128,
+        sqrt_price_limit_x96: Option<JsonU256>,
     },
 }
 
@@ -31,28 +34,42 @@ impl FungibleTokenReceiver for Contract {
         msg: String,
     ) -> PromiseOrValue<U128> {
         let token_in = env::predecessor_account_id();
-        let amount_in = amount;
+
+        if msg.is_empty() {
+            if !self.fungible_tokens.contains(&token_in) {
+                env::panic_str(UNSUPPORTED_TOKEN);
+            }
+            self.internal_deposit(&sender_id, &token_in, amount.0);
+            // failing refunds the transfer
+            self.internal_assert_storage_covered(&sender_id, 0);
+
+            let unused_amount = U128(0);
+            return PromiseOrValue::Value(unused_amount);
+        }
 
         let message = serde_json::from_str::<TokenReceiverMessage>(&msg).expect(WRONG_MSG_FORMAT);
         match message {
             TokenReceiverMessage::SwapSingle {
                 token_out,
                 fee,
+                amount_out_min,
                 sqrt_price_limit_x96,
             } => {
-                let receipient = sender_id;
-                let zero_for_one = token_in < token_out;
-                let pool_id =
-                    pool_account::compute_account(&self.factory, &token_in, &token_out, fee);
-                ext_ft_core::ext(token_in)
-                    .with_attached_deposit(ONE_YOCTO)
-                    .ft_transfer_call(pool_id.clone(), amount_in, None, String::from(""))
-                    .then(ext_zswap_pool::ext(pool_id).swap(
-                        receipient,
-                        zero_for_one,
-                        amount_in,
+                if !self.fungible_tokens.contains(&token_in) {
+                    env::panic_str(UNSUPPORTED_TOKEN);
+                }
+                self.internal_deposit(&sender_id, &token_in, amount.0);
+                self.internal_swap(
+                    sender_id,
+                    SwapSingleParams {
+                        token_in,
+                        token_out,
+                        fee,
+                        amount_in: amount,
+                        amount_out_min,
                         sqrt_price_limit_x96,
-                    ));
+                    },
+                );
             }
         }
 
@@ -61,18 +78,93 @@ impl FungibleTokenReceiver for Contract {
     }
 }
 
-// impl Contract {
-//     fn internal_deposit(&mut self, sender_id: &AccountId, token_id: &AccountId, amount: u128) {
-//         let token_key = get_token_key(sender_id, token_id);
-//         let token_opt = self.account_tokens.get(&token_key);
-//         match token_opt {
-//             Some(deposistted) => {
-//                 self.account_tokens
-//                     .insert(&token_key, &(deposistted + amount));
-//             }
-//             None => {
-//                 self.account_tokens.insert(&token_key, &amount);
-//             }
-//         }
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::json_types::U128;
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{serde_json, testing_env, AccountId, ONE_NEAR};
+    use schemars::schema_for;
+    use zswap_math_library::pool_account;
+
+    use super::TokenReceiverMessage;
+    use crate::Contract;
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    fn called_by(id: &str, attached_deposit: u128) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account("manager.testnet"))
+            .predecessor_account_id(account(id))
+            .attached_deposit(attached_deposit)
+            .build());
+    }
+
+    #[test]
+    fn test_swap_single_from_the_greater_token() {
+        let factory = account("factory.testnet");
+        let alice = account("alice.testnet");
+        let token_0 = account("token0.testnet");
+        let token_1 = account("token1.testnet");
+        called_by("alice.testnet", ONE_NEAR);
+        let mut contract = Contract::new(factory.clone());
+        contract.fungible_tokens.insert(&token_0);
+        contract.fungible_tokens.insert(&token_1);
+        contract.storage_deposit(None, None);
+
+        called_by("token1.testnet", 0);
+        let msg = serde_json::to_string(&TokenReceiverMessage::SwapSingle {
+            token_out: token_0.clone(),
+            fee: 3000,
+            amount_out_min: U128(1),
+            sqrt_price_limit_x96: None,
+        })
+        .unwrap();
+        contract.ft_on_transfer(alice.clone(), U128(1000), msg);
+
+        // the input goes through the ledger into the pool of the sorted tokens
+        assert_eq!(contract.get_deposit(alice, token_1.clone()), U128(0));
+        let pool = pool_account::compute_account(&factory, &token_0, &token_1, 3000);
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, token_1);
+        match &receipts[0].actions[0] {
+            VmAction::FunctionCall {
+                function_name,
+                args,
+                ..
+            } => {
+                assert_eq!(function_name, "ft_transfer_call");
+                let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+                assert_eq!(args["receiver_id"], pool.as_str());
+            }
+            action => panic!("unexpected action {:?}", action),
+        }
+    }
+
+    const SCHEMA_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../res/abi/zswap_manager_token_receiver_message.json"
+    );
+
+    /// `ft_on_transfer` takes the message as a string, so its shape is not in the ABI and is
+    /// checked in separately. Run with `UPDATE_SCHEMAS=1` to regenerate it
+    #[test]
+    fn test_token_receiver_message_schema() {
+        let schema =
+            serde_json::to_string_pretty(&schema_for!(TokenReceiverMessage)).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+            std::fs::write(SCHEMA_PATH, schema).unwrap();
+            return;
+        }
+        let checked_in = std::fs::read_to_string(SCHEMA_PATH).unwrap_or_default();
+        assert!(
+            checked_in == schema,
+            "{} is out of date, run the tests with UPDATE_SCHEMAS=1",
+            SCHEMA_PATH
+        );
+    }
+}
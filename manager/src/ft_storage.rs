@@ -1,4 +1,5 @@
 use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::json_types::U128;
 use near_sdk::{ext_contract, AccountId};
 
 #[ext_contract(ext_ft_storage)]
@@ -8,4 +9,6 @@ pub trait StorageManagement {
         account_id: Option<AccountId>,
         registration_only: Option<bool>,
     ) -> StorageBalance;
+
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance;
 }
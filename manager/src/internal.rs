@@ -1,14 +1,24 @@
 use std::cmp::Ordering;
 
-use near_sdk::{env, AccountId};
-use zswap_math_library::num256::U256;
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_sdk::json_types::U128;
+use near_sdk::{
+    env, serde_json, AccountId, Balance, Promise, PromiseResult, StorageUsage, ONE_YOCTO,
+};
+use zswap_math_library::json_types::JsonU256;
 use zswap_math_library::{liquidity_math, pool_account, tick_math};
 
-use crate::error::TOKENS_MUST_BE_DIFFERENT;
-use crate::pool::Slot0;
+use crate::error::{NOT_ENOUGH_BALANCE, TOKENS_MUST_BE_DIFFERENT, UNSUPPORTED_TOKEN, ZERO_AMOUNT};
+use crate::factory::ext_zswap_factory;
+use crate::ft_storage::ext_ft_storage;
+use crate::pool::{ext_zswap_pool, Slot0};
+use crate::storage::DEPOSIT_STORAGE;
+use crate::utils::{get_token_key, MintParams, SwapSingleParams};
 use crate::Contract;
 
 impl Contract {
+    /// Account of the unhooked pool of these tokens and fee. Hooked pools live on their own
+    /// accounts and are only reachable directly, the manager never routes to them
     pub fn internal_get_pool(
         &self,
         token_0: &AccountId,
@@ -32,6 +42,26 @@ impl Contract {
         pool_account::compute_account(&self.factory, ordered_token_0, ordered_token_1, fee)
     }
 
+    /// Queries the factory for the exact creation cost, then creates the pool paid by `payer`
+    pub fn internal_create_pool(
+        &self,
+        token_0: AccountId,
+        token_1: AccountId,
+        fee: u32,
+        sqrt_price_x96: JsonU256,
+        payer: AccountId,
+        deposit: Balance,
+    ) -> Promise {
+        ext_zswap_factory::ext(self.factory.clone())
+            .get_pool_creation_cost()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_attached_deposit(deposit)
+                    .with_unused_gas_weight(30)
+                    .create_pool_with_cost(token_0, token_1, fee, sqrt_price_x96, payer),
+            )
+    }
+
     pub fn internal_calculate_liquidity(
         &self,
         slot_0: Slot0,
@@ -44,11 +74,134 @@ impl Contract {
         let sqrt_price_lower_x96 = tick_math::get_sqrt_ratio_at_tick(lower_tick);
         let sqrt_price_upper_x96 = tick_math::get_sqrt_ratio_at_tick(upper_tick);
         liquidity_math::get_liquidity_for_amounts(
-            U256::from(sqrt_price_x96.0),
+            sqrt_price_x96.0,
             sqrt_price_lower_x96,
             sqrt_price_upper_x96,
             amount_0,
             amount_1,
         )
     }
+
+    pub fn internal_deposit(&mut self, account_id: &AccountId, token_id: &AccountId, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+
+        let initial_storage = env::storage_usage();
+        let token_key = get_token_key(account_id, token_id);
+        let deposited = self.account_tokens.get(&token_key).unwrap_or_default();
+        self.account_tokens
+            .insert(&token_key, &(deposited + amount));
+        self.internal_record_storage(account_id, initial_storage);
+    }
+
+    pub fn internal_withdraw(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: u128,
+    ) {
+        if amount == 0 {
+            return;
+        }
+
+        let token_key = get_token_key(account_id, token_id);
+        let deposited = self.account_tokens.get(&token_key).unwrap_or_default();
+        if deposited < amount {
+            env::panic_str(NOT_ENOUGH_BALANCE);
+        }
+
+        if deposited == amount {
+            let initial_storage = env::storage_usage();
+            self.account_tokens.remove(&token_key);
+            self.internal_record_storage(account_id, initial_storage);
+        } else {
+            self.account_tokens
+                .insert(&token_key, &(deposited - amount));
+        }
+    }
+
+    /// Returns the desired amounts of a mint that never reached the pool
+    pub fn internal_refund_mint(&mut self, recipient: &AccountId, params: &MintParams) {
+        self.internal_deposit(recipient, &params.token_0, params.amount_0_desired.0);
+        self.internal_deposit(recipient, &params.token_1, params.amount_1_desired.0);
+    }
+
+    /// Moves the manager's tokens into its deposit in the pool, skipping zero amounts. At least
+    /// one amount must be positive
+    pub fn internal_transfer_to_pool<const N: usize>(
+        &self,
+        pool: &AccountId,
+        amounts: [(AccountId, U128); N],
+    ) -> Promise {
+        amounts
+            .into_iter()
+            .filter(|(_, amount)| amount.0 > 0)
+            .map(|(token_id, amount)| {
+                ext_ft_core::ext(token_id)
+                    .with_attached_deposit(ONE_YOCTO)
+                    .ft_transfer_call(pool.clone(), amount, None, String::new())
+            })
+            .reduce(|transfers, transfer| transfers.and(transfer))
+            .unwrap_or_else(|| env::panic_str(ZERO_AMOUNT))
+    }
+
+    /// Reads, in the callback chained on `internal_transfer_to_pool`, how much of each amount
+    /// reached the pool. A failed transfer counts as nothing
+    pub fn internal_transferred_to_pool<const N: usize>(amounts: [U128; N]) -> [u128; N] {
+        let mut result_index = 0;
+        amounts.map(|amount| {
+            if amount.0 == 0 {
+                return 0;
+            }
+
+            let result = env::promise_result(result_index);
+            result_index += 1;
+            match result {
+                PromiseResult::Successful(used) => {
+                    serde_json::from_slice::<U128>(&used).map_or(0, |used| used.0)
+                }
+                _ => 0,
+            }
+        })
+    }
+
+    /// Takes back the storage balance the manager paid the pool for a position or limit order
+    /// that is gone. Fails, and leaves it there, while the pool still uses it for positions or
+    /// orders at the same ticks
+    pub fn internal_reclaim_pool_storage(&self, pool: &AccountId, storage: StorageUsage) {
+        ext_ft_storage::ext(pool.clone())
+            .with_attached_deposit(ONE_YOCTO)
+            .storage_withdraw(Some(U128(storage as Balance * env::storage_byte_cost())));
+    }
+
+    /// Takes back the manager's tokens left unused in its deposit in the pool
+    pub fn internal_withdraw_from_pool(&self, pool: &AccountId, token_id: AccountId, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+
+        ext_zswap_pool::ext(pool.clone())
+            .with_attached_deposit(ONE_YOCTO)
+            .withdraw(token_id, U128::from(amount));
+    }
+
+    /// Swaps the payer's deposit of `token_in` in the pool of the tokens, crediting the output
+    /// back to the payer's deposit
+    pub fn internal_swap(&mut self, payer: AccountId, params: SwapSingleParams) -> Promise {
+        if !self.fungible_tokens.contains(&params.token_out) {
+            env::panic_str(UNSUPPORTED_TOKEN);
+        }
+
+        if params.amount_in.0 == 0 {
+            env::panic_str(ZERO_AMOUNT);
+        }
+
+        self.internal_assert_storage_covered(&payer, DEPOSIT_STORAGE);
+        let pool = self.internal_get_pool(&params.token_in, &params.token_out, params.fee);
+        self.internal_withdraw(&payer, &params.token_in, params.amount_in.0);
+
+        self.internal_transfer_to_pool(&pool, [(params.token_in.clone(), params.amount_in)])
+            .then(Self::ext(env::current_account_id()).swap_after_transfer(payer, pool, params))
+    }
 }
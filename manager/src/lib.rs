@@ -1,24 +1,29 @@
 use ft_storage::ext_ft_storage;
+use near_contract_standards::fungible_token::core::ext_ft_core;
 use near_contract_standards::fungible_token::metadata::{ext_ft_metadata, FungibleTokenMetadata};
 use near_contract_standards::non_fungible_token::metadata::{
     NFTContractMetadata, NFT_METADATA_SPEC,
 };
 use near_contract_standards::non_fungible_token::NonFungibleToken;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, LookupMap, UnorderedSet};
-use near_sdk::json_types::{I128, U128};
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::U128;
 use near_sdk::{
-    env, log, near_bindgen, AccountId, Balance, BorshStorageKey, CryptoHash, PanicOnDefault,
-    Promise, PromiseError,
+    assert_one_yocto, env, log, near_bindgen, AccountId, Balance, BorshStorageKey, CryptoHash,
+    PanicOnDefault, Promise, PromiseError, PromiseOrValue, ONE_YOCTO,
 };
-use zswap_math_library::num256::U256;
-use zswap_math_library::{liquidity_math, sqrt_price_math, tick_math};
+use std::collections::HashMap;
+use zswap_math_library::json_types::JsonU256;
+use zswap_math_library::{liquidity_math, price_math, tick_math};
 
 use crate::error::*;
-use crate::factory::ext_zswap_factory;
+use crate::factory::{ext_zswap_factory, PoolView};
+use crate::limit_order::LimitOrder;
 use crate::nft::*;
 use crate::pool::{ext_zswap_pool, Slot0};
+use crate::storage::{StorageAccount, POSITION_STORAGE};
 use crate::utils::*;
+use zswap_math_library::pool_account::POOL_POSITION_STORAGE;
 
 mod callback;
 mod error;
@@ -26,12 +31,16 @@ mod factory;
 pub mod ft_receiver;
 mod ft_storage;
 mod internal;
+mod limit_order;
 mod nft;
 mod pool;
+mod storage;
 pub mod utils;
 
 const NEAR_PER_STORAGE: Balance = 10_000_000_000_000_000_000; // 10e18yⓃ
 const FT_STORAGE_DEPOSIT: Balance = 1500 * NEAR_PER_STORAGE;
+/// Registers the manager in a new pool, with room for the deposits it moves through it
+const POOL_STORAGE_DEPOSIT: Balance = 1000 * NEAR_PER_STORAGE;
 
 // Define the contract structure
 #[near_bindgen]
@@ -44,6 +53,9 @@ pub struct Contract {
     nft: NonFungibleToken,
     nft_id: u128,
     metadata: LazyOption<NFTContractMetadata>,
+    limit_orders: UnorderedMap<u128, LimitOrder>,
+    limit_order_id: u128,
+    storage_accounts: LookupMap<AccountId, StorageAccount>,
 }
 
 #[derive(BorshStorageKey, BorshSerialize)]
@@ -56,6 +68,8 @@ pub(crate) enum StorageKey {
     TokenMetadata,
     Enumeration,
     Approval,
+    LimitOrders,
+    StorageAccounts,
 }
 
 // Implement the contract structure
@@ -87,6 +101,9 @@ impl Contract {
             nft,
             nft_id: 0,
             metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+            limit_orders: UnorderedMap::new(StorageKey::LimitOrders),
+            limit_order_id: 0,
+            storage_accounts: LookupMap::new(StorageKey::StorageAccounts),
         }
     }
 
@@ -96,38 +113,169 @@ impl Contract {
         token_0: AccountId,
         token_1: AccountId,
         fee: u32,
-        sqrt_price_x96: U128,
+        sqrt_price_x96: JsonU256,
     ) -> Promise {
+        if token_0 == token_1 {
+            env::panic_str(TOKENS_MUST_BE_DIFFERENT);
+        }
+
+        self.internal_create_pool(
+            token_0,
+            token_1,
+            fee,
+            sqrt_price_x96,
+            env::predecessor_account_id(),
+            env::attached_deposit(),
+        )
+    }
+
+    /// Creates the pool if the factory doesn't know it yet, initialized at `sqrt_price_x96`,
+    /// and returns the pool id. An existing pool keeps its price, pools are initialized on
+    /// creation.
+    ///
+    /// Any deposit not needed to create the pool is returned to the caller.
+    #[payable]
+    pub fn create_and_initialize_pool_if_necessary(
+        &mut self,
+        token_0: AccountId,
+        token_1: AccountId,
+        fee: u32,
+        sqrt_price_x96: JsonU256,
+    ) -> Promise {
+        if token_0 == token_1 {
+            env::panic_str(TOKENS_MUST_BE_DIFFERENT);
+        }
+
+        ext_zswap_factory::ext(self.factory.clone())
+            .get_pool(token_0.clone(), token_1.clone(), fee)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_attached_deposit(env::attached_deposit())
+                    .with_unused_gas_weight(30)
+                    .create_and_initialize_pool_callback(
+                        token_0,
+                        token_1,
+                        fee,
+                        sqrt_price_x96,
+                        env::predecessor_account_id(),
+                    ),
+            )
+    }
+
+    #[payable]
+    #[private]
+    pub fn create_and_initialize_pool_callback(
+        &mut self,
+        #[callback_result] pool_res: Result<Option<PoolView>, PromiseError>,
+        token_0: AccountId,
+        token_1: AccountId,
+        fee: u32,
+        sqrt_price_x96: JsonU256,
+        payer: AccountId,
+    ) -> PromiseOrValue<Option<AccountId>> {
+        let attached = env::attached_deposit();
+        match pool_res {
+            Ok(Some(pool)) => {
+                if attached > 0 {
+                    Promise::new(payer).transfer(attached);
+                }
+                PromiseOrValue::Value(Some(pool.pool_id))
+            }
+            Ok(None) => self
+                .internal_create_pool(token_0, token_1, fee, sqrt_price_x96, payer, attached)
+                .into(),
+            Err(_) => {
+                log!(
+                    "{}, returning {}yⓃ to {}",
+                    POOL_CREATION_FAILED,
+                    attached,
+                    payer
+                );
+                Promise::new(payer).transfer(attached);
+                PromiseOrValue::Value(None)
+            }
+        }
+    }
+
+    #[payable]
+    #[private]
+    pub fn create_pool_with_cost(
+        &mut self,
+        #[callback_result] pool_creation_cost_res: Result<U128, PromiseError>,
+        token_0: AccountId,
+        token_1: AccountId,
+        fee: u32,
+        sqrt_price_x96: JsonU256,
+        payer: AccountId,
+    ) -> Promise {
+        let attached = env::attached_deposit();
+        let pool_creation_cost = match pool_creation_cost_res {
+            Ok(cost) => cost.0,
+            Err(_) => {
+                log!(
+                    "{}, returning {}yⓃ to {}",
+                    POOL_CREATION_FAILED,
+                    attached,
+                    payer
+                );
+                return Promise::new(payer).transfer(attached);
+            }
+        };
+
+        let minimum_needed = pool_creation_cost
+            + self
+                .get_token_registration_cost(token_0.clone(), token_1.clone())
+                .0;
+        if attached < minimum_needed {
+            // Panicking here would leave the deposit on the manager, so refund explicitly
+            log!(
+                "{}: attach at least {} yⓃ, returning {}yⓃ to {}",
+                NOT_ENOUGH_DEPOSIT,
+                minimum_needed,
+                attached,
+                payer
+            );
+            return Promise::new(payer).transfer(attached);
+        }
+        if attached > minimum_needed {
+            Promise::new(payer.clone()).transfer(attached - minimum_needed);
+        }
+
         let is_new_token_0 = self.fungible_tokens.insert(&token_0);
         let is_new_token_1 = self.fungible_tokens.insert(&token_1);
-        if !is_new_token_0 && !is_new_token_1 {
-            env::panic_str(POOL_ALREADY_EXISTS);
-        }
 
         let create_pool_promise = ext_zswap_factory::ext(self.factory.clone())
-            .with_attached_deposit(env::attached_deposit() - 2 * FT_STORAGE_DEPOSIT)
+            .with_attached_deposit(pool_creation_cost)
             .with_unused_gas_weight(30)
             .create_pool(token_0.clone(), token_1.clone(), fee, sqrt_price_x96);
 
-        let token_0_storage_deposit_promise = ext_ft_storage::ext(token_0.clone())
+        let token_0_storage_deposit_promise = ext_ft_storage::ext(token_0)
             .with_attached_deposit(FT_STORAGE_DEPOSIT)
             .storage_deposit(Some(env::current_account_id()), None);
-        let token_1_storage_deposit_promise = ext_ft_storage::ext(token_1.clone())
+        let token_1_storage_deposit_promise = ext_ft_storage::ext(token_1)
             .with_attached_deposit(FT_STORAGE_DEPOSIT)
             .storage_deposit(Some(env::current_account_id()), None);
 
-        if is_new_token_0 && is_new_token_1 {
+        let create_pool_promise = if is_new_token_0 && is_new_token_1 {
             token_0_storage_deposit_promise
                 .and(token_1_storage_deposit_promise)
                 .then(create_pool_promise)
         } else if is_new_token_0 {
             token_0_storage_deposit_promise.then(create_pool_promise)
-        } else {
+        } else if is_new_token_1 {
             token_1_storage_deposit_promise.then(create_pool_promise)
-        }
+        } else {
+            create_pool_promise
+        };
+
+        create_pool_promise.then(
+            Self::ext(env::current_account_id())
+                .create_pool_callback(payer, U128::from(pool_creation_cost + POOL_STORAGE_DEPOSIT)),
+        )
     }
 
-    #[payable]
+    /// Mints a liquidity NFT from the caller's deposits. The caller's storage balance pays for
+    /// the NFT and for the position in the pool, which the manager pays the pool for
     pub fn mint(&mut self, params: MintParams) -> Promise {
         let pool = self.internal_get_pool(&params.token_0, &params.token_1, params.fee);
         let slot_0_promise = ext_zswap_pool::ext(pool.clone()).get_slot_0();
@@ -136,18 +284,19 @@ impl Contract {
         let token_1_meta_promise = ext_ft_metadata::ext(params.token_1.clone()).ft_metadata();
 
         let recipient = env::predecessor_account_id();
+        self.internal_assert_storage_covered(&recipient, POSITION_STORAGE + POOL_POSITION_STORAGE);
+        self.internal_withdraw(&recipient, &params.token_0, params.amount_0_desired.0);
+        self.internal_withdraw(&recipient, &params.token_1, params.amount_1_desired.0);
 
         slot_0_promise
             .and(token_0_meta_promise)
             .and(token_1_meta_promise)
             .then(
                 Self::ext(env::current_account_id())
-                    .with_attached_deposit(env::attached_deposit())
                     .mint_calculate_liquidity(pool, recipient, params),
             )
     }
 
-    #[payable]
     #[private]
     pub fn mint_calculate_liquidity(
         &mut self,
@@ -157,8 +306,15 @@ impl Contract {
         pool: AccountId,
         recipient: AccountId,
         params: MintParams,
-    ) -> Promise {
+    ) -> PromiseOrValue<[U128; 2]> {
+        if slot_0_res.is_err() || token_0_meta_res.is_err() || token_1_meta_res.is_err() {
+            log!("{}, refunding {}", INVALID_FT_METADATA, recipient);
+            self.internal_refund_mint(&recipient, &params);
+            return PromiseOrValue::Value([U128(0), U128(0)]);
+        }
+
         let slot_0 = slot_0_res.unwrap();
+        let sqrt_price_x96 = slot_0.sqrt_price_x96;
         let liquidity = self.internal_calculate_liquidity(
             slot_0,
             params.lower_tick,
@@ -168,53 +324,154 @@ impl Contract {
         );
         log!("Liquidity: {}", liquidity);
 
-        if token_0_meta_res.is_err() || token_1_meta_res.is_err() {
-            env::panic_str(INVALID_FT_METADATA);
+        if liquidity == 0 {
+            log!("{}, refunding {}", ZERO_LIQUIDITY, recipient);
+            self.internal_refund_mint(&recipient, &params);
+            return PromiseOrValue::Value([U128(0), U128(0)]);
+        }
+
+        // the pool rounds the amounts it takes up
+        let [amount_0, amount_1] = self.get_amounts_for_liquidity(
+            sqrt_price_x96,
+            params.lower_tick,
+            params.upper_tick,
+            U128(liquidity),
+            true,
+        );
+        if amount_0 < params.amount_0_min || amount_1 < params.amount_1_min {
+            log!("{}, refunding {}", SLIPPAGE_CHECK_FAILED, recipient);
+            self.internal_refund_mint(&recipient, &params);
+            return PromiseOrValue::Value([U128(0), U128(0)]);
         }
 
         let mint_callback_params = MintCallbackParams {
-            token_0: params.token_0,
-            token_1: params.token_1,
+            token_0: params.token_0.clone(),
+            token_1: params.token_1.clone(),
             lower_tick: params.lower_tick,
             upper_tick: params.upper_tick,
             fee: params.fee,
             liquidity,
             symbol_0: token_0_meta_res.unwrap().symbol,
             symbol_1: token_1_meta_res.unwrap().symbol,
-            recipient: recipient.clone(),
+            recipient,
+            amount_0_desired: params.amount_0_desired,
+            amount_1_desired: params.amount_1_desired,
             amount_0_min: params.amount_0_min,
             amount_1_min: params.amount_1_min,
         };
 
+        // the manager pays the pool from its own deposit there, then withdraws what is left
+        self.internal_transfer_to_pool(
+            &pool,
+            [
+                (params.token_0, params.amount_0_desired),
+                (params.token_1, params.amount_1_desired),
+            ],
+        )
+        .then(Self::ext(env::current_account_id()).mint_after_transfer(pool, mint_callback_params))
+        .into()
+    }
+
+    /// Mints once both desired amounts reached the manager's deposit in the pool, otherwise
+    /// takes back what did and refunds the recipient
+    #[private]
+    pub fn mint_after_transfer(
+        &mut self,
+        pool: AccountId,
+        params: MintCallbackParams,
+    ) -> PromiseOrValue<[U128; 2]> {
+        let transferred =
+            Self::internal_transferred_to_pool([params.amount_0_desired, params.amount_1_desired]);
+        if transferred != [params.amount_0_desired.0, params.amount_1_desired.0] {
+            log!(
+                "{}, refunding {}",
+                TRANSFER_TO_POOL_FAILED,
+                params.recipient
+            );
+            self.internal_deposit(
+                &params.recipient,
+                &params.token_0,
+                params.amount_0_desired.0,
+            );
+            self.internal_deposit(
+                &params.recipient,
+                &params.token_1,
+                params.amount_1_desired.0,
+            );
+            self.internal_withdraw_from_pool(&pool, params.token_0, transferred[0]);
+            self.internal_withdraw_from_pool(&pool, params.token_1, transferred[1]);
+            return PromiseOrValue::Value([U128(0), U128(0)]);
+        }
+
+        // a failed mint returns the storage deposit to the manager
         ext_zswap_pool::ext(pool)
+            .with_attached_deposit(POOL_POSITION_STORAGE as Balance * env::storage_byte_cost())
             .mint(
-                recipient,
+                env::current_account_id(),
                 env::current_account_id(), // manager owns liquidity, recipient owns NFT
                 params.lower_tick,
                 params.upper_tick,
-                U128::from(liquidity),
-            )
-            .then(
-                Self::ext(env::current_account_id())
-                    .with_attached_deposit(env::attached_deposit())
-                    .mint_callback(mint_callback_params),
+                U128::from(params.liquidity),
             )
+            .then(Self::ext(env::current_account_id()).mint_callback(params))
+            .into()
     }
 
+    /// Swaps tokens deposited in the manager, crediting the output back to the caller's deposit
+    pub fn swap(&mut self, params: SwapSingleParams) -> Promise {
+        self.internal_swap(env::predecessor_account_id(), params)
+    }
+
+    /// Swaps once the input reached the manager's deposit in the pool, otherwise takes back
+    /// what did and refunds the payer
     #[private]
-    pub fn calculate_amount_out(
+    pub fn swap_after_transfer(
         &mut self,
-        #[callback_result] amounts_res: Result<[I128; 2], PromiseError>,
-        zero_for_one: bool,
-    ) -> U128 {
-        let amounts = amounts_res.unwrap();
-        if zero_for_one {
-            let amount_1 = -amounts[1].0 as u128;
-            U128::from(amount_1)
-        } else {
-            let amount_0 = -amounts[0].0 as u128;
-            U128::from(amount_0)
+        payer: AccountId,
+        pool: AccountId,
+        params: SwapSingleParams,
+    ) -> PromiseOrValue<U128> {
+        let [transferred] = Self::internal_transferred_to_pool([params.amount_in]);
+        if transferred != params.amount_in.0 {
+            log!("{}, refunding {}", TRANSFER_TO_POOL_FAILED, payer);
+            self.internal_deposit(&payer, &params.token_in, params.amount_in.0);
+            self.internal_withdraw_from_pool(&pool, params.token_in, transferred);
+            return PromiseOrValue::Value(U128(0));
         }
+
+        let zero_for_one = params.token_in < params.token_out;
+        ext_zswap_pool::ext(pool.clone())
+            .swap(
+                env::current_account_id(),
+                zero_for_one,
+                params.amount_in,
+                params.sqrt_price_limit_x96,
+                Some(params.amount_out_min),
+            )
+            .then(Self::ext(env::current_account_id()).swap_callback(
+                payer,
+                pool,
+                params.token_in,
+                params.token_out,
+                params.amount_in,
+            ))
+            .into()
+    }
+
+    /// Withdraws tokens deposited in the manager to the caller's wallet
+    #[payable]
+    pub fn withdraw(&mut self, token_id: AccountId, amount: U128) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_withdraw(&account_id, &token_id, amount.0);
+
+        ext_ft_core::ext(token_id.clone())
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer(account_id.clone(), amount, None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .withdraw_callback(account_id, token_id, amount),
+            )
     }
 
     #[payable]
@@ -255,20 +512,25 @@ impl Contract {
         lower_tick: i32,
         upper_tick: i32,
     ) -> Promise {
+        let initial_storage = env::storage_usage();
         self.nft.internal_burn(nft_id.0.to_string(), &recipient);
         self.nft_positions.remove(&nft_id.0);
+        self.internal_record_storage(&recipient, initial_storage);
+        self.internal_free_storage(&recipient, POOL_POSITION_STORAGE);
 
         log!("Burned NFT {:?}", nft_id);
 
         let token_amounts = token_amounts_res.unwrap();
 
-        ext_zswap_pool::ext(pool).collect(
-            recipient,
-            lower_tick,
-            upper_tick,
-            token_amounts[0],
-            token_amounts[1],
-        )
+        ext_zswap_pool::ext(pool.clone())
+            .collect(
+                recipient,
+                lower_tick,
+                upper_tick,
+                token_amounts[0],
+                token_amounts[1],
+            )
+            .then(Self::ext(env::current_account_id()).collect_callback(pool))
     }
 
     pub fn get_liquidity_for_amounts(
@@ -292,11 +554,11 @@ impl Contract {
     pub fn calculate_amount_1_with_amount_0(
         &self,
         amount_0: U128,
-        sqrt_price_x96: U128,
+        sqrt_price_x96: JsonU256,
         lower_tick: i32,
         upper_tick: i32,
     ) -> U128 {
-        let sqrt_price_x96 = U256::from(sqrt_price_x96.0);
+        let sqrt_price_x96 = sqrt_price_x96.0;
         let sqrt_price_lower_x96 = tick_math::get_sqrt_ratio_at_tick(lower_tick);
         let sqrt_price_upper_x96 = tick_math::get_sqrt_ratio_at_tick(upper_tick);
 
@@ -310,24 +572,23 @@ impl Contract {
             amount_0.0,
         );
 
-        sqrt_price_math::get_amount_1_delta_signed(
+        liquidity_math::get_amount_1_for_liquidity(
             sqrt_price_lower_x96,
             sqrt_price_x96,
-            liquidity as i128,
+            liquidity,
+            true,
         )
-        .abs()
-        .as_u128()
         .into()
     }
 
     pub fn calculate_amount_0_with_amount_1(
         &self,
         amount_1: U128,
-        sqrt_price_x96: U128,
+        sqrt_price_x96: JsonU256,
         lower_tick: i32,
         upper_tick: i32,
     ) -> U128 {
-        let sqrt_price_x96 = U256::from(sqrt_price_x96.0);
+        let sqrt_price_x96 = sqrt_price_x96.0;
         let sqrt_price_lower_x96 = tick_math::get_sqrt_ratio_at_tick(lower_tick);
         let sqrt_price_upper_x96 = tick_math::get_sqrt_ratio_at_tick(upper_tick);
 
@@ -341,19 +602,112 @@ impl Contract {
             amount_1.0,
         );
 
-        sqrt_price_math::get_amount_0_delta_signed(
+        liquidity_math::get_amount_0_for_liquidity(
             sqrt_price_x96,
             sqrt_price_upper_x96,
-            liquidity as i128,
+            liquidity,
+            true,
         )
-        .abs()
-        .as_u128()
         .into()
     }
 
+    /// Returns the token amounts `liquidity` holds between the two ticks at `sqrt_price_x96`.
+    /// Rounding down gives what a burn would release, e.g. to value a position or set burn minimums.
+    pub fn get_amounts_for_liquidity(
+        &self,
+        sqrt_price_x96: JsonU256,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity: U128,
+        round_up: bool,
+    ) -> [U128; 2] {
+        liquidity_math::get_amounts_for_liquidity(
+            sqrt_price_x96.0,
+            tick_math::get_sqrt_ratio_at_tick(lower_tick),
+            tick_math::get_sqrt_ratio_at_tick(upper_tick),
+            liquidity.0,
+            round_up,
+        )
+        .map(U128::from)
+    }
+
+    /// Converts a decimal price of token 0 in token 1, e.g. `"2000.5"`, into `sqrt_price_x96`
+    pub fn price_to_sqrt_price_x96(
+        &self,
+        price: String,
+        decimals_0: u8,
+        decimals_1: u8,
+    ) -> JsonU256 {
+        price_math::price_to_sqrt_price_x96(&price, decimals_0, decimals_1)
+            .map(JsonU256)
+            .unwrap_or_else(|err| env::panic_str(err))
+    }
+
+    pub fn sqrt_price_x96_to_price(
+        &self,
+        sqrt_price_x96: JsonU256,
+        decimals_0: u8,
+        decimals_1: u8,
+        precision: u8,
+    ) -> String {
+        price_math::sqrt_price_x96_to_price(sqrt_price_x96.0, decimals_0, decimals_1, precision)
+            .unwrap_or_else(|err| env::panic_str(err))
+    }
+
+    pub fn price_to_nearest_usable_tick(
+        &self,
+        price: String,
+        decimals_0: u8,
+        decimals_1: u8,
+        tick_spacing: u32,
+    ) -> i32 {
+        price_math::price_to_nearest_usable_tick(&price, decimals_0, decimals_1, tick_spacing)
+            .unwrap_or_else(|err| env::panic_str(err))
+    }
+
+    pub fn tick_to_price(
+        &self,
+        tick: i32,
+        decimals_0: u8,
+        decimals_1: u8,
+        precision: u8,
+    ) -> String {
+        price_math::tick_to_price(tick, decimals_0, decimals_1, precision)
+            .unwrap_or_else(|err| env::panic_str(err))
+    }
+
     pub fn get_fungible_tokens(&self) -> Vec<AccountId> {
         self.fungible_tokens.to_vec()
     }
+
+    pub fn get_deposit(&self, account_id: AccountId, token_id: AccountId) -> U128 {
+        self.account_tokens
+            .get(&get_token_key(&account_id, &token_id))
+            .unwrap_or_default()
+            .into()
+    }
+
+    pub fn get_deposits(&self, account_id: AccountId) -> HashMap<AccountId, U128> {
+        self.fungible_tokens
+            .iter()
+            .map(|token_id| {
+                let deposit = self.get_deposit(account_id.clone(), token_id.clone());
+                (token_id, deposit)
+            })
+            .filter(|(_, deposit)| deposit.0 > 0)
+            .collect()
+    }
+
+    /// Returns the deposit the manager needs on top of the factory's `get_pool_creation_cost`
+    /// to register itself in the new pool and in the tokens it has not seen yet
+    pub fn get_token_registration_cost(&self, token_0: AccountId, token_1: AccountId) -> U128 {
+        let new_tokens = [token_0, token_1]
+            .iter()
+            .filter(|token| !self.fungible_tokens.contains(token))
+            .count() as Balance;
+
+        U128::from(POOL_STORAGE_DEPOSIT + FT_STORAGE_DEPOSIT * new_tokens)
+    }
 }
 
 /*
@@ -364,7 +718,10 @@ impl Contract {
 mod tests {
     use super::*;
 
-    use near_sdk::{test_utils::VMContextBuilder, testing_env};
+    use near_sdk::{
+        test_utils::VMContextBuilder, testing_env, PromiseResult, RuntimeFeesConfig, VMConfig,
+    };
+    use zswap_math_library::pool_account;
 
     #[test]
     fn test_calculate_amount_0_and_amount_1() {
@@ -372,7 +729,7 @@ mod tests {
         testing_env!(VMContextBuilder::new().build());
 
         let contract = Contract::new(factory_id);
-        let sqrt_price_x96 = U128::from(10 * (2_u128).pow(96));
+        let sqrt_price_x96 = JsonU256::from(10 * (2_u128).pow(96));
         let lower_tick = 42000;
         let upper_tick = 48000;
         let amount_0 = U128::from(505327);
@@ -396,4 +753,318 @@ mod tests {
 
         assert_eq!(calculated_amount_0, amount_0);
     }
+
+    #[test]
+    fn test_get_amounts_for_liquidity() {
+        let factory_id = AccountId::new_unchecked("factory.testnet".to_string());
+        testing_env!(VMContextBuilder::new().build());
+
+        let contract = Contract::new(factory_id);
+        let slot_0 = Slot0 {
+            sqrt_price_x96: JsonU256::from(10 * (2_u128).pow(96)),
+            tick: 46054,
+        };
+        let liquidity = contract.get_liquidity_for_amounts(
+            slot_0.clone(),
+            42000,
+            48000,
+            U128::from(505327),
+            U128::from(100_000_111),
+        );
+
+        let [amount_0, amount_1] = contract.get_amounts_for_liquidity(
+            slot_0.sqrt_price_x96,
+            42000,
+            48000,
+            liquidity,
+            false,
+        );
+        assert!(amount_0.0 <= 505327 && 505327 - amount_0.0 <= 1);
+        assert!(amount_1.0 <= 100_000_111 && 100_000_111 - amount_1.0 <= 1);
+
+        // a position above the price only holds token 0
+        let [amount_0, amount_1] = contract.get_amounts_for_liquidity(
+            slot_0.sqrt_price_x96,
+            48000,
+            48060,
+            liquidity,
+            true,
+        );
+        assert!(amount_0.0 > 0);
+        assert_eq!(amount_1.0, 0);
+    }
+
+    #[test]
+    fn test_price_views() {
+        let factory_id = AccountId::new_unchecked("factory.testnet".to_string());
+        testing_env!(VMContextBuilder::new().build());
+
+        let contract = Contract::new(factory_id);
+        let sqrt_price_x96 = contract.price_to_sqrt_price_x96("100".to_string(), 18, 18);
+        assert_eq!(sqrt_price_x96, JsonU256::from(10 * (2_u128).pow(96)));
+        assert_eq!(
+            contract.sqrt_price_x96_to_price(sqrt_price_x96, 18, 18, 2),
+            "100.00"
+        );
+        assert_eq!(
+            contract.price_to_nearest_usable_tick("100".to_string(), 18, 18, 60),
+            46080
+        );
+        assert_eq!(contract.tick_to_price(46080, 18, 18, 4), "100.2603");
+    }
+
+    #[test]
+    fn test_get_token_registration_cost() {
+        let factory_id = AccountId::new_unchecked("factory.testnet".to_string());
+        testing_env!(VMContextBuilder::new().build());
+
+        let mut contract = Contract::new(factory_id);
+        let token_0 = AccountId::new_unchecked("token0.testnet".to_string());
+        let token_1 = AccountId::new_unchecked("token1.testnet".to_string());
+
+        assert_eq!(
+            contract.get_token_registration_cost(token_0.clone(), token_1.clone()),
+            U128::from(POOL_STORAGE_DEPOSIT + 2 * FT_STORAGE_DEPOSIT)
+        );
+
+        contract.fungible_tokens.insert(&token_0);
+        assert_eq!(
+            contract.get_token_registration_cost(token_0.clone(), token_1.clone()),
+            U128::from(POOL_STORAGE_DEPOSIT + FT_STORAGE_DEPOSIT)
+        );
+
+        // every pool needs the manager registered
+        contract.fungible_tokens.insert(&token_1);
+        assert_eq!(
+            contract.get_token_registration_cost(token_0, token_1),
+            U128::from(POOL_STORAGE_DEPOSIT)
+        );
+    }
+
+    #[test]
+    fn test_internal_get_pool_ignores_hooked_pools() {
+        let factory_id = AccountId::new_unchecked("factory.testnet".to_string());
+        testing_env!(VMContextBuilder::new().build());
+
+        let contract = Contract::new(factory_id.clone());
+        let token_0 = AccountId::new_unchecked("token0.testnet".to_string());
+        let token_1 = AccountId::new_unchecked("token1.testnet".to_string());
+        let hook = AccountId::new_unchecked("hook.testnet".to_string());
+
+        let pool = contract.internal_get_pool(&token_1, &token_0, 3000);
+        assert_eq!(
+            pool,
+            pool_account::compute_hooked_account(&factory_id, &token_0, &token_1, 3000, None)
+        );
+        assert_ne!(
+            pool,
+            pool_account::compute_hooked_account(
+                &factory_id,
+                &token_0,
+                &token_1,
+                3000,
+                Some(&hook)
+            )
+        );
+    }
+
+    #[test]
+    fn test_create_and_initialize_existing_pool() {
+        let factory_id = AccountId::new_unchecked("factory.testnet".to_string());
+        testing_env!(VMContextBuilder::new()
+            .attached_deposit(10u128.pow(24))
+            .build());
+
+        let mut contract = Contract::new(factory_id);
+        let token_0 = AccountId::new_unchecked("token0.testnet".to_string());
+        let token_1 = AccountId::new_unchecked("token1.testnet".to_string());
+        let payer = AccountId::new_unchecked("alice.testnet".to_string());
+        let sqrt_price_x96 = JsonU256::from(10 * (2_u128).pow(96));
+        let pool = PoolView {
+            pool_id: AccountId::new_unchecked("pool.testnet".to_string()),
+            token_0: token_0.clone(),
+            token_1: token_1.clone(),
+            fee: 3000,
+            tick_spacing: 60,
+        };
+
+        // pools are initialized by `new`, an existing one is returned as is
+        assert!(matches!(
+            contract.create_and_initialize_pool_callback(
+                Ok(Some(pool)),
+                token_0.clone(),
+                token_1.clone(),
+                3000,
+                sqrt_price_x96,
+                payer.clone()
+            ),
+            PromiseOrValue::Value(Some(id)) if id.as_str() == "pool.testnet"
+        ));
+
+        assert!(matches!(
+            contract.create_and_initialize_pool_callback(
+                Err(PromiseError::Failed),
+                token_0,
+                token_1,
+                3000,
+                sqrt_price_x96,
+                payer
+            ),
+            PromiseOrValue::Value(None)
+        ));
+    }
+
+    #[test]
+    fn test_mint_checks_slippage_before_minting() {
+        let factory_id = AccountId::new_unchecked("factory.testnet".to_string());
+        testing_env!(VMContextBuilder::new().build());
+
+        let mut contract = Contract::new(factory_id);
+        let pool = AccountId::new_unchecked("pool.testnet".to_string());
+        let alice = AccountId::new_unchecked("alice.testnet".to_string());
+        let token_0 = AccountId::new_unchecked("token0.testnet".to_string());
+        let token_1 = AccountId::new_unchecked("token1.testnet".to_string());
+        let metadata = |symbol: &str| FungibleTokenMetadata {
+            spec: "ft-1.0.0".to_string(),
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 18,
+        };
+
+        // at this price the range takes about 505327 of token 0, less than the minimum
+        let params = MintParams {
+            token_0: token_0.clone(),
+            token_1: token_1.clone(),
+            fee: 3000,
+            lower_tick: 42000,
+            upper_tick: 48000,
+            amount_0_desired: U128(1_000_000),
+            amount_1_desired: U128(100_000_111),
+            amount_0_min: U128(600_000),
+            amount_1_min: U128(0),
+        };
+        let slot_0 = Slot0 {
+            sqrt_price_x96: JsonU256::from(10 * (2_u128).pow(96)),
+            tick: 46054,
+        };
+        assert!(matches!(
+            contract.mint_calculate_liquidity(
+                Ok(slot_0),
+                Ok(metadata("T0")),
+                Ok(metadata("T1")),
+                pool,
+                alice.clone(),
+                params,
+            ),
+            PromiseOrValue::Value([U128(0), U128(0)])
+        ));
+        assert_eq!(
+            contract.get_deposit(alice.clone(), token_0),
+            U128(1_000_000)
+        );
+        assert_eq!(contract.get_deposit(alice, token_1), U128(100_000_111));
+    }
+
+    #[test]
+    fn test_refund_when_transfer_to_pool_fails() {
+        let factory_id = AccountId::new_unchecked("factory.testnet".to_string());
+        let pool = AccountId::new_unchecked("pool.testnet".to_string());
+        let alice = AccountId::new_unchecked("alice.testnet".to_string());
+        let token_0 = AccountId::new_unchecked("token0.testnet".to_string());
+        let token_1 = AccountId::new_unchecked("token1.testnet".to_string());
+
+        // token 0 reached the pool, token 1 didn't
+        testing_env!(
+            VMContextBuilder::new().build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![
+                PromiseResult::Successful(b"\"1000\"".to_vec()),
+                PromiseResult::Failed
+            ],
+        );
+        let mut contract = Contract::new(factory_id);
+        let params = MintCallbackParams {
+            token_0: token_0.clone(),
+            token_1: token_1.clone(),
+            lower_tick: 42000,
+            upper_tick: 48000,
+            fee: 3000,
+            liquidity: 1_000_000,
+            symbol_0: "T0".to_string(),
+            symbol_1: "T1".to_string(),
+            recipient: alice.clone(),
+            amount_0_desired: U128(1000),
+            amount_1_desired: U128(2000),
+            amount_0_min: U128(0),
+            amount_1_min: U128(0),
+        };
+        assert!(matches!(
+            contract.mint_after_transfer(pool.clone(), params),
+            PromiseOrValue::Value([U128(0), U128(0)])
+        ));
+        assert_eq!(
+            contract.get_deposit(alice.clone(), token_0.clone()),
+            U128(1000)
+        );
+        assert_eq!(
+            contract.get_deposit(alice.clone(), token_1.clone()),
+            U128(2000)
+        );
+
+        // the token took only part of the swap input
+        testing_env!(
+            VMContextBuilder::new().build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"\"400\"".to_vec())],
+        );
+        let params = SwapSingleParams {
+            token_in: token_0.clone(),
+            token_out: token_1,
+            fee: 3000,
+            amount_in: U128(1000),
+            amount_out_min: U128(0),
+            sqrt_price_limit_x96: None,
+        };
+        assert!(matches!(
+            contract.swap_after_transfer(alice.clone(), pool, params),
+            PromiseOrValue::Value(U128(0))
+        ));
+        assert_eq!(contract.get_deposit(alice, token_0), U128(2000));
+    }
+
+    #[test]
+    fn test_deposit_ledger() {
+        let factory_id = AccountId::new_unchecked("factory.testnet".to_string());
+        testing_env!(VMContextBuilder::new().build());
+
+        let mut contract = Contract::new(factory_id);
+        let alice = AccountId::new_unchecked("alice.testnet".to_string());
+        let token_0 = AccountId::new_unchecked("token0.testnet".to_string());
+        let token_1 = AccountId::new_unchecked("token1.testnet".to_string());
+        contract.fungible_tokens.insert(&token_0);
+        contract.fungible_tokens.insert(&token_1);
+
+        contract.internal_deposit(&alice, &token_0, 1_000);
+        contract.internal_deposit(&alice, &token_0, 500);
+        contract.internal_deposit(&alice, &token_1, 0);
+        assert_eq!(
+            contract.get_deposit(alice.clone(), token_0.clone()),
+            U128::from(1_500)
+        );
+
+        let deposits = contract.get_deposits(alice.clone());
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits.get(&token_0), Some(&U128::from(1_500)));
+
+        contract.internal_withdraw(&alice, &token_0, 1_500);
+        assert_eq!(contract.get_deposit(alice.clone(), token_0), U128::from(0));
+        assert!(contract.get_deposits(alice).is_empty());
+    }
 }
@@ -0,0 +1,329 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, log, near_bindgen, AccountId, Balance, Promise, PromiseError, PromiseOrValue};
+use schemars::JsonSchema;
+use zswap_math_library::pool_account::POOL_LIMIT_ORDER_STORAGE;
+
+use crate::error::*;
+use crate::pool::{ext_zswap_pool, PlacedLimitOrder};
+use crate::storage::LIMIT_ORDER_STORAGE;
+use crate::utils::{LimitOrderParams, LimitOrderView};
+use crate::{Contract, ContractExt};
+
+/// A limit order placed through the manager, which owns its share of the pool's epoch
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LimitOrder {
+    pub owner: AccountId,
+    pub pool: AccountId,
+    pub token_in: AccountId,
+    pub token_out: AccountId,
+    pub lower_tick: i32,
+    pub epoch: u64,
+    pub liquidity: u128,
+    pub amount_in: u128,
+}
+
+impl LimitOrder {
+    fn to_view(&self, order_id: u128) -> LimitOrderView {
+        LimitOrderView {
+            order_id: U128(order_id),
+            owner: self.owner.clone(),
+            pool: self.pool.clone(),
+            token_in: self.token_in.clone(),
+            token_out: self.token_out.clone(),
+            lower_tick: self.lower_tick,
+            epoch: U64(self.epoch),
+            liquidity: U128(self.liquidity),
+            amount_in: U128(self.amount_in),
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Places a limit order paid from the caller's deposit and returns its id, or `None` if the
+    /// pool rejected it. The deposit is refunded if the order can't be placed.
+    ///
+    /// The order fills once a swap moves the price through the whole range, and can then be
+    /// claimed by anyone with `claim_limit_order`, crediting `token_out` to the owner's deposit.
+    ///
+    /// The caller's storage balance pays for the order and for its share in the pool, which the
+    /// manager pays the pool for
+    pub fn place_limit_order(&mut self, params: LimitOrderParams) -> Promise {
+        if !self.fungible_tokens.contains(&params.token_out) {
+            env::panic_str(UNSUPPORTED_TOKEN);
+        }
+        if params.amount_in.0 == 0 {
+            env::panic_str(ZERO_AMOUNT);
+        }
+
+        let owner = env::predecessor_account_id();
+        self.internal_assert_storage_covered(
+            &owner,
+            LIMIT_ORDER_STORAGE + POOL_LIMIT_ORDER_STORAGE,
+        );
+        let pool = self.internal_get_pool(&params.token_in, &params.token_out, params.fee);
+        self.internal_withdraw(&owner, &params.token_in, params.amount_in.0);
+
+        self.internal_transfer_to_pool(&pool, [(params.token_in.clone(), params.amount_in)])
+            .then(
+                Self::ext(env::current_account_id())
+                    .place_limit_order_after_transfer(owner, pool, params),
+            )
+    }
+
+    /// Places the order once the input reached the manager's deposit in the pool, otherwise
+    /// takes back what did and refunds the owner
+    #[private]
+    pub fn place_limit_order_after_transfer(
+        &mut self,
+        owner: AccountId,
+        pool: AccountId,
+        params: LimitOrderParams,
+    ) -> PromiseOrValue<Option<U128>> {
+        let [transferred] = Self::internal_transferred_to_pool([params.amount_in]);
+        if transferred != params.amount_in.0 {
+            log!("{}, refunding {}", TRANSFER_TO_POOL_FAILED, owner);
+            self.internal_deposit(&owner, &params.token_in, params.amount_in.0);
+            self.internal_withdraw_from_pool(&pool, params.token_in, transferred);
+            return PromiseOrValue::Value(None);
+        }
+
+        let zero_for_one = params.token_in < params.token_out;
+        // a failed order returns the storage deposit to the manager
+        ext_zswap_pool::ext(pool.clone())
+            .with_attached_deposit(POOL_LIMIT_ORDER_STORAGE as Balance * env::storage_byte_cost())
+            .mint_limit_order(
+                env::current_account_id(),
+                env::current_account_id(), // manager owns the share, owner owns the order
+                params.lower_tick,
+                zero_for_one,
+                params.amount_in,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .place_limit_order_callback(owner, pool, params),
+            )
+            .into()
+    }
+
+    #[private]
+    pub fn place_limit_order_callback(
+        &mut self,
+        #[callback_result] placed_res: Result<PlacedLimitOrder, PromiseError>,
+        owner: AccountId,
+        pool: AccountId,
+        params: LimitOrderParams,
+    ) -> Option<U128> {
+        let placed = match placed_res {
+            Ok(placed) => placed,
+            Err(_) => {
+                log!("{}, refunding {}", PLACE_LIMIT_ORDER_FAILED, owner);
+                self.internal_deposit(&owner, &params.token_in, params.amount_in.0);
+                self.internal_withdraw_from_pool(&pool, params.token_in, params.amount_in.0);
+                return None;
+            }
+        };
+
+        // the liquidity rounds down, so the pool may take slightly less than offered
+        let unused_amount_in = params.amount_in.0 - placed.amount_in.0;
+        self.internal_deposit(&owner, &params.token_in, unused_amount_in);
+        self.internal_withdraw_from_pool(&pool, params.token_in.clone(), unused_amount_in);
+
+        let order_id = self.limit_order_id;
+        let initial_storage = env::storage_usage();
+        self.limit_orders.insert(
+            &order_id,
+            &LimitOrder {
+                owner: owner.clone(),
+                pool,
+                token_in: params.token_in,
+                token_out: params.token_out,
+                lower_tick: params.lower_tick,
+                epoch: placed.epoch.0,
+                liquidity: placed.liquidity.0,
+                amount_in: placed.amount_in.0,
+            },
+        );
+        self.limit_order_id += 1;
+        self.internal_record_storage(&owner, initial_storage);
+        self.internal_use_storage(&owner, POOL_LIMIT_ORDER_STORAGE);
+        log!("Placed limit order {}", order_id);
+
+        Some(U128(order_id))
+    }
+
+    /// Closes a filled limit order, crediting its output to the owner's deposit.
+    ///
+    /// Anyone can call it, so that keepers can close orders as soon as they fill
+    pub fn claim_limit_order(&mut self, order_id: U128) -> Promise {
+        let order = self.internal_take_limit_order(order_id.0);
+
+        ext_zswap_pool::ext(order.pool.clone())
+            .collect_limit_order(
+                env::current_account_id(),
+                U64(order.epoch),
+                U128(order.liquidity),
+            )
+            .then(Self::ext(env::current_account_id()).close_limit_order_callback(order_id, order))
+    }
+
+    /// Cancels an unfilled limit order, crediting what is left of the input, the output it got
+    /// so far and its share of the fees to the caller's deposit
+    pub fn cancel_limit_order(&mut self, order_id: U128) -> Promise {
+        let order = self.internal_take_limit_order(order_id.0);
+        if order.owner != env::predecessor_account_id() {
+            env::panic_str(LIMIT_ORDER_NOT_OWNED_BY_CALLER);
+        }
+
+        ext_zswap_pool::ext(order.pool.clone())
+            .burn_limit_order(
+                env::current_account_id(),
+                U64(order.epoch),
+                U128(order.liquidity),
+            )
+            .then(Self::ext(env::current_account_id()).close_limit_order_callback(order_id, order))
+    }
+
+    #[private]
+    pub fn close_limit_order_callback(
+        &mut self,
+        #[callback_result] amounts_res: Result<[U128; 2], PromiseError>,
+        order_id: U128,
+        order: LimitOrder,
+    ) -> [U128; 2] {
+        let amounts = match amounts_res {
+            Ok(amounts) => amounts,
+            Err(_) => {
+                log!("{} {}, restoring it", CLOSE_LIMIT_ORDER_FAILED, order_id.0);
+                let initial_storage = env::storage_usage();
+                self.limit_orders.insert(&order_id.0, &order);
+                self.internal_record_storage(&order.owner, initial_storage);
+                self.internal_use_storage(&order.owner, POOL_LIMIT_ORDER_STORAGE);
+                return [U128(0), U128(0)];
+            }
+        };
+
+        let (token_0, token_1) = if order.token_in < order.token_out {
+            (&order.token_in, &order.token_out)
+        } else {
+            (&order.token_out, &order.token_in)
+        };
+        self.internal_deposit(&order.owner, token_0, amounts[0].0);
+        self.internal_deposit(&order.owner, token_1, amounts[1].0);
+        self.internal_reclaim_pool_storage(&order.pool, POOL_LIMIT_ORDER_STORAGE);
+        log!("Closed limit order {}", order_id.0);
+
+        amounts
+    }
+
+    pub fn get_limit_order(&self, order_id: U128) -> Option<LimitOrderView> {
+        self.limit_orders
+            .get(&order_id.0)
+            .map(|order| order.to_view(order_id.0))
+    }
+
+    /// Returns the open limit orders, in no particular order
+    pub fn get_limit_orders(
+        &self,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<LimitOrderView> {
+        self.limit_orders
+            .iter()
+            .skip(from_index.map_or(0, |index| index.0 as usize))
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .map(|(order_id, order)| order.to_view(order_id))
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Removes the order while it is being closed, so that it can't be closed twice
+    fn internal_take_limit_order(&mut self, order_id: u128) -> LimitOrder {
+        let initial_storage = env::storage_usage();
+        let order = self
+            .limit_orders
+            .remove(&order_id)
+            .unwrap_or_else(|| env::panic_str(LIMIT_ORDER_NOT_FOUND));
+        self.internal_record_storage(&order.owner, initial_storage);
+        self.internal_free_storage(&order.owner, POOL_LIMIT_ORDER_STORAGE);
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::{U128, U64};
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, AccountId, PromiseError};
+
+    use crate::pool::PlacedLimitOrder;
+    use crate::utils::LimitOrderParams;
+    use crate::Contract;
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    #[test]
+    fn test_limit_order_ledger() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut contract = Contract::new(account("factory.testnet"));
+        let alice = account("alice.testnet");
+        let token_0 = account("token0.testnet");
+        let token_1 = account("token1.testnet");
+        let pool = account("pool.testnet");
+        let params = || LimitOrderParams {
+            token_in: token_1.clone(),
+            token_out: token_0.clone(),
+            fee: 3000,
+            lower_tick: 45960,
+            amount_in: U128(1_000),
+        };
+
+        let placed = PlacedLimitOrder {
+            epoch: U64(3),
+            liquidity: U128(21_000),
+            amount_in: U128(999),
+        };
+        let order_id =
+            contract.place_limit_order_callback(Ok(placed), alice.clone(), pool.clone(), params());
+        assert_eq!(order_id, Some(U128(0)));
+        // the rounding leftover goes back to the owner's deposit
+        assert_eq!(
+            contract.get_deposit(alice.clone(), token_1.clone()),
+            U128(1)
+        );
+        let order = contract.get_limit_order(U128(0)).unwrap();
+        assert_eq!(order.owner, alice);
+        assert_eq!(order.epoch, U64(3));
+        assert_eq!(order.liquidity, U128(21_000));
+
+        let order_id = contract.place_limit_order_callback(
+            Err(PromiseError::Failed),
+            alice.clone(),
+            pool,
+            params(),
+        );
+        assert_eq!(order_id, None);
+        assert_eq!(
+            contract.get_deposit(alice.clone(), token_1.clone()),
+            U128(1_001)
+        );
+        assert_eq!(contract.get_limit_orders(None, None).len(), 1);
+
+        // a failed close puts the order back, a successful one credits the owner
+        let order = contract.internal_take_limit_order(0);
+        contract.close_limit_order_callback(Err(PromiseError::Failed), U128(0), order);
+        assert!(contract.get_limit_order(U128(0)).is_some());
+
+        let order = contract.internal_take_limit_order(0);
+        let amounts = contract.close_limit_order_callback(Ok([U128(10), U128(0)]), U128(0), order);
+        assert_eq!(amounts, [U128(10), U128(0)]);
+        assert_eq!(contract.get_deposit(alice, token_0), U128(10));
+        assert!(contract.get_limit_orders(None, None).is_empty());
+    }
+}
@@ -1,14 +1,24 @@
+use near_sdk::json_types::{I128, U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::PromiseOrValue;
-use near_sdk::{ext_contract, json_types::U128, AccountId};
+use near_sdk::{ext_contract, AccountId, Promise};
+use schemars::JsonSchema;
+use zswap_math_library::json_types::JsonU256;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Slot0 {
-    pub sqrt_price_x96: U128,
+    pub sqrt_price_x96: JsonU256,
     pub tick: i32,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PlacedLimitOrder {
+    pub epoch: U64,
+    pub liquidity: U128,
+    pub amount_in: U128,
+}
+
 #[ext_contract(ext_zswap_pool)]
 pub trait ZswapPool {
     fn mint(
@@ -25,8 +35,9 @@ pub trait ZswapPool {
         recipient: AccountId,
         zero_for_one: bool,
         amount_specified: U128,
-        sqrt_price_limit_x96: Option<U128>,
-    ) -> PromiseOrValue<U128>;
+        sqrt_price_limit_x96: Option<JsonU256>,
+        amount_out_min: Option<U128>,
+    ) -> [I128; 2];
 
     fn burn(&mut self, lower_tick: i32, upper_tick: i32, amount: U128) -> [U128; 2];
 
@@ -39,5 +50,25 @@ pub trait ZswapPool {
         amount_1_requested: U128,
     ) -> [U128; 2];
 
+    fn mint_limit_order(
+        &mut self,
+        payer: AccountId,
+        recipient: AccountId,
+        lower_tick: i32,
+        zero_for_one: bool,
+        amount_in: U128,
+    ) -> PlacedLimitOrder;
+
+    fn burn_limit_order(&mut self, recipient: AccountId, epoch: U64, liquidity: U128) -> [U128; 2];
+
+    fn collect_limit_order(
+        &mut self,
+        recipient: AccountId,
+        epoch: U64,
+        liquidity: U128,
+    ) -> [U128; 2];
+
     fn get_slot_0(&self) -> Slot0;
+
+    fn withdraw(&mut self, token_id: AccountId, amount: U128) -> Promise;
 }
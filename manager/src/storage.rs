@@ -0,0 +1,424 @@
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{
+    assert_one_yocto, env, log, near_bindgen, AccountId, Balance, Promise, StorageUsage,
+};
+
+use crate::error::*;
+use crate::{Contract, ContractExt};
+
+// Measured by `test_storage_of_the_longest_ids`, with 64-character account ids
+
+/// Bytes of an account's own storage record
+const ACCOUNT_STORAGE: StorageUsage = 133;
+/// Bytes of a deposit of one token
+pub const DEPOSIT_STORAGE: StorageUsage = 89;
+/// Bytes of a liquidity NFT, its media and its position, with the widest range and 16-character
+/// token symbols
+pub const POSITION_STORAGE: StorageUsage = 2551;
+/// Bytes of a limit order
+pub const LIMIT_ORDER_STORAGE: StorageUsage = 498;
+
+/// NEAR an account deposited for its storage in the manager, and the bytes its state takes.
+///
+/// Accounts pay for their deposits, liquidity NFTs and limit orders. Removing them gives their
+/// storage back
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StorageAccount {
+    pub deposit: Balance,
+    pub used: StorageUsage,
+}
+
+impl StorageAccount {
+    fn available(&self) -> Balance {
+        self.deposit
+            .saturating_sub(Contract::min_storage_balance())
+            .saturating_sub(self.used as Balance * env::storage_byte_cost())
+    }
+
+    fn to_view(&self) -> StorageBalance {
+        StorageBalance {
+            total: U128(self.deposit),
+            available: U128(self.available()),
+        }
+    }
+}
+
+#[near_bindgen]
+impl StorageManagement for Contract {
+    /// Registers `account_id`, or the caller, with the attached deposit, or adds it to their
+    /// storage balance
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_only = registration_only.unwrap_or(false);
+        let min_balance = Self::min_storage_balance();
+
+        let refund = match self.storage_accounts.get(&account_id) {
+            Some(_) if registration_only => amount,
+            Some(_) => {
+                self.internal_storage_deposit(&account_id, amount);
+                0
+            }
+            None => {
+                let deposit = if registration_only {
+                    amount.min(min_balance)
+                } else {
+                    amount
+                };
+                self.internal_storage_deposit(&account_id, deposit);
+                amount - deposit
+            }
+        };
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+
+        self.storage_accounts.get(&account_id).unwrap().to_view()
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut storage_account = self
+            .storage_accounts
+            .get(&account_id)
+            .unwrap_or_else(|| env::panic_str(STORAGE_NOT_REGISTERED));
+
+        let available = storage_account.available();
+        let amount = amount.map_or(available, |amount| amount.0);
+        if amount > available {
+            env::panic_str(NOT_ENOUGH_STORAGE_BALANCE);
+        }
+        storage_account.deposit -= amount;
+        self.storage_accounts.insert(&account_id, &storage_account);
+        if amount > 0 {
+            Promise::new(account_id).transfer(amount);
+        }
+
+        storage_account.to_view()
+    }
+
+    /// Unregisters the caller once the manager holds nothing of theirs. `force` is not
+    /// supported, deposits, NFTs and orders can't be given up
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        if force.unwrap_or(false) {
+            env::panic_str(FORCE_UNREGISTER_NOT_SUPPORTED);
+        }
+
+        let account_id = env::predecessor_account_id();
+        let storage_account = match self.storage_accounts.get(&account_id) {
+            Some(storage_account) => storage_account,
+            None => return false,
+        };
+        if storage_account.used > 0 {
+            env::panic_str(STORAGE_STILL_USED);
+        }
+
+        self.storage_accounts.remove(&account_id);
+        Promise::new(account_id).transfer(storage_account.deposit);
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128(Self::min_storage_balance()),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts
+            .get(&account_id)
+            .map(|storage_account| storage_account.to_view())
+    }
+}
+
+impl Contract {
+    pub fn min_storage_balance() -> Balance {
+        ACCOUNT_STORAGE as Balance * env::storage_byte_cost()
+    }
+
+    fn internal_storage_deposit(&mut self, account_id: &AccountId, amount: Balance) {
+        let mut storage_account = match self.storage_accounts.get(account_id) {
+            Some(storage_account) => storage_account,
+            None if amount >= Self::min_storage_balance() => StorageAccount {
+                deposit: 0,
+                used: 0,
+            },
+            None => env::panic_str(NOT_ENOUGH_STORAGE_DEPOSIT),
+        };
+        storage_account.deposit += amount;
+        self.storage_accounts.insert(account_id, &storage_account);
+    }
+
+    /// Panics unless `account_id` is registered and their storage balance covers what they use
+    /// plus `extra` bytes.
+    ///
+    /// Callbacks can't fail, so actions check up front for the storage their callback adds
+    pub fn internal_assert_storage_covered(&self, account_id: &AccountId, extra: StorageUsage) {
+        let storage_account = self
+            .storage_accounts
+            .get(account_id)
+            .unwrap_or_else(|| env::panic_str(STORAGE_NOT_REGISTERED));
+        let needed = Self::min_storage_balance()
+            + (storage_account.used + extra) as Balance * env::storage_byte_cost();
+        if storage_account.deposit < needed {
+            env::panic_str(&format!(
+                "{}: {} needs {} yⓃ more",
+                NOT_ENOUGH_STORAGE_BALANCE,
+                account_id,
+                needed - storage_account.deposit
+            ));
+        }
+    }
+
+    /// Charges `account_id` for the storage the manager grew by since `initial_storage`, or
+    /// gives back what it shrank by
+    pub fn internal_record_storage(
+        &mut self,
+        account_id: &AccountId,
+        initial_storage: StorageUsage,
+    ) {
+        let storage_usage = env::storage_usage();
+        if storage_usage > initial_storage {
+            self.internal_use_storage(account_id, storage_usage - initial_storage);
+        } else if storage_usage < initial_storage {
+            self.internal_free_storage(account_id, initial_storage - storage_usage);
+        }
+    }
+
+    /// Adds `storage` bytes to what `account_id` uses, in the manager or in a pool the manager
+    /// paid. Never fails: an account whose balance doesn't cover it owes the rest, and can't
+    /// withdraw storage balance or take new actions until it tops it up
+    pub fn internal_use_storage(&mut self, account_id: &AccountId, storage: StorageUsage) {
+        let mut storage_account = self
+            .storage_accounts
+            .get(account_id)
+            .unwrap_or(StorageAccount {
+                deposit: 0,
+                used: 0,
+            });
+        storage_account.used += storage;
+        let needed = Self::min_storage_balance()
+            + storage_account.used as Balance * env::storage_byte_cost();
+        if storage_account.deposit < needed {
+            log!(
+                "{}: {} owes {} yⓃ",
+                NOT_ENOUGH_STORAGE_BALANCE,
+                account_id,
+                needed - storage_account.deposit
+            );
+        }
+        self.storage_accounts.insert(account_id, &storage_account);
+    }
+
+    pub fn internal_free_storage(&mut self, account_id: &AccountId, storage: StorageUsage) {
+        if let Some(mut storage_account) = self.storage_accounts.get(account_id) {
+            storage_account.used = storage_account.used.saturating_sub(storage);
+            self.storage_accounts.insert(account_id, &storage_account);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::json_types::{U128, U64};
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{env, testing_env, AccountId, Balance, ONE_YOCTO};
+
+    use super::{ACCOUNT_STORAGE, DEPOSIT_STORAGE, LIMIT_ORDER_STORAGE, POSITION_STORAGE};
+    use zswap_math_library::pool_account::POOL_POSITION_STORAGE;
+
+    use crate::callback::ManagerCallback;
+    use crate::pool::PlacedLimitOrder;
+    use crate::utils::{LimitOrderParams, MintCallbackParams};
+    use crate::Contract;
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    fn called_by(id: &str, attached_deposit: Balance) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account("manager.testnet"))
+            .predecessor_account_id(account(id))
+            .attached_deposit(attached_deposit)
+            .build());
+    }
+
+    fn used(contract: &Contract, account_id: &AccountId) -> u128 {
+        let balance = contract.storage_balance_of(account_id.clone()).unwrap();
+        (balance.total.0 - balance.available.0 - Contract::min_storage_balance())
+            / env::storage_byte_cost()
+    }
+
+    #[test]
+    fn test_storage_of_the_longest_ids() {
+        let long_account = |c: char| account(&c.to_string().repeat(64));
+        let alice = long_account('a');
+        let token_0 = long_account('b');
+        let token_1 = long_account('c');
+        called_by("manager.testnet", 0);
+        // leaves room for the 16-character prefix of pool accounts
+        let mut contract = Contract::new(account(&"f".repeat(47)));
+        contract.fungible_tokens.insert(&token_0);
+        contract.fungible_tokens.insert(&token_1);
+        contract.nft_id = 10_u128.pow(38);
+
+        called_by(alice.as_str(), 10_u128.pow(24));
+        let initial_storage = env::storage_usage();
+        contract.storage_deposit(None, None);
+        assert_eq!(env::storage_usage() - initial_storage, ACCOUNT_STORAGE);
+
+        called_by(token_0.as_str(), 0);
+        let initial_storage = env::storage_usage();
+        contract.ft_on_transfer(alice.clone(), U128(u128::MAX), String::new());
+        assert_eq!(env::storage_usage() - initial_storage, DEPOSIT_STORAGE);
+
+        called_by("manager.testnet", 0);
+        let initial_storage = env::storage_usage();
+        contract.mint_callback(
+            Ok([U128(u128::MAX), U128(u128::MAX)]),
+            MintCallbackParams {
+                token_0: token_0.clone(),
+                token_1: token_1.clone(),
+                lower_tick: -887272,
+                upper_tick: 887272,
+                fee: 10000,
+                liquidity: u128::MAX,
+                symbol_0: "S".repeat(16),
+                symbol_1: "T".repeat(16),
+                recipient: alice.clone(),
+                amount_0_desired: U128(u128::MAX),
+                amount_1_desired: U128(u128::MAX),
+                amount_0_min: U128(0),
+                amount_1_min: U128(0),
+            },
+        );
+        assert_eq!(env::storage_usage() - initial_storage, POSITION_STORAGE);
+
+        let initial_storage = env::storage_usage();
+        contract.place_limit_order_callback(
+            Ok(PlacedLimitOrder {
+                epoch: U64(u64::MAX),
+                liquidity: U128(u128::MAX),
+                amount_in: U128(u128::MAX),
+            }),
+            alice,
+            long_account('p'),
+            LimitOrderParams {
+                token_in: token_0,
+                token_out: token_1,
+                fee: 10000,
+                lower_tick: -887272,
+                amount_in: U128(u128::MAX),
+            },
+        );
+        assert_eq!(env::storage_usage() - initial_storage, LIMIT_ORDER_STORAGE);
+    }
+
+    #[test]
+    fn test_storage_is_charged_and_given_back() {
+        called_by("manager.testnet", 0);
+        let mut contract = Contract::new(account("factory.testnet"));
+        let alice = account("alice.testnet");
+        let token_0 = account("token0.testnet");
+        let token_1 = account("token1.testnet");
+        contract.fungible_tokens.insert(&token_0);
+        contract.fungible_tokens.insert(&token_1);
+
+        called_by("alice.testnet", 10_u128.pow(24));
+        contract.storage_deposit(None, None);
+        assert_eq!(used(&contract, &alice), 0);
+
+        // deposits are paid for
+        called_by("token0.testnet", 0);
+        contract.ft_on_transfer(alice.clone(), U128(1_000), String::new());
+        let deposit_storage = used(&contract, &alice);
+        assert!(deposit_storage > 0 && deposit_storage <= DEPOSIT_STORAGE as u128);
+
+        // and so are liquidity NFTs, until they are burned
+        called_by("manager.testnet", 0);
+        contract.mint_callback(
+            Ok([U128(1_000), U128(0)]),
+            MintCallbackParams {
+                token_0: token_0.clone(),
+                token_1,
+                lower_tick: 45960,
+                upper_tick: 46140,
+                fee: 3000,
+                liquidity: 21_000,
+                symbol_0: "wNEAR".to_string(),
+                symbol_1: "USDC".to_string(),
+                recipient: alice.clone(),
+                amount_0_desired: U128(1_000),
+                amount_1_desired: U128(0),
+                amount_0_min: U128(0),
+                amount_1_min: U128(0),
+            },
+        );
+        // the manager pays the pool for the position, and charges it to the recipient
+        let position_storage =
+            used(&contract, &alice) - deposit_storage - POOL_POSITION_STORAGE as u128;
+        assert!(position_storage > 0 && position_storage <= POSITION_STORAGE as u128);
+
+        contract.burn_callback(
+            Ok([U128(1_000), U128(0)]),
+            account("pool.testnet"),
+            alice.clone(),
+            U128(0),
+            45960,
+            46140,
+        );
+        assert_eq!(used(&contract, &alice), deposit_storage);
+
+        contract.internal_withdraw(&alice, &token_0, 1_000);
+        assert_eq!(used(&contract, &alice), 0);
+
+        called_by("alice.testnet", ONE_YOCTO);
+        assert!(contract.storage_unregister(None));
+        assert!(contract.storage_balance_of(alice).is_none());
+    }
+
+    #[test]
+    fn test_storage_is_owed_when_not_covered() {
+        called_by("manager.testnet", 0);
+        let mut contract = Contract::new(account("factory.testnet"));
+        let bob = account("bob.testnet");
+
+        // a callback can't fail, so bob owes what it adds
+        contract.internal_use_storage(&bob, DEPOSIT_STORAGE);
+        let balance = contract.storage_balance_of(bob.clone()).unwrap();
+        assert_eq!(balance.total.0, 0);
+        assert_eq!(balance.available.0, 0);
+
+        called_by("bob.testnet", Contract::min_storage_balance());
+        contract.storage_deposit(None, None);
+        assert_eq!(
+            contract
+                .storage_balance_of(bob.clone())
+                .unwrap()
+                .available
+                .0,
+            0
+        );
+
+        contract.internal_free_storage(&bob, DEPOSIT_STORAGE);
+        called_by("bob.testnet", ONE_YOCTO);
+        assert!(contract.storage_unregister(None));
+    }
+}
@@ -1,8 +1,10 @@
 use base64::engine::general_purpose;
 use base64::Engine;
-use near_sdk::json_types::U128;
+use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, AccountId};
+use near_sdk::{env, AccountId, CryptoHash};
+use schemars::JsonSchema;
+use zswap_math_library::json_types::JsonU256;
 
 // pub struct GetPositionParams {
 //     pub token_0: AccountId,
@@ -13,7 +15,7 @@ use near_sdk::{env, AccountId};
 //     pub upper_tick: i32,
 // }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct MintParams {
     pub token_0: AccountId,
@@ -27,7 +29,7 @@ pub struct MintParams {
     pub amount_1_min: U128,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct MintCallbackParams {
     pub token_0: AccountId,
@@ -39,18 +41,48 @@ pub struct MintCallbackParams {
     pub symbol_0: String,
     pub symbol_1: String,
     pub recipient: AccountId,
+    pub amount_0_desired: U128,
+    pub amount_1_desired: U128,
     pub amount_0_min: U128,
     pub amount_1_min: U128,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct SwapSingleParams {
     pub token_in: AccountId,
     pub token_out: AccountId,
     pub fee: u32,
     pub amount_in: U128,
-    pub sqrt_price_limit_x96: Option<U128>,
+    /// The swap fails, refunding `amount_in`, if it would credit less than this
+    pub amount_out_min: U128,
+    pub sqrt_price_limit_x96: Option<JsonU256>,
+}
+
+/// A limit order selling `amount_in` of `token_in` for `token_out` on the range of one tick
+/// spacing starting at `lower_tick`
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LimitOrderParams {
+    pub token_in: AccountId,
+    pub token_out: AccountId,
+    pub fee: u32,
+    pub lower_tick: i32,
+    pub amount_in: U128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LimitOrderView {
+    pub order_id: U128,
+    pub owner: AccountId,
+    pub pool: AccountId,
+    pub token_in: AccountId,
+    pub token_out: AccountId,
+    pub lower_tick: i32,
+    pub epoch: U64,
+    pub liquidity: U128,
+    pub amount_in: U128,
 }
 
 #[allow(unused)]
@@ -62,7 +94,7 @@ pub struct SwapParams {
     amount_out_min: u128,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct SwapCallbackData {
     pub token_0: AccountId,
@@ -71,7 +103,7 @@ pub struct SwapCallbackData {
     pub payer: AccountId,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct NftLiquidityInfo {
     pub token_0: AccountId,
@@ -90,9 +122,9 @@ pub struct NftLiquidityInfo {
 //     pub payer: AccountId,
 // }
 
-// pub fn get_token_key(owner: &AccountId, token_id: &AccountId) -> CryptoHash {
-//     env::keccak256_array(&[owner.as_bytes(), token_id.as_bytes()].concat())
-// }
+pub fn get_token_key(owner: &AccountId, token_id: &AccountId) -> CryptoHash {
+    env::keccak256_array(&[owner.as_bytes(), token_id.as_bytes()].concat())
+}
 
 pub fn generate_nft_media(
     nft_id: u128,
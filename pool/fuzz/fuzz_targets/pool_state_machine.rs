@@ -0,0 +1,17 @@
+//! Random sequences of mint, burn, swap, collect and limit orders against fixed and dynamic-fee pools.
+//!
+//! Run from `pool/` with `cargo +nightly fuzz run pool_state_machine`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zswap_pool::fuzz::{Op, PoolHarness, MAX_FEE};
+
+fuzz_target!(|input: (bool, Vec<Op>)| {
+    let (dynamic_fee, ops) = input;
+    let mut harness = PoolHarness::with_max_fee(dynamic_fee.then_some(MAX_FEE));
+    for op in ops {
+        harness.apply(op);
+        harness.check_invariants();
+    }
+});
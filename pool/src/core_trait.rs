@@ -1,4 +1,6 @@
-use near_sdk::{ext_contract, json_types::U128, AccountId, PromiseOrValue};
+use near_sdk::json_types::{I128, U128};
+use near_sdk::{ext_contract, AccountId, PromiseOrValue};
+use zswap_math_library::json_types::JsonU256;
 
 use crate::utils::Slot0;
 
@@ -12,17 +14,19 @@ pub trait CoreZswapPool {
         upper_tick: i32,
         amount: U128,
         // data: Vec<u8>,
-    ) -> [U128; 2];
+    ) -> PromiseOrValue<[U128; 2]>;
 
     fn swap(
         &mut self,
         recipient: AccountId,
         zero_for_one: bool,
         amount_specified: U128,
-        sqrt_price_limit_x96: Option<U128>,
-    ) -> PromiseOrValue<U128>;
+        sqrt_price_limit_x96: Option<JsonU256>,
+        amount_out_min: Option<U128>,
+    ) -> PromiseOrValue<[I128; 2]>;
 
-    fn burn(&mut self, lower_tick: i32, upper_tick: i32, amount: U128) -> [U128; 2];
+    fn burn(&mut self, lower_tick: i32, upper_tick: i32, amount: U128)
+        -> PromiseOrValue<[U128; 2]>;
 
     fn collect(
         &mut self,
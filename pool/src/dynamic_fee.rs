@@ -0,0 +1,101 @@
+use near_sdk::{env, log};
+
+use crate::Contract;
+
+impl Contract {
+    /// Measures the volatility on the first swap of a block and returns the fee swaps pay
+    pub fn internal_update_fee(&mut self) -> u32 {
+        let dynamic_fee = match &mut self.dynamic_fee {
+            Some(dynamic_fee) => dynamic_fee,
+            None => return self.fee,
+        };
+
+        dynamic_fee.update(self.slot_0.tick, env::block_height());
+        let fee = dynamic_fee.fee(self.fee, dynamic_fee.volatility);
+        if fee != self.slot_0.fee {
+            log!(
+                "Fee updated to {} for volatility {}",
+                fee,
+                dynamic_fee.volatility
+            );
+            self.slot_0.fee = fee;
+        }
+
+        self.slot_0.fee
+    }
+
+    /// The fee the next swap pays, as of the current block
+    pub fn internal_current_fee(&self) -> u32 {
+        match &self.dynamic_fee {
+            Some(dynamic_fee) => {
+                let volatility = dynamic_fee.volatility_at(self.slot_0.tick, env::block_height());
+                dynamic_fee.fee(self.fee, volatility)
+            }
+            None => self.fee,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, AccountId};
+    use zswap_math_library::json_types::JsonU256;
+    use zswap_math_library::num256::U256;
+    use zswap_math_library::tick_math;
+
+    use crate::core_trait::CoreZswapPool;
+    use crate::Contract;
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    fn at_block(block: u64) {
+        testing_env!(VMContextBuilder::new().block_index(block).build());
+    }
+
+    #[test]
+    fn test_dynamic_fee_follows_volatility() {
+        at_block(100);
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            500,
+            JsonU256(U256::from(10 * 2_u128.pow(96))),
+            Some(10_000),
+            None,
+        );
+        contract.modify_position(
+            &account("alice.testnet"),
+            42000,
+            48000,
+            1_517_882_343_751_509_868_544,
+        );
+        assert_eq!(contract.get_slot_0().fee, 500);
+
+        // the price moves 30 ticks within block 101
+        at_block(101);
+        let start_tick = contract.slot_0.tick;
+        let sqrt_price_limit_x96 = JsonU256(tick_math::get_sqrt_ratio_at_tick(start_tick + 30));
+        contract.internal_swap(false, 10_u128.pow(30), Some(sqrt_price_limit_x96));
+        assert_eq!(contract.slot_0.fee, 500);
+        assert_eq!(contract.slot_0.tick, start_tick + 30);
+
+        // and block 102 pays for it
+        at_block(102);
+        assert_eq!(contract.get_slot_0().fee, 500 + 30 * 100);
+        contract.internal_swap(true, 1_000, None);
+        assert_eq!(contract.slot_0.fee, 3_500);
+
+        // a quiet block halves the volatility, the fee never leaves its bounds
+        at_block(104);
+        assert_eq!(contract.get_slot_0().fee, 500 + 7 * 100);
+        at_block(200);
+        assert_eq!(contract.get_slot_0().fee, 500);
+
+        let dynamic_fee = contract.dynamic_fee.as_ref().unwrap();
+        assert_eq!(dynamic_fee.fee(500, 1_000), 10_000);
+    }
+}
@@ -1,11 +1,33 @@
 pub const ALREADY_INITIALIZED: &str = "Already initialized";
-pub const INVALID_PRICE_LIMIT: &str = "Invalid price limit";
 pub const INVALID_TICK_RANGE: &str = "Invalid tick range";
-pub const NOT_ENOUGH_LIQUIDITY: &str = "Not enough liquidity";
 pub const ZERO_LIQUIDITY: &str = "Zero liquidity";
 
 pub const INSUFFICIENT_INPUT_AMOUNT: &str = "Insufficient input amount";
 pub const NOT_AUTHORIZED: &str = "Not authorized";
+pub const SLIPPAGE_CHECK_FAILED: &str = "Slippage check failed";
+
+pub const INVALID_LIMIT_ORDER_RANGE: &str =
+    "Limit order must be one tick spacing wide, beyond the current price";
+pub const LIMIT_ORDER_NOT_FOUND: &str = "Limit order not found";
+pub const LIMIT_ORDER_FILLED: &str = "Limit order already filled";
+pub const LIMIT_ORDER_NOT_FILLED: &str = "Limit order not filled";
+pub const LIMIT_ORDERS_NOT_AVAILABLE: &str = "Limit orders are not available on pools with a hook";
+
+pub const POOL_PAUSED: &str = "Pool is paused";
+
+pub const NOTHING_TO_MIGRATE: &str = "No state to migrate";
+pub const ALREADY_MIGRATED: &str = "Pool state is already at the current version";
+
+pub const HOOK_REJECTED: &str = "Rejected by the pool's hook";
+pub const INVALID_HOOK_FEE: &str = "Hook fee must be below 1000000";
+
+pub const STORAGE_NOT_REGISTERED: &str = "Account is not registered, call storage_deposit first";
+pub const NOT_ENOUGH_STORAGE_DEPOSIT: &str =
+    "Attached deposit is less than the minimum storage balance";
+pub const NOT_ENOUGH_STORAGE_BALANCE: &str = "Not enough storage balance";
+pub const STORAGE_STILL_USED: &str =
+    "Account still holds state in the pool, burn and withdraw it first";
+pub const FORCE_UNREGISTER_NOT_SUPPORTED: &str = "Force unregister is not supported";
 
 // FTReceiver
 pub const UNSUPPORTED_TOKEN: &str = "Unsupported token";
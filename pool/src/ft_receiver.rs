@@ -1,17 +1,30 @@
+use near_contract_standards::fungible_token::core::ext_ft_core;
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, json_types::U128, near_bindgen, serde_json, AccountId, PromiseOrValue};
+use near_sdk::{
+    assert_one_yocto, env, is_promise_success, json_types::U128, log, near_bindgen, serde_json,
+    AccountId, Promise, PromiseOrValue, ONE_YOCTO,
+};
+use schemars::JsonSchema;
+use zswap_math_library::json_types::JsonU256;
 
 use crate::error::*;
 use crate::{Contract, ContractExt};
 
 /// Message parameters to receive via token function call.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 #[serde(rename_all = "snake_case")]
 pub enum TokenReceiverMessage {
-    /// Alternative to deposit + execute actions call.
+    /// Deposits the tokens and adds the transferred amount to `account_id`'s allowance.
     Approve { account_id: AccountId },
+    /// Swaps the transferred tokens in one step, returning the input left when the price limit is hit.
+    Swap {
+        zero_for_one: bool,
+        sqrt_price_limit_x96: Option<JsonU256>,
+        amount_out_min: U128,
+        recipient: AccountId,
+    },
 }
 
 #[near_bindgen]
@@ -23,6 +36,7 @@ impl FungibleTokenReceiver for Contract {
         msg: String,
     ) -> PromiseOrValue<U128> {
         let token_in = &env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
         if msg.is_empty() {
             if token_in == &self.token_0 {
                 self.token_0_deposit(&sender_id, amount.into());
@@ -31,6 +45,8 @@ impl FungibleTokenReceiver for Contract {
             } else {
                 env::panic_str(UNSUPPORTED_TOKEN)
             }
+            // failing refunds the transfer
+            self.internal_charge_storage(&sender_id, initial_storage);
             let unused_amount = U128(0);
             PromiseOrValue::Value(unused_amount)
         } else {
@@ -40,23 +56,204 @@ impl FungibleTokenReceiver for Contract {
                 TokenReceiverMessage::Approve { account_id } => {
                     if token_in == &self.token_0 {
                         self.token_0_deposit(&sender_id, amount.into());
-                        self.token_0_approve(&sender_id, &account_id);
                     } else if token_in == &self.token_1 {
                         self.token_1_deposit(&sender_id, amount.into());
-                        self.token_1_approve(&sender_id, &account_id);
                     } else {
                         env::panic_str(UNSUPPORTED_TOKEN)
                     }
+                    self.internal_increase_allowance(token_in, &sender_id, &account_id, amount.0);
+                    self.internal_charge_storage(&sender_id, initial_storage);
 
                     let unused_amount = U128(0);
                     PromiseOrValue::Value(unused_amount)
                 }
+                TokenReceiverMessage::Swap {
+                    zero_for_one,
+                    sqrt_price_limit_x96,
+                    amount_out_min,
+                    recipient,
+                } => {
+                    let token_in_expected = if zero_for_one {
+                        &self.token_0
+                    } else {
+                        &self.token_1
+                    };
+                    if token_in != token_in_expected {
+                        env::panic_str(UNSUPPORTED_TOKEN)
+                    }
+                    self.assert_not_paused();
+
+                    match self.hook.clone() {
+                        Some(hook) => self
+                            .internal_before_swap(
+                                hook,
+                                &sender_id,
+                                &recipient,
+                                zero_for_one,
+                                amount,
+                            )
+                            .then(Self::ext(env::current_account_id()).ft_swap_after_hook(
+                                sender_id,
+                                recipient,
+                                zero_for_one,
+                                amount,
+                                sqrt_price_limit_x96,
+                                amount_out_min,
+                            ))
+                            .into(),
+                        None => PromiseOrValue::Value(self.internal_ft_swap(
+                            &sender_id,
+                            recipient,
+                            zero_for_one,
+                            amount.0,
+                            sqrt_price_limit_x96,
+                            amount_out_min.0,
+                            None,
+                        )),
+                    }
+                }
             }
         }
     }
 }
 
+#[near_bindgen]
 impl Contract {
+    /// Withdraws tokens previously deposited by the caller and not used yet
+    #[payable]
+    pub fn withdraw(&mut self, token_id: AccountId, amount: U128) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        if token_id == self.token_0 {
+            self.token_0_withdraw(&account_id, amount.0);
+        } else if token_id == self.token_1 {
+            self.token_1_withdraw(&account_id, amount.0);
+        } else {
+            env::panic_str(UNSUPPORTED_TOKEN)
+        }
+        self.internal_record_storage(&account_id, initial_storage);
+
+        ext_ft_core::ext(token_id.clone())
+            .with_attached_deposit(ONE_YOCTO)
+            .ft_transfer(account_id.clone(), amount, None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .withdraw_callback(account_id, token_id, amount),
+            )
+    }
+
+    /// Sets the amount of the caller's deposit that `account_id` can spend
+    #[payable]
+    pub fn approve(&mut self, token_id: AccountId, account_id: AccountId, amount: U128) {
+        assert_one_yocto();
+        let owner_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        let approval_key = self.get_approval_key(&owner_id, &account_id);
+        let approvals = self.internal_approvals(&token_id);
+        if amount.0 == 0 {
+            approvals.remove(&approval_key);
+        } else {
+            approvals.insert(&approval_key, &amount.0);
+        }
+        self.internal_charge_storage(&owner_id, initial_storage);
+    }
+
+    /// Removes the allowance the caller gave to `account_id`
+    #[payable]
+    pub fn revoke(&mut self, token_id: AccountId, account_id: AccountId) {
+        assert_one_yocto();
+        let owner_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        let approval_key = self.get_approval_key(&owner_id, &account_id);
+        self.internal_approvals(&token_id).remove(&approval_key);
+        self.internal_record_storage(&owner_id, initial_storage);
+    }
+
+    pub fn allowance(
+        &self,
+        token_id: AccountId,
+        owner_id: AccountId,
+        account_id: AccountId,
+    ) -> U128 {
+        let approval_key = self.get_approval_key(&owner_id, &account_id);
+        let allowance = if token_id == self.token_0 {
+            self.approved_token_0.get(&approval_key)
+        } else if token_id == self.token_1 {
+            self.approved_token_1.get(&approval_key)
+        } else {
+            env::panic_str(UNSUPPORTED_TOKEN)
+        };
+        allowance.unwrap_or_default().into()
+    }
+
+    #[private]
+    pub fn withdraw_callback(&mut self, account_id: AccountId, token_id: AccountId, amount: U128) {
+        if is_promise_success() {
+            return;
+        }
+
+        log!(
+            "Failed to withdraw {} {}, restoring deposit of {}",
+            amount.0,
+            token_id,
+            account_id
+        );
+        let initial_storage = env::storage_usage();
+        if token_id == self.token_0 {
+            self.token_0_deposit(&account_id, amount.0);
+        } else {
+            self.token_1_deposit(&account_id, amount.0);
+        }
+        self.internal_record_storage(&account_id, initial_storage);
+    }
+}
+
+impl Contract {
+    /// Swaps `amount` just transferred by `sender_id`, sending the output to `recipient`, and
+    /// returns the input left unused for the token to refund
+    pub fn internal_ft_swap(
+        &mut self,
+        sender_id: &AccountId,
+        recipient: AccountId,
+        zero_for_one: bool,
+        amount: u128,
+        sqrt_price_limit_x96: Option<JsonU256>,
+        amount_out_min: u128,
+        fee: Option<u32>,
+    ) -> U128 {
+        let token_out = if zero_for_one {
+            self.token_1.clone()
+        } else {
+            self.token_0.clone()
+        };
+        let [amount_in, amount_out] =
+            self.internal_swap_with_fee(zero_for_one, amount, sqrt_price_limit_x96, fee);
+        if amount_out < amount_out_min {
+            env::panic_str(SLIPPAGE_CHECK_FAILED)
+        }
+
+        // an output the recipient cannot receive is kept as its deposit
+        if amount_out > 0 {
+            ext_ft_core::ext(token_out.clone())
+                .with_attached_deposit(ONE_YOCTO)
+                .ft_transfer(recipient.clone(), U128::from(amount_out), None)
+                .then(Self::ext(env::current_account_id()).withdraw_callback(
+                    recipient.clone(),
+                    token_out,
+                    U128::from(amount_out),
+                ));
+        }
+
+        let amounts = if zero_for_one {
+            [amount_in as i128, -(amount_out as i128)]
+        } else {
+            [-(amount_out as i128), amount_in as i128]
+        };
+        self.internal_after_swap(sender_id, &recipient, zero_for_one, amounts);
+        U128(amount - amount_in)
+    }
+
     fn token_0_deposit(&mut self, sender_id: &AccountId, amount: u128) {
         let deposited_token_opt = self.deposited_token_0.get(sender_id);
         match deposited_token_opt {
@@ -83,11 +280,50 @@ impl Contract {
         }
     }
 
-    fn token_0_approve(&mut self, owner_id: &AccountId, account_id: &AccountId) {
-        self.approved_token_0.insert(owner_id, account_id);
+    fn token_0_withdraw(&mut self, account_id: &AccountId, amount: u128) {
+        let deposited = self.deposited_token_0.get(account_id).unwrap_or_default();
+        if deposited < amount {
+            env::panic_str(INSUFFICIENT_INPUT_AMOUNT);
+        }
+        Self::internal_set_deposit(&mut self.deposited_token_0, account_id, deposited - amount);
     }
 
-    fn token_1_approve(&mut self, owner_id: &AccountId, account_id: &AccountId) {
-        self.approved_token_1.insert(owner_id, account_id);
+    fn token_1_withdraw(&mut self, account_id: &AccountId, amount: u128) {
+        let deposited = self.deposited_token_1.get(account_id).unwrap_or_default();
+        if deposited < amount {
+            env::panic_str(INSUFFICIENT_INPUT_AMOUNT);
+        }
+        Self::internal_set_deposit(&mut self.deposited_token_1, account_id, deposited - amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::serde_json;
+    use schemars::schema_for;
+
+    use super::TokenReceiverMessage;
+
+    const SCHEMA_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../res/abi/zswap_pool_token_receiver_message.json"
+    );
+
+    /// `ft_on_transfer` takes the message as a string, so its shape is not in the ABI and is
+    /// checked in separately. Run with `UPDATE_SCHEMAS=1` to regenerate it
+    #[test]
+    fn test_token_receiver_message_schema() {
+        let schema =
+            serde_json::to_string_pretty(&schema_for!(TokenReceiverMessage)).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+            std::fs::write(SCHEMA_PATH, schema).unwrap();
+            return;
+        }
+        let checked_in = std::fs::read_to_string(SCHEMA_PATH).unwrap_or_default();
+        assert!(
+            checked_in == schema,
+            "{} is out of date, run the tests with UPDATE_SCHEMAS=1",
+            SCHEMA_PATH
+        );
     }
 }
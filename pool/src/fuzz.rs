@@ -0,0 +1,508 @@
+//! State machine harness behind the `fuzz/` targets and the invariant test.
+//!
+//! `PoolHarness` drives the contract through its public `mint`/`burn`/`swap`/`collect` and
+//! limit order entry points under `testing_env!`, replaying every operation on
+//! `zswap-simulator` first. The
+//! simulator refuses the operations the contract would revert on, so those are skipped and
+//! anything the harness reaches is expected to succeed with the same amounts on both.
+//! After each step `check_invariants` verifies:
+//!
+//! - the liquidity of positions around the current tick adds up to the active liquidity
+//! - the tokens the pool received cover what every position and limit order is owed or could burn
+//! - global fee growth never decreases
+//! - the current tick matches the current sqrt price
+//! - the limit order epochs match the simulator's, so orders fill on the same crosses
+//! - a dynamic-fee pool measures the same volatility as the simulator and charges the same fee
+
+use near_sdk::json_types::{U128, U64};
+use near_sdk::test_utils::VMContextBuilder;
+use near_sdk::{env, testing_env, AccountId, PromiseOrValue};
+use zswap_math_library::json_types::JsonU256;
+use zswap_math_library::num256::U256;
+use zswap_math_library::{liquidity_math, tick_math};
+
+use crate::core_trait::CoreZswapPool;
+use crate::Contract;
+
+pub const TICK_SPACING: u32 = 60;
+pub const FEE: u32 = 3000;
+/// Fee cap of the harness' dynamic-fee pools
+pub const MAX_FEE: u32 = 30_000;
+/// 1 token 0 = 100 token 1, tick ~ 46054
+const SQRT_PRICE_X96: u128 = 10 * 2_u128.pow(96);
+/// Positions are placed around the compressed starting tick
+const BASE_COMPRESSED_TICK: i32 = 46054 / TICK_SPACING as i32;
+const OWNERS: [&str; 3] = ["alice.testnet", "bob.testnet", "carol.testnet"];
+const TRADER: &str = "trader.testnet";
+
+/// One step of the state machine. Fields are raw fuzzer input, mapped onto valid ranges by
+/// `PoolHarness::apply`.
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+#[derive(Clone, Copy, Debug)]
+pub enum Op {
+    Mint {
+        owner: u8,
+        lower: i8,
+        width: u8,
+        liquidity: u64,
+    },
+    Burn {
+        position: u8,
+        liquidity: u64,
+    },
+    Swap {
+        zero_for_one: bool,
+        amount: u64,
+    },
+    Collect {
+        position: u8,
+        amount_0: u64,
+        amount_1: u64,
+    },
+    MintLimitOrder {
+        owner: u8,
+        /// Tick spacings between the current tick and the order
+        distance: u8,
+        zero_for_one: bool,
+        amount: u64,
+    },
+    BurnLimitOrder {
+        order: u8,
+        liquidity: u64,
+    },
+    CollectLimitOrder {
+        order: u8,
+        liquidity: u64,
+    },
+    /// Moves on to a later block, where a dynamic-fee pool measures its volatility again
+    NextBlock {
+        blocks: u8,
+    },
+}
+
+pub struct PoolHarness {
+    contract: Contract,
+    simulator: zswap_simulator::Pool,
+    /// Every position ever minted, as `(owner, lower_tick, upper_tick)`
+    positions: Vec<(AccountId, i32, i32)>,
+    /// Every limit order ever placed, as `(owner, epoch)`
+    limit_orders: Vec<(AccountId, u64)>,
+    /// Tokens paid into the pool minus tokens sent out of it
+    balances: [u128; 2],
+    fee_growth_global_x128: [u128; 2],
+}
+
+impl Default for PoolHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PoolHarness {
+    pub fn new() -> Self {
+        Self::with_max_fee(None)
+    }
+
+    /// A harness whose pool is a dynamic-fee one if `max_fee` is set
+    pub fn with_max_fee(max_fee: Option<u32>) -> Self {
+        // `testing_env!` carries storage over, drop what a previous harness left behind
+        near_sdk::mock::with_mocked_blockchain(|blockchain| blockchain.take_storage());
+        testing_env!(VMContextBuilder::new().build());
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            TICK_SPACING,
+            FEE,
+            JsonU256::from(SQRT_PRICE_X96),
+            max_fee,
+            None,
+        );
+        for owner in OWNERS.iter().chain([TRADER].iter()) {
+            contract
+                .deposited_token_0
+                .insert(&account(owner), &(u128::MAX / 2));
+            contract
+                .deposited_token_1
+                .insert(&account(owner), &(u128::MAX / 2));
+            contract.internal_storage_deposit(&account(owner), 10_u128.pow(25));
+        }
+
+        let simulator = zswap_simulator::Pool::new(TICK_SPACING, FEE, U256::from(SQRT_PRICE_X96));
+        Self {
+            contract,
+            simulator: match max_fee {
+                Some(max_fee) => simulator.with_max_fee(max_fee),
+                None => simulator,
+            },
+            positions: Vec::new(),
+            limit_orders: Vec::new(),
+            balances: [0, 0],
+            fee_growth_global_x128: [0, 0],
+        }
+    }
+
+    /// Runs `op` on the simulator and, if it succeeds there, on the contract
+    pub fn apply(&mut self, op: Op) {
+        match op {
+            Op::Mint {
+                owner,
+                lower,
+                width,
+                liquidity,
+            } => {
+                let owner = account(OWNERS[owner as usize % OWNERS.len()]);
+                let lower_tick = (BASE_COMPRESSED_TICK + lower as i32) * TICK_SPACING as i32;
+                let upper_tick = lower_tick + (1 + width as i32 % 32) * TICK_SPACING as i32;
+                let liquidity = (liquidity as u128) << 16;
+                let Ok(expected) = self.simulator.mint(owner.as_str(), lower_tick, upper_tick, liquidity) else {
+                    return;
+                };
+
+                self.set_predecessor(&owner);
+                let amounts = value(self.contract.mint(
+                    owner.clone(),
+                    owner.clone(),
+                    lower_tick,
+                    upper_tick,
+                    U128(liquidity),
+                ));
+                assert_eq!(amounts.map(|amount| amount.0), expected);
+                self.balances[0] += expected[0];
+                self.balances[1] += expected[1];
+
+                if !self
+                    .positions
+                    .contains(&(owner.clone(), lower_tick, upper_tick))
+                {
+                    self.positions.push((owner, lower_tick, upper_tick));
+                }
+            }
+            Op::Burn {
+                position,
+                liquidity,
+            } => {
+                let Some((owner, lower_tick, upper_tick)) = self.pick_position(position) else {
+                    return;
+                };
+                let position_liquidity = self
+                    .simulator
+                    .position(owner.as_str(), lower_tick, upper_tick)
+                    .map_or(0, |position| position.liquidity);
+                if position_liquidity == 0 {
+                    return;
+                }
+                let liquidity = 1 + ((liquidity as u128) << 16) % position_liquidity;
+                let expected = self
+                    .simulator
+                    .burn(owner.as_str(), lower_tick, upper_tick, liquidity)
+                    .unwrap();
+
+                self.set_predecessor(&owner);
+                let amounts = value(self.contract.burn(lower_tick, upper_tick, U128(liquidity)));
+                assert_eq!(amounts.map(|amount| amount.0), expected);
+            }
+            Op::Swap {
+                zero_for_one,
+                amount,
+            } => {
+                let amount = (amount as u128) << 8;
+                if amount == 0 {
+                    return;
+                }
+                let Ok([amount_in, amount_out]) = self.simulator.swap(zero_for_one, amount, None) else {
+                    return;
+                };
+
+                let trader = account(TRADER);
+                self.set_predecessor(&trader);
+                let amounts =
+                    value(
+                        self.contract
+                            .swap(trader, zero_for_one, U128(amount), None, None),
+                    );
+                let (token_in, token_out) = if zero_for_one { (0, 1) } else { (1, 0) };
+                assert_eq!(amounts[token_in].0, amount_in as i128);
+                assert_eq!(amounts[token_out].0, -(amount_out as i128));
+
+                self.balances[token_in] += amount_in;
+                self.balances[token_out] = self.balances[token_out]
+                    .checked_sub(amount_out)
+                    .expect("swap paid out more than the pool holds");
+            }
+            Op::Collect {
+                position,
+                amount_0,
+                amount_1,
+            } => {
+                let Some((owner, lower_tick, upper_tick)) = self.pick_position(position) else {
+                    return;
+                };
+                // a fully burned and collected position is gone from the contract
+                let position_key = self
+                    .contract
+                    .get_position_key(&owner, lower_tick, upper_tick);
+                if self.contract.positions.get(&position_key).is_none() {
+                    return;
+                }
+                let (amount_0, amount_1) = (amount_0 as u128, amount_1 as u128);
+                let expected = self
+                    .simulator
+                    .collect(owner.as_str(), lower_tick, upper_tick, amount_0, amount_1)
+                    .unwrap();
+
+                self.set_predecessor(&owner);
+                let amounts = self.contract.collect(
+                    owner.clone(),
+                    lower_tick,
+                    upper_tick,
+                    U128(amount_0),
+                    U128(amount_1),
+                );
+                assert_eq!(amounts.map(|amount| amount.0), expected);
+                self.pay_out(expected);
+            }
+            Op::MintLimitOrder {
+                owner,
+                distance,
+                zero_for_one,
+                amount,
+            } => {
+                let owner = account(OWNERS[owner as usize % OWNERS.len()]);
+                let spacing = TICK_SPACING as i32;
+                let compressed = self.simulator.slot_0.tick.div_euclid(spacing);
+                let distance = (distance % 16) as i32;
+                let lower_tick = if zero_for_one {
+                    (compressed + 1 + distance) * spacing
+                } else {
+                    (compressed - 1 - distance) * spacing
+                };
+                let amount = (amount as u128) << 8;
+                let Ok(expected) = self.simulator.mint_limit_order(owner.as_str(), lower_tick, zero_for_one, amount) else {
+                    return;
+                };
+
+                self.set_predecessor(&owner);
+                let placed = self.contract.mint_limit_order(
+                    owner.clone(),
+                    owner.clone(),
+                    lower_tick,
+                    zero_for_one,
+                    U128(amount),
+                );
+                assert_eq!(
+                    (placed.epoch.0, placed.liquidity.0, placed.amount_in.0),
+                    (expected.epoch, expected.liquidity, expected.amount_in)
+                );
+                self.balances[if zero_for_one { 0 } else { 1 }] += expected.amount_in;
+
+                if !self.limit_orders.contains(&(owner.clone(), expected.epoch)) {
+                    self.limit_orders.push((owner, expected.epoch));
+                }
+            }
+            Op::BurnLimitOrder { order, liquidity } => {
+                let Some((owner, epoch, liquidity)) = self.pick_limit_order(order, liquidity) else {
+                    return;
+                };
+                let Ok(expected) = self.simulator.burn_limit_order(owner.as_str(), epoch, liquidity) else {
+                    return;
+                };
+
+                self.set_predecessor(&owner);
+                let amounts =
+                    self.contract
+                        .burn_limit_order(owner.clone(), U64(epoch), U128(liquidity));
+                assert_eq!(amounts.map(|amount| amount.0), expected);
+                self.pay_out(expected);
+            }
+            Op::CollectLimitOrder { order, liquidity } => {
+                let Some((owner, epoch, liquidity)) = self.pick_limit_order(order, liquidity) else {
+                    return;
+                };
+                let Ok(expected) = self.simulator.collect_limit_order(owner.as_str(), epoch, liquidity) else {
+                    return;
+                };
+
+                self.set_predecessor(&owner);
+                let amounts =
+                    self.contract
+                        .collect_limit_order(owner.clone(), U64(epoch), U128(liquidity));
+                assert_eq!(amounts.map(|amount| amount.0), expected);
+                self.pay_out(expected);
+            }
+            Op::NextBlock { blocks } => {
+                self.simulator.block_height += 1 + blocks as u64 % 8;
+            }
+        }
+    }
+
+    /// Panics if the contract state breaks one of the invariants listed in the module docs
+    pub fn check_invariants(&mut self) {
+        let contract = &self.contract;
+        let sqrt_price_x96 = contract.slot_0.sqrt_price_x96.0;
+        let tick = contract.slot_0.tick;
+
+        let mut active_liquidity = 0;
+        let mut owed = [0_u128; 2];
+        for (owner, lower_tick, upper_tick) in self.positions.iter() {
+            let Some(position) = contract
+                .positions
+                .get(&contract.get_position_key(owner, *lower_tick, *upper_tick)) else {
+                continue;
+            };
+
+            if (*lower_tick..*upper_tick).contains(&tick) {
+                active_liquidity += position.liquidity;
+            }
+
+            let [amount_0, amount_1] = liquidity_math::get_amounts_for_liquidity(
+                sqrt_price_x96,
+                tick_math::get_sqrt_ratio_at_tick(*lower_tick),
+                tick_math::get_sqrt_ratio_at_tick(*upper_tick),
+                position.liquidity,
+                false,
+            );
+            owed[0] += position.tokens_owed_0 + amount_0;
+            owed[1] += position.tokens_owed_1 + amount_1;
+        }
+
+        // the pool's own positions back the open orders, filled ones hold their amounts
+        let pool_id = env::current_account_id();
+        let mut epochs: Vec<u64> = self.limit_orders.iter().map(|(_, epoch)| *epoch).collect();
+        epochs.sort_unstable();
+        epochs.dedup();
+        for epoch in epochs {
+            let view = contract.get_limit_order_epoch(U64(epoch));
+            let expected = self.simulator.limit_order_epochs.get(&epoch);
+            assert_eq!(
+                view.as_ref().map(|view| (
+                    view.lower_tick,
+                    view.zero_for_one,
+                    view.liquidity.0,
+                    view.filled,
+                    view.amount_0.0,
+                    view.amount_1.0
+                )),
+                expected.map(|epoch| (
+                    epoch.lower_tick,
+                    epoch.zero_for_one,
+                    epoch.liquidity,
+                    epoch.filled,
+                    epoch.amount_0,
+                    epoch.amount_1
+                )),
+                "limit order epoch {}",
+                epoch
+            );
+            let Some(view) = view else {
+                continue;
+            };
+
+            if view.filled {
+                owed[0] += view.amount_0.0;
+                owed[1] += view.amount_1.0;
+                continue;
+            }
+            let position = contract
+                .positions
+                .get(&contract.get_position_key(&pool_id, view.lower_tick, view.upper_tick))
+                .unwrap();
+            if (view.lower_tick..view.upper_tick).contains(&tick) {
+                active_liquidity += position.liquidity;
+            }
+            let [amount_0, amount_1] = liquidity_math::get_amounts_for_liquidity(
+                sqrt_price_x96,
+                tick_math::get_sqrt_ratio_at_tick(view.lower_tick),
+                tick_math::get_sqrt_ratio_at_tick(view.upper_tick),
+                position.liquidity,
+                false,
+            );
+            owed[0] += position.tokens_owed_0 + amount_0;
+            owed[1] += position.tokens_owed_1 + amount_1;
+        }
+
+        assert_eq!(active_liquidity, contract.liquidity, "active liquidity");
+        assert!(owed[0] <= self.balances[0], "token 0 is insolvent");
+        assert!(owed[1] <= self.balances[1], "token 1 is insolvent");
+
+        let fee_growth_global_x128 = [
+            contract.fee_growth_global_0_x128,
+            contract.fee_growth_global_1_x128,
+        ];
+        assert!(fee_growth_global_x128[0] >= self.fee_growth_global_x128[0]);
+        assert!(fee_growth_global_x128[1] >= self.fee_growth_global_x128[1]);
+        self.fee_growth_global_x128 = fee_growth_global_x128;
+
+        // a swap ending exactly on an initialized tick while moving down leaves the tick one below it
+        assert!(
+            tick_math::get_tick_at_sqrt_ratio(sqrt_price_x96) == tick
+                || sqrt_price_x96 == tick_math::get_sqrt_ratio_at_tick(tick + 1),
+            "tick {} does not match sqrt price {}",
+            tick,
+            sqrt_price_x96
+        );
+        assert_eq!(
+            (contract.slot_0.sqrt_price_x96, tick),
+            (
+                self.simulator.slot_0.sqrt_price_x96,
+                self.simulator.slot_0.tick
+            )
+        );
+
+        assert_eq!(contract.dynamic_fee, self.simulator.dynamic_fee);
+        self.set_predecessor(&account(TRADER));
+        assert_eq!(
+            self.contract.get_slot_0().fee,
+            self.simulator.current_fee(),
+            "fee"
+        );
+    }
+
+    fn pick_position(&self, index: u8) -> Option<(AccountId, i32, i32)> {
+        if self.positions.is_empty() {
+            None
+        } else {
+            Some(self.positions[index as usize % self.positions.len()].clone())
+        }
+    }
+
+    /// Calls the contract as `account_id`, in the simulator's block
+    fn set_predecessor(&self, account_id: &AccountId) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id.clone())
+            .block_index(self.simulator.block_height)
+            .build());
+    }
+
+    /// A tracked order and some of the liquidity its owner still holds in it
+    fn pick_limit_order(&self, index: u8, liquidity: u64) -> Option<(AccountId, u64, u128)> {
+        if self.limit_orders.is_empty() {
+            return None;
+        }
+        let (owner, epoch) = self.limit_orders[index as usize % self.limit_orders.len()].clone();
+        let share = self.simulator.limit_order_liquidity(owner.as_str(), epoch);
+        if share == 0 {
+            return None;
+        }
+        Some((owner, epoch, 1 + ((liquidity as u128) << 16) % share))
+    }
+
+    fn pay_out(&mut self, amounts: [u128; 2]) {
+        for (balance, amount) in self.balances.iter_mut().zip(amounts) {
+            *balance = balance
+                .checked_sub(amount)
+                .expect("the pool paid out more than it holds");
+        }
+    }
+}
+
+fn account(id: &str) -> AccountId {
+    AccountId::new_unchecked(id.to_string())
+}
+
+/// The harness' pool has no hook, so its actions complete at once
+fn value<T>(result: PromiseOrValue<T>) -> T {
+    match result {
+        PromiseOrValue::Value(value) => value,
+        PromiseOrValue::Promise(_) => unreachable!(),
+    }
+}
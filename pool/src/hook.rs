@@ -0,0 +1,339 @@
+use near_sdk::json_types::{I128, U128};
+use near_sdk::{env, ext_contract, near_bindgen, AccountId, Gas, Promise, PromiseError};
+use zswap_math_library::json_types::JsonU256;
+
+use crate::error::{HOOK_REJECTED, INVALID_HOOK_FEE};
+use crate::{Contract, ContractExt};
+
+/// Gas given to each hook call, the rest goes to the action itself
+pub const HOOK_GAS: Gas = Gas(20_000_000_000_000);
+
+/// Interface of the contract a pool can be created with, called around its swaps and its
+/// liquidity changes.
+///
+/// A `before_*` call that panics rejects the action, and nothing is changed. `after_*` calls
+/// are notifications: the action has already happened, and their result is ignored.
+#[ext_contract(ext_zswap_hook)]
+pub trait ZswapHook {
+    /// Returns the fee the swap pays, in hundredths of a bip, or `None` to keep the pool's `fee`
+    fn before_swap(
+        &mut self,
+        sender: AccountId,
+        recipient: AccountId,
+        zero_for_one: bool,
+        amount_specified: U128,
+        fee: u32,
+    ) -> Option<u32>;
+
+    /// `amounts` are paid into the pool if positive and sent out of it if negative
+    fn after_swap(
+        &mut self,
+        sender: AccountId,
+        recipient: AccountId,
+        zero_for_one: bool,
+        amounts: [I128; 2],
+    );
+
+    /// Called before a mint, with a positive `liquidity_delta`, and before a burn
+    fn before_modify_position(
+        &mut self,
+        sender: AccountId,
+        owner: AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_delta: I128,
+    );
+
+    fn after_modify_position(
+        &mut self,
+        sender: AccountId,
+        owner: AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_delta: I128,
+        amounts: [I128; 2],
+    );
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the pool's hook, if it was created with one
+    pub fn get_hook(&self) -> Option<AccountId> {
+        self.hook.clone()
+    }
+
+    #[private]
+    pub fn mint_after_hook(
+        &mut self,
+        #[callback_result] approved_res: Result<(), PromiseError>,
+        caller: AccountId,
+        payer: AccountId,
+        recipient: AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount: U128,
+    ) -> [U128; 2] {
+        if approved_res.is_err() {
+            env::panic_str(HOOK_REJECTED);
+        }
+        // the pool may have been paused while the hook was called
+        self.assert_not_paused();
+        self.internal_mint(
+            &caller, &payer, &recipient, lower_tick, upper_tick, amount.0,
+        )
+    }
+
+    #[private]
+    pub fn burn_after_hook(
+        &mut self,
+        #[callback_result] approved_res: Result<(), PromiseError>,
+        owner: AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount: U128,
+    ) -> [U128; 2] {
+        if approved_res.is_err() {
+            env::panic_str(HOOK_REJECTED);
+        }
+        self.internal_burn(&owner, lower_tick, upper_tick, amount.0)
+    }
+
+    #[private]
+    pub fn swap_after_hook(
+        &mut self,
+        #[callback_result] fee_res: Result<Option<u32>, PromiseError>,
+        caller: AccountId,
+        recipient: AccountId,
+        zero_for_one: bool,
+        amount_specified: U128,
+        sqrt_price_limit_x96: Option<JsonU256>,
+        amount_out_min: Option<U128>,
+    ) -> [I128; 2] {
+        let fee = Self::internal_hook_fee(fee_res);
+        self.assert_not_paused();
+        self.internal_swap_from_deposit(
+            &caller,
+            recipient,
+            zero_for_one,
+            amount_specified.0,
+            sqrt_price_limit_x96,
+            amount_out_min.map_or(0, |amount| amount.0),
+            fee,
+        )
+    }
+
+    /// Rejecting the swap fails `ft_on_transfer`, so the token refunds the whole transfer
+    #[private]
+    pub fn ft_swap_after_hook(
+        &mut self,
+        #[callback_result] fee_res: Result<Option<u32>, PromiseError>,
+        sender_id: AccountId,
+        recipient: AccountId,
+        zero_for_one: bool,
+        amount: U128,
+        sqrt_price_limit_x96: Option<JsonU256>,
+        amount_out_min: U128,
+    ) -> U128 {
+        let fee = Self::internal_hook_fee(fee_res);
+        self.assert_not_paused();
+        self.internal_ft_swap(
+            &sender_id,
+            recipient,
+            zero_for_one,
+            amount.0,
+            sqrt_price_limit_x96,
+            amount_out_min.0,
+            fee,
+        )
+    }
+}
+
+impl Contract {
+    pub fn internal_before_swap(
+        &self,
+        hook: AccountId,
+        sender: &AccountId,
+        recipient: &AccountId,
+        zero_for_one: bool,
+        amount_specified: U128,
+    ) -> Promise {
+        ext_zswap_hook::ext(hook)
+            .with_static_gas(HOOK_GAS)
+            .with_unused_gas_weight(0)
+            .before_swap(
+                sender.clone(),
+                recipient.clone(),
+                zero_for_one,
+                amount_specified,
+                self.internal_current_fee(),
+            )
+    }
+
+    pub fn internal_before_modify_position(
+        &self,
+        hook: AccountId,
+        sender: &AccountId,
+        owner: &AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_delta: i128,
+    ) -> Promise {
+        ext_zswap_hook::ext(hook)
+            .with_static_gas(HOOK_GAS)
+            .with_unused_gas_weight(0)
+            .before_modify_position(
+                sender.clone(),
+                owner.clone(),
+                lower_tick,
+                upper_tick,
+                I128(liquidity_delta),
+            )
+    }
+
+    pub fn internal_after_swap(
+        &self,
+        sender: &AccountId,
+        recipient: &AccountId,
+        zero_for_one: bool,
+        amounts: [i128; 2],
+    ) {
+        if let Some(hook) = self.hook.clone() {
+            ext_zswap_hook::ext(hook)
+                .with_static_gas(HOOK_GAS)
+                .with_unused_gas_weight(0)
+                .after_swap(
+                    sender.clone(),
+                    recipient.clone(),
+                    zero_for_one,
+                    amounts.map(I128),
+                );
+        }
+    }
+
+    pub fn internal_after_modify_position(
+        &self,
+        sender: &AccountId,
+        owner: &AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_delta: i128,
+        amounts: [i128; 2],
+    ) {
+        if let Some(hook) = self.hook.clone() {
+            ext_zswap_hook::ext(hook)
+                .with_static_gas(HOOK_GAS)
+                .with_unused_gas_weight(0)
+                .after_modify_position(
+                    sender.clone(),
+                    owner.clone(),
+                    lower_tick,
+                    upper_tick,
+                    I128(liquidity_delta),
+                    amounts.map(I128),
+                );
+        }
+    }
+
+    /// The fee the hook set, if it approved the swap
+    fn internal_hook_fee(fee_res: Result<Option<u32>, PromiseError>) -> Option<u32> {
+        match fee_res {
+            Ok(Some(fee)) if fee >= 1_000_000 => env::panic_str(INVALID_HOOK_FEE),
+            Ok(fee) => fee,
+            Err(_) => env::panic_str(HOOK_REJECTED),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, AccountId, PromiseOrValue};
+    use zswap_math_library::json_types::JsonU256;
+    use zswap_math_library::num256::U256;
+
+    use crate::core_trait::CoreZswapPool;
+    use crate::Contract;
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    #[test]
+    fn test_hook_approves_actions_and_sets_the_fee() {
+        let alice = account("alice.testnet");
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account("pool.testnet"))
+            .predecessor_account_id(alice.clone())
+            // pays for the position
+            .attached_deposit(10_u128.pow(24))
+            .build());
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            JsonU256(U256::from(10 * 2_u128.pow(96))),
+            None,
+            Some(account("hook.testnet")),
+        );
+        assert_eq!(contract.get_hook(), Some(account("hook.testnet")));
+        contract.deposited_token_0.insert(&alice, &10_u128.pow(24));
+        contract.deposited_token_1.insert(&alice, &10_u128.pow(24));
+
+        // the actions only start the hook call
+        let liquidity = U128(1_517_882_343_751_509_868_544);
+        assert!(matches!(
+            contract.mint(alice.clone(), alice.clone(), 42000, 48000, liquidity),
+            PromiseOrValue::Promise(_)
+        ));
+        assert_eq!(contract.get_liquidity(), U128(0));
+        assert!(matches!(
+            contract.swap(alice.clone(), true, U128(1_000_000), None, None),
+            PromiseOrValue::Promise(_)
+        ));
+
+        // and complete once it approves them
+        let amounts = contract.mint_after_hook(
+            Ok(()),
+            alice.clone(),
+            alice.clone(),
+            alice.clone(),
+            42000,
+            48000,
+            liquidity,
+        );
+        assert!(amounts[0].0 > 0 && amounts[1].0 > 0);
+        assert_eq!(contract.get_liquidity(), liquidity);
+
+        let at_pool_fee = contract.swap_after_hook(
+            Ok(None),
+            alice.clone(),
+            alice.clone(),
+            true,
+            U128(1_000_000),
+            None,
+            None,
+        );
+        let at_zero_fee = contract.swap_after_hook(
+            Ok(Some(0)),
+            alice.clone(),
+            alice.clone(),
+            true,
+            U128(1_000_000),
+            None,
+            None,
+        );
+        assert_eq!(at_pool_fee[0].0, 1_000_000);
+        assert_eq!(at_zero_fee[0].0, 1_000_000);
+        // 0.3% less output at the pool's fee, give or take the price impact
+        let out_at_pool_fee = -at_pool_fee[1].0;
+        let out_at_zero_fee = -at_zero_fee[1].0;
+        assert!(out_at_zero_fee > out_at_pool_fee);
+        assert!((out_at_zero_fee - out_at_pool_fee) * 1000 / out_at_zero_fee == 3);
+
+        let amounts = contract.burn_after_hook(Ok(()), alice, 42000, 48000, liquidity);
+        assert!(amounts[0].0 > 0 && amounts[1].0 > 0);
+        assert_eq!(contract.get_liquidity(), U128(0));
+    }
+}
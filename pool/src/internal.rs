@@ -1,12 +1,21 @@
-use ethnum::I256;
 use near_contract_standards::fungible_token::core::ext_ft_core;
-use near_sdk::{env, AccountId, CryptoHash, Promise};
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::{I128, U128};
+use near_sdk::{env, log, AccountId, CryptoHash, Promise, ONE_YOCTO};
+use zswap_math_library::json_types::JsonU256;
+use zswap_math_library::swap::{self, SwapPool, SwapState};
 use zswap_math_library::{
-    liquidity_math, num256::U256, sqrt_price_math, tick, tick_bitmap::flip_tick, tick_math,
+    liquidity_math,
+    num256::{I256, U256},
+    sqrt_price_math,
+    tick::{self, TickInfo},
+    tick_bitmap::{self, flip_tick},
+    tick_math,
 };
 
 use crate::{
-    error::{INSUFFICIENT_INPUT_AMOUNT, NOT_AUTHORIZED},
+    error::{INSUFFICIENT_INPUT_AMOUNT, NOT_AUTHORIZED, SLIPPAGE_CHECK_FAILED, UNSUPPORTED_TOKEN},
+    storage::StorageItem,
     Contract,
 };
 
@@ -42,9 +51,25 @@ impl Contract {
         lower_tick: i32,
         upper_tick: i32,
         liquidity_delta: i128,
+    ) -> [i128; 2] {
+        self.internal_modify_position(owner, owner, lower_tick, upper_tick, liquidity_delta)
+    }
+
+    /// `modify_position` with `payer` paying for the position and the ticks it initializes.
+    /// Ticks and bitmap words it clears go back to whoever paid for them
+    pub fn internal_modify_position(
+        &mut self,
+        payer: &AccountId,
+        owner: &AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_delta: i128,
     ) -> [i128; 2] {
         let position_key = self.get_position_key(owner, lower_tick, upper_tick);
-        let mut position = self.positions.get(&position_key).unwrap_or_default();
+        let (mut position, new_position) = match self.positions.get(&position_key) {
+            Some(position) => (position, false),
+            None => (Default::default(), true),
+        };
 
         let current_tick = self.slot_0.tick;
         let fee_growth_global_0_x128 = self.fee_growth_global_0_x128;
@@ -59,7 +84,7 @@ impl Contract {
             fee_growth_global_1_x128,
             false,
         );
-        self.ticks.insert(&lower_tick, &lower_tick_info);
+        self.internal_write_tick(payer, lower_tick, &lower_tick_info, flipped_lower);
 
         let mut upper_tick_info = self.ticks.get(&upper_tick).unwrap_or_default();
         let flipped_upper = upper_tick_info.update(
@@ -70,14 +95,14 @@ impl Contract {
             fee_growth_global_1_x128,
             true,
         );
-        self.ticks.insert(&upper_tick, &upper_tick_info);
+        self.internal_write_tick(payer, upper_tick, &upper_tick_info, flipped_upper);
 
         if flipped_lower {
-            flip_tick(&mut self.tick_bitmap, lower_tick, self.tick_spacing as i32);
+            self.internal_flip_tick(payer, lower_tick);
         }
 
         if flipped_upper {
-            flip_tick(&mut self.tick_bitmap, upper_tick, self.tick_spacing as i32);
+            self.internal_flip_tick(payer, upper_tick);
         }
 
         let fees_growth_inside_x128 = tick::get_fee_growth_inside(
@@ -95,7 +120,11 @@ impl Contract {
             fees_growth_inside_x128[0],
             fees_growth_inside_x128[1],
         );
+        let initial_storage = env::storage_usage();
         self.positions.insert(&position_key, &position);
+        if new_position {
+            self.internal_pay_storage(payer, StorageItem::Position(position_key), initial_storage);
+        }
 
         let sqrt_current_price = self.slot_0.sqrt_price_x96.0;
         let mut amount_0 = I256::ZERO;
@@ -108,13 +137,13 @@ impl Contract {
             );
         } else if current_tick < upper_tick {
             amount_0 = sqrt_price_math::get_amount_0_delta_signed(
-                U256::from(sqrt_current_price),
+                sqrt_current_price,
                 tick_math::get_sqrt_ratio_at_tick(upper_tick),
                 liquidity_delta,
             );
             amount_1 = sqrt_price_math::get_amount_1_delta_signed(
                 tick_math::get_sqrt_ratio_at_tick(lower_tick),
-                U256::from(sqrt_current_price),
+                sqrt_current_price,
                 liquidity_delta,
             );
             self.liquidity = liquidity_math::add_liquidity(self.liquidity, liquidity_delta);
@@ -129,26 +158,285 @@ impl Contract {
         [amount_0.as_i128(), amount_1.as_i128()]
     }
 
-    pub fn internal_collect_token_0_to_mint(
+    /// Writes `tick` back after an update, initializing it at `payer`'s expense or clearing it
+    /// once no position references it anymore
+    fn internal_write_tick(
+        &mut self,
+        payer: &AccountId,
+        tick: i32,
+        tick_info: &TickInfo,
+        flipped: bool,
+    ) {
+        let initial_storage = env::storage_usage();
+        if flipped && tick_info.liquidity_gross == 0 {
+            self.ticks.remove(&tick);
+            self.internal_refund_storage(StorageItem::Tick(tick));
+        } else {
+            self.ticks.insert(&tick, tick_info);
+            if flipped {
+                self.internal_pay_storage(payer, StorageItem::Tick(tick), initial_storage);
+            }
+        }
+    }
+
+    /// Flips `tick` in the bitmap, with `payer` paying for the word if it starts one
+    fn internal_flip_tick(&mut self, payer: &AccountId, tick: i32) {
+        let (word_pos, _) = tick_bitmap::position(tick / self.tick_spacing as i32);
+        let new_word = self.tick_bitmap.get(&word_pos).is_none();
+
+        let initial_storage = env::storage_usage();
+        flip_tick(&mut self.tick_bitmap, tick, self.tick_spacing as i32);
+        if new_word {
+            self.internal_pay_storage(
+                payer,
+                StorageItem::TickBitmapWord(word_pos),
+                initial_storage,
+            );
+        } else if self.tick_bitmap.get(&word_pos).is_none() {
+            self.internal_refund_storage(StorageItem::TickBitmapWord(word_pos));
+        }
+    }
+
+    /// Runs the swap loop and updates the pool state, returning the input consumed and the output
+    pub fn internal_swap(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: u128,
+        sqrt_price_limit_x96: Option<JsonU256>,
+    ) -> [u128; 2] {
+        self.internal_swap_with_fee(zero_for_one, amount_specified, sqrt_price_limit_x96, None)
+    }
+
+    /// `internal_swap` charging `fee`, as set by the pool's hook, instead of the pool's fee
+    pub fn internal_swap_with_fee(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: u128,
+        sqrt_price_limit_x96: Option<JsonU256>,
+        fee: Option<u32>,
+    ) -> [u128; 2] {
+        let pool_fee = self.internal_update_fee();
+        let fee = fee.unwrap_or(pool_fee);
+        swap::swap(
+            self,
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_x96.map(U256::from),
+            fee,
+        )
+        .unwrap_or_else(|error| env::panic_str(error))
+    }
+
+    /// Adds `amount` of liquidity owned by `recipient`, paid from `payer`'s deposit by `caller`
+    pub fn internal_mint(
+        &mut self,
+        caller: &AccountId,
+        payer: &AccountId,
+        recipient: &AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount: u128,
+    ) -> [U128; 2] {
+        // the owner pays for the position and the ticks it initializes
+        let initial_storage = env::storage_usage();
+        let amounts = self.modify_position(recipient, lower_tick, upper_tick, amount as i128);
+        self.internal_assert_storage_paid(recipient, initial_storage);
+        let amount_0 = amounts[0] as u128;
+        let amount_1 = amounts[1] as u128;
+        log!("Used amount_0: {}", amount_0);
+        log!("Used amount_1: {}", amount_1);
+
+        let initial_storage = env::storage_usage();
+        if amount_0 > 0 {
+            self.internal_collect_token_0_to_mint(payer, caller, amount_0);
+        }
+
+        if amount_1 > 0 {
+            self.internal_collect_token_1_to_mint(payer, caller, amount_1);
+        }
+        self.internal_record_storage(payer, initial_storage);
+
+        self.internal_after_modify_position(
+            caller,
+            recipient,
+            lower_tick,
+            upper_tick,
+            amount as i128,
+            amounts,
+        );
+        [U128::from(amount_0), U128::from(amount_1)]
+    }
+
+    /// Removes `amount` of `owner`'s liquidity, owing them what it held
+    pub fn internal_burn(
+        &mut self,
+        owner: &AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount: u128,
+    ) -> [U128; 2] {
+        let amounts = self.modify_position(owner, lower_tick, upper_tick, -(amount as i128));
+        let amount_0 = amounts[0].unsigned_abs();
+        let amount_1 = amounts[1].unsigned_abs();
+
+        if amount_0 > 0 || amount_1 > 0 {
+            let position_key = self.get_position_key(owner, lower_tick, upper_tick);
+            let mut position = self.positions.get(&position_key).unwrap();
+            position.tokens_owed_0 += amount_0;
+            position.tokens_owed_1 += amount_1;
+
+            self.positions.insert(&position_key, &position);
+        }
+
+        self.internal_after_modify_position(
+            owner,
+            owner,
+            lower_tick,
+            upper_tick,
+            -(amount as i128),
+            amounts,
+        );
+        [U128::from(amount_0), U128::from(amount_1)]
+    }
+
+    /// Swaps from `caller`'s deposit, sending the output to `recipient`, and fails if that is
+    /// less than `amount_out_min`
+    pub fn internal_swap_from_deposit(
+        &mut self,
+        caller: &AccountId,
+        recipient: AccountId,
+        zero_for_one: bool,
+        amount_specified: u128,
+        sqrt_price_limit_x96: Option<JsonU256>,
+        amount_out_min: u128,
+        fee: Option<u32>,
+    ) -> [I128; 2] {
+        let [amount_in, amount_out] =
+            self.internal_swap_with_fee(zero_for_one, amount_specified, sqrt_price_limit_x96, fee);
+        if amount_out < amount_out_min {
+            env::panic_str(SLIPPAGE_CHECK_FAILED)
+        }
+
+        // only the caller's deposit is measured, what filled limit orders clear goes back to
+        // whoever paid for it
+        let initial_storage = env::storage_usage();
+        if zero_for_one {
+            let deposited_token_0 = self.deposited_token_0.get(caller).unwrap_or_default();
+            if deposited_token_0 < amount_in {
+                env::panic_str(INSUFFICIENT_INPUT_AMOUNT);
+            }
+            Self::internal_set_deposit(
+                &mut self.deposited_token_0,
+                caller,
+                deposited_token_0 - amount_in,
+            );
+
+            ext_ft_core::ext(self.token_1.clone())
+                .with_attached_deposit(ONE_YOCTO)
+                .ft_transfer(recipient.clone(), U128::from(amount_out), None);
+        } else {
+            let deposited_token_1 = self.deposited_token_1.get(caller).unwrap_or_default();
+            if deposited_token_1 < amount_in {
+                env::panic_str(INSUFFICIENT_INPUT_AMOUNT);
+            }
+            Self::internal_set_deposit(
+                &mut self.deposited_token_1,
+                caller,
+                deposited_token_1 - amount_in,
+            );
+
+            ext_ft_core::ext(self.token_0.clone())
+                .with_attached_deposit(ONE_YOCTO)
+                .ft_transfer(recipient.clone(), U128::from(amount_out), None);
+        }
+
+        self.internal_record_storage(caller, initial_storage);
+
+        // positive amounts are paid into the pool, negative amounts are sent out of it
+        let amounts = if zero_for_one {
+            [amount_in as i128, -(amount_out as i128)]
+        } else {
+            [-(amount_out as i128), amount_in as i128]
+        };
+        self.internal_after_swap(caller, &recipient, zero_for_one, amounts);
+        amounts.map(I128)
+    }
+
+    /// Sets `account_id`'s deposit, removing it once empty so that its storage is given back
+    pub fn internal_set_deposit(
+        deposits: &mut LookupMap<AccountId, u128>,
+        account_id: &AccountId,
+        amount: u128,
+    ) {
+        if amount == 0 {
+            deposits.remove(account_id);
+        } else {
+            deposits.insert(account_id, &amount);
+        }
+    }
+
+    pub fn get_approval_key(&self, owner: &AccountId, spender: &AccountId) -> CryptoHash {
+        env::keccak256_array([owner.as_bytes(), spender.as_bytes()].concat().as_slice())
+    }
+
+    pub fn internal_approvals(&mut self, token_id: &AccountId) -> &mut LookupMap<CryptoHash, u128> {
+        if token_id == &self.token_0 {
+            &mut self.approved_token_0
+        } else if token_id == &self.token_1 {
+            &mut self.approved_token_1
+        } else {
+            env::panic_str(UNSUPPORTED_TOKEN)
+        }
+    }
+
+    pub fn internal_increase_allowance(
         &mut self,
+        token_id: &AccountId,
+        owner: &AccountId,
+        spender: &AccountId,
+        amount: u128,
+    ) {
+        let approval_key = self.get_approval_key(owner, spender);
+        let approvals = self.internal_approvals(token_id);
+        let allowance = approvals.get(&approval_key).unwrap_or_default();
+        approvals.insert(&approval_key, &allowance.saturating_add(amount));
+    }
+
+    /// Spends `amount` of the allowance `owner` gave to `caller`, unless they are the same account
+    fn internal_spend_allowance(
+        &mut self,
+        token_id: &AccountId,
         owner: &AccountId,
         caller: &AccountId,
         amount: u128,
     ) {
-        if owner != caller {
-            let approval = self.approved_token_0.get(owner);
-            match approval {
-                Some(approved) => {
-                    if &approved != caller {
-                        env::panic_str(NOT_AUTHORIZED);
-                    }
-                }
-                None => {
-                    env::panic_str(NOT_AUTHORIZED);
-                }
-            }
+        if owner == caller {
+            return;
+        }
+
+        let approval_key = self.get_approval_key(owner, caller);
+        let approvals = self.internal_approvals(token_id);
+        let allowance = approvals.get(&approval_key).unwrap_or_default();
+        if allowance < amount {
+            env::panic_str(NOT_AUTHORIZED);
         }
 
+        if allowance == amount {
+            approvals.remove(&approval_key);
+        } else {
+            approvals.insert(&approval_key, &(allowance - amount));
+        }
+    }
+
+    pub fn internal_collect_token_0_to_mint(
+        &mut self,
+        owner: &AccountId,
+        caller: &AccountId,
+        amount: u128,
+    ) {
+        let token_id = self.token_0.clone();
+        self.internal_spend_allowance(&token_id, owner, caller, amount);
+
         let deposited_token_opt = self.deposited_token_0.get(owner);
         match deposited_token_opt {
             Some(deposited) => {
@@ -156,7 +444,7 @@ impl Contract {
                     env::panic_str(INSUFFICIENT_INPUT_AMOUNT);
                 }
 
-                self.deposited_token_0.insert(owner, &(deposited - amount));
+                Self::internal_set_deposit(&mut self.deposited_token_0, owner, deposited - amount);
             }
             None => {
                 env::panic_str(INSUFFICIENT_INPUT_AMOUNT);
@@ -170,19 +458,8 @@ impl Contract {
         caller: &AccountId,
         amount: u128,
     ) {
-        if owner != caller {
-            let approval = self.approved_token_1.get(owner);
-            match approval {
-                Some(approved) => {
-                    if &approved != caller {
-                        env::panic_str(NOT_AUTHORIZED);
-                    }
-                }
-                None => {
-                    env::panic_str(NOT_AUTHORIZED);
-                }
-            }
-        }
+        let token_id = self.token_1.clone();
+        self.internal_spend_allowance(&token_id, owner, caller, amount);
 
         let deposited_token_opt = self.deposited_token_1.get(owner);
         match deposited_token_opt {
@@ -191,7 +468,7 @@ impl Contract {
                     env::panic_str(INSUFFICIENT_INPUT_AMOUNT);
                 }
 
-                self.deposited_token_1.insert(owner, &(deposited - amount));
+                Self::internal_set_deposit(&mut self.deposited_token_1, owner, deposited - amount);
             }
             None => {
                 env::panic_str(INSUFFICIENT_INPUT_AMOUNT);
@@ -199,3 +476,58 @@ impl Contract {
         }
     }
 }
+
+impl SwapPool for Contract {
+    type TickBitmap = LookupMap<i16, U256>;
+
+    fn tick_spacing(&self) -> u32 {
+        self.tick_spacing
+    }
+
+    fn tick_bitmap(&self) -> &Self::TickBitmap {
+        &self.tick_bitmap
+    }
+
+    fn sqrt_price_x96(&self) -> U256 {
+        self.slot_0.sqrt_price_x96.0
+    }
+
+    fn tick(&self) -> i32 {
+        self.slot_0.tick
+    }
+
+    fn liquidity(&self) -> u128 {
+        self.liquidity
+    }
+
+    fn fee_growth_global_x128(&self) -> [u128; 2] {
+        [self.fee_growth_global_0_x128, self.fee_growth_global_1_x128]
+    }
+
+    fn cross_tick(
+        &mut self,
+        tick: i32,
+        fee_growth_global_0_x128: u128,
+        fee_growth_global_1_x128: u128,
+    ) -> i128 {
+        let mut tick_info = self.ticks.get(&tick).unwrap_or_default();
+        let liquidity_net = tick_info.cross(fee_growth_global_0_x128, fee_growth_global_1_x128);
+        self.ticks.insert(&tick, &tick_info);
+        liquidity_net
+    }
+
+    fn write_swap_state(&mut self, zero_for_one: bool, state: &SwapState) {
+        self.slot_0.sqrt_price_x96 = JsonU256(state.sqrt_price_x96);
+        self.slot_0.tick = state.tick;
+        self.liquidity = state.liquidity;
+        if zero_for_one {
+            self.fee_growth_global_0_x128 = state.fee_growth_global_x128;
+        } else {
+            self.fee_growth_global_1_x128 = state.fee_growth_global_x128;
+        }
+    }
+
+    fn fill_limit_orders(&mut self, crossed_tick: i32, zero_for_one: bool) {
+        self.internal_fill_limit_orders(crossed_tick, zero_for_one);
+    }
+}
@@ -1,33 +1,45 @@
-use ethnum::I256;
+#![allow(clippy::too_many_arguments)]
+
 use near_contract_standards::fungible_token::core::ext_ft_core;
 // Find all our documentation at https://docs.near.org
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::LookupMap;
-use near_sdk::json_types::U128;
+use near_sdk::json_types::{I128, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
     env, log, near_bindgen, AccountId, BorshStorageKey, CryptoHash, PanicOnDefault, PromiseOrValue,
     ONE_YOCTO,
 };
+use schemars::JsonSchema;
 
-use zswap_math_library::full_math::{FullMath, FullMathTrait};
-use zswap_math_library::num160::AsU160;
+use zswap_math_library::dynamic_fee::DynamicFee;
+use zswap_math_library::json_types::JsonU256;
 use zswap_math_library::num256::U256;
 use zswap_math_library::position::PositionInfo;
 use zswap_math_library::tick::TickInfo;
+use zswap_math_library::tick_bitmap::{self, TickBitmap};
+use zswap_math_library::tick_math;
 use zswap_math_library::tick_math::TickConstants;
-use zswap_math_library::{fixed_point_128, liquidity_math, swap_math, tick_bitmap, tick_math};
 
 use crate::core_trait::CoreZswapPool;
 use crate::error::*;
+use crate::storage::{StorageAccount, StorageItem, StoragePayer};
 use crate::utils::*;
 
 // mod callback;
 pub mod core_trait;
+mod dynamic_fee;
 mod error;
 pub mod ft_receiver;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzz;
+pub mod hook;
 mod internal;
+mod limit_order;
 mod manager;
+mod migrate;
+mod pause;
+mod storage;
 pub mod utils;
 
 // Define the contract structure
@@ -39,11 +51,14 @@ pub struct Contract {
     token_1: AccountId,
     deposited_token_0: LookupMap<AccountId, u128>,
     deposited_token_1: LookupMap<AccountId, u128>,
-    approved_token_0: LookupMap<AccountId, AccountId>, // improve to use HashMap later
-    approved_token_1: LookupMap<AccountId, AccountId>, // improve to use HashMap later
+    approved_token_0: LookupMap<CryptoHash, u128>, // allowance keyed by (owner, spender)
+    approved_token_1: LookupMap<CryptoHash, u128>, // allowance keyed by (owner, spender)
 
     tick_spacing: u32,
     fee: u32,
+    dynamic_fee: Option<DynamicFee>,
+    hook: Option<AccountId>,
+    paused: bool,
 
     fee_growth_global_0_x128: u128,
     fee_growth_global_1_x128: u128,
@@ -54,6 +69,14 @@ pub struct Contract {
     ticks: LookupMap<i32, TickInfo>,
     tick_bitmap: LookupMap<i16, U256>,
     positions: LookupMap<CryptoHash, PositionInfo>,
+
+    limit_orders: LookupMap<(i32, bool), u64>, // open epoch keyed by (lower tick, zero_for_one)
+    limit_order_epochs: LookupMap<u64, LimitOrderEpoch>,
+    limit_order_shares: LookupMap<CryptoHash, u128>, // liquidity keyed by (owner, epoch)
+    next_limit_order_epoch: u64,
+
+    storage_accounts: LookupMap<AccountId, StorageAccount>,
+    storage_payers: LookupMap<StorageItem, StoragePayer>,
 }
 
 /// Helper structure for keys of the persistent collections.
@@ -65,9 +88,14 @@ pub enum StorageKey {
     Shares { pool_id: u32 },
     DepositedToken { token_id: AccountId },
     ApprovedToken { token_id: AccountId },
+    LimitOrders,
+    LimitOrderEpochs,
+    LimitOrderShares,
+    StorageAccounts,
+    StoragePayers,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct PoolView {
     pub token_0: AccountId,
@@ -83,8 +111,12 @@ impl Contract {
         token_1: AccountId,
         tick_spacing: u32,
         fee: u32,
-        sqrt_price_x96: U128,
+        sqrt_price_x96: JsonU256,
+        max_fee: Option<u32>,
+        hook: Option<AccountId>,
     ) -> Self {
+        let tick = tick_math::get_tick_at_sqrt_ratio(sqrt_price_x96.0);
+        Self::write_state_version();
         Self {
             factory: env::predecessor_account_id(),
             token_0: token_0.clone(),
@@ -100,30 +132,103 @@ impl Contract {
 
             tick_spacing,
             fee,
+            dynamic_fee: max_fee.map(|max_fee| DynamicFee::new(max_fee, tick, env::block_height())),
+            hook,
+            paused: false,
             fee_growth_global_0_x128: 0,
             fee_growth_global_1_x128: 0,
             slot_0: Slot0 {
                 sqrt_price_x96,
-                tick: tick_math::get_tick_at_sqrt_ratio(U256::from(sqrt_price_x96.0)),
+                tick,
+                fee,
             },
             liquidity: 0,
             ticks: LookupMap::new(StorageKey::Pools),
             tick_bitmap: LookupMap::new(StorageKey::Pools),
             positions: LookupMap::new(StorageKey::Pools),
+            limit_orders: LookupMap::new(StorageKey::LimitOrders),
+            limit_order_epochs: LookupMap::new(StorageKey::LimitOrderEpochs),
+            limit_order_shares: LookupMap::new(StorageKey::LimitOrderShares),
+            next_limit_order_epoch: 0,
+            storage_accounts: LookupMap::new(StorageKey::StorageAccounts),
+            storage_payers: LookupMap::new(StorageKey::StoragePayers),
         }
     }
 
     #[payable]
-    pub fn initialize(&mut self, sqrt_price_x96: U128) {
-        if self.slot_0.sqrt_price_x96.0 != 0 {
+    pub fn initialize(&mut self, sqrt_price_x96: JsonU256) {
+        if !self.slot_0.sqrt_price_x96.0.is_zero() {
             env::panic_str(ALREADY_INITIALIZED);
         }
-        let tick = tick_math::get_tick_at_sqrt_ratio(U256::from(sqrt_price_x96.0));
+        let tick = tick_math::get_tick_at_sqrt_ratio(sqrt_price_x96.0);
 
-        self.slot_0 = Slot0 {
-            sqrt_price_x96,
+        self.slot_0.sqrt_price_x96 = sqrt_price_x96;
+        self.slot_0.tick = tick;
+        if let Some(dynamic_fee) = &mut self.dynamic_fee {
+            dynamic_fee.reference_tick = tick;
+        }
+    }
+
+    /// Liquidity in range at the current tick
+    pub fn get_liquidity(&self) -> U128 {
+        self.liquidity.into()
+    }
+
+    pub fn get_tick(&self, tick: i32) -> Option<TickView> {
+        let info = self.ticks.get(&tick)?;
+        Some(TickView {
             tick,
-        };
+            liquidity_gross: info.liquidity_gross.into(),
+            liquidity_net: info.liquidity_net.into(),
+            fee_growth_outside_0_x128: info.fee_growth_outside_0_x128.into(),
+            fee_growth_outside_1_x128: info.fee_growth_outside_1_x128.into(),
+        })
+    }
+
+    /// Returns the initialized ticks between `lower_tick` and `upper_tick`, both included.
+    /// Every bitmap word in the range is read, so wide ranges can run out of view gas
+    pub fn get_ticks(&self, lower_tick: i32, upper_tick: i32) -> Vec<TickView> {
+        let tick_spacing = self.tick_spacing as i32;
+        let lower_tick = lower_tick.max(TickConstants::MIN_TICK);
+        let upper_tick = upper_tick.min(TickConstants::MAX_TICK);
+        if lower_tick > upper_tick {
+            return Vec::new();
+        }
+
+        let (lower_word, _) = tick_bitmap::position(lower_tick.div_euclid(tick_spacing));
+        let (upper_word, _) = tick_bitmap::position(upper_tick.div_euclid(tick_spacing));
+        let mut ticks = Vec::new();
+        for word_pos in lower_word..=upper_word {
+            let word = self.tick_bitmap.get_word(word_pos);
+            if word.is_zero() {
+                continue;
+            }
+            for bit_pos in 0..256 {
+                let tick = (word_pos as i32 * 256 + bit_pos) * tick_spacing;
+                if word.bit(bit_pos as usize) && (lower_tick..=upper_tick).contains(&tick) {
+                    ticks.extend(self.get_tick(tick));
+                }
+            }
+        }
+        ticks
+    }
+
+    pub fn get_position(
+        &self,
+        owner: AccountId,
+        lower_tick: i32,
+        upper_tick: i32,
+    ) -> Option<PositionView> {
+        let position = self
+            .positions
+            .get(&self.get_position_key(&owner, lower_tick, upper_tick))?;
+        Some(PositionView {
+            liquidity: position.liquidity.into(),
+            fee_growth_inside_0_last_x128: position.fee_growth_inside_0_last_x128.into(),
+            fee_growth_inside_1_last_x128: position.fee_growth_inside_1_last_x128.into(),
+            tokens_owed_0: position.tokens_owed_0.into(),
+            tokens_owed_1: position.tokens_owed_1.into(),
+        })
     }
 }
 
@@ -132,7 +237,9 @@ impl Contract {
 impl CoreZswapPool for Contract {
     /// Mint liquidity for the given account
     ///
-    /// Note: This function is not called by user directly, but by ZswapManager
+    /// Note: This function is not called by user directly, but by ZswapManager.
+    /// On a pool with a hook, the mint waits for the hook to approve it.
+    /// The attached deposit tops up the storage balance of `recipient`, who pays for the position
     #[payable]
     fn mint(
         &mut self,
@@ -141,7 +248,7 @@ impl CoreZswapPool for Contract {
         lower_tick: i32,
         upper_tick: i32,
         amount: U128,
-    ) -> [U128; 2] {
+    ) -> PromiseOrValue<[U128; 2]> {
         if lower_tick >= upper_tick
             || lower_tick < TickConstants::MIN_TICK
             || upper_tick > TickConstants::MAX_TICK
@@ -152,210 +259,77 @@ impl CoreZswapPool for Contract {
         if amount.0 == 0 {
             env::panic_str(ZERO_LIQUIDITY);
         }
-        let amounts = self.modify_position(&recipient, lower_tick, upper_tick, amount.0 as i128);
-        let amount_0 = amounts[0] as u128;
-        let amount_1 = amounts[1] as u128;
-        log!("Used amount_0: {}", amount_0);
-        log!("Used amount_1: {}", amount_1);
+        self.assert_not_paused();
+        self.internal_storage_deposit(&recipient, env::attached_deposit());
 
-        if amount_0 > 0 {
-            self.internal_collect_token_0_to_mint(&payer, &env::predecessor_account_id(), amount_0);
-        }
-
-        if amount_1 > 0 {
-            self.internal_collect_token_1_to_mint(&payer, &env::predecessor_account_id(), amount_1);
+        let caller = env::predecessor_account_id();
+        match self.hook.clone() {
+            Some(hook) => self
+                .internal_before_modify_position(
+                    hook,
+                    &caller,
+                    &recipient,
+                    lower_tick,
+                    upper_tick,
+                    amount.0 as i128,
+                )
+                .then(
+                    Self::ext(env::current_account_id())
+                        .mint_after_hook(caller, payer, recipient, lower_tick, upper_tick, amount),
+                )
+                .into(),
+            None => PromiseOrValue::Value(self.internal_mint(
+                &caller, &payer, &recipient, lower_tick, upper_tick, amount.0,
+            )),
         }
-
-        [U128::from(amount_0), U128::from(amount_1)]
     }
 
+    /// On a pool with a hook, the swap waits for the hook to approve it and set its fee.
+    /// Fails if `recipient` would get less than `amount_out_min`
     #[payable]
     fn swap(
         &mut self,
         recipient: AccountId,
         zero_for_one: bool,
         amount_specified: U128,
-        sqrt_price_limit_x96: Option<U128>,
-    ) -> PromiseOrValue<U128> {
-        let sqrt_price_limit_x96 = match sqrt_price_limit_x96 {
-            Some(sqrt_price_limit) => {
-                if zero_for_one && (sqrt_price_limit.0 > self.slot_0.sqrt_price_x96.0) {
-                    env::panic_str(INVALID_PRICE_LIMIT);
-                }
-
-                if !zero_for_one && (sqrt_price_limit.0 < self.slot_0.sqrt_price_x96.0) {
-                    env::panic_str(INVALID_PRICE_LIMIT);
-                }
-
-                U256::from(sqrt_price_limit.0).as_u160()
-            }
-            None => {
-                if zero_for_one {
-                    TickConstants::min_sqrt_ratio()
-                } else {
-                    TickConstants::max_sqrt_ratio()
-                }
-            }
-        };
-
-        let mut state = SwapState {
-            amount_specified_remaining: amount_specified.0,
-            amount_calculated: 0,
-            sqrt_price_x96: self.slot_0.sqrt_price_x96.0,
-            tick: self.slot_0.tick,
-            fee_growth_global_x128: if zero_for_one {
-                self.fee_growth_global_0_x128
-            } else {
-                self.fee_growth_global_1_x128
-            },
-            liquidity: self.liquidity,
-        };
-
-        while state.amount_specified_remaining > 0
-            && state.sqrt_price_x96 != sqrt_price_limit_x96.as_u128()
-        {
-            let mut step = StepState::default();
-
-            (step.next_tick, _) = tick_bitmap::next_initialized_tick_within_one_word(
-                &self.tick_bitmap,
-                state.tick,
-                self.tick_spacing as i32,
-                zero_for_one,
-            );
-
-            step.sqrt_price_start_x96 = state.sqrt_price_x96;
-            step.sqrt_price_next_x96 = tick_math::get_sqrt_ratio_at_tick(step.next_tick).as_u128();
-
-            let sqrt_target_price_x96 = if (zero_for_one
-                && step.sqrt_price_next_x96 < sqrt_price_limit_x96.as_u128())
-                || (!zero_for_one && step.sqrt_price_next_x96 > sqrt_price_limit_x96.as_u128())
-            {
-                sqrt_price_limit_x96
-            } else {
-                U256::from(step.sqrt_price_next_x96).as_u160()
-            };
-
-            let (sqrt_price_x96, amount_in, amount_out, fee_amount) = swap_math::compute_swap_step(
-                U256::from(state.sqrt_price_x96),
-                sqrt_target_price_x96,
-                state.liquidity,
-                I256::from(state.amount_specified_remaining),
-                self.fee,
-            );
-
-            (
-                state.sqrt_price_x96,
-                step.amount_in,
-                step.amount_out,
-                step.fee_amount,
-            ) = (
-                sqrt_price_x96.as_u128(),
-                amount_in.as_u128(),
-                amount_out.as_u128(),
-                fee_amount.as_u128(),
-            );
-
-            state.amount_specified_remaining -= step.amount_in + step.fee_amount;
-            state.amount_calculated += step.amount_out;
-
-            if state.liquidity > 0 {
-                state.fee_growth_global_x128 += FullMath::mul_div(
-                    U256::from(step.fee_amount),
-                    fixed_point_128::get_q128(),
-                    U256::from(state.liquidity),
-                )
-                .as_u128();
-            }
-
-            if state.sqrt_price_x96 == step.sqrt_price_next_x96 {
-                let mut tick = self.ticks.get(&step.next_tick).unwrap_or_default();
-
-                let fee_growth_global_0_x128 = if zero_for_one {
-                    state.fee_growth_global_x128
-                } else {
-                    self.fee_growth_global_0_x128
-                };
-
-                let fee_growth_global_1_x128 = if zero_for_one {
-                    self.fee_growth_global_1_x128
-                } else {
-                    state.fee_growth_global_x128
-                };
-
-                let mut liquidity_delta =
-                    tick.cross(fee_growth_global_0_x128, fee_growth_global_1_x128);
-                self.ticks.insert(&step.next_tick, &tick);
-
-                if zero_for_one {
-                    liquidity_delta = -liquidity_delta;
-                }
-
-                state.liquidity = liquidity_math::add_delta(state.liquidity, liquidity_delta);
-
-                if state.liquidity == 0 {
-                    env::panic_str(NOT_ENOUGH_LIQUIDITY)
-                }
-
-                state.tick = if zero_for_one {
-                    step.next_tick - 1
-                } else {
-                    step.next_tick
-                }
-            } else if state.sqrt_price_x96 != step.sqrt_price_next_x96 {
-                state.tick = tick_math::get_tick_at_sqrt_ratio(U256::from(state.sqrt_price_x96));
-            }
-        }
-
-        if state.tick != self.slot_0.tick {
-            self.slot_0.sqrt_price_x96 = U128::from(state.sqrt_price_x96);
-            self.slot_0.tick = state.tick;
-        } else {
-            self.slot_0.sqrt_price_x96 = U128::from(state.sqrt_price_x96);
-        }
-
-        if self.liquidity != state.liquidity {
-            self.liquidity = state.liquidity;
-        }
-
-        if zero_for_one {
-            self.fee_growth_global_0_x128 = state.fee_growth_global_x128;
-        } else {
-            self.fee_growth_global_1_x128 = state.fee_growth_global_x128;
-        }
+        sqrt_price_limit_x96: Option<JsonU256>,
+        amount_out_min: Option<U128>,
+    ) -> PromiseOrValue<[I128; 2]> {
+        self.assert_not_paused();
 
-        let amount_in = amount_specified.0 - state.amount_specified_remaining;
-        let amount_out = state.amount_calculated;
         let caller = env::predecessor_account_id();
-
-        if zero_for_one {
-            let deposited_token_0 = self.deposited_token_0.get(&caller).unwrap_or_default();
-            if deposited_token_0 < amount_in {
-                env::panic_str(INSUFFICIENT_INPUT_AMOUNT);
-            }
-            self.deposited_token_0
-                .insert(&caller, &(deposited_token_0 - amount_in));
-
-            ext_ft_core::ext(self.token_1.clone())
-                .with_attached_deposit(ONE_YOCTO)
-                .ft_transfer(recipient, U128::from(amount_out), None);
-        } else {
-            let deposited_token_1 = self.deposited_token_1.get(&caller).unwrap_or_default();
-            if deposited_token_1 < amount_in {
-                env::panic_str(INSUFFICIENT_INPUT_AMOUNT);
-            }
-            self.deposited_token_1
-                .insert(&caller, &(deposited_token_1 - amount_in));
-
-            ext_ft_core::ext(self.token_0.clone())
-                .with_attached_deposit(ONE_YOCTO)
-                .ft_transfer(recipient, U128::from(amount_out), None);
+        match self.hook.clone() {
+            Some(hook) => self
+                .internal_before_swap(hook, &caller, &recipient, zero_for_one, amount_specified)
+                .then(Self::ext(env::current_account_id()).swap_after_hook(
+                    caller,
+                    recipient,
+                    zero_for_one,
+                    amount_specified,
+                    sqrt_price_limit_x96,
+                    amount_out_min,
+                ))
+                .into(),
+            None => PromiseOrValue::Value(self.internal_swap_from_deposit(
+                &caller,
+                recipient,
+                zero_for_one,
+                amount_specified.0,
+                sqrt_price_limit_x96,
+                amount_out_min.map_or(0, |amount| amount.0),
+                None,
+            )),
         }
-
-        PromiseOrValue::Value(U128::from(amount_out))
     }
 
+    /// On a pool with a hook, the burn waits for the hook to approve it
     #[payable]
-    fn burn(&mut self, lower_tick: i32, upper_tick: i32, amount: U128) -> [U128; 2] {
+    fn burn(
+        &mut self,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount: U128,
+    ) -> PromiseOrValue<[U128; 2]> {
         if lower_tick >= upper_tick
             || lower_tick < TickConstants::MIN_TICK
             || upper_tick > TickConstants::MAX_TICK
@@ -368,20 +342,25 @@ impl CoreZswapPool for Contract {
         }
 
         let owner = env::predecessor_account_id();
-        let amounts = self.modify_position(&owner, lower_tick, upper_tick, -(amount.0 as i128));
-        let amount_0 = amounts[0].unsigned_abs();
-        let amount_1 = amounts[1].unsigned_abs();
-
-        if amount_0 > 0 || amount_1 > 0 {
-            let position_key = self.get_position_key(&owner, lower_tick, upper_tick);
-            let mut position = self.positions.get(&position_key).unwrap();
-            position.tokens_owed_0 += amount_0;
-            position.tokens_owed_1 += amount_1;
-
-            self.positions.insert(&position_key, &position);
+        match self.hook.clone() {
+            Some(hook) => self
+                .internal_before_modify_position(
+                    hook,
+                    &owner,
+                    &owner,
+                    lower_tick,
+                    upper_tick,
+                    -(amount.0 as i128),
+                )
+                .then(
+                    Self::ext(env::current_account_id())
+                        .burn_after_hook(owner, lower_tick, upper_tick, amount),
+                )
+                .into(),
+            None => {
+                PromiseOrValue::Value(self.internal_burn(&owner, lower_tick, upper_tick, amount.0))
+            }
         }
-
-        [U128::from(amount_0), U128::from(amount_1)]
     }
 
     #[payable]
@@ -424,13 +403,22 @@ impl CoreZswapPool for Contract {
                 .ft_transfer(recipient, amount_1.into(), None);
         }
 
-        self.positions.insert(&position_key, &position);
+        // an emptied position is removed, giving its storage back to whoever paid for it
+        if position.liquidity == 0 && position.tokens_owed_0 == 0 && position.tokens_owed_1 == 0 {
+            self.positions.remove(&position_key);
+            self.internal_refund_storage(StorageItem::Position(position_key));
+        } else {
+            self.positions.insert(&position_key, &position);
+        }
 
         [U128::from(amount_0), U128::from(amount_1)]
     }
 
     fn get_slot_0(&self) -> Slot0 {
-        self.slot_0.clone()
+        Slot0 {
+            fee: self.internal_current_fee(),
+            ..self.slot_0.clone()
+        }
     }
 }
 
@@ -439,4 +427,303 @@ impl CoreZswapPool for Contract {
  * Learn more about Rust tests: https://doc.rust-lang.org/book/ch11-01-writing-tests.html
  */
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    use near_sdk::{test_utils::VMContextBuilder, testing_env};
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    #[test]
+    fn test_multiple_scoped_approvals() {
+        let owner = account("alice.testnet");
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(owner.clone())
+            .attached_deposit(ONE_YOCTO)
+            .build());
+
+        let token_0 = account("token0.testnet");
+        let token_1 = account("token1.testnet");
+        let manager = account("manager.testnet");
+        let router = account("router.testnet");
+        let mut contract = Contract::new(
+            token_0.clone(),
+            token_1.clone(),
+            60,
+            3000,
+            JsonU256::from(2_u128.pow(96)),
+            None,
+            None,
+        );
+        contract.internal_storage_deposit(&owner, 10_u128.pow(24));
+
+        contract.approve(token_0.clone(), manager.clone(), U128::from(1_000));
+        contract.approve(token_0.clone(), router.clone(), U128::from(500));
+        assert_eq!(
+            contract.allowance(token_0.clone(), owner.clone(), manager.clone()),
+            U128::from(1_000)
+        );
+        assert_eq!(
+            contract.allowance(token_0.clone(), owner.clone(), router.clone()),
+            U128::from(500)
+        );
+        assert_eq!(
+            contract.allowance(token_1, owner.clone(), manager.clone()),
+            U128::from(0)
+        );
+
+        contract.deposited_token_0.insert(&owner, &2_000);
+        contract.internal_collect_token_0_to_mint(&owner, &manager, 400);
+        assert_eq!(
+            contract.allowance(token_0.clone(), owner.clone(), manager.clone()),
+            U128::from(600)
+        );
+        assert_eq!(contract.deposited_token_0.get(&owner), Some(1_600));
+
+        contract.revoke(token_0.clone(), router.clone());
+        assert_eq!(
+            contract.allowance(token_0.clone(), owner.clone(), router),
+            U128::from(0)
+        );
+        assert_eq!(contract.allowance(token_0, owner, manager), U128::from(600));
+    }
+
+    #[test]
+    fn test_sqrt_price_above_u128() {
+        testing_env!(VMContextBuilder::new().build());
+
+        // an 18-decimal token against a 6-decimal token needs sqrt prices past 2^128
+        let sqrt_price_x96 = JsonU256(U256::one() << 150);
+        let contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            sqrt_price_x96,
+            None,
+            None,
+        );
+
+        let slot_0 = contract.get_slot_0();
+        assert_eq!(slot_0.sqrt_price_x96, sqrt_price_x96);
+        assert_eq!(
+            slot_0.tick,
+            tick_math::get_tick_at_sqrt_ratio(sqrt_price_x96.0)
+        );
+        assert!(slot_0.tick > 443636);
+    }
+
+    #[test]
+    fn test_tick_and_position_views() {
+        testing_env!(VMContextBuilder::new().build());
+
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            JsonU256(U256::from(10 * 2_u128.pow(96))),
+            None,
+            None,
+        );
+        let alice = account("alice.testnet");
+        contract.modify_position(&alice, -600, 46080, 3_000_000_000);
+        contract.modify_position(&alice, 45960, 46140, 80_000_000_000);
+
+        let ticks = contract.get_ticks(TickConstants::MIN_TICK, TickConstants::MAX_TICK);
+        let ticks: Vec<i32> = ticks.iter().map(|tick| tick.tick).collect();
+        assert_eq!(ticks, [-600, 45960, 46080, 46140]);
+        let ticks = contract.get_ticks(46000, 46100);
+        assert_eq!(ticks.len(), 1);
+        assert_eq!(ticks[0].tick, 46080);
+        assert_eq!(ticks[0].liquidity_net.0, -3_000_000_000);
+        assert!(contract.get_tick(46020).is_none());
+
+        // both ranges hold the current tick, 46054
+        assert_eq!(contract.get_liquidity().0, 83_000_000_000);
+        let position = contract.get_position(alice, 45960, 46140).unwrap();
+        assert_eq!(position.liquidity.0, 80_000_000_000);
+        assert!(contract
+            .get_position(account("bob.testnet"), 45960, 46140)
+            .is_none());
+    }
+
+    #[test]
+    fn test_simulator_matches_contract() {
+        testing_env!(VMContextBuilder::new().build());
+
+        let sqrt_price_x96 = U256::from(10 * 2_u128.pow(96));
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            JsonU256(sqrt_price_x96),
+            None,
+            None,
+        );
+        let mut simulator = zswap_simulator::Pool::new(60, 3000, sqrt_price_x96);
+
+        let alice = account("alice.testnet");
+        for (lower_tick, upper_tick, liquidity) in [
+            (42000, 48000, 1_517_882_343_751_509_868_544_i128),
+            (45960, 46140, 80_000_000_000_000_000_000),
+            (-600, 46080, 3_000_000_000),
+        ] {
+            let amounts = contract.modify_position(&alice, lower_tick, upper_tick, liquidity);
+            let simulated = simulator
+                .mint(alice.as_str(), lower_tick, upper_tick, liquidity as u128)
+                .unwrap();
+            assert_eq!(amounts.map(|amount| amount as u128), simulated);
+        }
+
+        for (zero_for_one, amount_specified) in [
+            (true, 5_000_000_000_000_000_000),
+            (false, 42_000_000_000_000_000_000),
+            (true, 10_000_000_000_000_000_000),
+        ] {
+            assert_eq!(
+                contract.internal_swap(zero_for_one, amount_specified, None),
+                simulator
+                    .swap(zero_for_one, amount_specified, None)
+                    .unwrap()
+            );
+            assert_eq!(
+                contract.slot_0.sqrt_price_x96,
+                simulator.slot_0.sqrt_price_x96
+            );
+            assert_eq!(contract.slot_0.tick, simulator.slot_0.tick);
+            assert_eq!(contract.liquidity, simulator.liquidity);
+            assert_eq!(
+                [
+                    contract.fee_growth_global_0_x128,
+                    contract.fee_growth_global_1_x128
+                ],
+                [
+                    simulator.fee_growth_global_0_x128,
+                    simulator.fee_growth_global_1_x128
+                ]
+            );
+        }
+
+        let burned = contract.modify_position(&alice, 45960, 46140, -40_000_000_000_000_000_000);
+        let simulated = simulator
+            .burn(alice.as_str(), 45960, 46140, 40_000_000_000_000_000_000)
+            .unwrap();
+        assert_eq!(burned.map(i128::unsigned_abs), simulated);
+
+        let position_key = contract.get_position_key(&alice, 45960, 46140);
+        let position = contract.positions.get(&position_key).unwrap();
+        let simulated_position = simulator.position(alice.as_str(), 45960, 46140).unwrap();
+        assert_eq!(position.liquidity, simulated_position.liquidity);
+        assert_eq!(
+            position.fee_growth_inside_0_last_x128,
+            simulated_position.fee_growth_inside_0_last_x128
+        );
+        assert_eq!(
+            position.fee_growth_inside_1_last_x128,
+            simulated_position.fee_growth_inside_1_last_x128
+        );
+    }
+
+    #[test]
+    fn test_swap_through_a_gap_wider_than_the_search_window() {
+        testing_env!(VMContextBuilder::new().build());
+
+        let sqrt_price_x96 = U256::from(10 * 2_u128.pow(96));
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            JsonU256(sqrt_price_x96),
+            None,
+            None,
+        );
+        let mut simulator = zswap_simulator::Pool::new(60, 3000, sqrt_price_x96);
+
+        // the only liquidity sits more than MAX_SEARCH_WORDS words below the current tick, 46054
+        let alice = account("alice.testnet");
+        let window = tick_bitmap::MAX_SEARCH_WORDS as i32 * 256 * 60;
+        let (lower_tick, upper_tick) = (-300000, -240000);
+        assert!(46054 - upper_tick > window);
+        contract.modify_position(&alice, lower_tick, upper_tick, 10_i128.pow(24));
+        simulator
+            .mint(alice.as_str(), lower_tick, upper_tick, 10_u128.pow(24))
+            .unwrap();
+        assert_eq!(contract.liquidity, 0);
+
+        let amounts = contract.internal_swap(true, 10_u128.pow(18), None);
+        assert_eq!(amounts[0], 10_u128.pow(18));
+        assert!(amounts[1] > 0);
+        assert!((lower_tick..upper_tick).contains(&contract.slot_0.tick));
+        assert_eq!(contract.liquidity, 10_u128.pow(24));
+        assert_eq!(
+            simulator.swap(true, 10_u128.pow(18), None).unwrap(),
+            amounts
+        );
+        assert_eq!(simulator.slot_0.tick, contract.slot_0.tick);
+    }
+
+    #[test]
+    fn test_state_machine_invariants() {
+        use crate::fuzz::{Op, PoolHarness, MAX_FEE};
+
+        // xorshift64, so every run replays the same sequences
+        let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for run in 0..8 {
+            // every other pool is a dynamic-fee one
+            let mut harness = PoolHarness::with_max_fee((run % 2 == 1).then_some(MAX_FEE));
+            for _ in 0..100 {
+                let [a, b, c] = [next(), next(), next()];
+                let op = match a % 8 {
+                    0 => Op::Mint {
+                        owner: b as u8,
+                        lower: (b >> 8) as i8 / 4,
+                        width: (b >> 16) as u8,
+                        liquidity: c >> ((a >> 2) % 64),
+                    },
+                    1 => Op::Burn {
+                        position: b as u8,
+                        liquidity: c,
+                    },
+                    2 => Op::Swap {
+                        zero_for_one: b % 2 == 0,
+                        amount: c >> ((a >> 2) % 64),
+                    },
+                    3 => Op::Collect {
+                        position: b as u8,
+                        amount_0: c,
+                        amount_1: c >> 32,
+                    },
+                    4 => Op::MintLimitOrder {
+                        owner: b as u8,
+                        distance: (b >> 8) as u8,
+                        zero_for_one: b % 2 == 0,
+                        amount: c >> ((a >> 3) % 64),
+                    },
+                    5 => Op::BurnLimitOrder {
+                        order: b as u8,
+                        liquidity: c,
+                    },
+                    6 => Op::CollectLimitOrder {
+                        order: b as u8,
+                        liquidity: c,
+                    },
+                    _ => Op::NextBlock { blocks: b as u8 },
+                };
+                harness.apply(op);
+                harness.check_invariants();
+            }
+        }
+    }
+}
@@ -0,0 +1,483 @@
+use near_contract_standards::fungible_token::core::ext_ft_core;
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, log, near_bindgen, AccountId, CryptoHash, ONE_YOCTO};
+use zswap_math_library::full_math::{FullMath, FullMathTrait};
+use zswap_math_library::num256::U256;
+use zswap_math_library::tick_math::TickConstants;
+use zswap_math_library::{liquidity_math, tick_math};
+
+use crate::error::*;
+use crate::storage::StorageItem;
+use crate::utils::{LimitOrderEpoch, LimitOrderEpochView, PlacedLimitOrder};
+use crate::{Contract, ContractExt};
+
+/// Limit orders are single-sided positions one tick spacing wide, held by the pool itself.
+///
+/// Orders placed on the same range and side share an epoch. When a swap crosses the far end of
+/// the range, the pool burns the epoch's liquidity on the spot, so the orders are left holding
+/// the output token only, and each owner collects its share of it.
+#[near_bindgen]
+impl Contract {
+    /// Places a limit order selling `amount_in` of token 0 (`zero_for_one`) or token 1 on
+    /// `[lower_tick, lower_tick + tick_spacing]`, owned by `recipient`.
+    ///
+    /// Note: like `mint`, the tokens are taken from `payer`'s deposit, and the attached deposit
+    /// tops up the storage balance of `recipient`, who pays for the order
+    #[payable]
+    pub fn mint_limit_order(
+        &mut self,
+        payer: AccountId,
+        recipient: AccountId,
+        lower_tick: i32,
+        zero_for_one: bool,
+        amount_in: U128,
+    ) -> PlacedLimitOrder {
+        // the hook could not approve the liquidity the orders add
+        if self.hook.is_some() {
+            env::panic_str(LIMIT_ORDERS_NOT_AVAILABLE);
+        }
+        self.assert_not_paused();
+
+        let upper_tick = lower_tick + self.tick_spacing as i32;
+        let beyond_price = if zero_for_one {
+            self.slot_0.tick < lower_tick
+        } else {
+            self.slot_0.tick >= upper_tick
+        };
+        if lower_tick.rem_euclid(self.tick_spacing as i32) != 0 || !beyond_price {
+            env::panic_str(INVALID_LIMIT_ORDER_RANGE);
+        }
+        if lower_tick < TickConstants::MIN_TICK || upper_tick > TickConstants::MAX_TICK {
+            env::panic_str(INVALID_TICK_RANGE);
+        }
+        self.internal_storage_deposit(&recipient, env::attached_deposit());
+
+        let sqrt_price_lower_x96 = tick_math::get_sqrt_ratio_at_tick(lower_tick);
+        let sqrt_price_upper_x96 = tick_math::get_sqrt_ratio_at_tick(upper_tick);
+        let liquidity = if zero_for_one {
+            liquidity_math::get_liquidity_for_amount_0(
+                sqrt_price_lower_x96,
+                sqrt_price_upper_x96,
+                amount_in.0,
+            )
+        } else {
+            liquidity_math::get_liquidity_for_amount_1(
+                sqrt_price_lower_x96,
+                sqrt_price_upper_x96,
+                amount_in.0,
+            )
+        };
+        if liquidity == 0 {
+            env::panic_str(ZERO_LIQUIDITY);
+        }
+
+        // `recipient` pays for what the order adds, the pool's position and ticks included
+        let initial_storage = env::storage_usage();
+        let amounts = self.internal_modify_position(
+            &recipient,
+            &env::current_account_id(),
+            lower_tick,
+            upper_tick,
+            liquidity as i128,
+        );
+        let amount_in = if zero_for_one {
+            amounts[0] as u128
+        } else {
+            amounts[1] as u128
+        };
+
+        let epoch_storage = env::storage_usage();
+        let (epoch_id, mut epoch, new_epoch) =
+            match self.limit_orders.get(&(lower_tick, zero_for_one)) {
+                Some(epoch_id) => (
+                    epoch_id,
+                    self.limit_order_epochs.get(&epoch_id).unwrap(),
+                    false,
+                ),
+                None => {
+                    let epoch_id = self.next_limit_order_epoch;
+                    self.next_limit_order_epoch += 1;
+                    self.limit_orders
+                        .insert(&(lower_tick, zero_for_one), &epoch_id);
+                    let epoch = LimitOrderEpoch {
+                        lower_tick,
+                        zero_for_one,
+                        liquidity: 0,
+                        filled: false,
+                        amount_0: 0,
+                        amount_1: 0,
+                    };
+                    (epoch_id, epoch, true)
+                }
+            };
+        epoch.liquidity += liquidity;
+        self.limit_order_epochs.insert(&epoch_id, &epoch);
+        if new_epoch {
+            self.internal_pay_storage(
+                &recipient,
+                StorageItem::LimitOrderEpoch(epoch_id),
+                epoch_storage,
+            );
+        }
+
+        let share_storage = env::storage_usage();
+        let share_key = self.get_limit_order_share_key(&recipient, epoch_id);
+        let share = self.limit_order_shares.get(&share_key).unwrap_or_default();
+        self.limit_order_shares
+            .insert(&share_key, &(share + liquidity));
+        if share == 0 {
+            self.internal_pay_storage(
+                &recipient,
+                StorageItem::LimitOrderShare(share_key),
+                share_storage,
+            );
+        }
+        self.internal_assert_storage_paid(&recipient, initial_storage);
+
+        let initial_storage = env::storage_usage();
+        let caller = env::predecessor_account_id();
+        if zero_for_one {
+            self.internal_collect_token_0_to_mint(&payer, &caller, amount_in);
+        } else {
+            self.internal_collect_token_1_to_mint(&payer, &caller, amount_in);
+        }
+        self.internal_record_storage(&payer, initial_storage);
+
+        log!(
+            "Limit order placed in epoch {}: liquidity {}, amount in {}",
+            epoch_id,
+            liquidity,
+            amount_in
+        );
+
+        PlacedLimitOrder {
+            epoch: U64(epoch_id),
+            liquidity: U128(liquidity),
+            amount_in: U128(amount_in),
+        }
+    }
+
+    /// Cancels `liquidity` of the caller's order in an unfilled epoch and sends what it holds,
+    /// with its share of the fees earned so far, to `recipient`
+    #[payable]
+    pub fn burn_limit_order(
+        &mut self,
+        recipient: AccountId,
+        epoch: U64,
+        liquidity: U128,
+    ) -> [U128; 2] {
+        let owner = env::predecessor_account_id();
+        let mut limit_order_epoch =
+            self.internal_spend_limit_order_share(&owner, epoch.0, liquidity.0);
+        if limit_order_epoch.filled {
+            env::panic_str(LIMIT_ORDER_FILLED);
+        }
+
+        let lower_tick = limit_order_epoch.lower_tick;
+        let upper_tick = lower_tick + self.tick_spacing as i32;
+        let pool_id = env::current_account_id();
+        let burned = self.modify_position(&pool_id, lower_tick, upper_tick, -(liquidity.0 as i128));
+
+        // the epoch's fees accrue on the pool's position, split them by liquidity
+        let position_key = self.get_position_key(&pool_id, lower_tick, upper_tick);
+        let mut position = self.positions.get(&position_key).unwrap();
+        let fees = [position.tokens_owed_0, position.tokens_owed_1].map(|tokens_owed| {
+            FullMath::mul_div(
+                U256::from(tokens_owed),
+                U256::from(liquidity.0),
+                U256::from(limit_order_epoch.liquidity),
+            )
+            .as_u128()
+        });
+        position.tokens_owed_0 -= fees[0];
+        position.tokens_owed_1 -= fees[1];
+
+        limit_order_epoch.liquidity -= liquidity.0;
+        if limit_order_epoch.liquidity == 0 {
+            // the last order out takes the position, emptied, and the epoch with it
+            self.positions.remove(&position_key);
+            self.internal_refund_storage(StorageItem::Position(position_key));
+            self.limit_orders
+                .remove(&(lower_tick, limit_order_epoch.zero_for_one));
+            self.limit_order_epochs.remove(&epoch.0);
+            self.internal_refund_storage(StorageItem::LimitOrderEpoch(epoch.0));
+        } else {
+            self.positions.insert(&position_key, &position);
+            self.limit_order_epochs.insert(&epoch.0, &limit_order_epoch);
+        }
+
+        let amounts = [
+            burned[0].unsigned_abs() + fees[0],
+            burned[1].unsigned_abs() + fees[1],
+        ];
+        log!(
+            "Limit order cancelled in epoch {}: amount 0 {}, amount 1 {}",
+            epoch.0,
+            amounts[0],
+            amounts[1]
+        );
+        self.internal_send(&recipient, amounts);
+        amounts.map(U128)
+    }
+
+    /// Sends the caller's share of a filled epoch, for `liquidity` of its orders, to `recipient`
+    #[payable]
+    pub fn collect_limit_order(
+        &mut self,
+        recipient: AccountId,
+        epoch: U64,
+        liquidity: U128,
+    ) -> [U128; 2] {
+        let owner = env::predecessor_account_id();
+        let mut limit_order_epoch =
+            self.internal_spend_limit_order_share(&owner, epoch.0, liquidity.0);
+        if !limit_order_epoch.filled {
+            env::panic_str(LIMIT_ORDER_NOT_FILLED);
+        }
+
+        let amounts = [limit_order_epoch.amount_0, limit_order_epoch.amount_1].map(|amount| {
+            FullMath::mul_div(
+                U256::from(amount),
+                U256::from(liquidity.0),
+                U256::from(limit_order_epoch.liquidity),
+            )
+            .as_u128()
+        });
+        limit_order_epoch.amount_0 -= amounts[0];
+        limit_order_epoch.amount_1 -= amounts[1];
+        limit_order_epoch.liquidity -= liquidity.0;
+        if limit_order_epoch.liquidity == 0 {
+            self.limit_order_epochs.remove(&epoch.0);
+            self.internal_refund_storage(StorageItem::LimitOrderEpoch(epoch.0));
+        } else {
+            self.limit_order_epochs.insert(&epoch.0, &limit_order_epoch);
+        }
+
+        log!(
+            "Limit order collected in epoch {}: amount 0 {}, amount 1 {}",
+            epoch.0,
+            amounts[0],
+            amounts[1]
+        );
+        self.internal_send(&recipient, amounts);
+        amounts.map(U128)
+    }
+
+    pub fn get_limit_order_epoch(&self, epoch: U64) -> Option<LimitOrderEpochView> {
+        let limit_order_epoch = self.limit_order_epochs.get(&epoch.0)?;
+        Some(LimitOrderEpochView {
+            lower_tick: limit_order_epoch.lower_tick,
+            upper_tick: limit_order_epoch.lower_tick + self.tick_spacing as i32,
+            zero_for_one: limit_order_epoch.zero_for_one,
+            liquidity: limit_order_epoch.liquidity.into(),
+            filled: limit_order_epoch.filled,
+            amount_0: limit_order_epoch.amount_0.into(),
+            amount_1: limit_order_epoch.amount_1.into(),
+        })
+    }
+
+    pub fn get_limit_order_liquidity(&self, owner: AccountId, epoch: U64) -> U128 {
+        self.limit_order_shares
+            .get(&self.get_limit_order_share_key(&owner, epoch.0))
+            .unwrap_or_default()
+            .into()
+    }
+}
+
+impl Contract {
+    /// Fills the epoch whose range the swap just left through `crossed_tick`, if any.
+    ///
+    /// Called from the swap loop right after `tick.cross`, once the pool state is written back:
+    /// going up, the orders selling token 0 just below the tick are all token 1 now, going down,
+    /// the orders selling token 1 just above it are all token 0
+    pub fn internal_fill_limit_orders(&mut self, crossed_tick: i32, zero_for_one: bool) {
+        let key = if zero_for_one {
+            (crossed_tick, false)
+        } else {
+            (crossed_tick - self.tick_spacing as i32, true)
+        };
+        let epoch_id = match self.limit_orders.remove(&key) {
+            Some(epoch_id) => epoch_id,
+            None => return,
+        };
+        let mut epoch = self.limit_order_epochs.get(&epoch_id).unwrap();
+
+        let (lower_tick, upper_tick) = (key.0, key.0 + self.tick_spacing as i32);
+        let pool_id = env::current_account_id();
+        let burned =
+            self.modify_position(&pool_id, lower_tick, upper_tick, -(epoch.liquidity as i128));
+        let position_key = self.get_position_key(&pool_id, lower_tick, upper_tick);
+        let position = self.positions.get(&position_key).unwrap();
+        self.positions.remove(&position_key);
+        self.internal_refund_storage(StorageItem::Position(position_key));
+
+        epoch.filled = true;
+        epoch.amount_0 = burned[0].unsigned_abs() + position.tokens_owed_0;
+        epoch.amount_1 = burned[1].unsigned_abs() + position.tokens_owed_1;
+        self.limit_order_epochs.insert(&epoch_id, &epoch);
+
+        log!(
+            "Limit orders filled in epoch {}: amount 0 {}, amount 1 {}",
+            epoch_id,
+            epoch.amount_0,
+            epoch.amount_1
+        );
+    }
+
+    fn get_limit_order_share_key(&self, owner: &AccountId, epoch: u64) -> CryptoHash {
+        env::keccak256_array([owner.as_bytes(), &epoch.to_le_bytes()].concat().as_slice())
+    }
+
+    /// Takes `liquidity` off `owner`'s share of the epoch, returning the epoch
+    fn internal_spend_limit_order_share(
+        &mut self,
+        owner: &AccountId,
+        epoch: u64,
+        liquidity: u128,
+    ) -> LimitOrderEpoch {
+        let limit_order_epoch = self
+            .limit_order_epochs
+            .get(&epoch)
+            .unwrap_or_else(|| env::panic_str(LIMIT_ORDER_NOT_FOUND));
+        let share_key = self.get_limit_order_share_key(owner, epoch);
+        let share = self.limit_order_shares.get(&share_key).unwrap_or_default();
+        if liquidity == 0 || share < liquidity {
+            env::panic_str(LIMIT_ORDER_NOT_FOUND);
+        }
+
+        if share == liquidity {
+            self.limit_order_shares.remove(&share_key);
+            self.internal_refund_storage(StorageItem::LimitOrderShare(share_key));
+        } else {
+            self.limit_order_shares
+                .insert(&share_key, &(share - liquidity));
+        }
+        limit_order_epoch
+    }
+
+    fn internal_send(&self, recipient: &AccountId, amounts: [u128; 2]) {
+        for (token_id, amount) in [(&self.token_0, amounts[0]), (&self.token_1, amounts[1])] {
+            if amount > 0 {
+                ext_ft_core::ext(token_id.clone())
+                    .with_attached_deposit(ONE_YOCTO)
+                    .ft_transfer(recipient.clone(), amount.into(), None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::{U128, U64};
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, AccountId};
+    use zswap_math_library::json_types::JsonU256;
+    use zswap_math_library::num256::U256;
+    use zswap_math_library::tick_math;
+
+    use crate::Contract;
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    #[test]
+    fn test_limit_orders_fill_into_the_output_token() {
+        let pool = account("pool.testnet");
+        let manager = account("manager.testnet");
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(pool.clone())
+            .predecessor_account_id(manager.clone())
+            // pays for the orders
+            .attached_deposit(10_u128.pow(24))
+            .build());
+
+        // 1 token 0 = 100 token 1, tick ~ 46054
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            JsonU256(U256::from(10 * 2_u128.pow(96))),
+            None,
+            None,
+        );
+        contract.modify_position(
+            &account("alice.testnet"),
+            42000,
+            48000,
+            1_517_882_343_751_509_868_544,
+        );
+        contract.deposited_token_0.insert(&manager, &1_000_000);
+        contract.deposited_token_1.insert(&manager, &100_000_000);
+
+        // selling token 0 above the price, filled once the price rises past 46140
+        let placed = contract.mint_limit_order(
+            manager.clone(),
+            manager.clone(),
+            46080,
+            true,
+            U128(1_000_000),
+        );
+        assert!(placed.amount_in.0 <= 1_000_000 && placed.amount_in.0 >= 999_999);
+        // an emptied deposit is removed
+        assert_eq!(
+            contract.deposited_token_0.get(&manager).unwrap_or_default(),
+            1_000_000 - placed.amount_in.0
+        );
+        let sqrt_price_limit_x96 = JsonU256(tick_math::get_sqrt_ratio_at_tick(46100));
+        contract.internal_swap(false, 10_u128.pow(30), Some(sqrt_price_limit_x96));
+        assert!(!contract.get_limit_order_epoch(placed.epoch).unwrap().filled);
+
+        let sqrt_price_limit_x96 = JsonU256(tick_math::get_sqrt_ratio_at_tick(46200));
+        contract.internal_swap(false, 10_u128.pow(30), Some(sqrt_price_limit_x96));
+        let epoch = contract.get_limit_order_epoch(placed.epoch).unwrap();
+        assert!(epoch.filled);
+        assert_eq!(epoch.amount_0.0, 0);
+        // ~100.6 token 1 per token 0 across the range, plus the fees it earned
+        assert!(epoch.amount_1.0 > 100_600_000 && epoch.amount_1.0 < 101_000_000);
+        // the range no longer holds the orders' liquidity
+        assert_eq!(
+            contract
+                .get_position(pool.clone(), 46080, 46140)
+                .map(|position| position.liquidity.0),
+            None
+        );
+
+        let collected =
+            contract.collect_limit_order(manager.clone(), placed.epoch, placed.liquidity);
+        assert_eq!(collected, [U128(0), epoch.amount_1]);
+        assert!(contract.get_limit_order_epoch(placed.epoch).is_none());
+
+        // selling token 1 below the price, cancelled before it fills
+        let placed = contract.mint_limit_order(
+            manager.clone(),
+            manager.clone(),
+            46080,
+            false,
+            U128(50_000_000),
+        );
+        assert_eq!(placed.epoch, U64(1));
+        let half = U128(placed.liquidity.0 / 2);
+        let burned = contract.burn_limit_order(manager.clone(), placed.epoch, half);
+        assert_eq!(burned[0].0, 0);
+        assert!(burned[1].0 >= 24_999_998 && burned[1].0 <= 25_000_000);
+        assert_eq!(
+            contract
+                .get_limit_order_liquidity(manager.clone(), placed.epoch)
+                .0,
+            placed.liquidity.0 - half.0
+        );
+
+        // the rest fills when the price falls through 46080
+        contract.internal_swap(
+            true,
+            10_u128.pow(30),
+            Some(JsonU256(tick_math::get_sqrt_ratio_at_tick(46000))),
+        );
+        let epoch = contract.get_limit_order_epoch(placed.epoch).unwrap();
+        assert!(epoch.filled);
+        assert_eq!(epoch.amount_1.0, 0);
+        assert!(epoch.amount_0.0 > 0);
+    }
+}
@@ -0,0 +1,294 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near_bindgen, AccountId, CryptoHash};
+use zswap_math_library::json_types::JsonU256;
+use zswap_math_library::num256::U256;
+use zswap_math_library::position::PositionInfo;
+use zswap_math_library::tick::TickInfo;
+
+use crate::error::*;
+use crate::{Contract, ContractExt, Slot0, StorageKey};
+
+/// Version of the pool's state layout, kept beside the state under `STATE_VERSION_KEY` so that
+/// `migrate` knows which layout it reads. Pools deployed without it are version 0
+pub const STATE_VERSION: u8 = 1;
+const STATE_VERSION_KEY: &[u8] = b"VERSION";
+
+#[derive(BorshDeserialize, BorshSerialize)]
+struct Slot0V0 {
+    sqrt_price_x96: U128,
+    tick: i32,
+}
+
+/// State of a pool deployed before dynamic fees, hooks, pausing, limit orders and storage
+/// management
+#[derive(BorshDeserialize, BorshSerialize)]
+struct ContractV0 {
+    factory: AccountId,
+    token_0: AccountId,
+    token_1: AccountId,
+    deposited_token_0: LookupMap<AccountId, u128>,
+    deposited_token_1: LookupMap<AccountId, u128>,
+    approved_token_0: LookupMap<AccountId, AccountId>,
+    approved_token_1: LookupMap<AccountId, AccountId>,
+
+    tick_spacing: u32,
+    fee: u32,
+
+    fee_growth_global_0_x128: u128,
+    fee_growth_global_1_x128: u128,
+
+    slot_0: Slot0V0,
+    liquidity: u128,
+
+    ticks: LookupMap<i32, TickInfo>,
+    tick_bitmap: LookupMap<i16, U256>,
+    positions: LookupMap<CryptoHash, PositionInfo>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Moves the pool's state to the current layout once its new code is deployed, run by the
+    /// pool itself with `--initFunction migrate`.
+    ///
+    /// Note: the old approvals named a spender for all of an owner's tokens and can't be listed,
+    /// so they are dropped, owners approve allowances again. Accounts holding deposits start
+    /// unregistered, the pool keeps paying for what they hold until they register
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        if Self::state_version() != 0 {
+            env::panic_str(ALREADY_MIGRATED);
+        }
+        let old: ContractV0 =
+            env::state_read().unwrap_or_else(|| env::panic_str(NOTHING_TO_MIGRATE));
+
+        // the allowances are keyed by hash, they can't meet the old approvals under the prefix
+        let contract = Self {
+            factory: old.factory,
+            token_0: old.token_0.clone(),
+            token_1: old.token_1.clone(),
+            deposited_token_0: old.deposited_token_0,
+            deposited_token_1: old.deposited_token_1,
+            approved_token_0: LookupMap::new(StorageKey::ApprovedToken {
+                token_id: old.token_0,
+            }),
+            approved_token_1: LookupMap::new(StorageKey::ApprovedToken {
+                token_id: old.token_1,
+            }),
+
+            tick_spacing: old.tick_spacing,
+            fee: old.fee,
+            dynamic_fee: None,
+            hook: None,
+            paused: false,
+            fee_growth_global_0_x128: old.fee_growth_global_0_x128,
+            fee_growth_global_1_x128: old.fee_growth_global_1_x128,
+            slot_0: Slot0 {
+                sqrt_price_x96: JsonU256(U256::from(old.slot_0.sqrt_price_x96.0)),
+                tick: old.slot_0.tick,
+                fee: old.fee,
+            },
+            liquidity: old.liquidity,
+            ticks: old.ticks,
+            tick_bitmap: old.tick_bitmap,
+            positions: old.positions,
+            limit_orders: LookupMap::new(StorageKey::LimitOrders),
+            limit_order_epochs: LookupMap::new(StorageKey::LimitOrderEpochs),
+            limit_order_shares: LookupMap::new(StorageKey::LimitOrderShares),
+            next_limit_order_epoch: 0,
+            storage_accounts: LookupMap::new(StorageKey::StorageAccounts),
+            storage_payers: LookupMap::new(StorageKey::StoragePayers),
+        };
+        Self::write_state_version();
+        log!("Migrated the pool state to version {}", STATE_VERSION);
+        contract
+    }
+}
+
+impl Contract {
+    pub fn state_version() -> u8 {
+        env::storage_read(STATE_VERSION_KEY).map_or(0, |version| version[0])
+    }
+
+    pub fn write_state_version() {
+        env::storage_write(STATE_VERSION_KEY, &[STATE_VERSION]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+    use zswap_math_library::tick_math;
+
+    use super::*;
+    use crate::core_trait::CoreZswapPool;
+
+    // set when `test_migrate_twice` runs in a process of its own
+    const MIGRATE_TWICE: &str = "ZSWAP_MIGRATE_TWICE";
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    fn called_by(id: &str, attached_deposit: u128) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account("pool.factory.testnet"))
+            .predecessor_account_id(account(id))
+            .attached_deposit(attached_deposit)
+            .build());
+    }
+
+    /// Writes the state of a pool deployed before `STATE_VERSION`, with a position and deposits
+    fn write_v0_pool() {
+        let (token_0, token_1) = (account("token0.testnet"), account("token1.testnet"));
+        let alice = account("alice.testnet");
+        let mut ticks = LookupMap::new(StorageKey::Pools);
+        let mut tick_bitmap = LookupMap::new(StorageKey::Pools);
+        let mut positions = LookupMap::new(StorageKey::Pools);
+        let liquidity = 1_517_882_343_751_509_868_544;
+        for (tick, liquidity_net) in [(42000, liquidity as i128), (48000, -(liquidity as i128))] {
+            ticks.insert(
+                &tick,
+                &TickInfo {
+                    liquidity_gross: liquidity,
+                    liquidity_net,
+                    initialized: true,
+                    ..Default::default()
+                },
+            );
+            zswap_math_library::tick_bitmap::flip_tick(&mut tick_bitmap, tick, 60);
+        }
+        let position_key = env::keccak256_array(
+            [
+                alice.as_bytes(),
+                &42000_i32.to_le_bytes(),
+                &48000_i32.to_le_bytes(),
+            ]
+            .concat()
+            .as_slice(),
+        );
+        positions.insert(
+            &position_key,
+            &PositionInfo {
+                liquidity,
+                ..Default::default()
+            },
+        );
+
+        let mut deposited_token_0 = LookupMap::new(StorageKey::DepositedToken {
+            token_id: token_0.clone(),
+        });
+        deposited_token_0.insert(&alice, &10_u128.pow(24));
+        let mut approved_token_0 = LookupMap::new(StorageKey::ApprovedToken {
+            token_id: token_0.clone(),
+        });
+        approved_token_0.insert(&alice, &account("manager.testnet"));
+
+        let sqrt_price_x96 = 10 * 2_u128.pow(96);
+        env::state_write(&ContractV0 {
+            factory: account("factory.testnet"),
+            token_0: token_0.clone(),
+            token_1: token_1.clone(),
+            deposited_token_0,
+            deposited_token_1: LookupMap::new(StorageKey::DepositedToken {
+                token_id: token_1.clone(),
+            }),
+            approved_token_0,
+            approved_token_1: LookupMap::new(StorageKey::ApprovedToken { token_id: token_1 }),
+            tick_spacing: 60,
+            fee: 3000,
+            fee_growth_global_0_x128: 0,
+            fee_growth_global_1_x128: 0,
+            slot_0: Slot0V0 {
+                sqrt_price_x96: U128(sqrt_price_x96),
+                tick: tick_math::get_tick_at_sqrt_ratio(U256::from(sqrt_price_x96)),
+            },
+            liquidity,
+            ticks,
+            tick_bitmap,
+            positions,
+        });
+    }
+
+    #[test]
+    fn test_migrate_from_version_0() {
+        called_by("pool.factory.testnet", 0);
+        write_v0_pool();
+        assert_eq!(Contract::state_version(), 0);
+
+        let mut contract = Contract::migrate();
+        assert_eq!(Contract::state_version(), STATE_VERSION);
+        let alice = account("alice.testnet");
+        let slot_0 = contract.get_slot_0();
+        assert_eq!(slot_0.tick, 46054);
+        assert_eq!(slot_0.fee, 3000);
+        assert_eq!(contract.get_liquidity().0, 1_517_882_343_751_509_868_544);
+        assert_eq!(
+            contract
+                .get_position(alice.clone(), 42000, 48000)
+                .unwrap()
+                .liquidity
+                .0,
+            1_517_882_343_751_509_868_544
+        );
+        assert_eq!(
+            contract.deposited_token_0.get(&alice),
+            Some(10_u128.pow(24))
+        );
+        assert!(!contract.is_paused());
+
+        // the migrated pool swaps through the old ticks, and takes limit orders and registrations
+        contract.internal_swap(true, 10_u128.pow(18), None);
+        assert!(contract.get_slot_0().tick < 46054);
+        called_by("alice.testnet", 10_u128.pow(24));
+        contract.storage_deposit(None, None);
+        let placed = contract.mint_limit_order(alice.clone(), alice, 46080, true, U128(1_000_000));
+        assert_eq!(placed.epoch.0, 0);
+    }
+
+    #[test]
+    fn test_new_pools_are_at_the_current_version() {
+        called_by("factory.testnet", 0);
+        Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            JsonU256(U256::from(10 * 2_u128.pow(96))),
+            None,
+            None,
+        );
+        assert_eq!(Contract::state_version(), STATE_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_twice() {
+        if std::env::var(MIGRATE_TWICE).is_err() {
+            // `env::panic_str` aborts the test binary, so the second migration runs on its own
+            let output = Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "migrate::tests::test_migrate_twice",
+                    "--exact",
+                    "--nocapture",
+                ])
+                .env(MIGRATE_TWICE, "1")
+                .output()
+                .unwrap();
+            assert!(!output.status.success());
+            assert!(String::from_utf8_lossy(&output.stderr).contains(ALREADY_MIGRATED));
+            return;
+        }
+
+        called_by("pool.factory.testnet", 0);
+        write_v0_pool();
+        let contract = Contract::migrate();
+        env::state_write(&contract);
+        Contract::migrate();
+    }
+}
@@ -0,0 +1,198 @@
+use near_sdk::serde_json::json;
+use near_sdk::{env, log, near_bindgen};
+
+use crate::error::{NOT_AUTHORIZED, POOL_PAUSED};
+use crate::{Contract, ContractExt};
+
+#[near_bindgen]
+impl Contract {
+    /// Pauses or unpauses the pool. Called by the factory, on its guardian's request
+    pub fn set_paused(&mut self, paused: bool) {
+        if env::predecessor_account_id() != self.factory {
+            env::panic_str(NOT_AUTHORIZED);
+        }
+        if paused != self.paused {
+            self.paused = paused;
+            // NEP-297 event, so indexers can follow the pool's state
+            log!(
+                "EVENT_JSON:{}",
+                json!({
+                    "standard": "zswap",
+                    "version": "1.0.0",
+                    "event": if paused { "pool_paused" } else { "pool_unpaused" },
+                    "data": [{ "pool_id": env::current_account_id() }],
+                })
+            );
+        }
+    }
+
+    /// A paused pool rejects swaps and mints, its liquidity can still be burned and collected
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+impl Contract {
+    pub fn assert_not_paused(&self) {
+        if self.paused {
+            env::panic_str(POOL_PAUSED);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, AccountId, PromiseOrValue};
+    use zswap_math_library::json_types::JsonU256;
+    use zswap_math_library::num256::U256;
+
+    use crate::core_trait::CoreZswapPool;
+    use crate::error::POOL_PAUSED;
+    use crate::Contract;
+
+    // names the call `test_rejected_while_paused` makes when run by `assert_rejected_while_paused`
+    const PAUSED_CALL: &str = "ZSWAP_PAUSED_CALL";
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    fn called_by(id: &str) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account("pool.factory.testnet"))
+            .predecessor_account_id(account(id))
+            .attached_deposit(10_u128.pow(24))
+            .build());
+    }
+
+    fn pool() -> Contract {
+        called_by("factory.testnet");
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            JsonU256(U256::from(10 * 2_u128.pow(96))),
+            None,
+            None,
+        );
+        let alice = account("alice.testnet");
+        contract.deposited_token_0.insert(&alice, &10_u128.pow(24));
+        contract.deposited_token_1.insert(&alice, &10_u128.pow(24));
+        contract
+    }
+
+    // `env::panic_str` aborts the test binary, so the call runs in a process of its own
+    fn assert_rejected_while_paused(call: &str) {
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args([
+                "pause::tests::test_rejected_while_paused",
+                "--exact",
+                "--nocapture",
+            ])
+            .env(PAUSED_CALL, call)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{} went through", call);
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains(POOL_PAUSED),
+            "{} failed for another reason",
+            call
+        );
+    }
+
+    #[test]
+    fn test_rejected_while_paused() {
+        let call = match std::env::var(PAUSED_CALL) {
+            Ok(call) => call,
+            Err(_) => return,
+        };
+        let mut contract = pool();
+        called_by("factory.testnet");
+        contract.set_paused(true);
+
+        let alice = account("alice.testnet");
+        called_by("pool.factory.testnet");
+        match call.as_str() {
+            "swap" => {
+                contract.swap(alice, true, U128(1000), None, None);
+            }
+            "mint" => {
+                contract.mint(alice.clone(), alice, 42000, 48000, U128(1000));
+            }
+            "swap_after_hook" => {
+                contract.swap_after_hook(
+                    Ok(None),
+                    alice.clone(),
+                    alice,
+                    true,
+                    U128(1000),
+                    None,
+                    None,
+                );
+            }
+            "mint_after_hook" => {
+                contract.mint_after_hook(
+                    Ok(()),
+                    alice.clone(),
+                    alice.clone(),
+                    alice,
+                    42000,
+                    48000,
+                    U128(1000),
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_paused_pool_rejects_swaps_and_mints() {
+        for call in ["swap", "mint", "swap_after_hook", "mint_after_hook"] {
+            assert_rejected_while_paused(call);
+        }
+    }
+
+    #[test]
+    fn test_paused_pool_lets_liquidity_out() {
+        let mut contract = pool();
+        let alice = account("alice.testnet");
+
+        called_by("alice.testnet");
+        contract.mint(
+            alice.clone(),
+            alice.clone(),
+            42000,
+            48000,
+            U128(1_517_882_343_751_509_868_544),
+        );
+
+        called_by("factory.testnet");
+        contract.set_paused(true);
+        assert!(contract.is_paused());
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"pool_id":"pool.factory.testnet"}],"event":"pool_paused","standard":"zswap","version":"1.0.0"}"#
+            ]
+        );
+
+        // burns and collects go through
+        called_by("alice.testnet");
+        let amounts = match contract.burn(42000, 48000, U128(1_517_882_343_751_509_868_544)) {
+            PromiseOrValue::Value(amounts) => amounts,
+            PromiseOrValue::Promise(_) => unreachable!(),
+        };
+        assert!(amounts[0].0 > 0 && amounts[1].0 > 0);
+        let collected = contract.collect(alice, 42000, 48000, amounts[0], amounts[1]);
+        assert_eq!(collected, amounts);
+
+        called_by("factory.testnet");
+        contract.set_paused(false);
+        assert!(!contract.is_paused());
+    }
+}
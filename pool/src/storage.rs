@@ -0,0 +1,599 @@
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{
+    assert_one_yocto, env, log, near_bindgen, AccountId, Balance, CryptoHash, Promise, StorageUsage,
+};
+
+use crate::error::*;
+use crate::{Contract, ContractExt};
+
+/// Bytes of an account's own storage record, with the longest account id
+const ACCOUNT_STORAGE: StorageUsage = 200;
+
+/// NEAR an account deposited for its storage in the pool, and the bytes its state takes.
+///
+/// Accounts pay for their deposits and allowances, and for the positions and limit orders they
+/// own, along with the ticks those initialize. Removing state gives its storage back
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StorageAccount {
+    pub deposit: Balance,
+    pub used: StorageUsage,
+}
+
+/// State shared between accounts, or removed by others than its owner, whose storage goes back
+/// to the account that paid for it whoever removes it
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum StorageItem {
+    Tick(i32),
+    TickBitmapWord(i16),
+    Position(CryptoHash),
+    LimitOrderEpoch(u64),
+    LimitOrderShare(CryptoHash),
+}
+
+/// Who paid for a `StorageItem`, and the bytes it takes with this record
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StoragePayer {
+    pub account_id: AccountId,
+    pub bytes: StorageUsage,
+}
+
+impl StorageAccount {
+    fn available(&self) -> Balance {
+        self.deposit
+            .saturating_sub(Contract::min_storage_balance())
+            .saturating_sub(self.used as Balance * env::storage_byte_cost())
+    }
+
+    fn to_view(&self) -> StorageBalance {
+        StorageBalance {
+            total: U128(self.deposit),
+            available: U128(self.available()),
+        }
+    }
+}
+
+#[near_bindgen]
+impl StorageManagement for Contract {
+    /// Registers `account_id`, or the caller, with the attached deposit, or adds it to their
+    /// storage balance
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_only = registration_only.unwrap_or(false);
+        let min_balance = Self::min_storage_balance();
+
+        let refund = match self.storage_accounts.get(&account_id) {
+            Some(_) if registration_only => amount,
+            Some(_) => {
+                self.internal_storage_deposit(&account_id, amount);
+                0
+            }
+            None => {
+                let deposit = if registration_only {
+                    amount.min(min_balance)
+                } else {
+                    amount
+                };
+                self.internal_storage_deposit(&account_id, deposit);
+                amount - deposit
+            }
+        };
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+
+        self.storage_accounts.get(&account_id).unwrap().to_view()
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut storage_account = self
+            .storage_accounts
+            .get(&account_id)
+            .unwrap_or_else(|| env::panic_str(STORAGE_NOT_REGISTERED));
+
+        let available = storage_account.available();
+        let amount = amount.map_or(available, |amount| amount.0);
+        if amount > available {
+            env::panic_str(NOT_ENOUGH_STORAGE_BALANCE);
+        }
+        storage_account.deposit -= amount;
+        self.storage_accounts.insert(&account_id, &storage_account);
+        if amount > 0 {
+            Promise::new(account_id).transfer(amount);
+        }
+
+        storage_account.to_view()
+    }
+
+    /// Unregisters the caller once the pool holds nothing of theirs. `force` is not supported,
+    /// positions and deposits can't be given up
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        if force.unwrap_or(false) {
+            env::panic_str(FORCE_UNREGISTER_NOT_SUPPORTED);
+        }
+
+        let account_id = env::predecessor_account_id();
+        let storage_account = match self.storage_accounts.get(&account_id) {
+            Some(storage_account) => storage_account,
+            None => return false,
+        };
+        if storage_account.used > 0 {
+            env::panic_str(STORAGE_STILL_USED);
+        }
+
+        self.storage_accounts.remove(&account_id);
+        Promise::new(account_id).transfer(storage_account.deposit);
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128(Self::min_storage_balance()),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts
+            .get(&account_id)
+            .map(|storage_account| storage_account.to_view())
+    }
+}
+
+impl Contract {
+    pub fn min_storage_balance() -> Balance {
+        ACCOUNT_STORAGE as Balance * env::storage_byte_cost()
+    }
+
+    /// Adds `amount` to the storage balance of `account_id`, registering them if they aren't
+    /// and `amount` covers it
+    pub fn internal_storage_deposit(&mut self, account_id: &AccountId, amount: Balance) {
+        if amount == 0 {
+            return;
+        }
+
+        let mut storage_account = match self.storage_accounts.get(account_id) {
+            Some(storage_account) => storage_account,
+            None if amount >= Self::min_storage_balance() => StorageAccount {
+                deposit: 0,
+                used: 0,
+            },
+            None => env::panic_str(NOT_ENOUGH_STORAGE_DEPOSIT),
+        };
+        storage_account.deposit += amount;
+        self.storage_accounts.insert(account_id, &storage_account);
+    }
+
+    /// Charges `account_id` for the storage the pool grew by since `initial_storage`, or gives
+    /// back what it shrank by. Panics if their storage balance doesn't cover it
+    pub fn internal_charge_storage(
+        &mut self,
+        account_id: &AccountId,
+        initial_storage: StorageUsage,
+    ) {
+        self.internal_record_storage(account_id, initial_storage);
+        self.internal_assert_storage_paid(account_id, initial_storage);
+    }
+
+    /// Panics if the pool grew since `initial_storage` and `account_id`'s storage balance
+    /// doesn't cover what they use
+    pub fn internal_assert_storage_paid(
+        &self,
+        account_id: &AccountId,
+        initial_storage: StorageUsage,
+    ) {
+        if env::storage_usage() <= initial_storage {
+            return;
+        }
+
+        let storage_account = self
+            .storage_accounts
+            .get(account_id)
+            .unwrap_or_else(|| env::panic_str(STORAGE_NOT_REGISTERED));
+        let needed = Self::min_storage_balance()
+            + storage_account.used as Balance * env::storage_byte_cost();
+        if storage_account.deposit < needed {
+            env::panic_str(&format!(
+                "{}: {} needs {} yⓃ more",
+                NOT_ENOUGH_STORAGE_BALANCE,
+                account_id,
+                needed - storage_account.deposit
+            ));
+        }
+    }
+
+    /// Like `internal_charge_storage`, without failing, for callbacks that must not revert.
+    /// What an account's balance doesn't cover is paid by the pool
+    pub fn internal_record_storage(
+        &mut self,
+        account_id: &AccountId,
+        initial_storage: StorageUsage,
+    ) {
+        let storage_usage = env::storage_usage();
+        if storage_usage == initial_storage {
+            return;
+        }
+
+        let mut storage_account = match self.storage_accounts.get(account_id) {
+            Some(storage_account) => storage_account,
+            None => {
+                log!(
+                    "{} is not registered, the pool pays its storage",
+                    account_id
+                );
+                return;
+            }
+        };
+        if storage_usage > initial_storage {
+            storage_account.used += storage_usage - initial_storage;
+        } else {
+            storage_account.used = storage_account
+                .used
+                .saturating_sub(initial_storage - storage_usage);
+        }
+        self.storage_accounts.insert(account_id, &storage_account);
+    }
+
+    /// Records `payer` as the owner of `item`, created since `initial_storage`, and adds its bytes
+    /// to what they use. Like `internal_record_storage`, the pool pays for unregistered accounts
+    pub fn internal_pay_storage(
+        &mut self,
+        payer: &AccountId,
+        item: StorageItem,
+        initial_storage: StorageUsage,
+    ) {
+        let mut storage_account = match self.storage_accounts.get(payer) {
+            Some(storage_account) => storage_account,
+            None => {
+                log!("{} is not registered, the pool pays its storage", payer);
+                return;
+            }
+        };
+
+        let mut storage_payer = StoragePayer {
+            account_id: payer.clone(),
+            bytes: 0,
+        };
+        self.storage_payers.insert(&item, &storage_payer);
+        storage_payer.bytes = env::storage_usage().saturating_sub(initial_storage);
+        self.storage_payers.insert(&item, &storage_payer);
+
+        storage_account.used += storage_payer.bytes;
+        self.storage_accounts.insert(payer, &storage_account);
+    }
+
+    /// Gives the storage of the removed `item` back to whoever paid for it
+    pub fn internal_refund_storage(&mut self, item: StorageItem) {
+        let storage_payer = match self.storage_payers.remove(&item) {
+            Some(storage_payer) => storage_payer,
+            None => return,
+        };
+        if let Some(mut storage_account) = self.storage_accounts.get(&storage_payer.account_id) {
+            storage_account.used = storage_account.used.saturating_sub(storage_payer.bytes);
+            self.storage_accounts
+                .insert(&storage_payer.account_id, &storage_account);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{env, testing_env, AccountId, Balance, ONE_YOCTO};
+    use zswap_math_library::json_types::JsonU256;
+    use zswap_math_library::num256::U256;
+    use zswap_math_library::{pool_account, tick_math};
+
+    use crate::core_trait::CoreZswapPool;
+    use crate::Contract;
+
+    fn account(id: &str) -> AccountId {
+        AccountId::new_unchecked(id.to_string())
+    }
+
+    fn called_by(id: &str, attached_deposit: Balance) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account("pool.testnet"))
+            .predecessor_account_id(account(id))
+            .attached_deposit(attached_deposit)
+            .build());
+    }
+
+    #[test]
+    fn test_init_storage_is_what_the_factory_charges() {
+        // the longest ids and every optional field
+        let long_account = |c: char| account(&c.to_string().repeat(64));
+        called_by(&"f".repeat(64), 0);
+        let initial_storage = env::storage_usage();
+        let contract = Contract::new(
+            long_account('a'),
+            long_account('b'),
+            60,
+            3000,
+            JsonU256(U256::from(10 * 2_u128.pow(96))),
+            Some(10000),
+            Some(long_account('h')),
+        );
+        env::state_write(&contract);
+        assert_eq!(
+            env::storage_usage() - initial_storage,
+            pool_account::POOL_STATE_STORAGE
+        );
+    }
+
+    #[test]
+    fn test_position_and_limit_order_storage() {
+        let long_account = |c: char| account(&c.to_string().repeat(64));
+        let manager = long_account('m');
+        called_by(&"f".repeat(64), 0);
+        let mut contract = Contract::new(
+            long_account('a'),
+            long_account('b'),
+            60,
+            3000,
+            JsonU256(U256::from(2_u128.pow(96))),
+            None,
+            None,
+        );
+        contract
+            .deposited_token_0
+            .insert(&manager, &10_u128.pow(30));
+        contract
+            .deposited_token_1
+            .insert(&manager, &10_u128.pow(30));
+
+        // fresh ticks in different bitmap words
+        called_by(manager.as_str(), 10_u128.pow(24));
+        let initial_storage = env::storage_usage();
+        contract.mint(
+            manager.clone(),
+            manager.clone(),
+            -887220,
+            887220,
+            U128(10_u128.pow(20)),
+        );
+        assert_eq!(
+            env::storage_usage() - initial_storage,
+            pool_account::POOL_POSITION_STORAGE
+        );
+
+        for (lower_tick, zero_for_one) in [(-600000, false), (600000, true)] {
+            let initial_storage = env::storage_usage();
+            contract.mint_limit_order(
+                manager.clone(),
+                manager.clone(),
+                lower_tick,
+                zero_for_one,
+                U128(10_u128.pow(20)),
+            );
+            assert_eq!(
+                env::storage_usage() - initial_storage,
+                pool_account::POOL_LIMIT_ORDER_STORAGE
+            );
+        }
+    }
+
+    #[test]
+    fn test_storage_is_charged_and_given_back() {
+        called_by("alice.testnet", 0);
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            JsonU256(U256::from(10 * 2_u128.pow(96))),
+            None,
+            None,
+        );
+        let alice = account("alice.testnet");
+        let min_balance = contract.storage_balance_bounds().min.0;
+        assert!(contract.storage_balance_of(alice.clone()).is_none());
+
+        called_by("alice.testnet", 10_u128.pow(24));
+        let balance = contract.storage_deposit(None, None);
+        assert_eq!(balance.total.0, 10_u128.pow(24));
+        assert_eq!(balance.available.0, 10_u128.pow(24) - min_balance);
+
+        // deposits are paid for, and given back once withdrawn
+        for token in ["token0.testnet", "token1.testnet"] {
+            called_by(token, 0);
+            contract.ft_on_transfer(alice.clone(), U128(10_u128.pow(24)), String::new());
+        }
+        let with_deposits = contract.storage_balance_of(alice.clone()).unwrap();
+        assert!(with_deposits.available.0 < balance.available.0);
+
+        called_by("alice.testnet", 0);
+        let liquidity = U128(1_517_882_343_751_509_868_544);
+        contract.mint(alice.clone(), alice.clone(), 42000, 48000, liquidity);
+        let with_position = contract.storage_balance_of(alice.clone()).unwrap();
+        assert!(with_position.available.0 < with_deposits.available.0);
+
+        // burning and collecting everything removes the position and its ticks
+        let position = contract.get_position(alice.clone(), 42000, 48000).unwrap();
+        contract.burn(42000, 48000, position.liquidity);
+        let position = contract.get_position(alice.clone(), 42000, 48000).unwrap();
+        contract.collect(
+            alice.clone(),
+            42000,
+            48000,
+            position.tokens_owed_0,
+            position.tokens_owed_1,
+        );
+        assert!(contract.get_position(alice.clone(), 42000, 48000).is_none());
+        assert!(contract.get_tick(42000).is_none() && contract.get_tick(48000).is_none());
+        assert_eq!(
+            contract
+                .storage_balance_of(alice.clone())
+                .unwrap()
+                .available,
+            with_deposits.available
+        );
+
+        called_by("alice.testnet", ONE_YOCTO);
+        let deposited_0 = contract.deposited_token_0.get(&alice).unwrap();
+        let deposited_1 = contract.deposited_token_1.get(&alice).unwrap();
+        contract.withdraw(account("token0.testnet"), U128(deposited_0));
+        contract.withdraw(account("token1.testnet"), U128(deposited_1));
+        assert_eq!(
+            contract
+                .storage_balance_of(alice.clone())
+                .unwrap()
+                .available,
+            balance.available
+        );
+
+        contract.storage_withdraw(None);
+        assert_eq!(
+            contract.storage_balance_of(alice.clone()).unwrap().total.0,
+            min_balance
+        );
+        assert!(contract.storage_unregister(None));
+        assert!(contract.storage_balance_of(alice).is_none());
+    }
+
+    #[test]
+    fn test_shared_ticks_go_back_to_whoever_paid_for_them() {
+        called_by("alice.testnet", 0);
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            JsonU256(U256::from(10 * 2_u128.pow(96))),
+            None,
+            None,
+        );
+        let (alice, bob) = (account("alice.testnet"), account("bob.testnet"));
+        let mut registered = Vec::new();
+        for owner in [&alice, &bob] {
+            called_by(owner.as_str(), 10_u128.pow(24));
+            contract.storage_deposit(None, None);
+            for token in ["token0.testnet", "token1.testnet"] {
+                called_by(token, 0);
+                contract.ft_on_transfer(owner.clone(), U128(10_u128.pow(24)), String::new());
+            }
+            registered.push(contract.storage_balance_of(owner.clone()).unwrap());
+        }
+        let available = |contract: &Contract, owner: &AccountId| {
+            contract
+                .storage_balance_of(owner.clone())
+                .unwrap()
+                .available
+                .0
+        };
+
+        // Alice initializes the ticks, Bob only pays for his position on them
+        for owner in [&alice, &bob] {
+            called_by(owner.as_str(), 0);
+            contract.mint(
+                owner.clone(),
+                owner.clone(),
+                42000,
+                48000,
+                U128(10_u128.pow(18)),
+            );
+        }
+        let alice_paid = registered[0].available.0 - available(&contract, &alice);
+        let bob_paid = registered[1].available.0 - available(&contract, &bob);
+        assert!(bob_paid < alice_paid);
+
+        let exit = |contract: &mut Contract, owner: &AccountId| {
+            called_by(owner.as_str(), 0);
+            let position = contract.get_position(owner.clone(), 42000, 48000).unwrap();
+            contract.burn(42000, 48000, position.liquidity);
+            let position = contract.get_position(owner.clone(), 42000, 48000).unwrap();
+            contract.collect(
+                owner.clone(),
+                42000,
+                48000,
+                position.tokens_owed_0,
+                position.tokens_owed_1,
+            );
+        };
+
+        // Alice leaving first keeps paying for the ticks Bob still uses
+        exit(&mut contract, &alice);
+        assert!(contract.get_tick(42000).is_some());
+        let alice_still_pays = registered[0].available.0 - available(&contract, &alice);
+        assert!(0 < alice_still_pays && alice_still_pays < alice_paid);
+        assert_eq!(
+            registered[1].available.0 - available(&contract, &bob),
+            bob_paid
+        );
+
+        // Bob clears them, and the storage goes back to Alice, not to him
+        exit(&mut contract, &bob);
+        assert!(contract.get_tick(42000).is_none() && contract.get_tick(48000).is_none());
+        assert_eq!(available(&contract, &alice), registered[0].available.0);
+        assert_eq!(available(&contract, &bob), registered[1].available.0);
+
+        called_by("alice.testnet", ONE_YOCTO);
+        let deposited_0 = contract.deposited_token_0.get(&alice).unwrap();
+        let deposited_1 = contract.deposited_token_1.get(&alice).unwrap();
+        contract.withdraw(account("token0.testnet"), U128(deposited_0));
+        contract.withdraw(account("token1.testnet"), U128(deposited_1));
+        assert!(contract.storage_unregister(None));
+    }
+
+    #[test]
+    fn test_filled_limit_orders_give_storage_back() {
+        called_by("alice.testnet", 0);
+        let mut contract = Contract::new(
+            account("token0.testnet"),
+            account("token1.testnet"),
+            60,
+            3000,
+            JsonU256(U256::from(10 * 2_u128.pow(96))),
+            None,
+            None,
+        );
+        let alice = account("alice.testnet");
+        contract.modify_position(&alice, 42000, 48000, 1_517_882_343_751_509_868_544);
+
+        called_by("bob.testnet", 10_u128.pow(24));
+        contract.storage_deposit(None, None);
+        called_by("token0.testnet", 0);
+        let bob = account("bob.testnet");
+        contract.ft_on_transfer(bob.clone(), U128(10_u128.pow(24)), String::new());
+        let registered = contract.storage_balance_of(bob.clone()).unwrap();
+
+        called_by("bob.testnet", 0);
+        let placed =
+            contract.mint_limit_order(bob.clone(), bob.clone(), 46080, true, U128(10_u128.pow(18)));
+        assert!(
+            contract
+                .storage_balance_of(bob.clone())
+                .unwrap()
+                .available
+                .0
+                < registered.available.0
+        );
+
+        // a trader's swap fills the order and clears its ticks, Bob gets their storage back
+        let sqrt_price_limit_x96 = JsonU256(tick_math::get_sqrt_ratio_at_tick(46200));
+        contract.internal_swap(false, u128::MAX / 2, Some(sqrt_price_limit_x96));
+        assert!(contract.get_tick(46080).is_none() && contract.get_tick(46140).is_none());
+        contract.collect_limit_order(bob.clone(), placed.epoch, placed.liquidity);
+        assert_eq!(
+            contract.storage_balance_of(bob).unwrap().available,
+            registered.available
+        );
+    }
+}
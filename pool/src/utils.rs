@@ -1,33 +1,72 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::json_types::U128;
+use near_sdk::json_types::{I128, U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use zswap_math_library::json_types::JsonU256;
 
 // First slot will contain essential data
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Slot0 {
     // Current sqrt(P)
-    pub sqrt_price_x96: U128,
+    pub sqrt_price_x96: JsonU256,
     // Current tick
     pub tick: i32,
+    // Fee the next swap pays, in hundredths of a bip
+    pub fee: u32,
 }
 
-pub struct SwapState {
-    pub amount_specified_remaining: u128,
-    pub amount_calculated: u128,
-    pub sqrt_price_x96: u128,
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TickView {
     pub tick: i32,
-    pub fee_growth_global_x128: u128,
+    pub liquidity_gross: U128,
+    pub liquidity_net: I128,
+    pub fee_growth_outside_0_x128: U128,
+    pub fee_growth_outside_1_x128: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PositionView {
+    pub liquidity: U128,
+    pub fee_growth_inside_0_last_x128: U128,
+    pub fee_growth_inside_1_last_x128: U128,
+    pub tokens_owed_0: U128,
+    pub tokens_owed_1: U128,
+}
+
+/// The limit orders resting on one range and side, filled together when a swap crosses it
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LimitOrderEpoch {
+    pub lower_tick: i32,
+    /// Sells token 0 for token 1
+    pub zero_for_one: bool,
+    /// Liquidity of the orders not yet cancelled or collected
     pub liquidity: u128,
+    pub filled: bool,
+    /// What that liquidity was burned into when filled, fees included
+    pub amount_0: u128,
+    pub amount_1: u128,
 }
 
-#[derive(Default)]
-pub struct StepState {
-    pub sqrt_price_start_x96: u128,
-    pub next_tick: i32,
-    pub initialized: bool,
-    pub sqrt_price_next_x96: u128,
-    pub amount_in: u128,
-    pub amount_out: u128,
-    pub fee_amount: u128,
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LimitOrderEpochView {
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+    pub zero_for_one: bool,
+    pub liquidity: U128,
+    pub filled: bool,
+    pub amount_0: U128,
+    pub amount_1: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PlacedLimitOrder {
+    pub epoch: U64,
+    pub liquidity: U128,
+    /// Token sold actually taken from the payer
+    pub amount_in: U128,
 }
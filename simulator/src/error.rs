@@ -0,0 +1,14 @@
+pub use zswap_math_library::error::{INVALID_PRICE_LIMIT, NOT_ENOUGH_LIQUIDITY};
+
+pub const INVALID_TICK_RANGE: &str = "Invalid tick range";
+pub const ZERO_LIQUIDITY: &str = "Zero liquidity";
+pub const POSITION_NOT_FOUND: &str = "Position not found";
+
+pub const INVALID_LIMIT_ORDER_RANGE: &str =
+    "Limit order must be one tick spacing wide, beyond the current price";
+pub const LIMIT_ORDER_NOT_FOUND: &str = "Limit order not found";
+pub const LIMIT_ORDER_FILLED: &str = "Limit order already filled";
+pub const LIMIT_ORDER_NOT_FILLED: &str = "Limit order not filled";
+
+// Snapshot
+pub const INVALID_TICK_SPACING: &str = "Invalid tick spacing";
@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+
+use zswap_math_library::swap::{self, SwapPool, SwapState};
+use zswap_math_library::{
+    liquidity_math,
+    num256::{I256, U256},
+    sqrt_price_math, tick,
+    tick_bitmap::flip_tick,
+    tick_math,
+};
+
+use crate::{position_key, Pool};
+
+// `modify_position` mirrors `pool/src/internal.rs`, keep the two in sync so results stay bit for
+// bit identical. The swap loop is `zswap_math_library::swap`, shared with the contract.
+impl Pool {
+    pub(crate) fn modify_position(
+        &mut self,
+        owner: &str,
+        lower_tick: i32,
+        upper_tick: i32,
+        liquidity_delta: i128,
+    ) -> [i128; 2] {
+        let current_tick = self.slot_0.tick;
+        let fee_growth_global_0_x128 = self.fee_growth_global_0_x128;
+        let fee_growth_global_1_x128 = self.fee_growth_global_1_x128;
+
+        let mut lower_tick_info = self.ticks.get(&lower_tick).copied().unwrap_or_default();
+        let flipped_lower = lower_tick_info.update(
+            lower_tick,
+            current_tick,
+            liquidity_delta,
+            fee_growth_global_0_x128,
+            fee_growth_global_1_x128,
+            false,
+        );
+        // a tick no position references anymore is cleared
+        if flipped_lower && liquidity_delta < 0 {
+            self.ticks.remove(&lower_tick);
+        } else {
+            self.ticks.insert(lower_tick, lower_tick_info);
+        }
+
+        let mut upper_tick_info = self.ticks.get(&upper_tick).copied().unwrap_or_default();
+        let flipped_upper = upper_tick_info.update(
+            upper_tick,
+            current_tick,
+            liquidity_delta,
+            fee_growth_global_0_x128,
+            fee_growth_global_1_x128,
+            true,
+        );
+        // a tick no position references anymore is cleared
+        if flipped_upper && liquidity_delta < 0 {
+            self.ticks.remove(&upper_tick);
+        } else {
+            self.ticks.insert(upper_tick, upper_tick_info);
+        }
+
+        if flipped_lower {
+            flip_tick(&mut self.tick_bitmap, lower_tick, self.tick_spacing as i32);
+        }
+
+        if flipped_upper {
+            flip_tick(&mut self.tick_bitmap, upper_tick, self.tick_spacing as i32);
+        }
+
+        let fees_growth_inside_x128 = tick::get_fee_growth_inside(
+            lower_tick,
+            upper_tick,
+            &lower_tick_info,
+            &upper_tick_info,
+            current_tick,
+            fee_growth_global_0_x128,
+            fee_growth_global_1_x128,
+        );
+
+        self.positions
+            .entry(position_key(owner, lower_tick, upper_tick))
+            .or_default()
+            .update(
+                liquidity_delta,
+                fees_growth_inside_x128[0],
+                fees_growth_inside_x128[1],
+            );
+
+        let sqrt_current_price = self.slot_0.sqrt_price_x96.0;
+        let mut amount_0 = I256::ZERO;
+        let mut amount_1 = I256::ZERO;
+        if current_tick < lower_tick {
+            amount_0 = sqrt_price_math::get_amount_0_delta_signed(
+                tick_math::get_sqrt_ratio_at_tick(lower_tick),
+                tick_math::get_sqrt_ratio_at_tick(upper_tick),
+                liquidity_delta,
+            );
+        } else if current_tick < upper_tick {
+            amount_0 = sqrt_price_math::get_amount_0_delta_signed(
+                sqrt_current_price,
+                tick_math::get_sqrt_ratio_at_tick(upper_tick),
+                liquidity_delta,
+            );
+            amount_1 = sqrt_price_math::get_amount_1_delta_signed(
+                tick_math::get_sqrt_ratio_at_tick(lower_tick),
+                sqrt_current_price,
+                liquidity_delta,
+            );
+            self.liquidity = liquidity_math::add_liquidity(self.liquidity, liquidity_delta);
+        } else {
+            amount_1 = sqrt_price_math::get_amount_1_delta_signed(
+                tick_math::get_sqrt_ratio_at_tick(lower_tick),
+                tick_math::get_sqrt_ratio_at_tick(upper_tick),
+                liquidity_delta,
+            );
+        }
+
+        [amount_0.as_i128(), amount_1.as_i128()]
+    }
+
+    /// Runs the swap loop and updates the pool state, returning the input consumed and the output.
+    /// Nothing is written when the swap fails, like a reverted contract call.
+    pub(crate) fn internal_swap(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: u128,
+        sqrt_price_limit_x96: Option<U256>,
+    ) -> Result<[u128; 2], &'static str> {
+        // the loop writes as it goes, so it runs on a copy that is only kept if it succeeds
+        let mut pool = self.clone();
+        let fee = pool.internal_update_fee();
+        let amounts = swap::swap(
+            &mut pool,
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_x96,
+            fee,
+        )?;
+        *self = pool;
+        Ok(amounts)
+    }
+
+    /// Measures the volatility on the first swap of a block and returns the fee swaps pay, like
+    /// the contract's `internal_update_fee`
+    fn internal_update_fee(&mut self) -> u32 {
+        match &mut self.dynamic_fee {
+            Some(dynamic_fee) => {
+                dynamic_fee.update(self.slot_0.tick, self.block_height);
+                dynamic_fee.fee(self.fee, dynamic_fee.volatility)
+            }
+            None => self.fee,
+        }
+    }
+}
+
+impl SwapPool for Pool {
+    type TickBitmap = BTreeMap<i16, U256>;
+
+    fn tick_spacing(&self) -> u32 {
+        self.tick_spacing
+    }
+
+    fn tick_bitmap(&self) -> &Self::TickBitmap {
+        &self.tick_bitmap
+    }
+
+    fn sqrt_price_x96(&self) -> U256 {
+        self.slot_0.sqrt_price_x96.0
+    }
+
+    fn tick(&self) -> i32 {
+        self.slot_0.tick
+    }
+
+    fn liquidity(&self) -> u128 {
+        self.liquidity
+    }
+
+    fn fee_growth_global_x128(&self) -> [u128; 2] {
+        [self.fee_growth_global_0_x128, self.fee_growth_global_1_x128]
+    }
+
+    fn cross_tick(
+        &mut self,
+        tick: i32,
+        fee_growth_global_0_x128: u128,
+        fee_growth_global_1_x128: u128,
+    ) -> i128 {
+        self.ticks
+            .entry(tick)
+            .or_default()
+            .cross(fee_growth_global_0_x128, fee_growth_global_1_x128)
+    }
+
+    fn write_swap_state(&mut self, zero_for_one: bool, state: &SwapState) {
+        self.slot_0.sqrt_price_x96.0 = state.sqrt_price_x96;
+        self.slot_0.tick = state.tick;
+        self.liquidity = state.liquidity;
+        if zero_for_one {
+            self.fee_growth_global_0_x128 = state.fee_growth_global_x128;
+        } else {
+            self.fee_growth_global_1_x128 = state.fee_growth_global_x128;
+        }
+    }
+
+    fn fill_limit_orders(&mut self, crossed_tick: i32, zero_for_one: bool) {
+        self.internal_fill_limit_orders(crossed_tick, zero_for_one);
+    }
+}
@@ -0,0 +1,429 @@
+//! In-memory copy of the zswap pool state machine for off-chain backtesting.
+//!
+//! `Pool` keeps the same state as `zswap-pool` (slot 0, active liquidity, fee growth, ticks,
+//! tick bitmap, positions, limit orders and dynamic fee) and runs the same
+//! `mint`/`burn`/`swap`/`collect` and limit order logic through `zswap-math-library`, whose
+//! `swap` loop and `DynamicFee` the contract runs too, so the amounts it returns match the
+//! contract bit for bit. Dynamic fees follow `block_height`, which the caller advances.
+//! Token balances, deposits and approvals are left to the caller.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use zswap_math_library::dynamic_fee::DynamicFee;
+use zswap_math_library::json_types::JsonU256;
+use zswap_math_library::num256::U256;
+use zswap_math_library::position::PositionInfo;
+use zswap_math_library::tick::TickInfo;
+use zswap_math_library::tick_math;
+use zswap_math_library::tick_math::TickConstants;
+
+use crate::error::*;
+use crate::limit_order::LimitOrderEpoch;
+
+pub mod error;
+mod internal;
+pub mod limit_order;
+pub mod snapshot;
+
+/// Positions are keyed by `(owner, lower_tick, upper_tick)`
+pub type PositionKey = (String, i32, i32);
+
+// First slot will contain essential data
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Slot0 {
+    // Current sqrt(P)
+    pub sqrt_price_x96: JsonU256,
+    // Current tick
+    pub tick: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Pool {
+    pub tick_spacing: u32,
+    /// Fee at rest of a dynamic-fee pool
+    pub fee: u32,
+    pub dynamic_fee: Option<DynamicFee>,
+    /// Block the next swap runs in
+    pub block_height: u64,
+
+    pub fee_growth_global_0_x128: u128,
+    pub fee_growth_global_1_x128: u128,
+
+    pub slot_0: Slot0,
+    pub liquidity: u128,
+
+    pub ticks: BTreeMap<i32, TickInfo>,
+    pub tick_bitmap: BTreeMap<i16, U256>,
+    pub positions: BTreeMap<PositionKey, PositionInfo>,
+
+    /// Epoch of the unfilled orders on each `(lower_tick, zero_for_one)`
+    pub limit_orders: BTreeMap<(i32, bool), u64>,
+    pub limit_order_epochs: BTreeMap<u64, LimitOrderEpoch>,
+    /// Liquidity each owner holds in each epoch, keyed by `(owner, epoch)`
+    pub limit_order_shares: BTreeMap<(String, u64), u128>,
+    pub next_limit_order_epoch: u64,
+}
+
+impl Pool {
+    pub fn new(tick_spacing: u32, fee: u32, sqrt_price_x96: U256) -> Self {
+        Self {
+            tick_spacing,
+            fee,
+            dynamic_fee: None,
+            block_height: 0,
+            fee_growth_global_0_x128: 0,
+            fee_growth_global_1_x128: 0,
+            slot_0: Slot0 {
+                sqrt_price_x96: JsonU256(sqrt_price_x96),
+                tick: tick_math::get_tick_at_sqrt_ratio(sqrt_price_x96),
+            },
+            liquidity: 0,
+            ticks: BTreeMap::new(),
+            tick_bitmap: BTreeMap::new(),
+            positions: BTreeMap::new(),
+            limit_orders: BTreeMap::new(),
+            limit_order_epochs: BTreeMap::new(),
+            limit_order_shares: BTreeMap::new(),
+            next_limit_order_epoch: 0,
+        }
+    }
+
+    /// Makes this a dynamic-fee pool charging up to `max_fee`, from `block_height` on
+    pub fn with_max_fee(mut self, max_fee: u32) -> Self {
+        self.dynamic_fee = Some(DynamicFee::new(
+            max_fee,
+            self.slot_0.tick,
+            self.block_height,
+        ));
+        self
+    }
+
+    /// The fee the next swap pays, as of `block_height`
+    pub fn current_fee(&self) -> u32 {
+        match &self.dynamic_fee {
+            Some(dynamic_fee) => {
+                let volatility = dynamic_fee.volatility_at(self.slot_0.tick, self.block_height);
+                dynamic_fee.fee(self.fee, volatility)
+            }
+            None => self.fee,
+        }
+    }
+
+    /// Adds liquidity to `recipient`'s position and returns the token amounts the pool would collect
+    pub fn mint(
+        &mut self,
+        recipient: &str,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount: u128,
+    ) -> Result<[u128; 2], &'static str> {
+        check_ticks(lower_tick, upper_tick)?;
+        if amount == 0 {
+            return Err(ZERO_LIQUIDITY);
+        }
+
+        let amounts = self.modify_position(recipient, lower_tick, upper_tick, amount as i128);
+        Ok([amounts[0] as u128, amounts[1] as u128])
+    }
+
+    /// Removes liquidity from `owner`'s position and credits the released amounts as tokens owed
+    pub fn burn(
+        &mut self,
+        owner: &str,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount: u128,
+    ) -> Result<[u128; 2], &'static str> {
+        check_ticks(lower_tick, upper_tick)?;
+        if amount == 0 {
+            return Err(ZERO_LIQUIDITY);
+        }
+        // the contract reverts on the underflow, the simulator refuses before touching any state
+        let liquidity = self
+            .position(owner, lower_tick, upper_tick)
+            .map(|position| position.liquidity)
+            .unwrap_or_default();
+        if liquidity < amount {
+            return Err(NOT_ENOUGH_LIQUIDITY);
+        }
+
+        let amounts = self.modify_position(owner, lower_tick, upper_tick, -(amount as i128));
+        let amount_0 = amounts[0].unsigned_abs();
+        let amount_1 = amounts[1].unsigned_abs();
+
+        if amount_0 > 0 || amount_1 > 0 {
+            let position = self
+                .positions
+                .get_mut(&position_key(owner, lower_tick, upper_tick))
+                .unwrap();
+            position.tokens_owed_0 += amount_0;
+            position.tokens_owed_1 += amount_1;
+        }
+
+        Ok([amount_0, amount_1])
+    }
+
+    /// Swaps `amount_specified` of the input token and returns `[amount_in, amount_out]`
+    pub fn swap(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: u128,
+        sqrt_price_limit_x96: Option<U256>,
+    ) -> Result<[u128; 2], &'static str> {
+        self.internal_swap(zero_for_one, amount_specified, sqrt_price_limit_x96)
+    }
+
+    /// Takes up to the requested amounts out of `owner`'s tokens owed
+    pub fn collect(
+        &mut self,
+        owner: &str,
+        lower_tick: i32,
+        upper_tick: i32,
+        amount_0_requested: u128,
+        amount_1_requested: u128,
+    ) -> Result<[u128; 2], &'static str> {
+        check_ticks(lower_tick, upper_tick)?;
+
+        let position = self
+            .positions
+            .get_mut(&position_key(owner, lower_tick, upper_tick))
+            .ok_or(POSITION_NOT_FOUND)?;
+
+        let amount_0 = position.tokens_owed_0.min(amount_0_requested);
+        let amount_1 = position.tokens_owed_1.min(amount_1_requested);
+        position.tokens_owed_0 -= amount_0;
+        position.tokens_owed_1 -= amount_1;
+
+        Ok([amount_0, amount_1])
+    }
+
+    pub fn position(&self, owner: &str, lower_tick: i32, upper_tick: i32) -> Option<&PositionInfo> {
+        self.positions
+            .get(&position_key(owner, lower_tick, upper_tick))
+    }
+}
+
+fn position_key(owner: &str, lower_tick: i32, upper_tick: i32) -> PositionKey {
+    (owner.to_string(), lower_tick, upper_tick)
+}
+
+fn check_ticks(lower_tick: i32, upper_tick: i32) -> Result<(), &'static str> {
+    if lower_tick >= upper_tick
+        || lower_tick < TickConstants::MIN_TICK
+        || upper_tick > TickConstants::MAX_TICK
+    {
+        Err(INVALID_TICK_RANGE)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::PoolSnapshot;
+
+    const ALICE: &str = "alice.testnet";
+    const BOB: &str = "bob.testnet";
+
+    /// 1 ZNEAR = 100 ZUSD, tick ~ 46054, with the README's mint
+    fn pool_with_liquidity() -> Pool {
+        let mut pool = Pool::new(60, 3000, U256::from(10 * 2_u128.pow(96)));
+        pool.mint(ALICE, 42000, 48000, 1_517_882_343_751_509_868_544)
+            .unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_mint_burn_collect() {
+        let mut pool = pool_with_liquidity();
+        assert_eq!(pool.slot_0.tick, 46054);
+        assert_eq!(pool.liquidity, 1_517_882_343_751_509_868_544);
+
+        let minted = pool.mint(BOB, 42000, 48000, 1_000_000).unwrap();
+        assert!(minted[0] > 0 && minted[1] > 0);
+        assert_eq!(
+            pool.burn(BOB, 42000, 48000, 2_000_000),
+            Err(NOT_ENOUGH_LIQUIDITY)
+        );
+
+        // burning rounds down while minting rounds up
+        let burned = pool.burn(BOB, 42000, 48000, 1_000_000).unwrap();
+        assert!(burned[0] <= minted[0] && minted[0] - burned[0] <= 1);
+        assert!(burned[1] <= minted[1] && minted[1] - burned[1] <= 1);
+
+        assert_eq!(
+            pool.collect(BOB, 42000, 48000, 1, u128::MAX).unwrap(),
+            [1, burned[1]]
+        );
+        assert_eq!(
+            pool.collect(BOB, 42000, 48000, u128::MAX, u128::MAX)
+                .unwrap(),
+            [burned[0] - 1, 0]
+        );
+        assert_eq!(pool.collect(BOB, 0, 60, 1, 1), Err(POSITION_NOT_FOUND));
+        assert_eq!(pool.mint(BOB, 60, 0, 1), Err(INVALID_TICK_RANGE));
+        assert_eq!(pool.liquidity, 1_517_882_343_751_509_868_544);
+    }
+
+    #[test]
+    fn test_burn_clears_unreferenced_ticks() {
+        let mut pool = pool_with_liquidity();
+        pool.mint(BOB, 46080, 46140, 1_000_000_000_000).unwrap();
+
+        // crossing the ticks both ways leaves fee growth outside them
+        let up = tick_math::get_sqrt_ratio_at_tick(46200);
+        pool.swap(false, u128::MAX / 2, Some(up)).unwrap();
+        let down = tick_math::get_sqrt_ratio_at_tick(46020);
+        pool.swap(true, u128::MAX / 2, Some(down)).unwrap();
+        assert_eq!(pool.slot_0.tick, 46020);
+        assert!(pool.ticks[&46080].fee_growth_outside_1_x128 > 0);
+
+        // like the contract, a re-initialized tick starts over instead of reusing it
+        pool.burn(BOB, 46080, 46140, 1_000_000_000_000).unwrap();
+        assert!(!pool.ticks.contains_key(&46080));
+        assert!(!pool.ticks.contains_key(&46140));
+        assert!(pool.ticks.contains_key(&42000));
+    }
+
+    #[test]
+    fn test_swap_accrues_fees() {
+        let mut pool = pool_with_liquidity();
+
+        let [amount_in, amount_out] = pool.swap(false, 42_000_000_000_000_000_000, None).unwrap();
+        assert_eq!(amount_in, 42_000_000_000_000_000_000);
+        assert!(amount_out > 0);
+        assert!(pool.slot_0.tick > 46054);
+        assert!(pool.fee_growth_global_1_x128 > 0);
+
+        let price_before = pool.slot_0.sqrt_price_x96.0;
+        assert_eq!(
+            pool.swap(true, 1, Some(price_before + 1)),
+            Err(INVALID_PRICE_LIMIT)
+        );
+
+        // burning settles the accrued fees into tokens owed
+        pool.burn(ALICE, 42000, 48000, 1).unwrap();
+        let position = pool.position(ALICE, 42000, 48000).unwrap();
+        assert!(position.tokens_owed_1 > 0);
+    }
+
+    #[test]
+    fn test_limit_orders_fill_when_crossed() {
+        let mut pool = pool_with_liquidity();
+        let placed = pool.mint_limit_order(BOB, 46080, true, 1_000_000).unwrap();
+        assert!(placed.amount_in > 0 && placed.amount_in <= 1_000_000);
+        let cancelled = pool
+            .mint_limit_order(ALICE, 46140, true, 1_000_000)
+            .unwrap();
+        assert_eq!(
+            pool.mint_limit_order(BOB, 45960, true, 1_000_000),
+            Err(INVALID_LIMIT_ORDER_RANGE)
+        );
+
+        // cancelling an unfilled order gives back what it sold
+        let burned = pool
+            .burn_limit_order(ALICE, cancelled.epoch, cancelled.liquidity)
+            .unwrap();
+        assert!(placed.amount_in - burned[0] <= 1 && burned[1] == 0);
+        assert_eq!(
+            pool.collect_limit_order(BOB, placed.epoch, placed.liquidity),
+            Err(LIMIT_ORDER_NOT_FILLED)
+        );
+
+        // going up through 46140 leaves the order all token 1
+        let limit = tick_math::get_sqrt_ratio_at_tick(46200);
+        pool.swap(false, u128::MAX / 2, Some(limit)).unwrap();
+        assert!(pool.limit_order_epochs[&placed.epoch].filled);
+        assert_eq!(
+            pool.burn_limit_order(BOB, placed.epoch, placed.liquidity),
+            Err(LIMIT_ORDER_FILLED)
+        );
+        let collected = pool
+            .collect_limit_order(BOB, placed.epoch, placed.liquidity)
+            .unwrap();
+        assert_eq!(collected[0], 0);
+        assert!(collected[1] > 0);
+        assert!(pool.limit_order_epochs.is_empty());
+        assert_eq!(
+            pool.collect_limit_order(BOB, placed.epoch, placed.liquidity),
+            Err(LIMIT_ORDER_NOT_FOUND)
+        );
+    }
+
+    #[test]
+    fn test_dynamic_fee_follows_volatility() {
+        let mut pool = Pool::new(60, 500, U256::from(10 * 2_u128.pow(96)));
+        pool.block_height = 100;
+        let mut pool = pool.with_max_fee(10_000);
+        pool.mint(ALICE, 42000, 48000, 1_517_882_343_751_509_868_544)
+            .unwrap();
+        let mut fixed = pool.clone();
+        fixed.dynamic_fee = None;
+
+        // the price moves 30 ticks within block 101
+        pool.block_height = 101;
+        let start_tick = pool.slot_0.tick;
+        let limit = tick_math::get_sqrt_ratio_at_tick(start_tick + 30);
+        pool.swap(false, u128::MAX / 2, Some(limit)).unwrap();
+        assert_eq!(pool.slot_0.tick, start_tick + 30);
+        assert_eq!(pool.current_fee(), 500);
+
+        // and block 102 pays for it
+        pool.block_height = 102;
+        fixed.slot_0 = pool.slot_0.clone();
+        fixed.liquidity = pool.liquidity;
+        assert_eq!(pool.current_fee(), 500 + 30 * 100);
+        let charged = pool.swap(true, 1_000_000, None).unwrap();
+        assert!(charged[1] < fixed.swap(true, 1_000_000, None).unwrap()[1]);
+        assert_eq!(pool.dynamic_fee.unwrap().volatility, 30);
+
+        // a quiet block halves the volatility
+        pool.block_height = 104;
+        assert_eq!(pool.current_fee(), 500 + 7 * 100);
+        pool.block_height = 200;
+        assert_eq!(pool.current_fee(), 500);
+
+        let restored = Pool::from_snapshot(&pool.snapshot()).unwrap();
+        assert_eq!(restored.dynamic_fee, pool.dynamic_fee);
+        assert_eq!(restored.current_fee(), pool.current_fee());
+    }
+
+    #[test]
+    fn test_swap_without_liquidity_is_atomic() {
+        let mut pool = pool_with_liquidity();
+        pool.mint_limit_order(BOB, 46080, true, 1_000_000).unwrap();
+        let before = pool.snapshot();
+
+        // crossing 48000 leaves no liquidity, which the contract rejects, after filling the order
+        assert_eq!(
+            pool.swap(false, u128::MAX / 2, None),
+            Err(NOT_ENOUGH_LIQUIDITY)
+        );
+        assert_eq!(pool.snapshot(), before);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut pool = pool_with_liquidity();
+        pool.mint(BOB, -600, 46080, 3_000_000_000).unwrap();
+        pool.mint_limit_order(BOB, 45960, false, 1_000_000).unwrap();
+        pool.mint_limit_order(ALICE, 46200, true, 1_000_000)
+            .unwrap();
+        let limit = tick_math::get_sqrt_ratio_at_tick(45900);
+        pool.swap(true, u128::MAX / 2, Some(limit)).unwrap();
+        assert!(pool.limit_order_epochs[&0].filled);
+
+        let json = pool.snapshot().to_json();
+        let mut restored = Pool::from_snapshot(&PoolSnapshot::from_json(&json).unwrap()).unwrap();
+        assert_eq!(restored.snapshot(), pool.snapshot());
+
+        // the restored bitmap drives the next swap exactly like the original one
+        assert_eq!(
+            restored.swap(true, 7_000_000_000_000_000, None),
+            pool.swap(true, 7_000_000_000_000_000, None)
+        );
+        assert_eq!(restored.snapshot(), pool.snapshot());
+    }
+}
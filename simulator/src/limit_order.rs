@@ -0,0 +1,267 @@
+use zswap_math_library::full_math::{FullMath, FullMathTrait};
+use zswap_math_library::num256::U256;
+use zswap_math_library::tick_math::TickConstants;
+use zswap_math_library::{liquidity_math, tick_math};
+
+use crate::error::*;
+use crate::{position_key, Pool};
+
+/// Owner of the positions behind limit orders, which the contract holds on its own account.
+/// No account id is empty, so it never clashes with a real owner
+pub const LIMIT_ORDER_OWNER: &str = "";
+
+/// Orders placed on the same range and side share an epoch, see `zswap-pool`'s `limit_order.rs`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LimitOrderEpoch {
+    pub lower_tick: i32,
+    pub zero_for_one: bool,
+    pub liquidity: u128,
+    pub filled: bool,
+    pub amount_0: u128,
+    pub amount_1: u128,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlacedLimitOrder {
+    pub epoch: u64,
+    pub liquidity: u128,
+    /// Token sold actually taken from the owner
+    pub amount_in: u128,
+}
+
+impl Pool {
+    /// Places `owner`'s order selling `amount_in` of token 0 (`zero_for_one`) or token 1 on
+    /// `[lower_tick, lower_tick + tick_spacing]`
+    pub fn mint_limit_order(
+        &mut self,
+        owner: &str,
+        lower_tick: i32,
+        zero_for_one: bool,
+        amount_in: u128,
+    ) -> Result<PlacedLimitOrder, &'static str> {
+        let upper_tick = lower_tick + self.tick_spacing as i32;
+        let beyond_price = if zero_for_one {
+            self.slot_0.tick < lower_tick
+        } else {
+            self.slot_0.tick >= upper_tick
+        };
+        if lower_tick.rem_euclid(self.tick_spacing as i32) != 0 || !beyond_price {
+            return Err(INVALID_LIMIT_ORDER_RANGE);
+        }
+        if lower_tick < TickConstants::MIN_TICK || upper_tick > TickConstants::MAX_TICK {
+            return Err(INVALID_TICK_RANGE);
+        }
+
+        let sqrt_price_lower_x96 = tick_math::get_sqrt_ratio_at_tick(lower_tick);
+        let sqrt_price_upper_x96 = tick_math::get_sqrt_ratio_at_tick(upper_tick);
+        let liquidity = if zero_for_one {
+            liquidity_math::get_liquidity_for_amount_0(
+                sqrt_price_lower_x96,
+                sqrt_price_upper_x96,
+                amount_in,
+            )
+        } else {
+            liquidity_math::get_liquidity_for_amount_1(
+                sqrt_price_lower_x96,
+                sqrt_price_upper_x96,
+                amount_in,
+            )
+        };
+        if liquidity == 0 {
+            return Err(ZERO_LIQUIDITY);
+        }
+
+        let amounts =
+            self.modify_position(LIMIT_ORDER_OWNER, lower_tick, upper_tick, liquidity as i128);
+        let amount_in = if zero_for_one {
+            amounts[0] as u128
+        } else {
+            amounts[1] as u128
+        };
+
+        let epoch_id = match self.limit_orders.get(&(lower_tick, zero_for_one)) {
+            Some(epoch_id) => *epoch_id,
+            None => {
+                let epoch_id = self.next_limit_order_epoch;
+                self.next_limit_order_epoch += 1;
+                self.limit_orders
+                    .insert((lower_tick, zero_for_one), epoch_id);
+                epoch_id
+            }
+        };
+        self.limit_order_epochs
+            .entry(epoch_id)
+            .or_insert(LimitOrderEpoch {
+                lower_tick,
+                zero_for_one,
+                liquidity: 0,
+                filled: false,
+                amount_0: 0,
+                amount_1: 0,
+            })
+            .liquidity += liquidity;
+        *self
+            .limit_order_shares
+            .entry((owner.to_string(), epoch_id))
+            .or_default() += liquidity;
+
+        Ok(PlacedLimitOrder {
+            epoch: epoch_id,
+            liquidity,
+            amount_in,
+        })
+    }
+
+    /// Cancels `liquidity` of `owner`'s order in an unfilled epoch, returning what it holds with
+    /// its share of the fees earned so far
+    pub fn burn_limit_order(
+        &mut self,
+        owner: &str,
+        epoch: u64,
+        liquidity: u128,
+    ) -> Result<[u128; 2], &'static str> {
+        let mut limit_order_epoch = self.limit_order_epoch_for(owner, epoch, liquidity)?;
+        if limit_order_epoch.filled {
+            return Err(LIMIT_ORDER_FILLED);
+        }
+        self.spend_limit_order_share(owner, epoch, liquidity);
+
+        let lower_tick = limit_order_epoch.lower_tick;
+        let upper_tick = lower_tick + self.tick_spacing as i32;
+        let burned = self.modify_position(
+            LIMIT_ORDER_OWNER,
+            lower_tick,
+            upper_tick,
+            -(liquidity as i128),
+        );
+
+        // the epoch's fees accrue on the pool's position, split them by liquidity
+        let key = position_key(LIMIT_ORDER_OWNER, lower_tick, upper_tick);
+        let position = self.positions.get_mut(&key).unwrap();
+        let fees = [position.tokens_owed_0, position.tokens_owed_1].map(|tokens_owed| {
+            FullMath::mul_div(
+                U256::from(tokens_owed),
+                U256::from(liquidity),
+                U256::from(limit_order_epoch.liquidity),
+            )
+            .as_u128()
+        });
+        position.tokens_owed_0 -= fees[0];
+        position.tokens_owed_1 -= fees[1];
+
+        limit_order_epoch.liquidity -= liquidity;
+        if limit_order_epoch.liquidity == 0 {
+            // the last order out takes the position, emptied, and the epoch with it
+            self.positions.remove(&key);
+            self.limit_orders
+                .remove(&(lower_tick, limit_order_epoch.zero_for_one));
+            self.limit_order_epochs.remove(&epoch);
+        } else {
+            self.limit_order_epochs.insert(epoch, limit_order_epoch);
+        }
+
+        Ok([
+            burned[0].unsigned_abs() + fees[0],
+            burned[1].unsigned_abs() + fees[1],
+        ])
+    }
+
+    /// Takes `owner`'s share of a filled epoch, for `liquidity` of its orders
+    pub fn collect_limit_order(
+        &mut self,
+        owner: &str,
+        epoch: u64,
+        liquidity: u128,
+    ) -> Result<[u128; 2], &'static str> {
+        let mut limit_order_epoch = self.limit_order_epoch_for(owner, epoch, liquidity)?;
+        if !limit_order_epoch.filled {
+            return Err(LIMIT_ORDER_NOT_FILLED);
+        }
+        self.spend_limit_order_share(owner, epoch, liquidity);
+
+        let amounts = [limit_order_epoch.amount_0, limit_order_epoch.amount_1].map(|amount| {
+            FullMath::mul_div(
+                U256::from(amount),
+                U256::from(liquidity),
+                U256::from(limit_order_epoch.liquidity),
+            )
+            .as_u128()
+        });
+        limit_order_epoch.amount_0 -= amounts[0];
+        limit_order_epoch.amount_1 -= amounts[1];
+        limit_order_epoch.liquidity -= liquidity;
+        if limit_order_epoch.liquidity == 0 {
+            self.limit_order_epochs.remove(&epoch);
+        } else {
+            self.limit_order_epochs.insert(epoch, limit_order_epoch);
+        }
+
+        Ok(amounts)
+    }
+
+    pub fn limit_order_liquidity(&self, owner: &str, epoch: u64) -> u128 {
+        self.limit_order_shares
+            .get(&(owner.to_string(), epoch))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Fills the epoch whose range the swap just left through `crossed_tick`, like the
+    /// contract's `internal_fill_limit_orders`
+    pub(crate) fn internal_fill_limit_orders(&mut self, crossed_tick: i32, zero_for_one: bool) {
+        let key = if zero_for_one {
+            (crossed_tick, false)
+        } else {
+            (crossed_tick - self.tick_spacing as i32, true)
+        };
+        let epoch_id = match self.limit_orders.remove(&key) {
+            Some(epoch_id) => epoch_id,
+            None => return,
+        };
+        let mut epoch = self.limit_order_epochs[&epoch_id].clone();
+
+        let (lower_tick, upper_tick) = (key.0, key.0 + self.tick_spacing as i32);
+        let burned = self.modify_position(
+            LIMIT_ORDER_OWNER,
+            lower_tick,
+            upper_tick,
+            -(epoch.liquidity as i128),
+        );
+        let position = self
+            .positions
+            .remove(&position_key(LIMIT_ORDER_OWNER, lower_tick, upper_tick))
+            .unwrap();
+
+        epoch.filled = true;
+        epoch.amount_0 = burned[0].unsigned_abs() + position.tokens_owed_0;
+        epoch.amount_1 = burned[1].unsigned_abs() + position.tokens_owed_1;
+        self.limit_order_epochs.insert(epoch_id, epoch);
+    }
+
+    /// The epoch `owner` can take `liquidity` of its orders out of
+    fn limit_order_epoch_for(
+        &self,
+        owner: &str,
+        epoch: u64,
+        liquidity: u128,
+    ) -> Result<LimitOrderEpoch, &'static str> {
+        let limit_order_epoch = self
+            .limit_order_epochs
+            .get(&epoch)
+            .ok_or(LIMIT_ORDER_NOT_FOUND)?;
+        if liquidity == 0 || self.limit_order_liquidity(owner, epoch) < liquidity {
+            return Err(LIMIT_ORDER_NOT_FOUND);
+        }
+        Ok(limit_order_epoch.clone())
+    }
+
+    fn spend_limit_order_share(&mut self, owner: &str, epoch: u64, liquidity: u128) {
+        let key = (owner.to_string(), epoch);
+        let share = self.limit_order_shares[&key];
+        if share == liquidity {
+            self.limit_order_shares.remove(&key);
+        } else {
+            self.limit_order_shares.insert(key, share - liquidity);
+        }
+    }
+}
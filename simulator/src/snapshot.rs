@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+use zswap_math_library::dynamic_fee::DynamicFee;
+use zswap_math_library::position::PositionInfo;
+use zswap_math_library::tick::TickInfo;
+use zswap_math_library::tick_bitmap::flip_tick;
+use zswap_math_library::tick_math::TickConstants;
+
+use crate::error::{INVALID_TICK_RANGE, INVALID_TICK_SPACING};
+use crate::limit_order::LimitOrderEpoch;
+use crate::{check_ticks, Pool, Slot0};
+
+/// JSON image of a pool, with 128-bit integers carried as decimal strings like `U128`.
+///
+/// Only ticks with liquidity are listed; the tick bitmap is rebuilt from them on load. Limit
+/// orders are listed by epoch, with the positions behind them under `LIMIT_ORDER_OWNER`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolSnapshot {
+    pub tick_spacing: u32,
+    pub fee: u32,
+    /// Set on dynamic-fee pools only
+    #[serde(default)]
+    pub dynamic_fee: Option<DynamicFeeSnapshot>,
+    #[serde(default)]
+    pub block_height: u64,
+    pub slot_0: Slot0,
+    #[serde(with = "dec_str")]
+    pub liquidity: u128,
+    #[serde(with = "dec_str")]
+    pub fee_growth_global_0_x128: u128,
+    #[serde(with = "dec_str")]
+    pub fee_growth_global_1_x128: u128,
+    #[serde(default)]
+    pub ticks: Vec<TickSnapshot>,
+    #[serde(default)]
+    pub positions: Vec<PositionSnapshot>,
+    #[serde(default)]
+    pub limit_order_epochs: Vec<LimitOrderEpochSnapshot>,
+    /// Id of the next epoch, past those already collected and gone
+    #[serde(default)]
+    pub next_limit_order_epoch: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DynamicFeeSnapshot {
+    pub max_fee: u32,
+    pub volatility: u32,
+    pub reference_tick: i32,
+    pub last_block: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TickSnapshot {
+    pub tick: i32,
+    #[serde(with = "dec_str")]
+    pub liquidity_gross: u128,
+    #[serde(with = "dec_str")]
+    pub liquidity_net: i128,
+    #[serde(with = "dec_str")]
+    pub fee_growth_outside_0_x128: u128,
+    #[serde(with = "dec_str")]
+    pub fee_growth_outside_1_x128: u128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PositionSnapshot {
+    pub owner: String,
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+    #[serde(with = "dec_str")]
+    pub liquidity: u128,
+    #[serde(with = "dec_str")]
+    pub fee_growth_inside_0_last_x128: u128,
+    #[serde(with = "dec_str")]
+    pub fee_growth_inside_1_last_x128: u128,
+    #[serde(with = "dec_str")]
+    pub tokens_owed_0: u128,
+    #[serde(with = "dec_str")]
+    pub tokens_owed_1: u128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LimitOrderEpochSnapshot {
+    pub epoch: u64,
+    pub lower_tick: i32,
+    pub zero_for_one: bool,
+    #[serde(with = "dec_str")]
+    pub liquidity: u128,
+    pub filled: bool,
+    #[serde(with = "dec_str")]
+    pub amount_0: u128,
+    #[serde(with = "dec_str")]
+    pub amount_1: u128,
+    /// Liquidity each owner holds in the epoch
+    #[serde(default)]
+    pub shares: Vec<LimitOrderShareSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LimitOrderShareSnapshot {
+    pub owner: String,
+    #[serde(with = "dec_str")]
+    pub liquidity: u128,
+}
+
+impl PoolSnapshot {
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl Pool {
+    pub fn from_snapshot(snapshot: &PoolSnapshot) -> Result<Self, &'static str> {
+        if snapshot.tick_spacing == 0 {
+            return Err(INVALID_TICK_SPACING);
+        }
+
+        let mut pool = Pool::new(
+            snapshot.tick_spacing,
+            snapshot.fee,
+            snapshot.slot_0.sqrt_price_x96.0,
+        );
+        // the stored tick can sit one below the price's tick after a downward swap ends on a boundary
+        pool.slot_0.tick = snapshot.slot_0.tick;
+        pool.dynamic_fee = snapshot.dynamic_fee.map(|dynamic_fee| DynamicFee {
+            max_fee: dynamic_fee.max_fee,
+            volatility: dynamic_fee.volatility,
+            reference_tick: dynamic_fee.reference_tick,
+            last_block: dynamic_fee.last_block,
+        });
+        pool.block_height = snapshot.block_height;
+        pool.liquidity = snapshot.liquidity;
+        pool.fee_growth_global_0_x128 = snapshot.fee_growth_global_0_x128;
+        pool.fee_growth_global_1_x128 = snapshot.fee_growth_global_1_x128;
+
+        for tick in snapshot.ticks.iter() {
+            if tick.tick % snapshot.tick_spacing as i32 != 0 {
+                return Err(INVALID_TICK_SPACING);
+            }
+            if tick.liquidity_gross == 0 {
+                continue;
+            }
+            if !(TickConstants::MIN_TICK..=TickConstants::MAX_TICK).contains(&tick.tick) {
+                return Err(INVALID_TICK_RANGE);
+            }
+
+            pool.ticks.insert(
+                tick.tick,
+                TickInfo {
+                    liquidity_gross: tick.liquidity_gross,
+                    liquidity_net: tick.liquidity_net,
+                    fee_growth_outside_0_x128: tick.fee_growth_outside_0_x128,
+                    fee_growth_outside_1_x128: tick.fee_growth_outside_1_x128,
+                    initialized: true,
+                    ..Default::default()
+                },
+            );
+            flip_tick(
+                &mut pool.tick_bitmap,
+                tick.tick,
+                snapshot.tick_spacing as i32,
+            );
+        }
+
+        for position in snapshot.positions.iter() {
+            check_ticks(position.lower_tick, position.upper_tick)?;
+            pool.positions.insert(
+                (
+                    position.owner.clone(),
+                    position.lower_tick,
+                    position.upper_tick,
+                ),
+                PositionInfo {
+                    liquidity: position.liquidity,
+                    fee_growth_inside_0_last_x128: position.fee_growth_inside_0_last_x128,
+                    fee_growth_inside_1_last_x128: position.fee_growth_inside_1_last_x128,
+                    tokens_owed_0: position.tokens_owed_0,
+                    tokens_owed_1: position.tokens_owed_1,
+                },
+            );
+        }
+
+        pool.next_limit_order_epoch = snapshot.next_limit_order_epoch;
+        for epoch in snapshot.limit_order_epochs.iter() {
+            if !epoch.filled {
+                pool.limit_orders
+                    .insert((epoch.lower_tick, epoch.zero_for_one), epoch.epoch);
+            }
+            pool.limit_order_epochs.insert(
+                epoch.epoch,
+                LimitOrderEpoch {
+                    lower_tick: epoch.lower_tick,
+                    zero_for_one: epoch.zero_for_one,
+                    liquidity: epoch.liquidity,
+                    filled: epoch.filled,
+                    amount_0: epoch.amount_0,
+                    amount_1: epoch.amount_1,
+                },
+            );
+            for share in epoch.shares.iter() {
+                pool.limit_order_shares
+                    .insert((share.owner.clone(), epoch.epoch), share.liquidity);
+            }
+            pool.next_limit_order_epoch = pool.next_limit_order_epoch.max(epoch.epoch + 1);
+        }
+
+        Ok(pool)
+    }
+
+    pub fn snapshot(&self) -> PoolSnapshot {
+        PoolSnapshot {
+            tick_spacing: self.tick_spacing,
+            fee: self.fee,
+            dynamic_fee: self.dynamic_fee.map(|dynamic_fee| DynamicFeeSnapshot {
+                max_fee: dynamic_fee.max_fee,
+                volatility: dynamic_fee.volatility,
+                reference_tick: dynamic_fee.reference_tick,
+                last_block: dynamic_fee.last_block,
+            }),
+            block_height: self.block_height,
+            slot_0: self.slot_0.clone(),
+            liquidity: self.liquidity,
+            fee_growth_global_0_x128: self.fee_growth_global_0_x128,
+            fee_growth_global_1_x128: self.fee_growth_global_1_x128,
+            ticks: self
+                .ticks
+                .iter()
+                .filter(|(_, info)| info.liquidity_gross > 0)
+                .map(|(tick, info)| TickSnapshot {
+                    tick: *tick,
+                    liquidity_gross: info.liquidity_gross,
+                    liquidity_net: info.liquidity_net,
+                    fee_growth_outside_0_x128: info.fee_growth_outside_0_x128,
+                    fee_growth_outside_1_x128: info.fee_growth_outside_1_x128,
+                })
+                .collect(),
+            positions: self
+                .positions
+                .iter()
+                .map(|((owner, lower_tick, upper_tick), info)| PositionSnapshot {
+                    owner: owner.clone(),
+                    lower_tick: *lower_tick,
+                    upper_tick: *upper_tick,
+                    liquidity: info.liquidity,
+                    fee_growth_inside_0_last_x128: info.fee_growth_inside_0_last_x128,
+                    fee_growth_inside_1_last_x128: info.fee_growth_inside_1_last_x128,
+                    tokens_owed_0: info.tokens_owed_0,
+                    tokens_owed_1: info.tokens_owed_1,
+                })
+                .collect(),
+            limit_order_epochs: self
+                .limit_order_epochs
+                .iter()
+                .map(|(epoch_id, epoch)| LimitOrderEpochSnapshot {
+                    epoch: *epoch_id,
+                    lower_tick: epoch.lower_tick,
+                    zero_for_one: epoch.zero_for_one,
+                    liquidity: epoch.liquidity,
+                    filled: epoch.filled,
+                    amount_0: epoch.amount_0,
+                    amount_1: epoch.amount_1,
+                    shares: self
+                        .limit_order_shares
+                        .iter()
+                        .filter(|((_, share_epoch), _)| share_epoch == epoch_id)
+                        .map(|((owner, _), liquidity)| LimitOrderShareSnapshot {
+                            owner: owner.clone(),
+                            liquidity: *liquidity,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            next_limit_order_epoch: self.next_limit_order_epoch,
+        }
+    }
+}
+
+/// (De)serializes integers as decimal strings, since JSON numbers lose precision past 2^53
+mod dec_str {
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_snapshot() {
+        let json = r#"{
+            "tick_spacing": 60,
+            "fee": 3000,
+            "slot_0": { "sqrt_price_x96": "792281625142643375935439503360", "tick": 46054 },
+            "liquidity": "1517882343751509868544",
+            "fee_growth_global_0_x128": "0",
+            "fee_growth_global_1_x128": "0",
+            "ticks": [
+                { "tick": 42000, "liquidity_gross": "1517882343751509868544", "liquidity_net": "1517882343751509868544", "fee_growth_outside_0_x128": "0", "fee_growth_outside_1_x128": "0" },
+                { "tick": 48000, "liquidity_gross": "1517882343751509868544", "liquidity_net": "-1517882343751509868544", "fee_growth_outside_0_x128": "0", "fee_growth_outside_1_x128": "0" }
+            ],
+            "positions": [
+                { "owner": "alice.testnet", "lower_tick": 42000, "upper_tick": 48000, "liquidity": "1517882343751509868544", "fee_growth_inside_0_last_x128": "0", "fee_growth_inside_1_last_x128": "0", "tokens_owed_0": "0", "tokens_owed_1": "0" }
+            ]
+        }"#;
+        let snapshot = PoolSnapshot::from_json(json).unwrap();
+        let loaded = Pool::from_snapshot(&snapshot).unwrap();
+
+        let mut minted = Pool::new(60, 3000, snapshot.slot_0.sqrt_price_x96.0);
+        minted
+            .mint("alice.testnet", 42000, 48000, 1_517_882_343_751_509_868_544)
+            .unwrap();
+        assert_eq!(loaded.snapshot(), minted.snapshot());
+        assert_eq!(loaded.tick_bitmap, minted.tick_bitmap);
+
+        let mut bad_spacing = snapshot.clone();
+        bad_spacing.ticks[0].tick = 42001;
+        assert_eq!(
+            Pool::from_snapshot(&bad_spacing).unwrap_err(),
+            INVALID_TICK_SPACING
+        );
+        assert!(PoolSnapshot::from_json(r#"{ "tick_spacing": 60 }"#).is_err());
+    }
+}
@@ -0,0 +1,29 @@
+//! Sandbox test support for the zswap contracts.
+//!
+//! `scenario` drives the contracts through `zswap-client`: a test declares its pools, the
+//! positions liquidity providers mint and the swaps traders make, runs them in a fresh sandbox
+//! and asserts on the resulting balances, deposits and positions.
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use zswap_test_support::scenario::Scenario;
+//!
+//! let env = Scenario::new()
+//!     .pool(3000, "100")
+//!     .mint("alice", 0, 42000, 48000, [10_000_000, 100_000_000])
+//!     .swap("bob", 0, true, 1_000)
+//!     .run()
+//!     .await?;
+//! assert_eq!(env.swaps[0].amount_in, 1_000);
+//! assert_eq!(env.positions("alice").await?.len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The contracts are loaded from `res/`, so run `build.sh` after changing them.
+
+pub mod scenario;
+
+pub const FT_CONTRACT: &[u8] = include_bytes!("../../res/mock/fungible_token.wasm");
+pub const ZSWAP_MANAGER_CONTRACT: &[u8] = include_bytes!("../../res/zswap_manager.wasm");
+pub const ZSWAP_FACTORY_CONTRACT: &[u8] = include_bytes!("../../res/zswap_factory.wasm");
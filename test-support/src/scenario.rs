@@ -0,0 +1,450 @@
+//! Declarative sandbox scenarios.
+//!
+//! A `Scenario` lists pools, then the mints, swaps and burns to run against them in order.
+//! `run` deploys two or more mock tokens, the factory and the manager in a fresh sandbox,
+//! creates every pool through the manager and replays the steps. Accounts are named in the
+//! steps and created on first use, registered in every token and funded with
+//! `ACCOUNT_BALANCE` of each.
+
+use std::collections::BTreeMap;
+
+use near_sdk::json_types::U128;
+use near_sdk::AccountId;
+use near_units::parse_near;
+use workspaces::network::Sandbox;
+use workspaces::{Account, Worker};
+use zswap_client::{FactoryClient, ManagerClient, PoolClient, TokenClient};
+use zswap_manager::utils::{MintParams, NftLiquidityInfo};
+use zswap_math_library::json_types::JsonU256;
+use zswap_math_library::price_math;
+use zswap_pool::utils::Slot0;
+
+use crate::{FT_CONTRACT, ZSWAP_FACTORY_CONTRACT, ZSWAP_MANAGER_CONTRACT};
+
+/// Tokens every scenario account starts with, in each token
+pub const ACCOUNT_BALANCE: u128 = 1_000_000_000_000_000_000;
+/// NEAR every scenario account starts with, for mint deposits and gas
+const ACCOUNT_NEAR_BALANCE: u128 = parse_near!("10 N");
+/// Deposit `create_pool` attaches, enough for the pool code and the token registrations
+const CREATE_POOL_DEPOSIT: u128 = parse_near!("30 N");
+/// Storage balance in the manager, for the deposits and position NFTs, and the positions in
+/// the pool the manager pays for
+const MANAGER_STORAGE_DEPOSIT: u128 = parse_near!("0.1 N");
+/// Storage registration in the mock token
+const TOKEN_STORAGE_DEPOSIT: u128 = parse_near!("0.00125 N");
+
+#[derive(Clone, Debug)]
+pub struct PoolSpec {
+    /// Indexes into the scenario's tokens, in any order
+    pub tokens: (usize, usize),
+    pub fee: u32,
+    /// Initial price of token 0 in token 1, in raw units
+    pub price: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum Step {
+    Mint {
+        account: String,
+        pool: usize,
+        lower_tick: i32,
+        upper_tick: i32,
+        /// Desired amounts of the pool's token 0 and token 1
+        amounts: [u128; 2],
+    },
+    Swap {
+        account: String,
+        pool: usize,
+        zero_for_one: bool,
+        amount_in: u128,
+    },
+    Burn {
+        account: String,
+        /// Index into `Env::positions_minted`
+        position: usize,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    tokens: usize,
+    pools: Vec<PoolSpec>,
+    steps: Vec<Step>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Self {
+            tokens: 2,
+            pools: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Deploys `count` tokens instead of two
+    pub fn tokens(mut self, count: usize) -> Self {
+        assert!(count >= 2, "a pool needs two tokens");
+        self.tokens = count;
+        self
+    }
+
+    /// Adds a pool between the first two tokens
+    pub fn pool(self, fee: u32, price: &str) -> Self {
+        self.pool_between(0, 1, fee, price)
+    }
+
+    pub fn pool_between(mut self, token_a: usize, token_b: usize, fee: u32, price: &str) -> Self {
+        self.pools.push(PoolSpec {
+            tokens: (token_a, token_b),
+            fee,
+            price: price.to_string(),
+        });
+        self
+    }
+
+    pub fn mint(
+        mut self,
+        account: &str,
+        pool: usize,
+        lower_tick: i32,
+        upper_tick: i32,
+        amounts: [u128; 2],
+    ) -> Self {
+        self.steps.push(Step::Mint {
+            account: account.to_string(),
+            pool,
+            lower_tick,
+            upper_tick,
+            amounts,
+        });
+        self
+    }
+
+    /// Swaps `amount_in` from `account`'s wallet through the manager's `SwapSingle` message
+    pub fn swap(mut self, account: &str, pool: usize, zero_for_one: bool, amount_in: u128) -> Self {
+        self.steps.push(Step::Swap {
+            account: account.to_string(),
+            pool,
+            zero_for_one,
+            amount_in,
+        });
+        self
+    }
+
+    /// Burns the `position`th minted position, which `account` has to own
+    pub fn burn(mut self, account: &str, position: usize) -> Self {
+        self.steps.push(Step::Burn {
+            account: account.to_string(),
+            position,
+        });
+        self
+    }
+
+    pub async fn run(self) -> anyhow::Result<Env> {
+        let worker = workspaces::sandbox().await?;
+        let root = worker.root_account()?;
+
+        let mut tokens = Vec::with_capacity(self.tokens);
+        for _ in 0..self.tokens {
+            let contract = worker.dev_deploy(FT_CONTRACT).await?;
+            contract
+                .call("new_default_meta")
+                .args_json((root.id(), U128::from(u128::MAX / 2)))
+                .max_gas()
+                .transact()
+                .await?
+                .into_result()?;
+            tokens.push(TokenClient::new(
+                root.clone(),
+                account_id(contract.as_account()),
+            ));
+        }
+        let contract = worker.dev_deploy(ZSWAP_FACTORY_CONTRACT).await?;
+        let factory = FactoryClient::new(root.clone(), account_id(contract.as_account()));
+        FactoryClient::new(contract, factory.id().clone())
+            .init()
+            .await?;
+        let contract = worker.dev_deploy(ZSWAP_MANAGER_CONTRACT).await?;
+        let manager = ManagerClient::new(root.clone(), account_id(contract.as_account()));
+        ManagerClient::new(contract, manager.id().clone())
+            .init(factory.id())
+            .await?;
+
+        let mut env = Env {
+            worker,
+            root,
+            tokens,
+            factory,
+            manager,
+            pools: Vec::new(),
+            accounts: BTreeMap::new(),
+            positions_minted: Vec::new(),
+            swaps: Vec::new(),
+            burns: Vec::new(),
+        };
+        for spec in self.pools.iter() {
+            env.create_pool(spec).await?;
+        }
+        for step in self.steps {
+            env.apply(step).await?;
+        }
+        Ok(env)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ScenarioPool {
+    pub client: PoolClient<Account>,
+    /// Indexes into `Env::tokens`, sorted the way the pool orders them
+    pub tokens: [usize; 2],
+    pub fee: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct MintedPosition {
+    pub account: String,
+    pub pool: usize,
+    pub nft_id: u128,
+    /// Amounts of token 0 and token 1 the pool took
+    pub amounts: [u128; 2],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapResult {
+    /// Wallet balance the trader spent
+    pub amount_in: u128,
+    /// Wallet balance the trader received
+    pub amount_out: u128,
+}
+
+/// A sandbox after running a `Scenario`, with what each step returned.
+///
+/// The token, factory, manager and pool clients sign as `root`.
+pub struct Env {
+    pub worker: Worker<Sandbox>,
+    /// Owns the token supply and pays for the pools
+    pub root: Account,
+    pub tokens: Vec<TokenClient<Account>>,
+    pub factory: FactoryClient<Account>,
+    pub manager: ManagerClient<Account>,
+    pub pools: Vec<ScenarioPool>,
+    accounts: BTreeMap<String, Account>,
+    pub positions_minted: Vec<MintedPosition>,
+    pub swaps: Vec<SwapResult>,
+    /// Amounts of token 0 and token 1 collected by each burn
+    pub burns: Vec<[u128; 2]>,
+}
+
+impl Env {
+    /// The account named `name` in the steps
+    pub fn account(&self, name: &str) -> &Account {
+        self.accounts
+            .get(name)
+            .unwrap_or_else(|| panic!("no account named {} in the scenario", name))
+    }
+
+    /// The manager, with `name` signing the calls
+    pub fn manager_as(&self, name: &str) -> ManagerClient<Account> {
+        ManagerClient::new(self.account(name).clone(), self.manager.id().clone())
+    }
+
+    pub async fn balance(&self, name: &str, token: usize) -> anyhow::Result<u128> {
+        self.tokens[token]
+            .ft_balance_of(&account_id(self.account(name)))
+            .await
+    }
+
+    pub async fn assert_balance(
+        &self,
+        name: &str,
+        token: usize,
+        expected: u128,
+    ) -> anyhow::Result<()> {
+        let balance = self.balance(name, token).await?;
+        assert_eq!(balance, expected, "balance of {} in token {}", name, token);
+        Ok(())
+    }
+
+    /// What `name` has deposited in the manager
+    pub async fn deposit(&self, name: &str, token: usize) -> anyhow::Result<u128> {
+        self.manager
+            .get_deposit(&account_id(self.account(name)), self.tokens[token].id())
+            .await
+    }
+
+    pub async fn positions(&self, name: &str) -> anyhow::Result<Vec<NftLiquidityInfo>> {
+        let positions = self
+            .manager
+            .positions(&account_id(self.account(name)))
+            .await?;
+        Ok(positions.into_iter().map(|(_, info)| info).collect())
+    }
+
+    pub async fn slot_0(&self, pool: usize) -> anyhow::Result<Slot0> {
+        self.pools[pool].client.get_slot_0().await
+    }
+
+    async fn create_pool(&mut self, spec: &PoolSpec) -> anyhow::Result<()> {
+        let mut tokens = [spec.tokens.0, spec.tokens.1];
+        tokens.sort_by(|a, b| self.tokens[*a].id().cmp(self.tokens[*b].id()));
+        let [token_0, token_1] = tokens.map(|token| self.tokens[token].id().clone());
+
+        let sqrt_price_x96 = price_math::price_to_sqrt_price_x96(&spec.price, 0, 0)
+            .map_err(|err| anyhow::anyhow!("{}: {}", err, spec.price))?;
+        self.manager
+            .create_pool(
+                &token_0,
+                &token_1,
+                spec.fee,
+                JsonU256(sqrt_price_x96),
+                CREATE_POOL_DEPOSIT,
+            )
+            .await?;
+        let pool = self
+            .factory
+            .get_pool(&token_0, &token_1, spec.fee)
+            .await?
+            .ok_or_else(|| {
+                anyhow::anyhow!("pool {}/{}/{} was not created", token_0, token_1, spec.fee)
+            })?;
+
+        self.pools.push(ScenarioPool {
+            client: PoolClient::new(self.root.clone(), pool.pool_id),
+            tokens,
+            fee: spec.fee,
+        });
+        Ok(())
+    }
+
+    async fn apply(&mut self, step: Step) -> anyhow::Result<()> {
+        match step {
+            Step::Mint {
+                account,
+                pool,
+                lower_tick,
+                upper_tick,
+                amounts,
+            } => {
+                let manager = self.get_or_create_manager(&account).await?;
+                let pool_tokens = self.pools[pool].tokens;
+                for (token, amount) in pool_tokens.iter().zip(amounts) {
+                    manager.deposit(self.tokens[*token].id(), amount).await?;
+                }
+
+                let params = MintParams {
+                    token_0: self.tokens[pool_tokens[0]].id().clone(),
+                    token_1: self.tokens[pool_tokens[1]].id().clone(),
+                    fee: self.pools[pool].fee,
+                    lower_tick,
+                    upper_tick,
+                    amount_0_desired: U128::from(amounts[0]),
+                    amount_1_desired: U128::from(amounts[1]),
+                    amount_0_min: U128::from(0),
+                    amount_1_min: U128::from(0),
+                };
+                let amounts = manager.mint(&params).await?;
+
+                // the manager numbers its NFTs from 0 in minting order
+                let nft_id = self.positions_minted.len() as u128;
+                self.positions_minted.push(MintedPosition {
+                    account,
+                    pool,
+                    nft_id,
+                    amounts,
+                });
+            }
+            Step::Swap {
+                account,
+                pool,
+                zero_for_one,
+                amount_in,
+            } => {
+                let manager = self.get_or_create_manager(&account).await?;
+                let trader = account_id(manager.transport());
+                let [token_0, token_1] = self.pools[pool].tokens;
+                let (token_in, token_out) = if zero_for_one {
+                    (token_0, token_1)
+                } else {
+                    (token_1, token_0)
+                };
+
+                // the input leaves the wallet, the output is credited to the deposit
+                let token_out_id = self.tokens[token_out].id();
+                let balances_before = [
+                    self.tokens[token_in].ft_balance_of(&trader).await?,
+                    manager.get_deposit(&trader, token_out_id).await?,
+                ];
+                manager
+                    .swap_single(
+                        self.tokens[token_in].id(),
+                        amount_in,
+                        token_out_id,
+                        self.pools[pool].fee,
+                        0,
+                        None,
+                    )
+                    .await?;
+                let balances_after = [
+                    self.tokens[token_in].ft_balance_of(&trader).await?,
+                    manager.get_deposit(&trader, token_out_id).await?,
+                ];
+
+                self.swaps.push(SwapResult {
+                    amount_in: balances_before[0] - balances_after[0],
+                    amount_out: balances_after[1] - balances_before[1],
+                });
+            }
+            Step::Burn { account, position } => {
+                let manager = self.get_or_create_manager(&account).await?;
+                let nft_id = self.positions_minted[position].nft_id;
+                let amounts = manager.burn(nft_id).await?;
+                self.burns.push(amounts);
+            }
+        }
+        Ok(())
+    }
+
+    /// The manager with `name` signing, creating the account on first use
+    async fn get_or_create_manager(
+        &mut self,
+        name: &str,
+    ) -> anyhow::Result<ManagerClient<Account>> {
+        if !self.accounts.contains_key(name) {
+            self.create_account(name).await?;
+        }
+        Ok(self.manager_as(name))
+    }
+
+    async fn create_account(&mut self, name: &str) -> anyhow::Result<()> {
+        let account = self
+            .root
+            .create_subaccount(name)
+            .initial_balance(ACCOUNT_NEAR_BALANCE)
+            .transact()
+            .await?
+            .into_result()?;
+        let id = account_id(&account);
+        for token in self.tokens.iter() {
+            token
+                .storage_deposit(Some(&id), TOKEN_STORAGE_DEPOSIT)
+                .await?;
+            token.ft_transfer(&id, ACCOUNT_BALANCE).await?;
+        }
+
+        self.accounts.insert(name.to_string(), account);
+        self.manager_as(name)
+            .storage_deposit(None, MANAGER_STORAGE_DEPOSIT)
+            .await?;
+        Ok(())
+    }
+}
+
+fn account_id(account: &Account) -> AccountId {
+    account.id().as_str().parse().unwrap()
+}